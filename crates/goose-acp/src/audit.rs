@@ -0,0 +1,264 @@
+//! Append-only record of the tool calls run for each ACP session, one JSON object per line in
+//! `<data dir>/audit/<session id>.jsonl`, and of every permission prompt across sessions in
+//! `<data dir>/audit/permissions.jsonl`. Arguments are stored as a hash so the log doesn't keep
+//! copies of file contents or secrets passed to tools.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use fs_err as fs;
+use rmcp::model::JsonObject;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+const AUDIT_DIR: &str = "audit";
+const PERMISSIONS_LOG: &str = "permissions";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditDecision {
+    /// Ran without asking, because of the mode, a stored rule or a read-only tool.
+    Auto,
+    /// The user allowed it, either when asked or earlier in the session.
+    Approved,
+    /// The user rejected it or dismissed the permission request.
+    Denied,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub session_id: String,
+    pub tool: String,
+    /// Hex SHA-256 of the call's arguments serialized as JSON.
+    pub args_hash: String,
+    pub decision: AuditDecision,
+    pub outcome: AuditOutcome,
+    /// From the model's request to the tool's result, including any wait for permission.
+    pub duration_ms: u64,
+}
+
+/// A permission prompt and how it was answered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionRecord {
+    pub requested_at: DateTime<Utc>,
+    pub resolved_at: DateTime<Utc>,
+    pub session_id: String,
+    /// The client's name from `initialize`, if it sent one.
+    pub client: Option<String>,
+    pub tool: String,
+    pub args_hash: String,
+    /// The option the user picked, or none if the prompt was dismissed or failed.
+    pub option_id: Option<String>,
+    pub decision: AuditDecision,
+}
+
+/// Filters for [`AuditLog::query_permissions`]; unset fields match every record.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PermissionQuery {
+    pub session_id: Option<String>,
+    pub tool: Option<String>,
+    pub decision: Option<AuditDecision>,
+    /// Only prompts raised at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only prompts raised before this time.
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl PermissionQuery {
+    fn matches(&self, record: &PermissionRecord) -> bool {
+        self.session_id
+            .as_ref()
+            .is_none_or(|session_id| *session_id == record.session_id)
+            && self.tool.as_ref().is_none_or(|tool| *tool == record.tool)
+            && self
+                .decision
+                .is_none_or(|decision| decision == record.decision)
+            && self.since.is_none_or(|since| record.requested_at >= since)
+            && self.until.is_none_or(|until| record.requested_at < until)
+    }
+}
+
+pub fn args_hash(arguments: Option<&JsonObject>) -> String {
+    let json = serde_json::to_string(&arguments).unwrap_or_default();
+    format!("{:x}", Sha256::digest(json.as_bytes()))
+}
+
+#[derive(Clone)]
+pub struct AuditLog {
+    dir: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            dir: data_dir.join(AUDIT_DIR),
+        }
+    }
+
+    fn path(&self, session_id: &str) -> Result<PathBuf> {
+        let valid = !session_id.is_empty()
+            && session_id != PERMISSIONS_LOG
+            && session_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            bail!("Invalid session id: {}", session_id);
+        }
+        Ok(self.dir.join(format!("{}.jsonl", session_id)))
+    }
+
+    fn append_line<T: Serialize>(&self, path: &Path, record: &T) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn read_lines<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        BufReader::new(file)
+            .lines()
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+
+    pub fn append(&self, record: &AuditRecord) -> Result<()> {
+        self.append_line(&self.path(&record.session_id)?, record)
+    }
+
+    /// The session's records, oldest first. A session that hasn't run any tools has none.
+    pub fn read(&self, session_id: &str) -> Result<Vec<AuditRecord>> {
+        Self::read_lines(&self.path(session_id)?)
+    }
+
+    pub fn append_permission(&self, record: &PermissionRecord) -> Result<()> {
+        let path = self.dir.join(format!("{}.jsonl", PERMISSIONS_LOG));
+        self.append_line(&path, record)
+    }
+
+    /// Permission prompts from every session matching `query`, oldest first.
+    pub fn query_permissions(&self, query: &PermissionQuery) -> Result<Vec<PermissionRecord>> {
+        let path = self.dir.join(format!("{}.jsonl", PERMISSIONS_LOG));
+        let records: Vec<PermissionRecord> = Self::read_lines(&path)?;
+        Ok(records
+            .into_iter()
+            .filter(|record| query.matches(record))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(session_id: &str, tool: &str) -> AuditRecord {
+        AuditRecord {
+            timestamp: Utc::now(),
+            session_id: session_id.to_string(),
+            tool: tool.to_string(),
+            args_hash: args_hash(json!({"command": "ls"}).as_object()),
+            decision: AuditDecision::Auto,
+            outcome: AuditOutcome::Success,
+            duration_ms: 12,
+        }
+    }
+
+    #[test]
+    fn test_audit_log_round_trips_per_session() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::new(temp_dir.path());
+        let first = record("20250101_1", "developer__shell");
+        let second = record("20250101_1", "developer__text_editor");
+        log.append(&first).unwrap();
+        log.append(&record("20250101_2", "developer__shell"))
+            .unwrap();
+        log.append(&second).unwrap();
+
+        assert_eq!(log.read("20250101_1").unwrap(), vec![first, second]);
+        assert!(log.read("20250101_3").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_audit_log_rejects_path_session_ids() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::new(temp_dir.path());
+        assert!(log.read("../permission").is_err());
+        assert!(log.read(PERMISSIONS_LOG).is_err());
+        assert!(log.append(&record("a/b", "developer__shell")).is_err());
+    }
+
+    #[test]
+    fn test_query_permissions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::new(temp_dir.path());
+        let now = Utc::now();
+        let prompt = |session_id: &str, tool: &str, decision, minutes_ago| PermissionRecord {
+            requested_at: now - chrono::Duration::minutes(minutes_ago),
+            resolved_at: now,
+            session_id: session_id.to_string(),
+            client: Some("zed".to_string()),
+            tool: tool.to_string(),
+            args_hash: args_hash(None),
+            option_id: None,
+            decision,
+        };
+        let old = prompt(
+            "20250101_1",
+            "developer__shell",
+            AuditDecision::Approved,
+            90,
+        );
+        let denied = prompt("20250101_1", "developer__shell", AuditDecision::Denied, 5);
+        let other = prompt("20250101_2", "lookup__get_code", AuditDecision::Approved, 1);
+        for record in [&old, &denied, &other] {
+            log.append_permission(record).unwrap();
+        }
+
+        let all = log.query_permissions(&PermissionQuery::default()).unwrap();
+        assert_eq!(all, vec![old.clone(), denied.clone(), other.clone()]);
+        let session = PermissionQuery {
+            session_id: Some("20250101_1".to_string()),
+            decision: Some(AuditDecision::Approved),
+            ..Default::default()
+        };
+        assert_eq!(log.query_permissions(&session).unwrap(), vec![old]);
+        let recent = PermissionQuery {
+            since: Some(now - chrono::Duration::minutes(10)),
+            tool: Some("developer__shell".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(log.query_permissions(&recent).unwrap(), vec![denied]);
+        assert!(log.read("20250101_1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_args_hash_depends_on_arguments() {
+        let ls = args_hash(json!({"command": "ls"}).as_object());
+        assert_eq!(ls, args_hash(json!({"command": "ls"}).as_object()));
+        assert_ne!(ls, args_hash(json!({"command": "rm"}).as_object()));
+        assert_ne!(ls, args_hash(None));
+    }
+}
@@ -0,0 +1,192 @@
+//! Backends for the ACP `authenticate` method. Each one is advertised as an auth method in
+//! `initialize`, and sessions can't be created until one of them reports success.
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use goose::config::Config;
+use goose::providers::base::{ConfigKey, Provider, ProviderMetadata};
+use sacp::schema::AuthMethod;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait AuthBackend: Send + Sync {
+    fn method(&self) -> AuthMethod;
+
+    /// Whether credentials are already in place, so clients can skip `authenticate`.
+    async fn is_authenticated(&self) -> bool;
+
+    async fn authenticate(&self) -> Result<()>;
+}
+
+fn missing_keys(keys: &[ConfigKey]) -> Vec<&str> {
+    let config = Config::global();
+    keys.iter()
+        .filter(|key| config.get(&key.name, key.secret).is_err())
+        .map(|key| key.name.as_str())
+        .collect()
+}
+
+/// Passes once the provider's required keys are set in the environment, config or keyring.
+/// There's no way to enter a key over ACP, so `authenticate` just re-checks after the user has
+/// set them.
+pub struct ApiKeyAuth {
+    provider_name: String,
+    keys: Vec<ConfigKey>,
+}
+
+impl ApiKeyAuth {
+    pub fn new(metadata: &ProviderMetadata) -> Self {
+        Self {
+            provider_name: metadata.name.clone(),
+            keys: metadata
+                .config_keys
+                .iter()
+                .filter(|key| key.required && !key.oauth_flow && key.default.is_none())
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl AuthBackend for ApiKeyAuth {
+    fn method(&self) -> AuthMethod {
+        let names: Vec<&str> = self.keys.iter().map(|key| key.name.as_str()).collect();
+        AuthMethod::new("api_key", format!("{} API key", self.provider_name)).description(format!(
+            "Set {} in the environment or with `goose configure`",
+            names.join(", ")
+        ))
+    }
+
+    async fn is_authenticated(&self) -> bool {
+        missing_keys(&self.keys).is_empty()
+    }
+
+    async fn authenticate(&self) -> Result<()> {
+        let missing = missing_keys(&self.keys);
+        if !missing.is_empty() {
+            bail!("Missing configuration: {}", missing.join(", "));
+        }
+        Ok(())
+    }
+}
+
+/// Runs the provider's OAuth device flow, which stores the resulting token in goose's config.
+pub struct OAuthAuth {
+    provider: Arc<dyn Provider>,
+    provider_name: String,
+    keys: Vec<ConfigKey>,
+}
+
+impl OAuthAuth {
+    pub fn new(metadata: &ProviderMetadata, provider: Arc<dyn Provider>) -> Self {
+        Self {
+            provider,
+            provider_name: metadata.name.clone(),
+            keys: metadata
+                .config_keys
+                .iter()
+                .filter(|key| key.oauth_flow)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl AuthBackend for OAuthAuth {
+    fn method(&self) -> AuthMethod {
+        AuthMethod::new("oauth", format!("Sign in to {}", self.provider_name))
+            .description("Opens a browser to complete the provider's device login".to_string())
+    }
+
+    async fn is_authenticated(&self) -> bool {
+        missing_keys(&self.keys).is_empty()
+    }
+
+    async fn authenticate(&self) -> Result<()> {
+        Ok(self.provider.configure_oauth().await?)
+    }
+}
+
+/// The backends that apply to a provider, based on the config keys it declares.
+pub fn provider_backends(
+    metadata: &ProviderMetadata,
+    provider: Arc<dyn Provider>,
+) -> Vec<Arc<dyn AuthBackend>> {
+    let mut backends: Vec<Arc<dyn AuthBackend>> = Vec::new();
+    if metadata.config_keys.iter().any(|key| key.oauth_flow) {
+        backends.push(Arc::new(OAuthAuth::new(metadata, provider)));
+    }
+    let api_key = ApiKeyAuth::new(metadata);
+    if !api_key.keys.is_empty() {
+        backends.push(Arc::new(api_key));
+    }
+    backends
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goose::model::ModelConfig;
+    use goose::providers::api_client::{ApiClient, AuthMethod as ApiAuth};
+    use goose::providers::openai::OpenAiProvider;
+
+    const KEY: &str = "GOOSE_ACP_TEST_AUTH_KEY";
+
+    fn metadata(config_keys: Vec<ConfigKey>) -> ProviderMetadata {
+        ProviderMetadata::new("test", "Test", "", "model", vec![], "", config_keys)
+    }
+
+    fn provider() -> Arc<dyn Provider> {
+        let api_client = ApiClient::new("http://localhost".to_string(), ApiAuth::NoAuth).unwrap();
+        Arc::new(OpenAiProvider::new(
+            api_client,
+            ModelConfig::new_or_fail("gpt-5-nano"),
+        ))
+    }
+
+    #[test]
+    fn test_provider_backends_follow_config_keys() {
+        let ids = |keys: Vec<ConfigKey>| -> Vec<String> {
+            provider_backends(&metadata(keys), provider())
+                .iter()
+                .map(|backend| backend.method().id.0.to_string())
+                .collect()
+        };
+
+        assert!(ids(vec![]).is_empty());
+        assert!(ids(vec![ConfigKey::new("HOST", true, false, Some("localhost"))]).is_empty());
+        assert_eq!(
+            ids(vec![ConfigKey::new(KEY, true, true, None)]),
+            ["api_key"]
+        );
+        assert_eq!(
+            ids(vec![
+                ConfigKey::new_oauth("TOKEN", true, true, None),
+                ConfigKey::new(KEY, true, true, None),
+            ]),
+            ["oauth", "api_key"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_key_auth_rechecks_keys() {
+        let auth = ApiKeyAuth::new(&metadata(vec![
+            ConfigKey::new(KEY, true, false, None),
+            ConfigKey::new("GOOSE_ACP_TEST_AUTH_OPTIONAL", false, false, None),
+        ]));
+        assert!(auth.method().description.unwrap().contains(KEY));
+
+        {
+            let _guard = env_lock::lock_env([(KEY, None::<&str>)]);
+            assert!(!auth.is_authenticated().await);
+            let error = auth.authenticate().await.unwrap_err();
+            assert_eq!(error.to_string(), format!("Missing configuration: {}", KEY));
+        }
+
+        let _guard = env_lock::lock_env([(KEY, Some("set"))]);
+        assert!(auth.is_authenticated().await);
+        auth.authenticate().await.unwrap();
+    }
+}
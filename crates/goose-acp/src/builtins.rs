@@ -0,0 +1,121 @@
+//! The builtin extensions new sessions start with. They can come from a YAML file with a
+//! `builtins` list, which is re-read whenever it changes so a long-running server picks up edits
+//! without a restart. Sessions that are already running keep what they started with.
+
+use fs_err as fs;
+use serde::Deserialize;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::warn;
+
+#[derive(Deserialize)]
+struct BuiltinsFile {
+    builtins: Vec<String>,
+}
+
+struct Loaded {
+    modified: SystemTime,
+    len: u64,
+    builtins: Vec<String>,
+}
+
+pub struct Builtins {
+    /// Used while there's no file, or it has never parsed.
+    default: Vec<String>,
+    path: Option<PathBuf>,
+    loaded: Mutex<Option<Loaded>>,
+}
+
+impl Builtins {
+    pub fn new(default: Vec<String>, path: Option<PathBuf>) -> Self {
+        Self {
+            default,
+            path,
+            loaded: Mutex::new(None),
+        }
+    }
+
+    /// A file that fails to parse is reported and the last good list stays in use.
+    pub fn current(&self) -> Vec<String> {
+        let Some(path) = &self.path else {
+            return self.default.clone();
+        };
+        let mut loaded = self.loaded.lock().unwrap();
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                if e.kind() != ErrorKind::NotFound {
+                    warn!(path = %path.display(), error = %e, "failed to read builtins file");
+                }
+                *loaded = None;
+                return self.default.clone();
+            }
+        };
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        if let Some(current) = loaded
+            .as_ref()
+            .filter(|current| current.modified == modified && current.len == metadata.len())
+        {
+            return current.builtins.clone();
+        }
+
+        let parsed = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_yaml::from_str::<BuiltinsFile>(&content)?));
+        match parsed {
+            Ok(file) => {
+                *loaded = Some(Loaded {
+                    modified,
+                    len: metadata.len(),
+                    builtins: file.builtins.clone(),
+                });
+                file.builtins
+            }
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "invalid builtins file");
+                loaded
+                    .as_ref()
+                    .map(|current| current.builtins.clone())
+                    .unwrap_or_else(|| self.default.clone())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write(path: &std::path::Path, content: &str, age_secs: u64) {
+        fs::write(path, content).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_builtins_follow_file_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("builtins.yaml");
+        let builtins = Builtins::new(vec!["developer".to_string()], Some(path.clone()));
+        assert_eq!(builtins.current(), vec!["developer"]);
+
+        write(&path, "builtins: [developer, memory]\n", 60);
+        assert_eq!(builtins.current(), vec!["developer", "memory"]);
+
+        write(&path, "builtins: [memory]\n", 30);
+        assert_eq!(builtins.current(), vec!["memory"]);
+
+        write(&path, "builtins: memory\n", 10);
+        assert_eq!(builtins.current(), vec!["memory"]);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(builtins.current(), vec!["developer"]);
+    }
+}
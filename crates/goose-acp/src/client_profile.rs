@@ -0,0 +1,78 @@
+//! What the connected client can handle, worked out once from the capabilities it sent in
+//! `initialize`. Prompt turns consult it rather than the raw capabilities so every place that
+//! talks to the client makes the same call.
+
+use sacp::schema::{ClientCapabilities, InitializeRequest};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientProfile {
+    pub read_text_file: bool,
+    pub write_text_file: bool,
+    pub terminal: bool,
+    /// ACP has no capability for this, so clients are assumed to show images unless their
+    /// capabilities carry `"_meta": {"goose": {"images": false}}`.
+    pub images: bool,
+    /// From `clientInfo`, so records can say which client a user answered in.
+    pub name: Option<String>,
+}
+
+impl ClientProfile {
+    pub fn from_capabilities(capabilities: &ClientCapabilities) -> Self {
+        let images = capabilities
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.get("goose"))
+            .and_then(|goose| goose.get("images"))
+            .and_then(|images| images.as_bool())
+            .unwrap_or(true);
+        Self {
+            read_text_file: capabilities.fs.read_text_file,
+            write_text_file: capabilities.fs.write_text_file,
+            terminal: capabilities.terminal,
+            images,
+            name: None,
+        }
+    }
+
+    pub fn from_initialize(request: &InitializeRequest) -> Self {
+        Self {
+            name: request.client_info.as_ref().map(|info| info.name.clone()),
+            ..Self::from_capabilities(&request.client_capabilities)
+        }
+    }
+}
+
+/// Before `initialize`, the client is assumed to handle nothing beyond the protocol's basics.
+impl Default for ClientProfile {
+    fn default() -> Self {
+        Self::from_capabilities(&ClientCapabilities::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sacp::schema::FileSystemCapability;
+
+    #[test]
+    fn test_client_profile_from_capabilities() {
+        let capabilities = ClientCapabilities::new()
+            .fs(FileSystemCapability::new().read_text_file(true))
+            .meta(
+                serde_json::json!({"goose": {"images": false}})
+                    .as_object()
+                    .cloned(),
+            );
+        assert_eq!(
+            ClientProfile::from_capabilities(&capabilities),
+            ClientProfile {
+                read_text_file: true,
+                write_text_file: false,
+                terminal: false,
+                images: false,
+                name: None,
+            }
+        );
+        assert!(ClientProfile::default().images);
+    }
+}
@@ -0,0 +1,376 @@
+//! Tools that run through the ACP client rather than locally. They are registered as goose
+//! frontend tools, so the agent runs its usual permission checks, then hands each call back to us
+//! and waits for the result.
+
+use goose::agents::{ExtensionConfig, FileLimits, ToolFilter};
+use goose::conversation::message::FrontendToolRequest;
+use goose::mcp_utils::ToolResult;
+use rmcp::model::{CallToolRequestParams, CallToolResult, Content, ErrorData, Tool};
+use rmcp::object;
+use sacp::schema::{
+    CreateTerminalRequest, KillTerminalCommandRequest, ReadTextFileRequest, ReleaseTerminalRequest,
+    SessionId, SessionNotification, SessionUpdate, Terminal, TerminalExitStatus, TerminalId,
+    TerminalOutputRequest, ToolCallContent, ToolCallId, ToolCallStatus, ToolCallUpdate,
+    ToolCallUpdateFields, WaitForTerminalExitRequest, WriteTextFileRequest,
+};
+use sacp::{AgentToClient, JrConnectionCx};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::client_profile::ClientProfile;
+
+const EXTENSION_NAME: &str = "editor";
+const READ_TEXT_FILE: &str = "editor__read_text_file";
+const WRITE_TEXT_FILE: &str = "editor__write_text_file";
+const SHELL: &str = "editor__shell";
+const DEVELOPER_TEXT_EDITOR: &str = "developer__text_editor";
+const DEVELOPER_SHELL: &str = "developer__shell";
+
+const SHELL_OUTPUT_BYTE_LIMIT: u64 = 100_000;
+
+const INSTRUCTIONS: &str = "These tools go through the user's editor. Prefer them over other \
+    file tools: reads include unsaved changes, writes show up in the editor for review, and shell \
+    commands run in a terminal the user can watch and stop.";
+
+#[derive(Deserialize)]
+struct ReadTextFileArgs {
+    path: String,
+    line: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct WriteTextFileArgs {
+    path: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ShellArgs {
+    command: String,
+    cwd: Option<PathBuf>,
+}
+
+fn read_text_file_tool() -> Tool {
+    Tool::new(
+        READ_TEXT_FILE,
+        "Read a text file, including any unsaved changes open in the editor.",
+        object!({
+            "type": "object",
+            "required": ["path"],
+            "properties": {
+                "path": {"type": "string", "description": "Absolute path to the file"},
+                "line": {"type": "integer", "description": "1-based line to start reading from"},
+                "limit": {"type": "integer", "description": "Maximum number of lines to read"}
+            }
+        }),
+    )
+}
+
+fn write_text_file_tool() -> Tool {
+    Tool::new(
+        WRITE_TEXT_FILE,
+        "Write the full contents of a text file through the editor, creating it if needed.",
+        object!({
+            "type": "object",
+            "required": ["path", "content"],
+            "properties": {
+                "path": {"type": "string", "description": "Absolute path to the file"},
+                "content": {"type": "string", "description": "The new file contents"}
+            }
+        }),
+    )
+}
+
+fn shell_tool() -> Tool {
+    Tool::new(
+        SHELL,
+        "Run a shell command in the user's editor terminal and return its output once it exits.",
+        object!({
+            "type": "object",
+            "required": ["command"],
+            "properties": {
+                "command": {"type": "string", "description": "The command line to run"},
+                "cwd": {"type": "string", "description": "Absolute working directory for the command"}
+            }
+        }),
+    )
+}
+
+/// The frontend extension matching what the client can do, if it can do anything.
+pub fn extension_config(profile: &ClientProfile) -> Option<ExtensionConfig> {
+    let mut tools = Vec::new();
+    if profile.read_text_file {
+        tools.push(read_text_file_tool());
+    }
+    if profile.write_text_file {
+        tools.push(write_text_file_tool());
+    }
+    if profile.terminal {
+        tools.push(shell_tool());
+    }
+    if tools.is_empty() {
+        return None;
+    }
+
+    Some(ExtensionConfig::Frontend {
+        name: EXTENSION_NAME.to_string(),
+        description: "Tools provided by the ACP client".to_string(),
+        tools,
+        instructions: Some(INSTRUCTIONS.to_string()),
+        bundled: None,
+        available_tools: vec![],
+    })
+}
+
+/// Hides the developer tools that the client's tools stand in for, so the model can't go around
+/// the editor with them.
+pub fn replaced_tools(profile: &ClientProfile) -> ToolFilter {
+    let mut deny = Vec::new();
+    if profile.read_text_file && profile.write_text_file {
+        deny.push(DEVELOPER_TEXT_EDITOR.to_string());
+    }
+    if profile.terminal {
+        deny.push(DEVELOPER_SHELL.to_string());
+    }
+    ToolFilter {
+        allow: Vec::new(),
+        deny,
+    }
+}
+
+fn parse_args<T: DeserializeOwned>(tool_call: &CallToolRequestParams) -> ToolResult<T> {
+    let arguments = tool_call.arguments.clone().unwrap_or_default();
+    serde_json::from_value(serde_json::Value::Object(arguments))
+        .map_err(|e| ErrorData::invalid_params(e.to_string(), None))
+}
+
+fn client_error(e: sacp::Error) -> ErrorData {
+    ErrorData::internal_error(format!("Client request failed: {}", e), None)
+}
+
+fn shell_invocation(command: String) -> (String, Vec<String>) {
+    if cfg!(windows) {
+        ("cmd".to_string(), vec!["/C".to_string(), command])
+    } else {
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+        (shell, vec!["-c".to_string(), command])
+    }
+}
+
+fn format_exit_status(status: &TerminalExitStatus) -> String {
+    match (status.exit_code, &status.signal) {
+        (Some(code), _) => format!("exit code {}", code),
+        (None, Some(signal)) => format!("signal {}", signal),
+        (None, None) => "unknown exit status".to_string(),
+    }
+}
+
+async fn run_in_terminal(
+    args: ShellArgs,
+    tool_call_id: &str,
+    session_id: &SessionId,
+    cancel_token: &CancellationToken,
+    cx: &JrConnectionCx<AgentToClient>,
+) -> ToolResult<CallToolResult> {
+    let (command, command_args) = shell_invocation(args.command);
+    let terminal_id = cx
+        .send_request(
+            CreateTerminalRequest::new(session_id.clone(), command)
+                .args(command_args)
+                .cwd(args.cwd)
+                .output_byte_limit(SHELL_OUTPUT_BYTE_LIMIT),
+        )
+        .block_task()
+        .await
+        .map_err(client_error)?
+        .terminal_id;
+
+    cx.send_notification(SessionNotification::new(
+        session_id.clone(),
+        SessionUpdate::ToolCallUpdate(ToolCallUpdate::new(
+            ToolCallId::new(tool_call_id.to_string()),
+            ToolCallUpdateFields::new()
+                .status(ToolCallStatus::InProgress)
+                .content(vec![ToolCallContent::Terminal(Terminal::new(
+                    terminal_id.clone(),
+                ))]),
+        )),
+    ))
+    .map_err(client_error)?;
+
+    let result = wait_for_output(&terminal_id, session_id, cancel_token, cx).await;
+    if let Err(e) = cx
+        .send_request(ReleaseTerminalRequest::new(session_id.clone(), terminal_id))
+        .block_task()
+        .await
+    {
+        warn!(error = ?e, "failed to release client terminal");
+    }
+    result
+}
+
+async fn wait_for_output(
+    terminal_id: &TerminalId,
+    session_id: &SessionId,
+    cancel_token: &CancellationToken,
+    cx: &JrConnectionCx<AgentToClient>,
+) -> ToolResult<CallToolResult> {
+    let exit = cx
+        .send_request(WaitForTerminalExitRequest::new(
+            session_id.clone(),
+            terminal_id.clone(),
+        ))
+        .block_task();
+    tokio::select! {
+        exit = exit => {
+            exit.map_err(client_error)?;
+        }
+        _ = cancel_token.cancelled() => {
+            cx.send_request(KillTerminalCommandRequest::new(
+                session_id.clone(),
+                terminal_id.clone(),
+            ))
+            .block_task()
+            .await
+            .map_err(client_error)?;
+        }
+    }
+
+    let output = cx
+        .send_request(TerminalOutputRequest::new(
+            session_id.clone(),
+            terminal_id.clone(),
+        ))
+        .block_task()
+        .await
+        .map_err(client_error)?;
+
+    let mut text = output.output;
+    if output.truncated {
+        text.insert_str(0, "[output truncated to the most recent bytes]\n");
+    }
+    let failed = output
+        .exit_status
+        .as_ref()
+        .is_none_or(|status| status.exit_code != Some(0));
+    if let Some(status) = &output.exit_status {
+        text.push_str(&format!("\n[{}]", format_exit_status(status)));
+    }
+
+    let content = vec![Content::text(text)];
+    Ok(if failed {
+        CallToolResult::error(content)
+    } else {
+        CallToolResult::success(content)
+    })
+}
+
+/// Runs a frontend tool request on the client. Shell commands are killed when `cancel_token`
+/// fires so a cancelled turn doesn't leave them running in the user's terminal.
+/// Calls that need something the client didn't offer, or that break `file_limits`, fail without a
+/// request being sent. The former can happen when a session outlives the connection that set up
+/// its tools.
+pub async fn call(
+    request: &FrontendToolRequest,
+    profile: &ClientProfile,
+    file_limits: Option<&FileLimits>,
+    cwd: &Path,
+    session_id: &SessionId,
+    cancel_token: &CancellationToken,
+    cx: &JrConnectionCx<AgentToClient>,
+) -> ToolResult<CallToolResult> {
+    let tool_call = request.tool_call.as_ref().map_err(|e| e.clone())?;
+    let supported = match &*tool_call.name {
+        READ_TEXT_FILE => profile.read_text_file,
+        WRITE_TEXT_FILE => profile.write_text_file,
+        SHELL => profile.terminal,
+        _ => true,
+    };
+    if !supported {
+        return Err(ErrorData::invalid_request(
+            format!("The client doesn't support {}", tool_call.name),
+            None,
+        ));
+    }
+    if let Some(limits) = file_limits {
+        limits
+            .check(tool_call.arguments.as_ref(), cwd)
+            .map_err(|reason| ErrorData::invalid_params(reason, None))?;
+    }
+    match &*tool_call.name {
+        READ_TEXT_FILE => {
+            let args: ReadTextFileArgs = parse_args(tool_call)?;
+            let response = cx
+                .send_request(
+                    ReadTextFileRequest::new(session_id.clone(), args.path)
+                        .line(args.line)
+                        .limit(args.limit),
+                )
+                .block_task()
+                .await
+                .map_err(client_error)?;
+            Ok(CallToolResult::success(vec![Content::text(
+                response.content,
+            )]))
+        }
+        WRITE_TEXT_FILE => {
+            let args: WriteTextFileArgs = parse_args(tool_call)?;
+            cx.send_request(WriteTextFileRequest::new(
+                session_id.clone(),
+                args.path.clone(),
+                args.content,
+            ))
+            .block_task()
+            .await
+            .map_err(client_error)?;
+            Ok(CallToolResult::success(vec![Content::text(format!(
+                "Wrote {}",
+                args.path
+            ))]))
+        }
+        SHELL => {
+            let args: ShellArgs = parse_args(tool_call)?;
+            run_in_terminal(args, &request.id, session_id, cancel_token, cx).await
+        }
+        name => Err(ErrorData::invalid_params(
+            format!("Unknown client tool: {}", name),
+            None,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_fs_replaces_developer_text_editor() {
+        let mut profile = ClientProfile {
+            read_text_file: true,
+            ..ClientProfile::default()
+        };
+        assert!(replaced_tools(&profile).allows(DEVELOPER_TEXT_EDITOR));
+
+        profile.write_text_file = true;
+        let filter = replaced_tools(&profile);
+        assert!(!filter.allows(DEVELOPER_TEXT_EDITOR));
+        assert!(filter.allows(DEVELOPER_SHELL));
+        assert!(filter.allows(WRITE_TEXT_FILE));
+    }
+
+    #[test]
+    fn test_client_terminal_replaces_developer_shell() {
+        let profile = ClientProfile {
+            terminal: true,
+            ..ClientProfile::default()
+        };
+        let filter = replaced_tools(&profile);
+        assert!(!filter.allows(DEVELOPER_SHELL));
+        assert!(filter.allows(DEVELOPER_TEXT_EDITOR));
+        assert!(filter.allows(SHELL));
+    }
+}
@@ -0,0 +1,168 @@
+//! Transcripts for `_goose/session/export`. Only messages the user saw are included, so
+//! summaries and other agent-only context stay out of the export.
+
+use anyhow::Result;
+use goose::conversation::message::{Message, MessageContent};
+use goose::session::Session;
+use rmcp::model::{RawContent, Role};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    /// The messages as goose stores them.
+    Json,
+}
+
+pub fn export_session(session: &Session, format: ExportFormat) -> Result<String> {
+    let messages: Vec<&Message> = session
+        .conversation
+        .as_ref()
+        .map(|conversation| {
+            conversation
+                .messages()
+                .iter()
+                .filter(|message| message.is_user_visible())
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(match format {
+        ExportFormat::Markdown => to_markdown(&session.name, &messages),
+        ExportFormat::Json => serde_json::to_string_pretty(&messages)?,
+    })
+}
+
+fn fenced(language: &str, text: &str) -> String {
+    let fence = if text.contains("```") { "~~~~" } else { "```" };
+    format!("{}{}\n{}\n{}\n\n", fence, language, text.trim_end(), fence)
+}
+
+fn content_to_markdown(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text(text) => format!("{}\n\n", text.text.trim_end()),
+        MessageContent::Thinking(thinking) => format!(
+            "> {}\n\n",
+            thinking.thinking.trim_end().replace('\n', "\n> ")
+        ),
+        MessageContent::Image(image) => format!("*Image ({})*\n\n", image.mime_type),
+        MessageContent::ToolRequest(request) => match &request.tool_call {
+            Ok(call) => {
+                let arguments = serde_json::to_string_pretty(&call.arguments).unwrap_or_default();
+                format!(
+                    "**Tool call:** `{}`\n\n{}",
+                    call.name,
+                    fenced("json", &arguments)
+                )
+            }
+            Err(e) => format!("**Invalid tool call:** {}\n\n", e.message),
+        },
+        MessageContent::ToolResponse(response) => match &response.tool_result {
+            Ok(result) => {
+                let text = result
+                    .content
+                    .iter()
+                    .filter_map(|content| match &content.raw {
+                        RawContent::Text(text) => Some(text.text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let label = if result.is_error == Some(true) {
+                    "Tool error"
+                } else {
+                    "Tool result"
+                };
+                format!("**{}:**\n\n{}", label, fenced("", &text))
+            }
+            Err(e) => format!("**Tool error:** {}\n\n", e.message),
+        },
+        _ => String::new(),
+    }
+}
+
+fn to_markdown(name: &str, messages: &[&Message]) -> String {
+    let mut markdown = format!("# {}\n\n", name);
+    for message in messages {
+        let only_tool_responses = message
+            .content
+            .iter()
+            .all(|content| matches!(content, MessageContent::ToolResponse(_)));
+        if !only_tool_responses {
+            let heading = match message.role {
+                Role::User => "User",
+                Role::Assistant => "goose",
+            };
+            markdown.push_str(&format!("## {}\n\n", heading));
+        }
+        for content in &message.content {
+            markdown.push_str(&content_to_markdown(content));
+        }
+    }
+    format!("{}\n", markdown.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goose::conversation::Conversation;
+    use rmcp::model::{CallToolRequestParams, CallToolResult, Content};
+    use rmcp::object;
+
+    fn session(messages: Vec<Message>) -> Session {
+        Session {
+            name: "Fix the build".to_string(),
+            conversation: Some(Conversation::new_unvalidated(messages)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_markdown() {
+        let session = session(vec![
+            Message::user().with_text("Why does the build fail?"),
+            Message::assistant()
+                .with_text("Let me check.")
+                .with_tool_request(
+                    "call_1",
+                    Ok(CallToolRequestParams {
+                        meta: None,
+                        task: None,
+                        name: "developer__shell".into(),
+                        arguments: Some(object!({"command": "cargo build"})),
+                    }),
+                ),
+            Message::user().with_tool_response(
+                "call_1",
+                Ok(CallToolResult::success(vec![Content::text("error[E0432]")])),
+            ),
+            Message::assistant()
+                .with_text("Summary of earlier work")
+                .agent_only(),
+            Message::assistant().with_text("An import is missing."),
+        ]);
+
+        assert_eq!(
+            export_session(&session, ExportFormat::Markdown).unwrap(),
+            "# Fix the build\n\n\
+             ## User\n\nWhy does the build fail?\n\n\
+             ## goose\n\nLet me check.\n\n\
+             **Tool call:** `developer__shell`\n\n```json\n{\n  \"command\": \"cargo build\"\n}\n```\n\n\
+             **Tool result:**\n\n```\nerror[E0432]\n```\n\n\
+             ## goose\n\nAn import is missing.\n"
+        );
+    }
+
+    #[test]
+    fn test_export_json_keeps_visible_messages() {
+        let session = session(vec![
+            Message::user().with_text("hello"),
+            Message::assistant().with_text("hidden").agent_only(),
+        ]);
+        let json = export_session(&session, ExportFormat::Json).unwrap();
+        let messages: Vec<Message> = serde_json::from_str(&json).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].as_concat_text(), "hello");
+    }
+}
@@ -0,0 +1,333 @@
+//! Wraps a session's provider with backups to switch to when it keeps failing. Providers retry
+//! transient errors themselves, so an outage error reaching us means those retries ran out. The
+//! switch sticks for the rest of the session rather than paying for the retries again each turn.
+
+use async_trait::async_trait;
+use goose::conversation::message::Message;
+use goose::model::ModelConfig;
+use goose::providers::base::{
+    stream_from_single_message, MessageStream, Provider, ProviderMetadata, ProviderUsage,
+};
+use goose::providers::errors::ProviderError;
+use goose::providers::RetryConfig;
+use rmcp::model::Tool;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::server::ProviderFactory;
+
+/// A provider to switch to, created only once it's needed.
+#[derive(Clone)]
+pub struct Backup {
+    pub name: String,
+    pub factory: ProviderFactory,
+    pub model: String,
+}
+
+/// Server errors, timeouts and failed connections. Anything else, like a bad request, would
+/// most likely fail the same way on the next provider.
+fn is_outage(error: &ProviderError) -> bool {
+    match error {
+        ProviderError::ServerError(_) => true,
+        ProviderError::RequestFailed(message) => {
+            let message = message.to_lowercase();
+            ["timeout", "timed out", "connect"]
+                .iter()
+                .any(|needle| message.contains(needle))
+        }
+        _ => false,
+    }
+}
+
+pub struct FailoverProvider {
+    /// The primary provider's name, then each backup's.
+    names: Vec<String>,
+    backups: Vec<Backup>,
+    active: Mutex<(usize, Arc<dyn Provider>)>,
+    switching: tokio::sync::Mutex<()>,
+}
+
+impl FailoverProvider {
+    pub fn new(primary: Arc<dyn Provider>, backups: Vec<Backup>) -> Self {
+        let names = std::iter::once(primary.get_name().to_string())
+            .chain(backups.iter().map(|backup| backup.name.clone()))
+            .collect();
+        Self {
+            names,
+            backups,
+            active: Mutex::new((0, primary)),
+            switching: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn current(&self) -> (usize, Arc<dyn Provider>) {
+        let active = self.active.lock().unwrap();
+        (active.0, Arc::clone(&active.1))
+    }
+
+    /// Moves past the provider at `failed`, unless a concurrent call already did. Backups that
+    /// can't be created are skipped; `error` comes back once there are none left.
+    async fn fail_over(&self, failed: usize, error: ProviderError) -> Result<(), ProviderError> {
+        let _switching = self.switching.lock().await;
+        if self.current().0 != failed {
+            return Ok(());
+        }
+        for (index, backup) in self.backups.iter().enumerate().skip(failed) {
+            let provider = match ModelConfig::new(&backup.model) {
+                Ok(model_config) => (backup.factory)(model_config).await,
+                Err(e) => Err(e.into()),
+            };
+            match provider {
+                Ok(provider) => {
+                    warn!(
+                        error = %error,
+                        from = %self.names[failed],
+                        to = %backup.name,
+                        model = %backup.model,
+                        "provider failed, switching to backup"
+                    );
+                    *self.active.lock().unwrap() = (index + 1, provider);
+                    return Ok(());
+                }
+                Err(e) => warn!(provider = %backup.name, error = %e, "backup provider unavailable"),
+            }
+        }
+        Err(error)
+    }
+}
+
+#[async_trait]
+impl Provider for FailoverProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "failover",
+            "Failover Provider",
+            "A provider that switches to backups when the current one keeps failing",
+            "",
+            vec![],
+            "",
+            vec![],
+        )
+    }
+
+    fn get_name(&self) -> &str {
+        &self.names[self.current().0]
+    }
+
+    async fn complete_with_model(
+        &self,
+        session_id: Option<&str>,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let (mut index, mut provider) = self.current();
+        let mut model_config = model_config.clone();
+        loop {
+            match provider
+                .complete_with_model(session_id, &model_config, system, messages, tools)
+                .await
+            {
+                Err(e) if is_outage(&e) => {
+                    self.fail_over(index, e).await?;
+                    (index, provider) = self.current();
+                    model_config = provider.get_model_config();
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.current().1.get_model_config()
+    }
+
+    fn retry_config(&self) -> RetryConfig {
+        self.current().1.retry_config()
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.current().1.fetch_supported_models().await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.current().1.supports_embeddings()
+    }
+
+    async fn supports_cache_control(&self) -> bool {
+        self.current().1.supports_cache_control().await
+    }
+
+    async fn create_embeddings(
+        &self,
+        session_id: &str,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.current().1.create_embeddings(session_id, texts).await
+    }
+
+    /// Only failures to start a response fail over; one that breaks off midway has already
+    /// been partly shown to the user.
+    async fn stream_with_model(
+        &self,
+        session_id: &str,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let (mut index, mut provider) = self.current();
+        let mut model_config = model_config.clone();
+        loop {
+            let result = if provider.supports_streaming() {
+                provider
+                    .stream_with_model(session_id, &model_config, system, messages, tools)
+                    .await
+            } else {
+                provider
+                    .complete_with_model(Some(session_id), &model_config, system, messages, tools)
+                    .await
+                    .map(|(message, usage)| stream_from_single_message(message, usage))
+            };
+            match result {
+                Err(e) if is_outage(&e) => {
+                    self.fail_over(index, e).await?;
+                    (index, provider) = self.current();
+                    model_config = provider.get_model_config();
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    async fn configure_oauth(&self) -> Result<(), ProviderError> {
+        self.current().1.configure_oauth().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+    use goose::providers::base::Usage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use test_case::test_case;
+
+    struct MockProvider {
+        name: String,
+        model: String,
+        error: Option<fn() -> ProviderError>,
+        calls: AtomicUsize,
+    }
+
+    impl MockProvider {
+        fn new(name: &str, error: Option<fn() -> ProviderError>) -> Arc<Self> {
+            Arc::new(Self {
+                name: name.to_string(),
+                model: format!("{}-model", name),
+                error,
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            &self.name
+        }
+
+        async fn complete_with_model(
+            &self,
+            _session_id: Option<&str>,
+            _model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if let Some(error) = self.error {
+                return Err(error());
+            }
+            Ok((
+                Message::assistant().with_text(&self.name),
+                ProviderUsage::new(self.model.clone(), Usage::default()),
+            ))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail(&self.model)
+        }
+    }
+
+    fn backup(provider: Arc<MockProvider>) -> Backup {
+        Backup {
+            name: provider.name.clone(),
+            model: provider.model.clone(),
+            factory: Arc::new(move |_| {
+                let provider: Arc<dyn Provider> = provider.clone();
+                async move { Ok(provider) }.boxed()
+            }),
+        }
+    }
+
+    async fn complete(provider: &FailoverProvider) -> Result<String, ProviderError> {
+        let (message, _) = provider
+            .complete("session", "system", &[Message::user().with_text("hi")], &[])
+            .await?;
+        Ok(message.as_concat_text())
+    }
+
+    #[tokio::test]
+    async fn test_failover_switches_and_sticks() {
+        let primary =
+            MockProvider::new("primary", Some(|| ProviderError::ServerError("502".into())));
+        let broken = MockProvider::new(
+            "broken",
+            Some(|| ProviderError::RequestFailed("connect".into())),
+        );
+        let backup_provider = MockProvider::new("backup", None);
+        let provider = FailoverProvider::new(
+            primary.clone(),
+            vec![backup(broken.clone()), backup(backup_provider.clone())],
+        );
+
+        assert_eq!(complete(&provider).await.unwrap(), "backup");
+        assert_eq!(complete(&provider).await.unwrap(), "backup");
+        assert_eq!(provider.get_name(), "backup");
+        assert_eq!(provider.get_model_config().model_name, "backup-model");
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(broken.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(backup_provider.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failover_returns_last_error_when_exhausted() {
+        let primary =
+            MockProvider::new("primary", Some(|| ProviderError::ServerError("503".into())));
+        let backup_provider =
+            MockProvider::new("backup", Some(|| ProviderError::ServerError("504".into())));
+        let provider = FailoverProvider::new(primary, vec![backup(backup_provider)]);
+
+        let error = complete(&provider).await.unwrap_err();
+        assert!(matches!(error, ProviderError::ServerError(message) if message == "504"));
+    }
+
+    #[test_case(ProviderError::ServerError("Server error (500)".into()), true; "server error")]
+    #[test_case(ProviderError::RequestFailed("error sending request (timeout)".into()), true; "timeout")]
+    #[test_case(ProviderError::RequestFailed("Bad request (400): nope".into()), false; "bad request")]
+    #[test_case(ProviderError::Authentication("bad key".into()), false; "authentication")]
+    #[test_case(ProviderError::ContextLengthExceeded("too long".into()), false; "context length")]
+    fn test_is_outage(error: ProviderError, expected: bool) {
+        assert_eq!(is_outage(&error), expected);
+    }
+}
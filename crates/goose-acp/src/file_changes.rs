@@ -0,0 +1,112 @@
+//! Tells the agent which workspace files changed between prompts, so a long session doesn't
+//! keep working from what it read before the user edited those files in their editor.
+
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// At most this many paths are listed; the rest are only counted.
+const MAX_LISTED_CHANGES: usize = 50;
+
+pub struct WorkspaceChanges {
+    root: PathBuf,
+    changed: Arc<Mutex<BTreeSet<PathBuf>>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl WorkspaceChanges {
+    pub fn watch(root: &Path) -> notify::Result<Self> {
+        let changed = Arc::new(Mutex::new(BTreeSet::new()));
+        let events = changed.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!(error = %e, "workspace watch error");
+                        return;
+                    }
+                };
+                if !matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(_)
+                ) || matches!(event.kind, EventKind::Modify(ModifyKind::Metadata(_)))
+                {
+                    return;
+                }
+                events.lock().unwrap().extend(event.paths);
+            })?;
+        watcher.watch(root, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            changed,
+            _watcher: watcher,
+        })
+    }
+
+    /// Paths changed since the last call, relative to the workspace root.
+    pub fn take(&self) -> Vec<PathBuf> {
+        let changed = std::mem::take(&mut *self.changed.lock().unwrap());
+        changed
+            .into_iter()
+            .filter_map(|path| path.strip_prefix(&self.root).ok().map(Path::to_path_buf))
+            .filter(|path| !is_ignored(path))
+            .collect()
+    }
+}
+
+fn is_ignored(path: &Path) -> bool {
+    path.as_os_str().is_empty()
+        || path
+            .components()
+            .any(|component| component == Component::Normal(".git".as_ref()))
+}
+
+/// The text added to the next prompt, or `None` when nothing changed.
+pub fn changes_note(paths: &[PathBuf]) -> Option<String> {
+    if paths.is_empty() {
+        return None;
+    }
+    let mut note = String::from(
+        "Files in the workspace changed since your last turn. Read them again before relying on earlier contents:",
+    );
+    for path in paths.iter().take(MAX_LISTED_CHANGES) {
+        note.push_str(&format!("\n- {}", path.display()));
+    }
+    if paths.len() > MAX_LISTED_CHANGES {
+        note.push_str(&format!(
+            "\n- ...and {} more",
+            paths.len() - MAX_LISTED_CHANGES
+        ));
+    }
+    Some(note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_note() {
+        assert_eq!(changes_note(&[]), None);
+
+        let paths: Vec<PathBuf> = (0..MAX_LISTED_CHANGES + 2)
+            .map(|i| PathBuf::from(format!("src/file{}.rs", i)))
+            .collect();
+        let note = changes_note(&paths).unwrap();
+        assert!(note.contains("\n- src/file0.rs"));
+        assert!(!note.contains(&format!("src/file{}.rs", MAX_LISTED_CHANGES)));
+        assert!(note.ends_with("...and 2 more"));
+    }
+
+    #[test]
+    fn test_git_internals_are_ignored() {
+        assert!(is_ignored(Path::new(".git/index")));
+        assert!(is_ignored(Path::new("")));
+        assert!(!is_ignored(Path::new("src/.gitignore")));
+    }
+}
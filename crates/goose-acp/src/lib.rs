@@ -1 +1,17 @@
+#![recursion_limit = "256"]
+
+pub mod audit;
+pub mod auth;
+pub mod builtins;
+mod client_profile;
+mod client_tools;
+pub mod export;
+pub mod failover;
+mod file_changes;
+mod mcp_sse;
+mod profile;
+mod recipe;
 pub mod server;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+mod wire_log;
@@ -0,0 +1,185 @@
+//! Client side of the legacy MCP HTTP+SSE transport, which rmcp no longer ships.
+//!
+//! The server streams JSON-RPC messages over a long-lived `GET` and announces, in its first
+//! `endpoint` event, the URL that client messages must be `POST`ed to.
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use goose::agents::mcp_client::McpClient;
+use goose::agents::types::SharedProvider;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT};
+use rmcp::service::{RxJsonRpcMessage, TxJsonRpcMessage};
+use rmcp::RoleClient;
+use sse_stream::{Sse, SseStream};
+use std::time::Duration;
+use tracing::warn;
+use url::Url;
+
+pub async fn connect(
+    uri: &str,
+    headers: &[(String, String)],
+    timeout: Duration,
+    provider: SharedProvider,
+) -> Result<McpClient> {
+    let mut default_headers = HeaderMap::new();
+    for (name, value) in headers {
+        default_headers.insert(
+            HeaderName::try_from(name).map_err(|_| anyhow!("invalid header: {}", name))?,
+            HeaderValue::try_from(value).map_err(|_| anyhow!("invalid header value: {}", name))?,
+        );
+    }
+    let http_client = reqwest::Client::builder()
+        .default_headers(default_headers)
+        .build()?;
+
+    let base = Url::parse(uri)?;
+    let response = http_client
+        .get(base.clone())
+        .header(ACCEPT, "text/event-stream")
+        .send()
+        .await?
+        .error_for_status()?;
+    let mut events = Box::pin(SseStream::from_byte_stream(response.bytes_stream()));
+
+    let endpoint = loop {
+        match events.next().await {
+            Some(Ok(sse)) if sse.event.as_deref() == Some("endpoint") => {
+                break base.join(sse.data.as_deref().unwrap_or_default())?;
+            }
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(anyhow!("SSE stream error: {}", e)),
+            None => return Err(anyhow!("SSE stream closed before the endpoint event")),
+        }
+    };
+
+    let incoming = events.filter_map(|event| async move {
+        match event {
+            Ok(sse) => parse_message(sse),
+            Err(e) => {
+                warn!(error = %e, "dropping malformed SSE event");
+                None
+            }
+        }
+    });
+    let outgoing = futures::sink::unfold(
+        http_client,
+        move |http_client, message: TxJsonRpcMessage<RoleClient>| {
+            let endpoint = endpoint.clone();
+            async move {
+                http_client
+                    .post(endpoint)
+                    .json(&message)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(std::io::Error::other)?;
+                Ok::<_, std::io::Error>(http_client)
+            }
+        },
+    );
+
+    Ok(McpClient::connect((Box::pin(outgoing), Box::pin(incoming)), timeout, provider).await?)
+}
+
+fn parse_message(sse: Sse) -> Option<RxJsonRpcMessage<RoleClient>> {
+    if sse.event.as_deref().is_some_and(|event| event != "message") {
+        return None;
+    }
+    let data = sse.data?;
+    serde_json::from_str(&data)
+        .inspect_err(|e| warn!(error = %e, "dropping unparseable SSE message"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goose::agents::mcp_client::McpClientTrait;
+    use std::sync::Arc;
+    use test_case::test_case;
+    use tokio::sync::Mutex;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const INITIALIZE_RESULT: &str = r#"{"jsonrpc":"2.0","id":0,"result":{"protocolVersion":"2025-03-26","capabilities":{"tools":{}},"serverInfo":{"name":"legacy","version":"1.0.0"}}}"#;
+
+    async fn sse_server(events: String) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/sse"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(events),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_connect_posts_to_announced_endpoint() {
+        let server = sse_server(format!(
+            "event: endpoint\ndata: /messages?session=1\n\nevent: message\ndata: {}\n\n",
+            INITIALIZE_RESULT
+        ))
+        .await;
+        Mock::given(method("POST"))
+            .and(path("/messages"))
+            .and(query_param("session", "1"))
+            .and(header("x-api-key", "secret"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&server)
+            .await;
+
+        let client = connect(
+            &format!("{}/sse", server.uri()),
+            &[("x-api-key".to_string(), "secret".to_string())],
+            Duration::from_secs(5),
+            Arc::new(Mutex::new(None)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(client.get_info().unwrap().server_info.name, "legacy");
+        let posted = server.received_requests().await.unwrap();
+        let initialize: serde_json::Value = posted
+            .iter()
+            .find(|request| request.method.as_str() == "POST")
+            .unwrap()
+            .body_json()
+            .unwrap();
+        assert_eq!(initialize["method"], "initialize");
+    }
+
+    #[tokio::test]
+    async fn test_connect_fails_without_endpoint_event() {
+        let server = sse_server(format!("event: message\ndata: {}\n\n", INITIALIZE_RESULT)).await;
+
+        let error = connect(
+            &format!("{}/sse", server.uri()),
+            &[],
+            Duration::from_secs(5),
+            Arc::new(Mutex::new(None)),
+        )
+        .await
+        .err()
+        .unwrap();
+
+        assert!(error.to_string().contains("before the endpoint event"));
+    }
+
+    #[test_case(None, Some(INITIALIZE_RESULT), true; "unnamed event")]
+    #[test_case(Some("message"), Some(INITIALIZE_RESULT), true; "message event")]
+    #[test_case(Some("endpoint"), Some("/messages"), false; "other event")]
+    #[test_case(Some("message"), Some("not json"), false; "unparseable data")]
+    #[test_case(Some("message"), None, false; "no data")]
+    fn test_parse_message(event: Option<&str>, data: Option<&str>, parsed: bool) {
+        let sse = Sse {
+            event: event.map(str::to_string),
+            data: data.map(str::to_string),
+            ..Default::default()
+        };
+        assert_eq!(parse_message(sse).is_some(), parsed);
+    }
+}
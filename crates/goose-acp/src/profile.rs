@@ -0,0 +1,121 @@
+//! Agent profiles from config, chosen on `session/new` with
+//! `"_meta": {"goose": {"profile": "reviewer"}}`. The name is saved with the session so
+//! `session/load` sets the agent up from the profile again.
+
+use goose::agents::Agent;
+use goose::config::{get_extension_by_name, AgentProfile, Config};
+use goose::session::{ExtensionData, ExtensionState};
+use sacp::schema::Meta;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionProfile {
+    pub name: String,
+}
+
+impl ExtensionState for SessionProfile {
+    const EXTENSION_NAME: &'static str = "acp_profile";
+    const VERSION: &'static str = "v0";
+}
+
+pub fn requested_profile(
+    meta: Option<&Meta>,
+) -> Result<Option<(SessionProfile, AgentProfile)>, sacp::Error> {
+    let Some(value) = meta
+        .and_then(|meta| meta.get("goose"))
+        .and_then(|goose| goose.get("profile"))
+    else {
+        return Ok(None);
+    };
+    let name = value
+        .as_str()
+        .ok_or_else(|| sacp::Error::invalid_params().data("Profile must be a name".to_string()))?;
+    let profile = AgentProfile::from_config(Config::global(), name)
+        .map_err(|e| sacp::Error::invalid_params().data(format!("Invalid profiles: {}", e)))?
+        .ok_or_else(|| sacp::Error::invalid_params().data(format!("Unknown profile: {}", name)))?;
+    Ok(Some((
+        SessionProfile {
+            name: name.to_string(),
+        },
+        profile,
+    )))
+}
+
+/// The profile a stored session was started with. Loading fails when that profile has since
+/// been removed from config or broken, rather than resuming the session without its
+/// permission rules.
+pub fn stored_profile(extension_data: &ExtensionData) -> Result<Option<AgentProfile>, sacp::Error> {
+    let Some(SessionProfile { name }) = SessionProfile::from_extension_data(extension_data) else {
+        return Ok(None);
+    };
+    match AgentProfile::from_config(Config::global(), &name) {
+        Ok(Some(profile)) => Ok(Some(profile)),
+        Ok(None) => Err(sacp::Error::invalid_params()
+            .data(format!("Session profile {} is no longer configured", name))),
+        Err(e) => Err(sacp::Error::internal_error()
+            .data(format!("Failed to read session profile {}: {}", name, e))),
+    }
+}
+
+/// Applies the profile's extensions, mode and system prompt. Its provider, model and
+/// permissions are resolved by the caller when it builds the agent.
+pub async fn apply_profile(
+    agent: &Agent,
+    profile: &AgentProfile,
+    cwd: &Path,
+) -> Result<(), sacp::Error> {
+    for name in &profile.extensions {
+        let extension = get_extension_by_name(name).ok_or_else(|| {
+            sacp::Error::invalid_params().data(format!("Unknown profile extension: {}", name))
+        })?;
+        agent
+            .add_extension_with_working_dir(extension, Some(cwd.to_path_buf()))
+            .await
+            .map_err(|e| {
+                sacp::Error::internal_error()
+                    .data(format!("Failed to add profile extension {}: {}", name, e))
+            })?;
+    }
+
+    if let Some(mode) = profile.mode {
+        agent.update_goose_mode(mode).await;
+    }
+
+    if let Some(system_prompt) = &profile.system_prompt {
+        agent.extend_system_prompt(system_prompt.clone()).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_requested_profile_rejects_bad_references() {
+        let meta = |profile: serde_json::Value| {
+            json!({"goose": {"profile": profile}})
+                .as_object()
+                .unwrap()
+                .clone()
+        };
+        assert!(requested_profile(None).unwrap().is_none());
+        assert!(requested_profile(Some(&meta(json!({"name": "reviewer"})))).is_err());
+        assert!(requested_profile(Some(&meta(json!("definitely-not-a-profile")))).is_err());
+    }
+
+    #[test]
+    fn test_stored_profile_fails_closed() {
+        let mut extension_data = ExtensionData::default();
+        assert!(stored_profile(&extension_data).unwrap().is_none());
+
+        SessionProfile {
+            name: "definitely-not-a-profile".to_string(),
+        }
+        .to_extension_data(&mut extension_data)
+        .unwrap();
+        assert!(stored_profile(&extension_data).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Recipes passed to `session/new` as `"_meta": {"goose": {"recipe": {...}}}`, with either a
+//! `path` (relative to the session cwd) or inline `yaml`, plus optional string `params`.
+
+use fs_err as fs;
+use goose::agents::Agent;
+use goose::recipe::build_recipe::build_recipe_from_template;
+use goose::recipe::Recipe;
+use sacp::schema::Meta;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize)]
+struct RecipeRef {
+    path: Option<PathBuf>,
+    yaml: Option<String>,
+    #[serde(default)]
+    params: HashMap<String, String>,
+}
+
+pub fn requested_recipe(meta: Option<&Meta>, cwd: &Path) -> Result<Option<Recipe>, sacp::Error> {
+    let Some(value) = meta
+        .and_then(|meta| meta.get("goose"))
+        .and_then(|goose| goose.get("recipe"))
+    else {
+        return Ok(None);
+    };
+    let recipe_ref: RecipeRef = serde_json::from_value(value.clone())
+        .map_err(|e| sacp::Error::invalid_params().data(format!("Invalid recipe: {}", e)))?;
+
+    let (content, recipe_dir) = match (recipe_ref.path, recipe_ref.yaml) {
+        (Some(path), None) => {
+            let path = cwd.join(path);
+            let content = fs::read_to_string(&path).map_err(|e| {
+                sacp::Error::invalid_params().data(format!("Failed to read recipe: {}", e))
+            })?;
+            let recipe_dir = path.parent().unwrap_or(cwd).to_path_buf();
+            (content, recipe_dir)
+        }
+        (None, Some(yaml)) => (yaml, cwd.to_path_buf()),
+        _ => {
+            return Err(sacp::Error::invalid_params()
+                .data("Recipe needs exactly one of path or yaml".to_string()))
+        }
+    };
+
+    let recipe = build_recipe_from_template(
+        content,
+        &recipe_dir,
+        recipe_ref.params.into_iter().collect(),
+        None::<fn(&str, &str) -> anyhow::Result<String>>,
+    )
+    .map_err(|e| sacp::Error::invalid_params().data(e.to_string()))?;
+    Ok(Some(recipe))
+}
+
+/// Applies everything but the recipe's settings, which the caller resolves against the
+/// providers and models it offers.
+pub async fn apply_recipe(agent: &Agent, recipe: &Recipe, cwd: &Path) -> Result<(), sacp::Error> {
+    for extension in recipe.extensions.iter().flatten() {
+        let name = extension.name();
+        agent
+            .add_extension_with_working_dir(extension.clone(), Some(cwd.to_path_buf()))
+            .await
+            .map_err(|e| {
+                sacp::Error::internal_error()
+                    .data(format!("Failed to add recipe extension {}: {}", name, e))
+            })?;
+    }
+
+    agent
+        .apply_recipe_components(recipe.sub_recipes.clone(), recipe.response.clone(), true)
+        .await;
+
+    if let Some(instructions) = &recipe.instructions {
+        agent.extend_system_prompt(instructions.clone()).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn meta(recipe: serde_json::Value) -> Meta {
+        json!({"goose": {"recipe": recipe}})
+            .as_object()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn test_requested_recipe_renders_inline_yaml() {
+        let yaml = "version: 1.0.0\ntitle: Review\ndescription: Review code\n\
+            instructions: Review {{ target }}\nparameters:\n  - key: target\n    \
+            input_type: string\n    requirement: required\n    description: what to review\n";
+        let recipe = requested_recipe(
+            Some(&meta(
+                json!({"yaml": yaml, "params": {"target": "src/lib.rs"}}),
+            )),
+            Path::new("/"),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(recipe.instructions.as_deref(), Some("Review src/lib.rs"));
+    }
+
+    #[test]
+    fn test_requested_recipe_rejects_bad_references() {
+        assert!(requested_recipe(None, Path::new("/")).unwrap().is_none());
+        assert!(requested_recipe(
+            Some(&meta(json!({"path": "a.yaml", "yaml": "title: x"}))),
+            Path::new("/")
+        )
+        .is_err());
+        assert!(requested_recipe(
+            Some(&meta(json!({"path": "definitely-missing.yaml"}))),
+            Path::new("/")
+        )
+        .is_err());
+    }
+}
@@ -1,67 +1,578 @@
 use anyhow::Result;
 use fs_err as fs;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use goose::action_required_manager::ActionRequiredManager;
+use goose::agents::execute_commands::list_commands;
 use goose::agents::extension::{Envs, PLATFORM_EXTENSIONS};
-use goose::agents::{Agent, AgentConfig, ExtensionConfig, SessionConfig};
+use goose::agents::mcp_client::McpClientTrait;
+use goose::agents::types::{RetryConfig, ToolFilter, ToolTimeouts};
+use goose::agents::{Agent, AgentConfig, ExtensionConfig, FileLimits, SessionConfig};
 use goose::config::paths::Paths;
-use goose::config::permission::PermissionManager;
-use goose::config::Config;
-use goose::conversation::message::{ActionRequiredData, Message, MessageContent};
+use goose::config::permission::{PermissionConfig, PermissionLevel, PermissionManager};
+use goose::config::{Config, ConfigError, GooseMode, DEFAULT_EXTENSION_TIMEOUT};
+use goose::conversation::message::{
+    ActionRequiredData, Message, MessageContent, SystemNotificationType,
+};
 use goose::conversation::Conversation;
 use goose::mcp_utils::ToolResult;
+use goose::model::ModelConfig;
+use goose::oauth::{with_authorization_url_handler, AuthorizationUrlHandler};
 use goose::permission::permission_confirmation::PrincipalType;
 use goose::permission::{Permission, PermissionConfirmation};
-use goose::providers::create;
+use goose::providers::base::Provider;
+use goose::providers::canonical::estimate_cost_usd;
+use goose::providers::{
+    create, providers, with_retry_scope, RetryConfig as ProviderRetryConfig, RetryNotice,
+    RetryScope,
+};
+use goose::recipe::Recipe;
+use goose::security::wire_log::{self, Direction};
 use goose::session::session_manager::SessionType;
-use goose::session::{Session, SessionManager};
-use rmcp::model::{CallToolResult, RawContent, ResourceContents, Role};
+use goose::session::{ExtensionState, RetentionPolicy, Session, SessionManager};
+use goose::slash_commands;
+use rmcp::model::{
+    CallToolRequestParams, CallToolResult, RawContent, ResourceContents, Role, ServerNotification,
+};
 use sacp::schema::{
-    AgentCapabilities, AuthenticateRequest, AuthenticateResponse, BlobResourceContents,
-    CancelNotification, Content, ContentBlock, ContentChunk, EmbeddedResource,
+    AgentCapabilities, AuthenticateRequest, AuthenticateResponse, AvailableCommand,
+    AvailableCommandInput, AvailableCommandsUpdate, BlobResourceContents, CancelNotification,
+    Content, ContentBlock, ContentChunk, CurrentModeUpdate, Diff, EmbeddedResource,
     EmbeddedResourceResource, ImageContent, InitializeRequest, InitializeResponse,
-    LoadSessionRequest, LoadSessionResponse, McpCapabilities, McpServer, NewSessionRequest,
-    NewSessionResponse, PermissionOption, PermissionOptionKind, PromptCapabilities, PromptRequest,
-    PromptResponse, RequestPermissionOutcome, RequestPermissionRequest, ResourceLink, SessionId,
-    SessionNotification, SessionUpdate, StopReason, TextContent, TextResourceContents, ToolCall,
-    ToolCallContent, ToolCallId, ToolCallLocation, ToolCallStatus, ToolCallUpdate,
-    ToolCallUpdateFields, ToolKind,
+    ListSessionsRequest, ListSessionsResponse, LoadSessionRequest, LoadSessionResponse,
+    McpCapabilities, McpServer, McpServerSse, Meta, ModelId, ModelInfo, NewSessionRequest,
+    NewSessionResponse, PermissionOption, PermissionOptionKind, Plan, PlanEntry, PlanEntryPriority,
+    PlanEntryStatus, PromptCapabilities, PromptRequest, PromptResponse, RequestPermissionOutcome,
+    RequestPermissionRequest, ResourceLink, SessionCapabilities, SessionId, SessionInfo,
+    SessionInfoUpdate, SessionListCapabilities, SessionMode, SessionModeId, SessionModeState,
+    SessionModelState, SessionNotification, SessionUpdate, SetSessionModeRequest,
+    SetSessionModeResponse, SetSessionModelRequest, SetSessionModelResponse, StopReason,
+    TextContent, TextResourceContents, ToolCall, ToolCallContent, ToolCallId, ToolCallLocation,
+    ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields, ToolKind, UnstructuredCommandInput,
+};
+use sacp::{
+    AgentToClient, ByteStreams, Handled, JrConnectionCx, JrMessageHandler, JrNotification,
+    JrRequest, JrResponsePayload, MessageCx,
 };
-use sacp::{AgentToClient, ByteStreams, Handled, JrConnectionCx, JrMessageHandler, MessageCx};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
 use tokio_util::compat::{TokioAsyncReadCompatExt as _, TokioAsyncWriteCompatExt as _};
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, field, info, info_span, instrument, warn, Instrument, Span};
 use url::Url;
 
+use crate::audit::{
+    self, AuditDecision, AuditLog, AuditOutcome, AuditRecord, PermissionQuery, PermissionRecord,
+};
+use crate::auth::{self, AuthBackend};
+use crate::builtins::Builtins;
+use crate::client_profile::ClientProfile;
+use crate::export::{export_session, ExportFormat};
+use crate::failover::{Backup, FailoverProvider};
+use crate::file_changes::{changes_note, WorkspaceChanges};
+use crate::wire_log::WireLogged;
+use crate::{client_tools, mcp_sse, profile, recipe};
+
+const TODO_WRITE_TOOL: &str = "todo__todo_write";
+
+/// Live tool output keeps only its tail; the complete output arrives with the tool result.
+const MAX_STREAMED_TOOL_OUTPUT: usize = 32 * 1024;
+const SESSION_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 struct GooseAcpSession {
+    agent: Arc<Agent>,
     messages: Conversation,
     tool_requests: HashMap<String, goose::conversation::message::ToolRequest>,
+    /// Output streamed so far by tools that are still running, keyed by tool call id.
+    tool_output: HashMap<String, String>,
+    /// Tools the user allowed for the rest of this session from a permission request.
+    allowed_tools: Arc<Mutex<HashSet<String>>>,
+    /// When each pending tool call was requested, for the audit log.
+    tool_started: HashMap<String, Instant>,
+    /// Diffs for pending file edits, taken before the edit runs so they hold the old contents.
+    tool_diffs: HashMap<String, Vec<Diff>>,
+    /// Permission decisions for pending tool calls; calls that never needed one are missing.
+    tool_decisions: Arc<Mutex<HashMap<String, AuditDecision>>>,
     cancel_token: Option<CancellationToken>,
+    cwd: PathBuf,
+    /// Name of the `GooseAcpConfig::providers` entry this session uses, if not the default.
+    provider: Option<String>,
+    max_turns: Option<u32>,
+    retry_config: Option<RetryConfig>,
+    /// Environment for the session's stdio MCP servers, kept in memory only since it usually
+    /// holds secrets. Clients pass it again on `session/load`.
+    mcp_env: HashMap<String, String>,
+    file_changes: Option<WorkspaceChanges>,
 }
 
 pub struct GooseAcpAgent {
     sessions: Arc<Mutex<HashMap<String, GooseAcpSession>>>,
-    agent: Arc<Agent>,
-    provider: Arc<dyn goose::providers::base::Provider>,
+    session_manager: Arc<SessionManager>,
+    permission_manager: Arc<PermissionManager>,
+    /// Managers for rules granted within a session's cwd, keyed by that cwd.
+    workspace_permissions: Mutex<HashMap<PathBuf, Arc<PermissionManager>>>,
+    config_dir: PathBuf,
+    audit_log: AuditLog,
+    provider: Arc<dyn Provider>,
+    provider_factory: ProviderFactory,
+    models: Vec<String>,
+    providers: HashMap<String, NamedProvider>,
+    builtins: Builtins,
+    goose_mode: GooseMode,
+    client_profile: Mutex<ClientProfile>,
+    auth_backends: Vec<Arc<dyn AuthBackend>>,
+    authenticated: AtomicBool,
+    prompt_limits: PromptLimits,
+    max_parallel_tool_calls: Option<usize>,
+    tool_timeouts: ToolTimeouts,
+    tool_filter: ToolFilter,
+    file_limits: Option<FileLimits>,
+    failover: Vec<String>,
+    provider_retry: Option<ProviderRetryConfig>,
+    watch_files: bool,
+}
+
+/// Creates the configured provider for another model, used by `session/set_model`.
+pub type ProviderFactory =
+    Arc<dyn Fn(ModelConfig) -> BoxFuture<'static, Result<Arc<dyn Provider>>> + Send + Sync>;
+
+/// A provider that `session/new` can select with `"_meta": {"goose": {"provider": "<name>"}}`.
+/// Register it under the provider's own name so loaded sessions come back on it.
+#[derive(Clone)]
+pub struct NamedProvider {
+    pub factory: ProviderFactory,
+    /// New sessions start on the first model.
+    pub models: Vec<String>,
 }
 
 pub struct GooseAcpConfig {
-    pub provider: Arc<dyn goose::providers::base::Provider>,
+    pub provider: Arc<dyn Provider>,
+    pub provider_factory: ProviderFactory,
+    /// Models offered to clients; the provider's own model is always included.
+    pub models: Vec<String>,
+    /// Other providers sessions can opt into, keyed by provider name.
+    pub providers: HashMap<String, NamedProvider>,
     pub builtins: Vec<String>,
+    /// A YAML file whose `builtins` list replaces `builtins` for new sessions while it exists.
+    /// Edits apply from the next session on.
+    pub builtins_file: Option<PathBuf>,
     pub data_dir: std::path::PathBuf,
     pub config_dir: std::path::PathBuf,
-    pub goose_mode: goose::config::GooseMode,
+    pub goose_mode: GooseMode,
+    /// Empty means clients never need to authenticate.
+    pub auth_backends: Vec<Arc<dyn AuthBackend>>,
+    pub prompt_limits: PromptLimits,
+    /// How many tool calls from one model response may run at once, including those run
+    /// through the client. Unlimited when unset.
+    pub max_parallel_tool_calls: Option<usize>,
+    /// Tools that run longer than this fail with a timeout error and the turn carries on.
+    /// Tools the client runs aren't covered.
+    pub tool_timeouts: ToolTimeouts,
+    /// Applies to every session; a session's own filter can narrow it further but not widen it.
+    pub tool_filter: ToolFilter,
+    /// Size and location limits for files that builtin tools read and write. Calls over the
+    /// limits fail instead of running.
+    pub file_limits: Option<FileLimits>,
+    /// `providers` entries to switch to, in order, once a session's provider keeps failing with
+    /// server errors or timeouts. Each starts on its first model.
+    pub failover: Vec<String>,
+    /// How model requests retry rate limits and transient failures; each provider's own policy
+    /// when unset. Clients see a notice while a retry waits.
+    pub provider_retry: Option<ProviderRetryConfig>,
+    /// Stored sessions outside this policy are deleted at startup and then hourly.
+    pub session_retention: Option<RetentionPolicy>,
+    /// Watch each session's cwd and tell the agent which files changed since its last turn.
+    pub watch_files: bool,
+}
+
+/// Caps on a single `session/prompt`, so unattended runs can't loop on tools forever. A turn is
+/// one model response that requested tools. Hitting either cap ends the prompt with
+/// `StopReason::MaxTurnRequests` before the offending tools run.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PromptLimits {
+    pub max_turns: Option<u32>,
+    pub max_tool_calls: Option<u32>,
+}
+
+impl PromptLimits {
+    fn exceeded(&self, turns: u32, tool_calls: u32) -> bool {
+        self.max_turns.is_some_and(|max| turns > max)
+            || self.max_tool_calls.is_some_and(|max| tool_calls > max)
+    }
+}
+
+/// `session/set_model` is still unstable in the ACP schema, so sacp doesn't route it for us.
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "session/set_model", response = SetModelResponse)]
+#[serde(transparent)]
+pub struct SetModelRequest(pub SetSessionModelRequest);
+
+#[derive(Debug, Serialize, Deserialize, JrResponsePayload)]
+#[serde(transparent)]
+pub struct SetModelResponse(pub SetSessionModelResponse);
+
+/// `session/list` is unstable in the ACP schema too. All stored sessions are returned in one
+/// page.
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "session/list", response = SessionListResponse)]
+#[serde(transparent)]
+pub struct SessionListRequest(pub ListSessionsRequest);
+
+#[derive(Debug, Serialize, Deserialize, JrResponsePayload)]
+#[serde(transparent)]
+pub struct SessionListResponse(pub ListSessionsResponse);
+
+/// Attaches an MCP server or a builtin to a running session; exactly one must be set.
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "_goose/extensions/add", response = AddExtensionResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct AddExtensionRequest {
+    pub session_id: SessionId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mcp_server: Option<McpServer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub builtin: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JrResponsePayload)]
+pub struct AddExtensionResponse {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "_goose/extensions/remove", response = RemoveExtensionResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveExtensionRequest {
+    pub session_id: SessionId,
+    pub name: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JrResponsePayload)]
+pub struct RemoveExtensionResponse {}
+
+/// Stops one running tool call, such as a runaway shell command, without cancelling the turn. The
+/// call is reported as failed and the model carries on with the cancellation error.
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "_goose/session/cancel_tool_call", response = CancelToolCallResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelToolCallRequest {
+    pub session_id: SessionId,
+    pub tool_call_id: ToolCallId,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JrResponsePayload)]
+pub struct CancelToolCallResponse {}
+
+/// Asks the user for input that an MCP server requested while one of its tools runs, such as a
+/// missing parameter or a choice between options. The answer must match `requestedSchema`, a
+/// JSON schema for a flat object. Clients that don't support this method just fail it, which
+/// fails the request back to the server.
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "_goose/elicitation/create", response = CreateElicitationResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateElicitationRequest {
+    pub session_id: SessionId,
+    pub message: String,
+    pub requested_schema: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElicitationAction {
+    Accept,
+    Decline,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JrResponsePayload)]
+pub struct CreateElicitationResponse {
+    pub action: ElicitationAction,
+    /// The user's answer when they accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<serde_json::Value>,
+}
+
+/// Sent when goose summarizes a session's older messages to stay within the model's context
+/// window, either automatically or for `/compact`.
+#[derive(Debug, Clone, Serialize, Deserialize, JrNotification)]
+#[notification(method = "_goose/session/compacted")]
+#[serde(rename_all = "camelCase")]
+pub struct SessionCompactedNotification {
+    pub session_id: SessionId,
+    pub messages_before: usize,
+    pub messages_after: usize,
+    /// Tokens the compacted conversation takes up in the context window, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_tokens: Option<i32>,
+}
+
+/// Sent when a session's provider keeps failing and a failover provider answers instead, before
+/// that provider's first response. The session stays on it from then on.
+#[derive(Debug, Clone, Serialize, Deserialize, JrNotification)]
+#[notification(method = "_goose/session/failover")]
+#[serde(rename_all = "camelCase")]
+pub struct SessionFailoverNotification {
+    pub session_id: SessionId,
+    pub provider: String,
+    pub model: String,
+}
+
+/// Returns the audit log of a session's tool calls, oldest first. Works for any session that has
+/// run tools, whether or not it's loaded.
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "_goose/session/audit", response = ReadAuditLogResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadAuditLogRequest {
+    pub session_id: SessionId,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JrResponsePayload)]
+pub struct ReadAuditLogResponse {
+    pub records: Vec<AuditRecord>,
+}
+
+/// Returns the permission prompts raised in any session and how they were answered, oldest
+/// first, narrowed by the query's filters.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JrRequest)]
+#[request(method = "_goose/permissions/audit", response = ReadPermissionAuditResponse)]
+pub struct ReadPermissionAuditRequest {
+    #[serde(flatten)]
+    pub query: PermissionQuery,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JrResponsePayload)]
+pub struct ReadPermissionAuditResponse {
+    pub records: Vec<PermissionRecord>,
+}
+
+/// Turns the redacted wire log of ACP frames and provider bodies on or off.
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "_goose/wire_log", response = SetWireLogResponse)]
+pub struct SetWireLogRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JrResponsePayload)]
+pub struct SetWireLogResponse {}
+
+/// Sent while a request that starts a session's MCP servers waits for the user to authorize one
+/// of them. The request finishes once the user has signed in at `url`; the tokens are kept for
+/// later sessions.
+#[derive(Debug, Clone, Serialize, Deserialize, JrNotification)]
+#[notification(method = "_goose/mcp/authorization_required")]
+#[serde(rename_all = "camelCase")]
+pub struct McpAuthorizationNotification {
+    pub session_id: SessionId,
+    pub server: String,
+    pub url: String,
+}
+
+/// Returns a session's transcript, as Markdown by default. Works for any stored session, whether
+/// or not it's loaded.
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "_goose/session/export", response = ExportSessionResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSessionRequest {
+    pub session_id: SessionId,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JrResponsePayload)]
+pub struct ExportSessionResponse {
+    pub content: String,
+}
+
+/// Copies a stored session's history up to and including `message_id` into a new session, which
+/// the client can then load. Message ids are in the JSON export.
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "_goose/session/fork", response = ForkSessionResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkSessionRequest {
+    pub session_id: SessionId,
+    pub message_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JrResponsePayload)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkSessionResponse {
+    pub session_id: SessionId,
+}
+
+/// Sent after an extension is added or removed, with the session's full tool list.
+#[derive(Debug, Clone, Serialize, Deserialize, JrNotification)]
+#[notification(method = "_goose/tools/list_changed")]
+#[serde(rename_all = "camelCase")]
+pub struct ToolsChangedNotification {
+    pub session_id: SessionId,
+    pub tools: Vec<String>,
 }
 
-fn mcp_server_to_extension_config(mcp_server: McpServer) -> Result<ExtensionConfig, String> {
+/// Extra system-prompt text a client passed as `"_meta": {"goose": {"instructions": "..."}}` on
+/// `session/new`. It sits alongside .goosehints rather than replacing them, and is saved with the
+/// session so `session/load` restores it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionInstructions {
+    text: String,
+}
+
+impl ExtensionState for SessionInstructions {
+    const EXTENSION_NAME: &'static str = "acp_instructions";
+    const VERSION: &'static str = "v0";
+}
+
+/// Tools a client limited a session to with
+/// `"_meta": {"goose": {"tools": {"allow": ["developer"], "deny": ["developer__shell"]}}}` on
+/// `session/new`, saved with the session so `session/load` keeps the limit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+struct SessionToolFilter(ToolFilter);
+
+impl ExtensionState for SessionToolFilter {
+    const EXTENSION_NAME: &'static str = "acp_tool_filter";
+    const VERSION: &'static str = "v0";
+}
+
+fn requested_tool_filter(meta: Option<&Meta>) -> Result<Option<SessionToolFilter>, sacp::Error> {
+    let Some(tools) = meta
+        .and_then(|meta| meta.get("goose"))
+        .and_then(|goose| goose.get("tools"))
+    else {
+        return Ok(None);
+    };
+    let filter: ToolFilter = serde_json::from_value(tools.clone())
+        .map_err(|e| sacp::Error::invalid_params().data(format!("Invalid tool filter: {}", e)))?;
+    Ok(Some(SessionToolFilter(filter)).filter(|filter| filter.0 != ToolFilter::default()))
+}
+
+/// Where permissions granted in a session are kept. Clients start a scratch session with
+/// `"_meta": {"goose": {"permissionScope": "session"}}` on `session/new` so its grants last only
+/// as long as the session; the scope is saved with the session so `session/load` keeps it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PermissionScope {
+    #[default]
+    Workspace,
+    Session,
+}
+
+impl ExtensionState for PermissionScope {
+    const EXTENSION_NAME: &'static str = "acp_permission_scope";
+    const VERSION: &'static str = "v0";
+}
+
+fn requested_permission_scope(meta: Option<&Meta>) -> Result<PermissionScope, sacp::Error> {
+    let Some(scope) = meta
+        .and_then(|meta| meta.get("goose"))
+        .and_then(|goose| goose.get("permissionScope"))
+    else {
+        return Ok(PermissionScope::default());
+    };
+    serde_json::from_value(scope.clone())
+        .map_err(|e| sacp::Error::invalid_params().data(format!("Invalid permission scope: {}", e)))
+}
+
+/// Tags from `"_meta": {"goose": {"tags": {"project": "web"}}}`, stored on new sessions and used
+/// to filter `session/list`.
+fn requested_tags(meta: Option<&Meta>) -> Result<HashMap<String, String>, sacp::Error> {
+    let Some(tags) = meta
+        .and_then(|meta| meta.get("goose"))
+        .and_then(|goose| goose.get("tags"))
+    else {
+        return Ok(HashMap::new());
+    };
+    serde_json::from_value(tags.clone())
+        .map_err(|e| sacp::Error::invalid_params().data(format!("Invalid tags: {}", e)))
+}
+
+fn requested_instructions(meta: Option<&Meta>) -> Option<SessionInstructions> {
+    meta.and_then(|meta| meta.get("goose"))
+        .and_then(|goose| goose.get("instructions"))
+        .and_then(|instructions| instructions.as_str())
+        .filter(|text| !text.trim().is_empty())
+        .map(|text| SessionInstructions {
+            text: text.to_string(),
+        })
+}
+
+/// Generation settings a client can change for a single `session/prompt` with
+/// `"_meta": {"goose": {"temperature": 0.2, "maxTokens": 4096, "reasoningEffort": "high"}}`.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationOverrides {
+    temperature: Option<f32>,
+    max_tokens: Option<i32>,
+    reasoning_effort: Option<String>,
+}
+
+const REASONING_EFFORTS: [&str; 3] = ["low", "medium", "high"];
+
+fn requested_overrides(meta: Option<&Meta>) -> Result<Option<GenerationOverrides>, sacp::Error> {
+    let Some(goose) = meta.and_then(|meta| meta.get("goose")) else {
+        return Ok(None);
+    };
+    let overrides: GenerationOverrides = serde_json::from_value(goose.clone()).map_err(|e| {
+        sacp::Error::invalid_params().data(format!("Invalid generation overrides: {}", e))
+    })?;
+    if let Some(effort) = &overrides.reasoning_effort {
+        if !REASONING_EFFORTS.contains(&effort.as_str()) {
+            return Err(
+                sacp::Error::invalid_params().data(format!("Unknown reasoning effort: {}", effort))
+            );
+        }
+    }
+    Ok(Some(overrides).filter(|overrides| *overrides != GenerationOverrides::default()))
+}
+
+/// Environment variables a client passed as `"_meta": {"goose": {"mcpEnv": {"API_KEY": "..."}}}`
+/// for every stdio MCP server the session starts. Variables that steer how programs load, like
+/// `PATH` or `LD_PRELOAD`, are refused just as they are in extension configs; since `PATH` is
+/// the one clients most often try to extend, it gets an error saying what to do instead.
+fn requested_mcp_env(meta: Option<&Meta>) -> Result<HashMap<String, String>, sacp::Error> {
+    let Some(env) = meta
+        .and_then(|meta| meta.get("goose"))
+        .and_then(|goose| goose.get("mcpEnv"))
+    else {
+        return Ok(HashMap::new());
+    };
+    let envs: Envs = serde_json::from_value(env.clone()).map_err(|e| {
+        sacp::Error::invalid_params().data(format!("Invalid MCP environment: {}", e))
+    })?;
+    if envs
+        .get_env()
+        .keys()
+        .any(|key| key.eq_ignore_ascii_case("PATH"))
+    {
+        return Err(sacp::Error::invalid_params().data(
+            "PATH cannot be set through mcpEnv; give the MCP server's command as an absolute path instead",
+        ));
+    }
+    envs.validate()
+        .map_err(|e| sacp::Error::invalid_params().data(e.to_string()))?;
+    Ok(envs.get_env())
+}
+
+/// A stdio server's own variables win over `session_env`.
+fn mcp_server_to_extension_config(
+    mcp_server: McpServer,
+    session_env: &HashMap<String, String>,
+) -> Result<ExtensionConfig, String> {
     match mcp_server {
         McpServer::Stdio(stdio) => Ok(ExtensionConfig::Stdio {
             name: stdio.name,
             description: String::new(),
             cmd: stdio.command.to_string_lossy().to_string(),
             args: stdio.args,
-            envs: Envs::new(stdio.env.into_iter().map(|e| (e.name, e.value)).collect()),
+            envs: Envs::new(
+                session_env
+                    .clone()
+                    .into_iter()
+                    .chain(stdio.env.into_iter().map(|e| (e.name, e.value)))
+                    .collect(),
+            ),
             env_keys: vec![],
             timeout: None,
             bundled: Some(false),
@@ -82,7 +593,6 @@ fn mcp_server_to_extension_config(mcp_server: McpServer) -> Result<ExtensionConf
             bundled: Some(false),
             available_tools: vec![],
         }),
-        McpServer::Sse(_) => Err("SSE is unsupported, migrate to streamable_http".to_string()),
         _ => Err("Unknown MCP server type".to_string()),
     }
 }
@@ -169,6 +679,137 @@ fn extract_tool_locations(
     locations
 }
 
+/// Files a tool call is about to touch, known before it runs so clients can follow along.
+fn request_locations(tool_call: &CallToolRequestParams, cwd: &Path) -> Vec<ToolCallLocation> {
+    let arg = |name: &str| tool_call.arguments.as_ref().and_then(|args| args.get(name));
+    match &*tool_call.name {
+        "developer__text_editor" | "editor__read_text_file" | "editor__write_text_file" => {
+            let line = arg("line")
+                .or_else(|| arg("insert_line"))
+                .and_then(|line| line.as_u64())
+                .map(|line| line as u32);
+            arg("path")
+                .and_then(|path| path.as_str())
+                .map(|path| vec![ToolCallLocation::new(cwd.join(path)).line(line)])
+                .unwrap_or_default()
+        }
+        "developer__shell" | "editor__shell" => arg("command")
+            .and_then(|command| command.as_str())
+            .map(|command| shell_file_locations(command, cwd))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn shell_file_locations(command: &str, cwd: &Path) -> Vec<ToolCallLocation> {
+    let words = shlex::split(command)
+        .unwrap_or_else(|| command.split_whitespace().map(str::to_string).collect());
+    let mut paths: Vec<PathBuf> = words
+        .iter()
+        .filter(|word| !word.starts_with('-'))
+        .map(|word| cwd.join(word))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.dedup();
+    paths.into_iter().map(ToolCallLocation::new).collect()
+}
+
+fn tool_call_status(tool_response: &goose::conversation::message::ToolResponse) -> ToolCallStatus {
+    match &tool_response.tool_result {
+        Ok(result) if result.is_error == Some(true) => ToolCallStatus::Failed,
+        Ok(_) => ToolCallStatus::Completed,
+        Err(_) => ToolCallStatus::Failed,
+    }
+}
+
+/// Structured diffs for a file edit, rebuilt from the edit's arguments so clients can show a
+/// proper review instead of the tool's text summary. Call it before the edit runs: a file that
+/// gets overwritten is read here for the diff's old text.
+fn edit_diffs(tool_call: &CallToolRequestParams, cwd: &Path) -> Vec<Diff> {
+    let arg = |name: &str| {
+        tool_call
+            .arguments
+            .as_ref()
+            .and_then(|args| args.get(name))
+            .and_then(|value| value.as_str())
+    };
+    let Some(path) = arg("path").map(|path| cwd.join(path)) else {
+        return Vec::new();
+    };
+    let overwrite = |text: &str| {
+        let diff = Diff::new(&path, text);
+        match std::fs::read_to_string(&path) {
+            Ok(old) => vec![diff.old_text(old)],
+            Err(_) => vec![diff],
+        }
+    };
+
+    match (&*tool_call.name, arg("command")) {
+        ("developer__text_editor", Some("write")) => {
+            arg("file_text").map(overwrite).unwrap_or_default()
+        }
+        ("developer__text_editor", Some("str_replace")) => {
+            match (arg("diff"), arg("old_str"), arg("new_str")) {
+                (Some(diff), _, _) => unified_diff_edits(&path, diff),
+                (None, Some(old), Some(new)) => {
+                    vec![Diff::new(&path, new).old_text(old.to_string())]
+                }
+                _ => Vec::new(),
+            }
+        }
+        ("editor__write_text_file", _) => arg("content").map(overwrite).unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Splits a unified diff into the old and new text of its hunks, one `Diff` per file. Paths
+/// resolve the same way the developer extension applies them: against `base_path`, or its
+/// parent when it names a file.
+fn unified_diff_edits(base_path: &Path, diff: &str) -> Vec<Diff> {
+    let base_dir = if base_path.is_file() {
+        base_path.parent().unwrap_or(base_path)
+    } else {
+        base_path
+    };
+
+    let mut diffs = Vec::new();
+    let mut current: Option<(PathBuf, String, String)> = None;
+    for line in diff.lines() {
+        if let Some(target) = line.strip_prefix("+++ ") {
+            diffs.extend(current.take());
+            let target = target.split('\t').next().unwrap_or(target).trim();
+            let target = target.strip_prefix("b/").unwrap_or(target);
+            current = Some((base_dir.join(target), String::new(), String::new()));
+            continue;
+        }
+        let Some((_, old, new)) = current.as_mut() else {
+            continue;
+        };
+        if line.starts_with("@@") || line.starts_with("--- ") {
+            continue;
+        }
+        if let Some(removed) = line.strip_prefix('-') {
+            old.push_str(removed);
+            old.push('\n');
+        } else if let Some(added) = line.strip_prefix('+') {
+            new.push_str(added);
+            new.push('\n');
+        } else {
+            let context = line.strip_prefix(' ').unwrap_or(line);
+            old.push_str(context);
+            old.push('\n');
+            new.push_str(context);
+            new.push('\n');
+        }
+    }
+    diffs.extend(current);
+
+    diffs
+        .into_iter()
+        .map(|(path, old, new)| Diff::new(path, new).old_text(old))
+        .collect()
+}
+
 fn extract_view_line_range(text: &str) -> Option<(usize, usize)> {
     // Pattern: "(lines X-Y)" or "(lines X-end)"
     let re = regex::Regex::new(r"\(lines (\d+)-(\d+|end)\)").ok()?;
@@ -250,33 +891,358 @@ fn format_tool_name(tool_name: &str) -> String {
     }
 }
 
-async fn add_builtins(agent: &Agent, builtins: Vec<String>) {
-    for builtin in builtins {
-        let config = if PLATFORM_EXTENSIONS.contains_key(builtin.as_str()) {
-            ExtensionConfig::Platform {
-                name: builtin.clone(),
-                bundled: None,
-                description: builtin.clone(),
-                available_tools: Vec::new(),
-            }
-        } else {
-            ExtensionConfig::Builtin {
-                name: builtin.clone(),
-                display_name: None,
-                timeout: None,
-                bundled: None,
-                description: builtin.clone(),
-                available_tools: Vec::new(),
+/// Translates the todo extension's markdown checklist into ACP plan entries.
+fn plan_from_todo(content: &str) -> Plan {
+    let entries = content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_start();
+            let item = line
+                .strip_prefix("- [")
+                .or_else(|| line.strip_prefix("* ["))?;
+            let (marker, text) = item.split_once(']')?;
+            let status = match marker {
+                "x" | "X" => PlanEntryStatus::Completed,
+                " " | "" => PlanEntryStatus::Pending,
+                _ => PlanEntryStatus::InProgress,
+            };
+            Some(PlanEntry::new(
+                text.trim(),
+                PlanEntryPriority::Medium,
+                status,
+            ))
+        })
+        .collect();
+    Plan::new(entries)
+}
+
+/// A line of live output from a running tool, as the developer shell sends it.
+fn tool_output_line(notification: &ServerNotification) -> Option<&str> {
+    let ServerNotification::LoggingMessageNotification(log) = notification else {
+        return None;
+    };
+    let data = &log.params.data;
+    if data.get("type").and_then(|t| t.as_str()) != Some("shell_output") {
+        return None;
+    }
+    data.get("output").and_then(|output| output.as_str())
+}
+
+fn append_tool_output(output: &mut String, line: &str) {
+    output.push_str(line);
+    output.push('\n');
+    if output.len() > MAX_STREAMED_TOOL_OUTPUT {
+        let mut cut = output.len() - MAX_STREAMED_TOOL_OUTPUT;
+        while !output.is_char_boundary(cut) {
+            cut += 1;
+        }
+        output.drain(..cut);
+    }
+}
+
+/// Token usage for the turn between two snapshots of the session, reported under
+/// `_meta.goose.usage` so clients can show running costs. Summarizing for compaction counts
+/// towards the turn, and `contextTokens` is what the conversation fills afterwards.
+fn usage_meta(before: &Session, after: &Session) -> Meta {
+    let tokens = |session: &Session| {
+        (
+            session.accumulated_input_tokens.unwrap_or(0),
+            session.accumulated_output_tokens.unwrap_or(0),
+        )
+    };
+    let (input_before, output_before) = tokens(before);
+    let (input_after, output_after) = tokens(after);
+    let input_tokens = (input_after - input_before).max(0) as usize;
+    let output_tokens = (output_after - output_before).max(0) as usize;
+
+    let mut usage = serde_json::json!({
+        "inputTokens": input_tokens,
+        "outputTokens": output_tokens,
+        "totalTokens": input_tokens + output_tokens,
+        "accumulatedTotalTokens": after.accumulated_total_tokens.unwrap_or(0),
+        "contextTokens": after.total_tokens.unwrap_or(0),
+    });
+    let cost = after
+        .provider_name
+        .as_deref()
+        .zip(after.model_config.as_ref())
+        .and_then(|(provider, model_config)| {
+            estimate_cost_usd(
+                provider,
+                &model_config.model_name,
+                input_tokens,
+                output_tokens,
+            )
+        });
+    if let Some(cost) = cost {
+        usage["costUsd"] = serde_json::json!(cost);
+    }
+
+    Meta::from_iter([("goose".to_string(), serde_json::json!({ "usage": usage }))])
+}
+
+fn session_info_update(session: &Session) -> SessionUpdate {
+    SessionUpdate::SessionInfoUpdate(
+        SessionInfoUpdate::new()
+            .title(session.name.clone())
+            .updated_at(session.updated_at.to_rfc3339()),
+    )
+}
+
+fn mode_id(mode: GooseMode) -> &'static str {
+    match mode {
+        GooseMode::Auto => "auto",
+        GooseMode::Approve => "approve",
+        GooseMode::SmartApprove => "smart_approve",
+        GooseMode::Chat => "chat",
+    }
+}
+
+fn session_mode_state(current: GooseMode) -> SessionModeState {
+    let available_modes = [
+        (
+            GooseMode::Auto,
+            "Auto",
+            "Run tools without asking for approval",
+        ),
+        (
+            GooseMode::Approve,
+            "Approve",
+            "Ask for approval before running any tool",
+        ),
+        (
+            GooseMode::SmartApprove,
+            "Smart Approve",
+            "Ask for approval only before tools that may have side effects",
+        ),
+        (GooseMode::Chat, "Chat", "Chat without running any tools"),
+    ]
+    .into_iter()
+    .map(|(mode, name, description)| {
+        SessionMode::new(SessionModeId::new(mode_id(mode)), name).description(description)
+    })
+    .collect();
+
+    SessionModeState::new(SessionModeId::new(mode_id(current)), available_modes)
+}
+
+/// The session's model settings carried over to `model_name`, so switching models keeps the
+/// temperature, token limits and the rest of what the session was configured with.
+fn switched_model_config(mut current: ModelConfig, model_name: &str) -> ModelConfig {
+    current.model_name = model_name.to_string();
+    current
+}
+
+fn available_commands() -> Vec<AvailableCommand> {
+    let agent_commands = list_commands()
+        .iter()
+        .map(|command| AvailableCommand::new(command.name, command.description));
+    let mode_command = AvailableCommand::new("mode", "Switch the session mode").input(
+        AvailableCommandInput::Unstructured(UnstructuredCommandInput::new(
+            "auto | approve | smart_approve | chat",
+        )),
+    );
+    let recipe_commands = slash_commands::list_commands().into_iter().map(|mapping| {
+        AvailableCommand::new(
+            mapping.command,
+            format!("Run recipe {}", mapping.recipe_path),
+        )
+        .input(AvailableCommandInput::Unstructured(
+            UnstructuredCommandInput::new("recipe parameters"),
+        ))
+    });
+
+    agent_commands
+        .chain(std::iter::once(mode_command))
+        .chain(recipe_commands)
+        .collect()
+}
+
+/// Stdio servers are spawned in the client's working directory rather than goose's own.
+async fn add_mcp_servers(
+    agent: &Agent,
+    mcp_servers: Vec<McpServer>,
+    cwd: &Path,
+    mcp_env: &HashMap<String, String>,
+    session_id: &str,
+    cx: &JrConnectionCx<AgentToClient>,
+) -> Result<(), sacp::Error> {
+    let session_id = SessionId::new(session_id);
+    let cx = cx.clone();
+    let show_url: AuthorizationUrlHandler = Arc::new(move |server, url| {
+        info!(server, "waiting for MCP server authorization");
+        let notification = McpAuthorizationNotification {
+            session_id: session_id.clone(),
+            server: server.to_string(),
+            url: url.to_string(),
+        };
+        if let Err(e) = cx.send_notification(notification) {
+            warn!(server, error = ?e, "failed to send authorization URL");
+        }
+    });
+    with_authorization_url_handler(
+        show_url,
+        connect_mcp_servers(agent, mcp_servers, cwd, mcp_env),
+    )
+    .await
+}
+
+async fn connect_mcp_servers(
+    agent: &Agent,
+    mcp_servers: Vec<McpServer>,
+    cwd: &Path,
+    mcp_env: &HashMap<String, String>,
+) -> Result<(), sacp::Error> {
+    for mcp_server in mcp_servers {
+        if let McpServer::Sse(sse) = mcp_server {
+            add_sse_server(agent, sse).await?;
+            continue;
+        }
+        let config = match mcp_server_to_extension_config(mcp_server, mcp_env) {
+            Ok(c) => c,
+            Err(msg) => {
+                return Err(sacp::Error::invalid_params().data(msg));
             }
         };
+        let name = config.name().to_string();
+        if let Err(e) = agent
+            .add_extension_with_working_dir(config, Some(cwd.to_path_buf()))
+            .await
+        {
+            return Err(sacp::Error::internal_error()
+                .data(format!("Failed to add MCP server '{}': {}", name, e)));
+        }
+    }
+    Ok(())
+}
+
+/// Legacy SSE servers are connected here since the extension manager only speaks streamable HTTP.
+async fn add_sse_server(agent: &Agent, sse: McpServerSse) -> Result<(), sacp::Error> {
+    let headers: Vec<_> = sse.headers.into_iter().map(|h| (h.name, h.value)).collect();
+    let client = mcp_sse::connect(
+        &sse.url,
+        &headers,
+        Duration::from_secs(DEFAULT_EXTENSION_TIMEOUT),
+        agent.extension_manager.get_provider().clone(),
+    )
+    .await
+    .map_err(|e| {
+        sacp::Error::internal_error()
+            .data(format!("Failed to add MCP server '{}': {}", sse.name, e))
+    })?;
+    let info = client.get_info().cloned();
+    // goose hides every tool of an `Sse` extension, so the client is filed as the HTTP server
+    // it is. ACP sessions never reconnect extensions from this config.
+    let config = ExtensionConfig::streamable_http(
+        sse.name.clone(),
+        sse.url,
+        String::new(),
+        DEFAULT_EXTENSION_TIMEOUT,
+    );
+    let client: Box<dyn McpClientTrait> = Box::new(client);
+    agent
+        .extension_manager
+        .add_client(sse.name, config, Arc::new(Mutex::new(client)), info, None)
+        .await;
+    Ok(())
+}
 
-        match agent.add_extension(config).await {
+/// Session extensions resolve relative paths against the client's cwd, so it has to be usable.
+fn validate_cwd(cwd: &Path) -> Result<(), sacp::Error> {
+    if !cwd.is_absolute() {
+        return Err(sacp::Error::invalid_params()
+            .data(format!("cwd must be an absolute path: {}", cwd.display())));
+    }
+    if !cwd.is_dir() {
+        return Err(sacp::Error::invalid_params()
+            .data(format!("cwd is not a directory: {}", cwd.display())));
+    }
+    Ok(())
+}
+
+fn builtin_extension_config(builtin: &str) -> ExtensionConfig {
+    if PLATFORM_EXTENSIONS.contains_key(builtin) {
+        ExtensionConfig::Platform {
+            name: builtin.to_string(),
+            bundled: None,
+            description: builtin.to_string(),
+            available_tools: Vec::new(),
+        }
+    } else {
+        ExtensionConfig::Builtin {
+            name: builtin.to_string(),
+            display_name: None,
+            timeout: None,
+            bundled: None,
+            description: builtin.to_string(),
+            available_tools: Vec::new(),
+        }
+    }
+}
+
+async fn add_builtins(agent: &Agent, builtins: Vec<String>, cwd: &Path) {
+    for builtin in builtins {
+        let config = builtin_extension_config(&builtin);
+        match agent
+            .add_extension_with_working_dir(config, Some(cwd.to_path_buf()))
+            .await
+        {
             Ok(_) => info!(extension = %builtin, "extension loaded"),
             Err(e) => warn!(extension = %builtin, error = %e, "extension load failed"),
         }
     }
 }
 
+fn retry_notice_text(notice: &RetryNotice) -> String {
+    let reason = if notice.rate_limited {
+        "Rate limited"
+    } else {
+        "Model request failed"
+    };
+    format!(
+        "{}, retrying in {}s (attempt {} of {})",
+        reason,
+        notice.delay.as_secs_f64().ceil().max(1.0) as u64,
+        notice.attempt,
+        notice.max_retries
+    )
+}
+
+async fn answering_model(agent: &Agent) -> Option<(String, String)> {
+    let provider = agent.provider().await.ok()?;
+    Some((
+        provider.get_name().to_string(),
+        provider.get_model_config().model_name,
+    ))
+}
+
+/// Registers each named goose provider on its default model, so it can back up the default.
+async fn failover_providers(names: &[String]) -> Result<HashMap<String, NamedProvider>> {
+    let all = providers().await;
+    let mut named = HashMap::new();
+    for name in names {
+        let (metadata, _) = all
+            .iter()
+            .find(|(metadata, _)| &metadata.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown failover provider: {}", name))?;
+        let mut models = vec![metadata.default_model.clone()];
+        models.extend(
+            metadata
+                .known_models
+                .iter()
+                .map(|model| model.name.clone())
+                .filter(|model| model != &metadata.default_model),
+        );
+        let provider_name = name.clone();
+        let factory: ProviderFactory = Arc::new(move |model_config: ModelConfig| {
+            let provider_name = provider_name.clone();
+            async move { create(&provider_name, model_config).await }.boxed()
+        });
+        named.insert(name.clone(), NamedProvider { factory, models });
+    }
+    Ok(named)
+}
+
 impl GooseAcpAgent {
     pub async fn new(builtins: Vec<String>) -> Result<Self> {
         let config = Config::global();
@@ -298,64 +1264,410 @@ impl GooseAcpAgent {
             toolshim_model: None,
             fast_model: None,
             request_params: None,
+            reasoning_effort: None,
+            response_schema: None,
+            stop_sequences: None,
         };
         let provider = create(&provider_name, model_config).await?;
-        let goose_mode = config
-            .get_goose_mode()
-            .unwrap_or(goose::config::GooseMode::Auto);
+        let goose_mode = config.get_goose_mode().unwrap_or(GooseMode::Auto);
+
+        let metadata = providers()
+            .await
+            .into_iter()
+            .find(|(metadata, _)| metadata.name == provider_name)
+            .map(|(metadata, _)| metadata);
+        let auth_backends = metadata
+            .as_ref()
+            .map(|metadata| auth::provider_backends(metadata, provider.clone()))
+            .unwrap_or_default();
+        let models = metadata
+            .map(|metadata| metadata.known_models.into_iter().map(|m| m.name).collect())
+            .unwrap_or_default();
+        let provider_factory: ProviderFactory = Arc::new(move |model_config: ModelConfig| {
+            let provider_name = provider_name.clone();
+            async move { create(&provider_name, model_config).await }.boxed()
+        });
+
+        let failover: Vec<String> = config
+            .get_param("GOOSE_ACP_FAILOVER_PROVIDERS")
+            .unwrap_or_default();
+        let failover_providers = failover_providers(&failover).await?;
+        // File limits restrict the client, so a value that can't be parsed must not lift them.
+        let file_limits = match config.get_param("GOOSE_ACP_FILE_LIMITS") {
+            Ok(file_limits) => Some(file_limits),
+            Err(ConfigError::NotFound(_)) => None,
+            Err(e) => anyhow::bail!("Invalid GOOSE_ACP_FILE_LIMITS: {}", e),
+        };
 
         Self::with_config(GooseAcpConfig {
             provider,
+            provider_factory,
+            models,
+            providers: failover_providers,
             builtins,
+            builtins_file: config.get_param("GOOSE_ACP_BUILTINS_FILE").ok(),
             data_dir: Paths::data_dir(),
             config_dir: Paths::config_dir(),
             goose_mode,
+            auth_backends,
+            prompt_limits: PromptLimits {
+                max_turns: config.get_param("GOOSE_ACP_MAX_TURNS").ok(),
+                max_tool_calls: config.get_param("GOOSE_ACP_MAX_TOOL_CALLS").ok(),
+            },
+            max_parallel_tool_calls: config.get_param("GOOSE_ACP_MAX_PARALLEL_TOOL_CALLS").ok(),
+            tool_timeouts: config
+                .get_param("GOOSE_ACP_TOOL_TIMEOUTS")
+                .unwrap_or_default(),
+            tool_filter: config
+                .get_param("GOOSE_ACP_TOOL_FILTER")
+                .unwrap_or_default(),
+            file_limits,
+            failover,
+            provider_retry: config.get_param("GOOSE_ACP_PROVIDER_RETRY").ok(),
+            session_retention: config.get_param("GOOSE_SESSION_RETENTION").ok(),
+            watch_files: config.get_param("GOOSE_ACP_WATCH_FILES").unwrap_or(false),
         })
         .await
     }
 
     pub async fn with_config(config: GooseAcpConfig) -> Result<Self> {
-        let session_manager = Arc::new(SessionManager::new(config.data_dir));
-        let permission_manager = Arc::new(PermissionManager::new(config.config_dir));
-
-        let agent = Agent::with_config(AgentConfig::new(
-            Arc::clone(&session_manager),
-            permission_manager,
-            None,
-            config.goose_mode,
-        ));
+        let mut models = config.models;
+        let default_model = config.provider.get_model_config().model_name;
+        if !models.contains(&default_model) {
+            models.insert(0, default_model);
+        }
 
-        let agent_ptr = Arc::new(agent);
+        if let Some((name, _)) = config
+            .providers
+            .iter()
+            .find(|(_, provider)| provider.models.is_empty())
+        {
+            anyhow::bail!("Provider {} has no models", name);
+        }
+        if let Some(name) = config
+            .failover
+            .iter()
+            .find(|name| !config.providers.contains_key(*name))
+        {
+            anyhow::bail!("Failover provider {} is not configured", name);
+        }
 
-        add_builtins(&agent_ptr, config.builtins).await;
+        let session_manager = Arc::new(SessionManager::new(config.data_dir.clone()));
+        let sessions: Arc<Mutex<HashMap<String, GooseAcpSession>>> = Arc::default();
+        if let Some(policy) = config.session_retention {
+            let sessions = sessions.clone();
+            session_manager.spawn_pruning(policy, SESSION_PRUNE_INTERVAL, move || {
+                let sessions = sessions.clone();
+                async move { sessions.lock().await.keys().cloned().collect() }
+            });
+        }
 
         Ok(Self {
-            provider: config.provider.clone(),
-            sessions: Arc::new(Mutex::new(HashMap::new())),
-            agent: agent_ptr,
+            sessions,
+            audit_log: AuditLog::new(&config.data_dir),
+            session_manager,
+            permission_manager: Arc::new(PermissionManager::new(config.config_dir.clone())),
+            workspace_permissions: Mutex::new(HashMap::new()),
+            config_dir: config.config_dir,
+            provider: config.provider,
+            provider_factory: config.provider_factory,
+            models,
+            providers: config.providers,
+            builtins: Builtins::new(config.builtins, config.builtins_file),
+            goose_mode: config.goose_mode,
+            client_profile: Mutex::new(ClientProfile::default()),
+            authenticated: AtomicBool::new(config.auth_backends.is_empty()),
+            auth_backends: config.auth_backends,
+            prompt_limits: config.prompt_limits,
+            max_parallel_tool_calls: config.max_parallel_tool_calls,
+            tool_timeouts: config.tool_timeouts,
+            tool_filter: config.tool_filter,
+            file_limits: config.file_limits,
+            failover: config.failover,
+            provider_retry: config.provider_retry,
+            watch_files: config.watch_files,
         })
     }
 
-    fn convert_acp_prompt_to_message(&self, prompt: Vec<ContentBlock>) -> Message {
-        let mut user_message = Message::user();
+    fn watch_workspace(&self, cwd: &Path) -> Option<WorkspaceChanges> {
+        if !self.watch_files {
+            return None;
+        }
+        WorkspaceChanges::watch(cwd)
+            .inspect_err(|e| warn!(cwd = %cwd.display(), error = %e, "failed to watch workspace"))
+            .ok()
+    }
 
-        // Process all content blocks from the prompt
-        for block in prompt {
-            match block {
-                ContentBlock::Text(text) => {
-                    user_message = user_message.with_text(&text.text);
-                }
-                ContentBlock::Image(image) => {
-                    // Goose supports images via base64 encoded data
-                    // The ACP ImageContent has data as a String directly
-                    user_message = user_message.with_image(&image.data, &image.mime_type);
-                }
-                ContentBlock::Resource(resource) => {
-                    // Embed resource content as text with context
-                    match &resource.resource {
-                        EmbeddedResourceResource::TextResourceContents(text_resource) => {
-                            let header = format!("--- Resource: {} ---\n", text_resource.uri);
-                            let content = format!("{}{}\n---\n", header, text_resource.text);
+    async fn ensure_authenticated(&self) -> Result<(), sacp::Error> {
+        if self.authenticated.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        for backend in &self.auth_backends {
+            if backend.is_authenticated().await {
+                self.authenticated.store(true, Ordering::SeqCst);
+                return Ok(());
+            }
+        }
+        Err(sacp::Error::auth_required())
+    }
+
+    /// Rules granted in a session are stored for its cwd, so allowing a tool in one repo doesn't
+    /// allow it in another. The global permission.yaml still applies wherever the workspace has
+    /// no rule of its own.
+    async fn workspace_permission_manager(&self, cwd: &Path) -> Arc<PermissionManager> {
+        let mut managers = self.workspace_permissions.lock().await;
+        managers
+            .entry(cwd.to_path_buf())
+            .or_insert_with(|| {
+                Arc::new(PermissionManager::for_workspace(
+                    self.config_dir.clone(),
+                    cwd,
+                    Arc::clone(&self.permission_manager),
+                ))
+            })
+            .clone()
+    }
+
+    /// Each ACP session gets its own agent so extensions, provider and mode don't leak across sessions.
+    async fn create_agent(
+        &self,
+        goose_session: &Session,
+        cwd: &Path,
+        tool_filter: Option<&SessionToolFilter>,
+        permission_scope: PermissionScope,
+        profile_permissions: Option<&PermissionConfig>,
+    ) -> Result<Arc<Agent>, sacp::Error> {
+        let client_profile = self.client_profile.lock().await.clone();
+        let mut permission_manager = self.workspace_permission_manager(cwd).await;
+        // A profile's rules sit in a layer of their own, so grants made in the session stay in
+        // memory too rather than landing next to them in a file
+        if let Some(rules) = profile_permissions {
+            permission_manager = Arc::new(
+                PermissionManager::for_session(permission_manager).with_user_rules(rules.clone()),
+            );
+        } else if permission_scope == PermissionScope::Session {
+            permission_manager = Arc::new(PermissionManager::for_session(permission_manager));
+        }
+        let mut agent_config = AgentConfig::new(
+            Arc::clone(&self.session_manager),
+            permission_manager,
+            None,
+            self.goose_mode,
+        )
+        .with_tool_timeouts(self.tool_timeouts.clone())
+        .with_tool_filter(self.tool_filter.clone())
+        .with_tool_filter(client_tools::replaced_tools(&client_profile));
+        if let Some(file_limits) = &self.file_limits {
+            agent_config = agent_config.with_file_limits(file_limits.clone());
+        }
+        if let Some(SessionToolFilter(filter)) = tool_filter {
+            agent_config = agent_config.with_tool_filter(filter.clone());
+        }
+        if let Some(limit) = self.max_parallel_tool_calls {
+            agent_config = agent_config.with_max_parallel_tool_calls(limit);
+        }
+        let agent = Arc::new(Agent::with_config(agent_config));
+        add_builtins(&agent, self.builtins.current(), cwd).await;
+        if let Some(config) = client_tools::extension_config(&client_profile) {
+            agent.add_extension(config).await.map_err(|e| {
+                sacp::Error::internal_error().data(format!("Failed to add client tools: {}", e))
+            })?;
+        }
+        agent
+            .update_provider(self.with_failover(self.provider.clone()), &goose_session.id)
+            .await
+            .map_err(|e| {
+                sacp::Error::internal_error().data(format!("Failed to set provider: {}", e))
+            })?;
+        Ok(agent)
+    }
+
+    /// Adds the failover providers behind `provider`, skipping `provider` itself.
+    fn with_failover(&self, provider: Arc<dyn Provider>) -> Arc<dyn Provider> {
+        let backups: Vec<Backup> = self
+            .failover
+            .iter()
+            .filter(|name| name.as_str() != provider.get_name())
+            .filter_map(|name| {
+                let named = self.providers.get(name)?;
+                Some(Backup {
+                    name: name.clone(),
+                    factory: named.factory.clone(),
+                    model: named.models[0].clone(),
+                })
+            })
+            .collect();
+        if backups.is_empty() {
+            return provider;
+        }
+        Arc::new(FailoverProvider::new(provider, backups))
+    }
+
+    /// The factory and models for a session's provider; `None` is the default provider.
+    fn provider_models(&self, provider: Option<&str>) -> (&ProviderFactory, &[String]) {
+        match provider.and_then(|name| self.providers.get(name)) {
+            Some(named) => (&named.factory, &named.models),
+            None => (&self.provider_factory, &self.models),
+        }
+    }
+
+    /// The model a session starts on: `preferred` when its provider offers it, otherwise the
+    /// provider's default.
+    fn initial_model(&self, provider: Option<&str>, preferred: Option<&str>) -> String {
+        let (_, models) = self.provider_models(provider);
+        match (preferred, provider) {
+            (Some(model), _) if models.iter().any(|m| m == model) => model.to_string(),
+            (_, Some(_)) => models[0].clone(),
+            (_, None) => self.provider.get_model_config().model_name,
+        }
+    }
+
+    /// Starts the session's agent on its provider and model, then applies its recipe if any.
+    async fn configure_session_agent(
+        &self,
+        agent: &Agent,
+        session_id: &str,
+        provider: Option<&str>,
+        model_name: &str,
+        recipe: Option<&Recipe>,
+        cwd: &Path,
+    ) -> Result<(), sacp::Error> {
+        if provider.is_some() || model_name != self.provider.get_model_config().model_name {
+            self.update_model(agent, session_id, provider, model_name)
+                .await?;
+        }
+        if let Some(recipe) = recipe {
+            recipe::apply_recipe(agent, recipe, cwd).await?;
+        }
+        Ok(())
+    }
+
+    fn requested_provider(&self, meta: Option<&Meta>) -> Result<Option<String>, sacp::Error> {
+        let Some(name) = meta
+            .and_then(|meta| meta.get("goose"))
+            .and_then(|goose| goose.get("provider"))
+            .and_then(|provider| provider.as_str())
+        else {
+            return Ok(None);
+        };
+        if !self.providers.contains_key(name) {
+            return Err(sacp::Error::invalid_params().data(format!("Unknown provider: {}", name)));
+        }
+        Ok(Some(name.to_string()))
+    }
+
+    /// The session provider for a profile's `provider`; `None` when it names the default one.
+    fn profile_provider(&self, name: &str) -> Result<Option<String>, sacp::Error> {
+        if self.providers.contains_key(name) {
+            Ok(Some(name.to_string()))
+        } else if name == self.provider.get_name() {
+            Ok(None)
+        } else {
+            Err(sacp::Error::invalid_params().data(format!("Unknown profile provider: {}", name)))
+        }
+    }
+
+    /// Puts `overrides` on the session's current model for one prompt, returning the provider to
+    /// restore afterwards.
+    async fn override_generation(
+        &self,
+        agent: &Agent,
+        session_id: &str,
+        overrides: &GenerationOverrides,
+    ) -> Result<Arc<dyn Provider>, sacp::Error> {
+        let original = agent.provider().await.map_err(|e| {
+            sacp::Error::internal_error().data(format!("Failed to get provider: {}", e))
+        })?;
+        let mut model_config = original.get_model_config();
+        if let Some(temperature) = overrides.temperature {
+            model_config = model_config.with_temperature(Some(temperature));
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            model_config = model_config.with_max_tokens(Some(max_tokens));
+        }
+        if let Some(effort) = &overrides.reasoning_effort {
+            model_config = model_config.with_reasoning_effort(Some(effort.clone()));
+        }
+
+        let factory = match self.providers.get(original.get_name()) {
+            Some(named) => &named.factory,
+            None => &self.provider_factory,
+        };
+        let provider = factory(model_config).await.map_err(|e| {
+            sacp::Error::internal_error().data(format!("Failed to create provider: {}", e))
+        })?;
+        agent
+            .update_provider(self.with_failover(provider), session_id)
+            .await
+            .map_err(|e| {
+                sacp::Error::internal_error().data(format!("Failed to set provider: {}", e))
+            })?;
+        Ok(original)
+    }
+
+    async fn update_model(
+        &self,
+        agent: &Agent,
+        session_id: &str,
+        provider: Option<&str>,
+        model_name: &str,
+    ) -> Result<(), sacp::Error> {
+        let current = match agent.provider().await {
+            Ok(current) => current.get_model_config(),
+            Err(_) => self.provider.get_model_config(),
+        };
+        let model_config = switched_model_config(current, model_name);
+        let (factory, _) = self.provider_models(provider);
+        let provider = factory(model_config).await.map_err(|e| {
+            sacp::Error::internal_error().data(format!("Failed to create provider: {}", e))
+        })?;
+        agent
+            .update_provider(self.with_failover(provider), session_id)
+            .await
+            .map_err(|e| {
+                sacp::Error::internal_error().data(format!("Failed to set provider: {}", e))
+            })
+    }
+
+    fn session_model_state(&self, provider: Option<&str>, current: &str) -> SessionModelState {
+        let (_, models) = self.provider_models(provider);
+        let available_models = models
+            .iter()
+            .map(|model| ModelInfo::new(ModelId::new(model.as_str()), model.as_str()))
+            .collect();
+        SessionModelState::new(ModelId::new(current), available_models)
+    }
+
+    async fn session_agent(&self, session_id: &str) -> Result<Arc<Agent>, sacp::Error> {
+        let sessions = self.sessions.lock().await;
+        sessions
+            .get(session_id)
+            .map(|session| session.agent.clone())
+            .ok_or_else(|| {
+                sacp::Error::invalid_params().data(format!("Session not found: {}", session_id))
+            })
+    }
+
+    fn convert_acp_prompt_to_message(&self, prompt: Vec<ContentBlock>) -> Message {
+        let mut user_message = Message::user();
+
+        // Process all content blocks from the prompt
+        for block in prompt {
+            match block {
+                ContentBlock::Text(text) => {
+                    user_message = user_message.with_text(&text.text);
+                }
+                ContentBlock::Image(image) => {
+                    // Goose supports images via base64 encoded data
+                    // The ACP ImageContent has data as a String directly
+                    user_message = user_message.with_image(&image.data, &image.mime_type);
+                }
+                ContentBlock::Resource(resource) => {
+                    // Embed resource content as text with context
+                    match &resource.resource {
+                        EmbeddedResourceResource::TextResourceContents(text_resource) => {
+                            let header = format!("--- Resource: {} ---\n", text_resource.uri);
+                            let content = format!("{}{}\n---\n", header, text_resource.text);
                             user_message = user_message.with_text(&content);
                         }
                         _ => {
@@ -379,6 +1691,7 @@ impl GooseAcpAgent {
     async fn handle_message_content(
         &self,
         content_item: &MessageContent,
+        profile: &ClientProfile,
         session_id: &SessionId,
         session: &mut GooseAcpSession,
         cx: &JrConnectionCx<AgentToClient>,
@@ -394,13 +1707,35 @@ impl GooseAcpAgent {
                 ))?;
             }
             MessageContent::ToolRequest(tool_request) => {
+                session
+                    .tool_started
+                    .insert(tool_request.id.clone(), Instant::now());
+                // Only while the call is live: a replayed edit already happened, so the file on
+                // disk no longer holds its old text.
+                if let Ok(tool_call) = &tool_request.tool_call {
+                    let diffs = edit_diffs(tool_call, &session.cwd);
+                    if !diffs.is_empty() {
+                        session.tool_diffs.insert(tool_request.id.clone(), diffs);
+                    }
+                }
                 self.handle_tool_request(tool_request, session_id, session, cx)
                     .await?;
             }
             MessageContent::ToolResponse(tool_response) => {
-                self.handle_tool_response(tool_response, session_id, session, cx)
+                self.audit_tool_call(tool_response, session_id, session)
+                    .await;
+                self.handle_tool_response(tool_response, profile, session_id, session, cx)
                     .await?;
             }
+            MessageContent::Image(_) if !profile.images => {}
+            MessageContent::Image(image) => {
+                cx.send_notification(SessionNotification::new(
+                    session_id.clone(),
+                    SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Image(
+                        ImageContent::new(image.data.clone(), image.mime_type.clone()),
+                    ))),
+                ))?;
+            }
             MessageContent::Thinking(thinking) => {
                 // Stream thinking/reasoning content as thought chunks
                 cx.send_notification(SessionNotification::new(
@@ -410,15 +1745,50 @@ impl GooseAcpAgent {
                     ))),
                 ))?;
             }
-            MessageContent::ActionRequired(action_required) => {
-                if let ActionRequiredData::ToolConfirmation {
+            MessageContent::SystemNotification(notification) => {
+                let chunk = ContentChunk::new(ContentBlock::Text(TextContent::new(
+                    notification.msg.clone(),
+                )));
+                let update = match notification.notification_type {
+                    SystemNotificationType::InlineMessage => {
+                        SessionUpdate::AgentMessageChunk(chunk)
+                    }
+                    SystemNotificationType::ThinkingMessage => {
+                        SessionUpdate::AgentThoughtChunk(chunk)
+                    }
+                };
+                cx.send_notification(SessionNotification::new(session_id.clone(), update))?;
+            }
+            MessageContent::ActionRequired(action_required) => match &action_required.data {
+                ActionRequiredData::ToolConfirmation {
                     id,
                     tool_name,
                     arguments,
                     prompt,
-                } = &action_required.data
-                {
+                } => {
+                    if session.allowed_tools.lock().await.contains(tool_name)
+                        && !admin_requires_asking(&session.agent, tool_name, arguments)
+                    {
+                        session
+                            .tool_decisions
+                            .lock()
+                            .await
+                            .insert(id.clone(), AuditDecision::Approved);
+                        session
+                            .agent
+                            .handle_confirmation(
+                                id.clone(),
+                                PermissionConfirmation {
+                                    principal_type: PrincipalType::Tool,
+                                    permission: Permission::AllowOnce,
+                                },
+                            )
+                            .await;
+                        return Ok(());
+                    }
                     self.handle_tool_permission_request(
+                        session,
+                        profile,
                         id.clone(),
                         tool_name.clone(),
                         arguments.clone(),
@@ -427,7 +1797,21 @@ impl GooseAcpAgent {
                         cx,
                     )?;
                 }
-            }
+                ActionRequiredData::Elicitation {
+                    id,
+                    message,
+                    requested_schema,
+                } => {
+                    Self::handle_elicitation_request(
+                        id.clone(),
+                        message.clone(),
+                        requested_schema.clone(),
+                        session_id,
+                        cx,
+                    )?;
+                }
+                ActionRequiredData::ElicitationResponse { .. } => {}
+            },
             _ => {
                 // Ignore other content types for now
             }
@@ -453,6 +1837,10 @@ impl GooseAcpAgent {
             Err(_) => "error".to_string(),
         };
 
+        let locations = match &tool_request.tool_call {
+            Ok(tool_call) => request_locations(tool_call, &session.cwd),
+            Err(_) => Vec::new(),
+        };
         // Send tool call notification using the provider's tool call ID directly
         cx.send_notification(SessionNotification::new(
             session_id.clone(),
@@ -461,30 +1849,54 @@ impl GooseAcpAgent {
                     ToolCallId::new(tool_request.id.clone()),
                     format_tool_name(&tool_name),
                 )
-                .status(ToolCallStatus::Pending),
+                .status(ToolCallStatus::Pending)
+                .locations(locations),
             ),
         ))?;
 
+        if let Ok(tool_call) = &tool_request.tool_call {
+            let todo_content = tool_call
+                .arguments
+                .as_ref()
+                .and_then(|args| args.get("content"))
+                .and_then(|content| content.as_str())
+                .filter(|_| tool_call.name == TODO_WRITE_TOOL);
+            if let Some(content) = todo_content {
+                cx.send_notification(SessionNotification::new(
+                    session_id.clone(),
+                    SessionUpdate::Plan(plan_from_todo(content)),
+                ))?;
+            }
+        }
+
         Ok(())
     }
 
     async fn handle_tool_response(
         &self,
         tool_response: &goose::conversation::message::ToolResponse,
+        profile: &ClientProfile,
         session_id: &SessionId,
         session: &mut GooseAcpSession,
         cx: &JrConnectionCx<AgentToClient>,
     ) -> Result<(), sacp::Error> {
-        let status = match &tool_response.tool_result {
-            Ok(result) if result.is_error == Some(true) => ToolCallStatus::Failed,
-            Ok(_) => ToolCallStatus::Completed,
-            Err(_) => ToolCallStatus::Failed,
-        };
+        let status = tool_call_status(tool_response);
 
-        let content = build_tool_call_content(&tool_response.tool_result);
+        session.tool_output.remove(&tool_response.id);
+        let tool_request = session.tool_requests.get(&tool_response.id);
+        let diffs = session
+            .tool_diffs
+            .remove(&tool_response.id)
+            .filter(|_| status == ToolCallStatus::Completed)
+            .unwrap_or_default();
+        let content = if diffs.is_empty() {
+            build_tool_call_content(&tool_response.tool_result, profile.images)
+        } else {
+            diffs.into_iter().map(ToolCallContent::Diff).collect()
+        };
 
         // Extract locations from the tool request and response
-        let locations = if let Some(tool_request) = session.tool_requests.get(&tool_response.id) {
+        let locations = if let Some(tool_request) = tool_request {
             extract_tool_locations(tool_request, tool_response)
         } else {
             Vec::new()
@@ -506,8 +1918,82 @@ impl GooseAcpAgent {
         Ok(())
     }
 
+    /// Records a finished tool call in the audit log. Only calls that ran on this server are
+    /// recorded, so replaying a loaded session doesn't log its old calls again.
+    async fn audit_tool_call(
+        &self,
+        tool_response: &goose::conversation::message::ToolResponse,
+        session_id: &SessionId,
+        session: &mut GooseAcpSession,
+    ) {
+        let status = tool_call_status(tool_response);
+        let tool_call = match session.tool_requests.get(&tool_response.id) {
+            Some(request) => request.tool_call.as_ref().ok(),
+            None => None,
+        };
+        let duration = session
+            .tool_started
+            .remove(&tool_response.id)
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+        let decision = session
+            .tool_decisions
+            .lock()
+            .await
+            .remove(&tool_response.id)
+            .unwrap_or(AuditDecision::Auto);
+        let record = AuditRecord {
+            timestamp: chrono::Utc::now(),
+            session_id: session_id.0.to_string(),
+            tool: tool_call.map_or_else(|| "error".to_string(), |call| call.name.to_string()),
+            args_hash: audit::args_hash(tool_call.and_then(|call| call.arguments.as_ref())),
+            decision,
+            outcome: if status == ToolCallStatus::Completed {
+                AuditOutcome::Success
+            } else {
+                AuditOutcome::Error
+            },
+            duration_ms: duration.as_millis() as u64,
+        };
+        if let Err(e) = self.audit_log.append(&record) {
+            warn!(error = %e, "failed to write tool audit record");
+        }
+    }
+
+    fn handle_elicitation_request(
+        request_id: String,
+        message: String,
+        requested_schema: serde_json::Value,
+        session_id: &SessionId,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<(), sacp::Error> {
+        cx.send_request(CreateElicitationRequest {
+            session_id: session_id.clone(),
+            message,
+            requested_schema,
+        })
+        .on_receiving_result(move |result| async move {
+            if let Err(e) = &result {
+                warn!(error = ?e, "elicitation request failed");
+            }
+            let manager = ActionRequiredManager::global();
+            let submitted = match elicitation_answer(result) {
+                Some(content) => manager.submit_response(request_id, content).await,
+                None => manager.cancel(&request_id).await,
+            };
+            if let Err(e) = submitted {
+                warn!(error = %e, "failed to deliver elicitation answer");
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn handle_tool_permission_request(
         &self,
+        session: &GooseAcpSession,
+        profile: &ClientProfile,
         request_id: String,
         tool_name: String,
         arguments: serde_json::Map<String, serde_json::Value>,
@@ -516,10 +2002,25 @@ impl GooseAcpAgent {
         cx: &JrConnectionCx<AgentToClient>,
     ) -> Result<(), sacp::Error> {
         let cx = cx.clone();
-        let agent = self.agent.clone();
         let session_id = session_id.clone();
+        let agent = session.agent.clone();
+        let allowed_tools = session.allowed_tools.clone();
+        let tool_decisions = session.tool_decisions.clone();
+        let audit_log = self.audit_log.clone();
+        let mut record = PermissionRecord {
+            requested_at: chrono::Utc::now(),
+            resolved_at: chrono::Utc::now(),
+            session_id: session_id.0.to_string(),
+            client: profile.name.clone(),
+            tool: tool_name.clone(),
+            args_hash: audit::args_hash(Some(&arguments)),
+            option_id: None,
+            decision: AuditDecision::Denied,
+        };
 
         let formatted_name = format_tool_name(&tool_name);
+        let prefix = command_prefix(&tool_name, &arguments);
+        let options = permission_options(&formatted_name, prefix.as_deref());
 
         // Use the request_id (provider's tool call ID) directly
         let mut fields = ToolCallUpdateFields::new()
@@ -534,38 +2035,69 @@ impl GooseAcpAgent {
         }
         let tool_call_update = ToolCallUpdate::new(ToolCallId::new(request_id.clone()), fields);
 
-        fn option(kind: PermissionOptionKind) -> PermissionOption {
-            let id = serde_json::to_value(kind)
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string();
-            PermissionOption::new(id.clone(), id, kind)
-        }
-        let options = vec![
-            option(PermissionOptionKind::AllowAlways),
-            option(PermissionOptionKind::AllowOnce),
-            option(PermissionOptionKind::RejectOnce),
-            option(PermissionOptionKind::RejectAlways),
-        ];
-
         let permission_request =
-            RequestPermissionRequest::new(session_id, tool_call_update, options);
+            RequestPermissionRequest::new(session_id, tool_call_update, options).meta(
+                permission_scope_meta(&session.cwd, &agent.config.permission_manager),
+            );
 
         cx.send_request(permission_request)
             .on_receiving_result(move |result| async move {
                 match result {
                     Ok(response) => {
-                        agent
-                            .handle_confirmation(
-                                request_id,
-                                outcome_to_confirmation(&response.outcome),
-                            )
-                            .await;
+                        if let RequestPermissionOutcome::Selected(selected) = &response.outcome {
+                            match &*selected.option_id.0 {
+                                ALLOW_SESSION_OPTION => {
+                                    allowed_tools.lock().await.insert(tool_name);
+                                }
+                                ALLOW_HOUR_OPTION => {
+                                    agent.config.permission_manager.grant_user_permission_until(
+                                        &tool_name,
+                                        chrono::Utc::now() + chrono::Duration::hours(1),
+                                    );
+                                }
+                                ALLOW_PREFIX_OPTION => {
+                                    if let Some(prefix) = &prefix {
+                                        agent
+                                            .config
+                                            .permission_manager
+                                            .add_user_command_prefix(prefix);
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                        let confirmation = outcome_to_confirmation(&response.outcome);
+                        let decision = match confirmation.permission {
+                            Permission::AlwaysAllow | Permission::AllowOnce => {
+                                AuditDecision::Approved
+                            }
+                            _ => AuditDecision::Denied,
+                        };
+                        tool_decisions
+                            .lock()
+                            .await
+                            .insert(request_id.clone(), decision);
+                        if let RequestPermissionOutcome::Selected(selected) = &response.outcome {
+                            record.option_id = Some(selected.option_id.0.to_string());
+                        }
+                        record.decision = decision;
+                        record.resolved_at = chrono::Utc::now();
+                        if let Err(e) = audit_log.append_permission(&record) {
+                            warn!(error = %e, "failed to write permission audit record");
+                        }
+                        agent.handle_confirmation(request_id, confirmation).await;
                         Ok(())
                     }
                     Err(e) => {
                         error!(error = ?e, "permission request failed");
+                        tool_decisions
+                            .lock()
+                            .await
+                            .insert(request_id.clone(), AuditDecision::Denied);
+                        record.resolved_at = chrono::Utc::now();
+                        if let Err(e) = audit_log.append_permission(&record) {
+                            warn!(error = %e, "failed to write permission audit record");
+                        }
                         agent
                             .handle_confirmation(
                                 request_id,
@@ -584,9 +2116,148 @@ impl GooseAcpAgent {
     }
 }
 
+/// The answer to pass back to the MCP server, or `None` if the user didn't give one.
+fn elicitation_answer(
+    result: Result<CreateElicitationResponse, sacp::Error>,
+) -> Option<serde_json::Value> {
+    match result {
+        Ok(CreateElicitationResponse {
+            action: ElicitationAction::Accept,
+            content,
+        }) => Some(content.unwrap_or_else(|| serde_json::json!({}))),
+        _ => None,
+    }
+}
+
+/// Tells the client where "always" choices are stored: `_meta.goose.permissionScope` holds the
+/// workspace they apply to and the file they're written to.
+fn permission_scope_meta(cwd: &Path, permission_manager: &PermissionManager) -> Meta {
+    Meta::from_iter([(
+        "goose".to_string(),
+        serde_json::json!({
+            "permissionScope": {
+                "workspace": cwd,
+                "path": permission_manager
+                    .persists()
+                    .then(|| permission_manager.get_config_path()),
+            }
+        }),
+    )])
+}
+
+const ALLOW_SESSION_OPTION: &str = "allow_session";
+const ALLOW_PREFIX_OPTION: &str = "allow_prefix";
+const ALLOW_HOUR_OPTION: &str = "allow_hour";
+
+/// Whether the admin policy says to ask before every call like this one, which an "allow for
+/// session" choice mustn't skip.
+fn admin_requires_asking(
+    agent: &Agent,
+    tool_name: &str,
+    arguments: &serde_json::Map<String, serde_json::Value>,
+) -> bool {
+    let tool_call = CallToolRequestParams {
+        meta: None,
+        task: None,
+        name: tool_name.to_string().into(),
+        arguments: Some(arguments.clone()),
+    };
+    agent
+        .config
+        .permission_manager
+        .get_admin_permission_for_call(&tool_call)
+        .is_some_and(|level| level != PermissionLevel::AlwaysAllow)
+}
+
+/// How many words of a shell command a prefix rule keeps at most.
+const COMMAND_PREFIX_WORDS: usize = 2;
+
+/// The command a shell permission can be widened to: the program and its subcommand, if it has
+/// one, e.g. `cargo test` for `cargo test -p goose`. Flags, paths and other arguments are left
+/// out so the rule covers the next run too.
+fn command_prefix(
+    tool_name: &str,
+    arguments: &serde_json::Map<String, serde_json::Value>,
+) -> Option<String> {
+    if tool_name != "developer__shell" {
+        return None;
+    }
+    let command = arguments.get("command")?.as_str()?;
+    let mut words = command.split_whitespace();
+    let program = words.next()?;
+    let subcommands = words.take(COMMAND_PREFIX_WORDS - 1).take_while(|word| {
+        !word.starts_with('-')
+            && word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    });
+    Some(
+        std::iter::once(program)
+            .chain(subcommands)
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Options for a permission request. The `allow_always` and `reject_always` choices are stored
+/// by the agent as tool permissions, `allow_prefix` as a command prefix rule, and
+/// `allow_session` only lives as long as this ACP session.
+fn permission_options(formatted_name: &str, prefix: Option<&str>) -> Vec<PermissionOption> {
+    fn option(kind: PermissionOptionKind, name: String) -> PermissionOption {
+        let id = serde_json::to_value(kind)
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_string();
+        PermissionOption::new(id, name, kind)
+    }
+
+    let mut options = vec![
+        option(
+            PermissionOptionKind::AllowAlways,
+            format!("Always allow {}", formatted_name),
+        ),
+        PermissionOption::new(
+            ALLOW_SESSION_OPTION,
+            format!("Allow {} for this session", formatted_name),
+            PermissionOptionKind::AllowAlways,
+        ),
+        PermissionOption::new(
+            ALLOW_HOUR_OPTION,
+            format!("Allow {} for 1 hour", formatted_name),
+            PermissionOptionKind::AllowAlways,
+        ),
+    ];
+    if let Some(prefix) = prefix {
+        options.push(PermissionOption::new(
+            ALLOW_PREFIX_OPTION,
+            format!("Always allow `{}` commands", prefix),
+            PermissionOptionKind::AllowAlways,
+        ));
+    }
+    options.extend([
+        option(PermissionOptionKind::AllowOnce, "Allow once".to_string()),
+        option(PermissionOptionKind::RejectOnce, "Reject".to_string()),
+        option(
+            PermissionOptionKind::RejectAlways,
+            format!("Always reject {}", formatted_name),
+        ),
+    ]);
+    options
+}
+
 fn outcome_to_confirmation(outcome: &RequestPermissionOutcome) -> PermissionConfirmation {
     let permission = match outcome {
         RequestPermissionOutcome::Cancelled => Permission::Cancel,
+        // The grant itself is recorded by the caller; the agent just runs this call.
+        RequestPermissionOutcome::Selected(selected)
+            if matches!(
+                &*selected.option_id.0,
+                ALLOW_SESSION_OPTION | ALLOW_HOUR_OPTION | ALLOW_PREFIX_OPTION
+            ) =>
+        {
+            Permission::AllowOnce
+        }
         RequestPermissionOutcome::Selected(selected) => {
             match serde_json::from_value::<PermissionOptionKind>(serde_json::Value::String(
                 selected.option_id.0.to_string(),
@@ -607,11 +2278,28 @@ fn outcome_to_confirmation(outcome: &RequestPermissionOutcome) -> PermissionConf
     }
 }
 
-fn build_tool_call_content(tool_result: &ToolResult<CallToolResult>) -> Vec<ToolCallContent> {
+fn is_image(content: &rmcp::model::Content) -> bool {
+    match &content.raw {
+        RawContent::Image(_) => true,
+        RawContent::Resource(resource) => matches!(
+            &resource.resource,
+            ResourceContents::BlobResourceContents { mime_type: Some(mime_type), .. }
+                if mime_type.starts_with("image/")
+        ),
+        _ => false,
+    }
+}
+
+/// Images are left out for clients that can't show them.
+fn build_tool_call_content(
+    tool_result: &ToolResult<CallToolResult>,
+    images: bool,
+) -> Vec<ToolCallContent> {
     match tool_result {
         Ok(result) => result
             .content
             .iter()
+            .filter(|content| images || !is_image(content))
             .filter_map(|content| match &content.raw {
                 RawContent::Text(val) => Some(ToolCallContent::Content(Content::new(
                     ContentBlock::Text(TextContent::new(val.text.clone())),
@@ -620,6 +2308,23 @@ fn build_tool_call_content(tool_result: &ToolResult<CallToolResult>) -> Vec<Tool
                     ContentBlock::Image(ImageContent::new(val.data.clone(), val.mime_type.clone())),
                 ))),
                 RawContent::Resource(val) => {
+                    // Renderers often hand screenshots back as blobs; show those as images.
+                    if let ResourceContents::BlobResourceContents {
+                        mime_type: Some(mime_type),
+                        blob,
+                        uri,
+                        ..
+                    } = &val.resource
+                    {
+                        if mime_type.starts_with("image/") {
+                            return Some(ToolCallContent::Content(Content::new(
+                                ContentBlock::Image(
+                                    ImageContent::new(blob.clone(), mime_type.clone())
+                                        .uri(uri.clone()),
+                                ),
+                            )));
+                        }
+                    }
                     let resource = match &val.resource {
                         ResourceContents::TextResourceContents {
                             mime_type,
@@ -665,8 +2370,10 @@ impl GooseAcpAgent {
     ) -> Result<InitializeResponse, sacp::Error> {
         debug!(?args, "initialize request");
 
+        *self.client_profile.lock().await = ClientProfile::from_initialize(&args);
+
         // Advertise Goose's capabilities
-        let capabilities = AgentCapabilities::new()
+        let mut capabilities = AgentCapabilities::new()
             .load_session(true)
             .prompt_capabilities(
                 PromptCapabilities::new()
@@ -674,18 +2381,67 @@ impl GooseAcpAgent {
                     .audio(false)
                     .embedded_context(true),
             )
-            .mcp_capabilities(McpCapabilities::new().http(true));
-        Ok(InitializeResponse::new(args.protocol_version).agent_capabilities(capabilities))
+            .mcp_capabilities(McpCapabilities::new().http(true).sse(true));
+        capabilities.session_capabilities =
+            SessionCapabilities::new().list(SessionListCapabilities::new());
+        let auth_methods = self
+            .auth_backends
+            .iter()
+            .map(|backend| backend.method())
+            .collect();
+        Ok(InitializeResponse::new(args.protocol_version)
+            .agent_capabilities(capabilities)
+            .auth_methods(auth_methods))
+    }
+
+    async fn on_authenticate(
+        &self,
+        args: AuthenticateRequest,
+    ) -> Result<AuthenticateResponse, sacp::Error> {
+        let backend = self
+            .auth_backends
+            .iter()
+            .find(|backend| backend.method().id == args.method_id)
+            .ok_or_else(|| {
+                sacp::Error::invalid_params()
+                    .data(format!("Unknown auth method: {}", args.method_id))
+            })?;
+        backend.authenticate().await.map_err(|e| {
+            sacp::Error::auth_required().data(format!("Authentication failed: {}", e))
+        })?;
+        self.authenticated.store(true, Ordering::SeqCst);
+        Ok(AuthenticateResponse::new())
     }
 
+    #[instrument(skip_all, fields(session.id))]
     async fn on_new_session(
         &self,
         args: NewSessionRequest,
+        cx: &JrConnectionCx<AgentToClient>,
     ) -> Result<NewSessionResponse, sacp::Error> {
         debug!(?args, "new session request");
+        self.ensure_authenticated().await?;
+        validate_cwd(&args.cwd)?;
+        let tool_filter = requested_tool_filter(args.meta.as_ref())?;
+        let permission_scope = requested_permission_scope(args.meta.as_ref())?;
+        let mcp_env = requested_mcp_env(args.meta.as_ref())?;
+        let tags = requested_tags(args.meta.as_ref())?;
+        let recipe = recipe::requested_recipe(args.meta.as_ref(), &args.cwd)?;
+        let settings = recipe.as_ref().and_then(|recipe| recipe.settings.as_ref());
+        let requested_profile = profile::requested_profile(args.meta.as_ref())?;
+        let agent_profile = requested_profile.as_ref().map(|(_, profile)| profile);
+        let provider = match self.requested_provider(args.meta.as_ref())? {
+            Some(provider) => Some(provider),
+            None => match agent_profile.and_then(|profile| profile.provider.as_deref()) {
+                Some(name) => self.profile_provider(name)?,
+                None => settings
+                    .and_then(|settings| settings.goose_provider.clone())
+                    .filter(|name| self.providers.contains_key(name)),
+            },
+        };
 
-        let manager = self.agent.config.session_manager.clone();
-        let goose_session = manager
+        let goose_session = self
+            .session_manager
             .create_session(
                 args.cwd.clone(),
                 "ACP Session".to_string(), // just an initial name - may be replaced by maybe_update_name
@@ -695,27 +2451,108 @@ impl GooseAcpAgent {
             .map_err(|e| {
                 sacp::Error::internal_error().data(format!("Failed to create session: {}", e))
             })?;
-        self.update_session_with_provider(&goose_session).await?;
-
-        // Add MCP servers specified in the session request
-        for mcp_server in args.mcp_servers {
-            let config = match mcp_server_to_extension_config(mcp_server) {
-                Ok(c) => c,
-                Err(msg) => {
-                    return Err(sacp::Error::invalid_params().data(msg));
-                }
-            };
-            let name = config.name().to_string();
-            if let Err(e) = self.agent.add_extension(config).await {
-                return Err(sacp::Error::internal_error()
-                    .data(format!("Failed to add MCP server '{}': {}", name, e)));
+        tracing::Span::current().record("session.id", goose_session.id.as_str());
+        let agent = self
+            .create_agent(
+                &goose_session,
+                &args.cwd,
+                tool_filter.as_ref(),
+                permission_scope,
+                agent_profile.and_then(|profile| profile.permissions.as_ref()),
+            )
+            .await?;
+        let model_name = self.initial_model(
+            provider.as_deref(),
+            agent_profile
+                .and_then(|profile| profile.model.as_deref())
+                .or_else(|| settings.and_then(|settings| settings.goose_model.as_deref())),
+        );
+        self.configure_session_agent(
+            &agent,
+            &goose_session.id,
+            provider.as_deref(),
+            &model_name,
+            recipe.as_ref(),
+            &args.cwd,
+        )
+        .await?;
+        if let Some(profile) = agent_profile {
+            profile::apply_profile(&agent, profile, &args.cwd).await?;
+        }
+        add_mcp_servers(
+            &agent,
+            args.mcp_servers,
+            &args.cwd,
+            &mcp_env,
+            &goose_session.id,
+            cx,
+        )
+        .await?;
+
+        let instructions = requested_instructions(args.meta.as_ref());
+        if let Some(instructions) = &instructions {
+            agent.extend_system_prompt(instructions.text.clone()).await;
+        }
+
+        let scratch = permission_scope == PermissionScope::Session;
+        if recipe.is_some()
+            || instructions.is_some()
+            || tool_filter.is_some()
+            || requested_profile.is_some()
+            || scratch
+            || !tags.is_empty()
+        {
+            let mut extension_data = goose_session.extension_data.clone();
+            if let Some((session_profile, _)) = &requested_profile {
+                session_profile
+                    .to_extension_data(&mut extension_data)
+                    .map_err(|e| sacp::Error::internal_error().data(e.to_string()))?;
             }
+            if scratch {
+                permission_scope
+                    .to_extension_data(&mut extension_data)
+                    .map_err(|e| sacp::Error::internal_error().data(e.to_string()))?;
+            }
+            if let Some(instructions) = &instructions {
+                instructions
+                    .to_extension_data(&mut extension_data)
+                    .map_err(|e| sacp::Error::internal_error().data(e.to_string()))?;
+            }
+            if let Some(tool_filter) = &tool_filter {
+                tool_filter
+                    .to_extension_data(&mut extension_data)
+                    .map_err(|e| sacp::Error::internal_error().data(e.to_string()))?;
+            }
+            self.session_manager
+                .update(&goose_session.id)
+                .recipe(recipe.clone())
+                .extension_data(extension_data)
+                .tags(tags)
+                .apply()
+                .await
+                .map_err(|e| {
+                    sacp::Error::internal_error().data(format!("Failed to save session: {}", e))
+                })?;
         }
 
         let session = GooseAcpSession {
+            agent,
             messages: Conversation::new_unvalidated(Vec::new()),
             tool_requests: HashMap::new(),
+            tool_output: HashMap::new(),
+            allowed_tools: Arc::default(),
+            tool_started: HashMap::new(),
+            tool_diffs: HashMap::new(),
+            tool_decisions: Arc::default(),
             cancel_token: None,
+            file_changes: self.watch_workspace(&args.cwd),
+            cwd: args.cwd,
+            provider: provider.clone(),
+            max_turns: settings
+                .and_then(|settings| settings.max_turns)
+                .map(|max_turns| max_turns as u32),
+            retry_config: recipe.as_ref().and_then(|recipe| recipe.retry.clone()),
+            mcp_env,
         };
 
         let mut sessions = self.sessions.lock().await;
@@ -727,44 +2564,41 @@ impl GooseAcpAgent {
             "Session started"
         );
 
-        Ok(NewSessionResponse::new(SessionId::new(goose_session.id)))
-    }
-
-    async fn update_session_with_provider(
-        &self,
-        goose_session: &Session,
-    ) -> Result<(), sacp::Error> {
-        self.agent
-            .update_provider(self.provider.clone(), &goose_session.id)
-            .await
-            .map_err(|e| {
-                sacp::Error::internal_error().data(format!("Failed to set provider: {}", e))
-            })?;
-        Ok(())
+        let mode = agent_profile
+            .and_then(|profile| profile.mode)
+            .unwrap_or(self.goose_mode);
+        Ok(NewSessionResponse::new(SessionId::new(goose_session.id))
+            .modes(session_mode_state(mode))
+            .models(self.session_model_state(provider.as_deref(), &model_name)))
     }
 
+    #[instrument(skip_all, fields(session.id = %args.session_id.0))]
     async fn on_load_session(
         &self,
         args: LoadSessionRequest,
         cx: &JrConnectionCx<AgentToClient>,
     ) -> Result<LoadSessionResponse, sacp::Error> {
         debug!(?args, "load session request");
+        self.ensure_authenticated().await?;
+        validate_cwd(&args.cwd)?;
 
         let session_id = args.session_id.0.to_string();
+        let mcp_env = requested_mcp_env(args.meta.as_ref())?;
 
-        let manager = self.agent.config.session_manager.clone();
-        let goose_session = manager.get_session(&session_id, true).await.map_err(|e| {
-            sacp::Error::invalid_params()
-                .data(format!("Failed to load session {}: {}", session_id, e))
-        })?;
-        self.update_session_with_provider(&goose_session).await?;
-
-        let conversation = goose_session.conversation.ok_or_else(|| {
+        let goose_session = self
+            .session_manager
+            .get_session(&session_id, true)
+            .await
+            .map_err(|e| {
+                sacp::Error::invalid_params()
+                    .data(format!("Failed to load session {}: {}", session_id, e))
+            })?;
+        let conversation = goose_session.conversation.clone().ok_or_else(|| {
             sacp::Error::internal_error()
                 .data(format!("Session {} has no conversation data", session_id))
         })?;
 
-        manager
+        self.session_manager
             .update(&session_id)
             .working_dir(args.cwd.clone())
             .apply()
@@ -774,12 +2608,82 @@ impl GooseAcpAgent {
                     .data(format!("Failed to update session working directory: {}", e))
             })?;
 
-        let mut session = GooseAcpSession {
+        let tool_filter = SessionToolFilter::from_extension_data(&goose_session.extension_data);
+        let permission_scope =
+            PermissionScope::from_extension_data(&goose_session.extension_data).unwrap_or_default();
+        let agent_profile = profile::stored_profile(&goose_session.extension_data)?;
+        let agent = self
+            .create_agent(
+                &goose_session,
+                &args.cwd,
+                tool_filter.as_ref(),
+                permission_scope,
+                agent_profile
+                    .as_ref()
+                    .and_then(|profile| profile.permissions.as_ref()),
+            )
+            .await?;
+        let provider = goose_session
+            .provider_name
+            .clone()
+            .filter(|name| self.providers.contains_key(name));
+        let model_name = self.initial_model(
+            provider.as_deref(),
+            goose_session
+                .model_config
+                .as_ref()
+                .map(|model_config| model_config.model_name.as_str()),
+        );
+        let recipe = goose_session.recipe.as_ref();
+        self.configure_session_agent(
+            &agent,
+            &session_id,
+            provider.as_deref(),
+            &model_name,
+            recipe,
+            &args.cwd,
+        )
+        .await?;
+        if let Some(profile) = &agent_profile {
+            profile::apply_profile(&agent, profile, &args.cwd).await?;
+        }
+        if let Some(instructions) =
+            SessionInstructions::from_extension_data(&goose_session.extension_data)
+        {
+            agent.extend_system_prompt(instructions.text).await;
+        }
+        add_mcp_servers(
+            &agent,
+            args.mcp_servers,
+            &args.cwd,
+            &mcp_env,
+            &session_id,
+            cx,
+        )
+        .await?;
+
+        let mut session = GooseAcpSession {
+            agent,
             messages: conversation.clone(),
             tool_requests: HashMap::new(),
+            tool_output: HashMap::new(),
+            allowed_tools: Arc::default(),
+            tool_started: HashMap::new(),
+            tool_diffs: HashMap::new(),
+            tool_decisions: Arc::default(),
             cancel_token: None,
+            file_changes: self.watch_workspace(&args.cwd),
+            cwd: args.cwd,
+            provider: provider.clone(),
+            max_turns: recipe
+                .and_then(|recipe| recipe.settings.as_ref())
+                .and_then(|settings| settings.max_turns)
+                .map(|max_turns| max_turns as u32),
+            retry_config: recipe.and_then(|recipe| recipe.retry.clone()),
+            mcp_env,
         };
 
+        let profile = self.client_profile.lock().await.clone();
         // Replay conversation history to client
         for message in conversation.messages() {
             // Only replay user-visible messages
@@ -809,6 +2713,7 @@ impl GooseAcpAgent {
                     MessageContent::ToolResponse(tool_response) => {
                         self.handle_tool_response(
                             tool_response,
+                            &profile,
                             &args.session_id,
                             &mut session,
                             cx,
@@ -830,6 +2735,11 @@ impl GooseAcpAgent {
             }
         }
 
+        cx.send_notification(SessionNotification::new(
+            args.session_id.clone(),
+            session_info_update(&goose_session),
+        ))?;
+
         let mut sessions = self.sessions.lock().await;
         sessions.insert(session_id.clone(), session);
 
@@ -839,36 +2749,308 @@ impl GooseAcpAgent {
             "Session loaded"
         );
 
-        Ok(LoadSessionResponse::new())
+        let mode = agent_profile
+            .and_then(|profile| profile.mode)
+            .unwrap_or(self.goose_mode);
+        Ok(LoadSessionResponse::new()
+            .modes(session_mode_state(mode))
+            .models(self.session_model_state(provider.as_deref(), &model_name)))
+    }
+
+    async fn on_set_mode(
+        &self,
+        args: SetSessionModeRequest,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<SetSessionModeResponse, sacp::Error> {
+        debug!(?args, "set mode request");
+
+        let mode = args
+            .mode_id
+            .0
+            .parse::<GooseMode>()
+            .map_err(|e| sacp::Error::invalid_params().data(e))?;
+        let agent = self.session_agent(&args.session_id.0).await?;
+        self.set_session_mode(&agent, mode, &args.session_id, cx)
+            .await?;
+
+        Ok(SetSessionModeResponse::new())
+    }
+
+    /// Switches the session's mode and tells the client, whichever way the change was asked for.
+    async fn set_session_mode(
+        &self,
+        agent: &Agent,
+        mode: GooseMode,
+        session_id: &SessionId,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<(), sacp::Error> {
+        agent.update_goose_mode(mode).await;
+        cx.send_notification(SessionNotification::new(
+            session_id.clone(),
+            SessionUpdate::CurrentModeUpdate(CurrentModeUpdate::new(SessionModeId::new(mode_id(
+                mode,
+            )))),
+        ))?;
+        info!(session_id = %session_id.0, mode = mode_id(mode), "session mode changed");
+        Ok(())
+    }
+
+    fn send_available_commands(
+        &self,
+        session_id: SessionId,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<(), sacp::Error> {
+        cx.send_notification(SessionNotification::new(
+            session_id,
+            SessionUpdate::AvailableCommandsUpdate(AvailableCommandsUpdate::new(
+                available_commands(),
+            )),
+        ))
+    }
+
+    async fn session_agent_and_cwd(
+        &self,
+        session_id: &str,
+    ) -> Result<(Arc<Agent>, PathBuf), sacp::Error> {
+        let sessions = self.sessions.lock().await;
+        sessions
+            .get(session_id)
+            .map(|session| (session.agent.clone(), session.cwd.clone()))
+            .ok_or_else(|| {
+                sacp::Error::invalid_params().data(format!("Session not found: {}", session_id))
+            })
     }
 
+    async fn send_tools_changed(
+        &self,
+        agent: &Agent,
+        session_id: SessionId,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<(), sacp::Error> {
+        let tools = agent
+            .list_tools(&session_id.0, None)
+            .await
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        cx.send_notification(ToolsChangedNotification { session_id, tools })
+    }
+
+    async fn on_add_extension(
+        &self,
+        args: AddExtensionRequest,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<AddExtensionResponse, sacp::Error> {
+        debug!(?args, "add extension request");
+
+        let (agent, cwd) = self.session_agent_and_cwd(&args.session_id.0).await?;
+        let mcp_env = self
+            .sessions
+            .lock()
+            .await
+            .get(&*args.session_id.0)
+            .map(|session| session.mcp_env.clone())
+            .unwrap_or_default();
+        match (args.mcp_server, args.builtin) {
+            (Some(mcp_server), None) => {
+                add_mcp_servers(
+                    &agent,
+                    vec![mcp_server],
+                    &cwd,
+                    &mcp_env,
+                    &args.session_id.0,
+                    cx,
+                )
+                .await?
+            }
+            (None, Some(builtin)) => agent
+                .add_extension_with_working_dir(builtin_extension_config(&builtin), Some(cwd))
+                .await
+                .map_err(|e| {
+                    sacp::Error::internal_error()
+                        .data(format!("Failed to add builtin {}: {}", builtin, e))
+                })?,
+            _ => {
+                return Err(sacp::Error::invalid_params()
+                    .data("Set exactly one of mcpServer or builtin".to_string()))
+            }
+        }
+
+        self.send_tools_changed(&agent, args.session_id, cx).await?;
+        Ok(AddExtensionResponse::default())
+    }
+
+    async fn on_remove_extension(
+        &self,
+        args: RemoveExtensionRequest,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<RemoveExtensionResponse, sacp::Error> {
+        debug!(?args, "remove extension request");
+
+        let agent = self.session_agent(&args.session_id.0).await?;
+        if !agent.list_extensions().await.contains(&args.name) {
+            return Err(
+                sacp::Error::invalid_params().data(format!("Extension not found: {}", args.name))
+            );
+        }
+        agent.remove_extension(&args.name).await.map_err(|e| {
+            sacp::Error::invalid_params()
+                .data(format!("Failed to remove extension {}: {}", args.name, e))
+        })?;
+
+        self.send_tools_changed(&agent, args.session_id, cx).await?;
+        Ok(RemoveExtensionResponse::default())
+    }
+
+    /// `/mode` is handled here rather than by the agent since modes are an ACP session concept.
+    async fn on_mode_command(
+        &self,
+        agent: &Agent,
+        mode: Option<&str>,
+        session_id: &SessionId,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<PromptResponse, sacp::Error> {
+        let reply = match mode.map(str::parse::<GooseMode>) {
+            Some(Ok(mode)) => {
+                self.set_session_mode(agent, mode, session_id, cx).await?;
+                format!("Mode set to {}", mode_id(mode))
+            }
+            Some(Err(e)) => e,
+            None => format!("Current mode is {}", mode_id(agent.goose_mode().await)),
+        };
+
+        cx.send_notification(SessionNotification::new(
+            session_id.clone(),
+            SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Text(
+                TextContent::new(reply),
+            ))),
+        ))?;
+        Ok(PromptResponse::new(StopReason::EndTurn))
+    }
+
+    async fn on_set_model(
+        &self,
+        args: SetSessionModelRequest,
+    ) -> Result<SetSessionModelResponse, sacp::Error> {
+        debug!(?args, "set model request");
+
+        let model_name = args.model_id.0.to_string();
+        let (agent, provider) = {
+            let sessions = self.sessions.lock().await;
+            let session = sessions.get(&*args.session_id.0).ok_or_else(|| {
+                sacp::Error::invalid_params()
+                    .data(format!("Session not found: {}", args.session_id.0))
+            })?;
+            (session.agent.clone(), session.provider.clone())
+        };
+        let (_, models) = self.provider_models(provider.as_deref());
+        if !models.contains(&model_name) {
+            return Err(
+                sacp::Error::invalid_params().data(format!("Unknown model: {}", model_name))
+            );
+        }
+        self.update_model(&agent, &args.session_id.0, provider.as_deref(), &model_name)
+            .await?;
+
+        info!(session_id = %args.session_id.0, model = %model_name, "session model changed");
+
+        Ok(SetSessionModelResponse::new())
+    }
+
+    #[instrument(skip_all, fields(session.id = %args.session_id.0))]
     async fn on_prompt(
         &self,
         args: PromptRequest,
         cx: &JrConnectionCx<AgentToClient>,
     ) -> Result<PromptResponse, sacp::Error> {
+        self.ensure_authenticated().await?;
         let session_id = args.session_id.0.to_string();
-        let cancel_token = CancellationToken::new();
+        let overrides = requested_overrides(args.meta.as_ref())?;
+        let mut user_message = self.convert_acp_prompt_to_message(args.prompt);
 
-        {
+        let message_text = user_message.as_concat_text();
+        let mut words = message_text.split_whitespace();
+        if words.next() == Some("/mode") {
+            let agent = self.session_agent(&session_id).await?;
+            return self
+                .on_mode_command(&agent, words.next(), &args.session_id, cx)
+                .await;
+        }
+
+        let cancel_token = CancellationToken::new();
+        let (agent, session_config) = {
             let mut sessions = self.sessions.lock().await;
             let session = sessions.get_mut(&session_id).ok_or_else(|| {
                 sacp::Error::invalid_params().data(format!("Session not found: {}", session_id))
             })?;
             session.cancel_token = Some(cancel_token.clone());
-        }
-
-        let user_message = self.convert_acp_prompt_to_message(args.prompt);
+            let changed = session
+                .file_changes
+                .as_ref()
+                .map(WorkspaceChanges::take)
+                .unwrap_or_default();
+            if let Some(note) = changes_note(&changed) {
+                user_message = user_message.with_text(note);
+            }
+            let session_config = SessionConfig {
+                id: session_id.clone(),
+                schedule_id: None,
+                max_turns: session.max_turns,
+                retry_config: session.retry_config.clone(),
+            };
+            (session.agent.clone(), session_config)
+        };
 
-        let session_config = SessionConfig {
-            id: session_id.clone(),
-            schedule_id: None,
-            max_turns: None,
-            retry_config: None,
+        let original_provider = match overrides {
+            Some(overrides) => Some(
+                self.override_generation(&agent, &session_id, &overrides)
+                    .await?,
+            ),
+            None => None,
         };
+        let result = self
+            .run_prompt(
+                agent.clone(),
+                &args.session_id,
+                user_message,
+                session_config,
+                cancel_token,
+                cx,
+            )
+            .await;
+        // Whatever changed during the turn was the agent's own doing, or at least happened
+        // while it could see it, so only changes between turns are reported.
+        if let Some(changes) = self
+            .sessions
+            .lock()
+            .await
+            .get(&session_id)
+            .and_then(|session| session.file_changes.as_ref())
+        {
+            changes.take();
+        }
+        if let Some(provider) = original_provider {
+            if let Err(e) = agent.update_provider(provider, &session_id).await {
+                warn!(error = %e, "failed to restore provider after prompt overrides");
+            }
+        }
+        result
+    }
 
-        let mut stream = self
-            .agent
+    async fn run_prompt(
+        &self,
+        agent: Arc<Agent>,
+        acp_session_id: &SessionId,
+        user_message: Message,
+        session_config: SessionConfig,
+        cancel_token: CancellationToken,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<PromptResponse, sacp::Error> {
+        let session_id = acp_session_id.0.to_string();
+        let profile = self.client_profile.lock().await.clone();
+        let (_, cwd) = self.session_agent_and_cwd(&session_id).await?;
+        let usage_before = self.session_manager.get_session(&session_id, false).await;
+        let mut stream = agent
             .reply(user_message, session_config, Some(cancel_token.clone()))
             .await
             .map_err(|e| {
@@ -877,27 +3059,172 @@ impl GooseAcpAgent {
 
         use futures::StreamExt;
 
-        let mut was_cancelled = false;
+        let (retry_notices, mut retries) = tokio::sync::mpsc::unbounded_channel();
+        let retry_scope = RetryScope {
+            config: self.provider_retry.clone(),
+            notices: Some(retry_notices),
+        };
+        let mut answering = answering_model(&agent).await;
+        let mut turns = 0;
+        let mut tool_calls = 0;
+        let mut limit_reached = false;
+        let client_tool_slots = Arc::new(Semaphore::new(
+            self.max_parallel_tool_calls
+                .unwrap_or(Semaphore::MAX_PERMITS)
+                .max(1),
+        ));
 
-        while let Some(event) = stream.next().await {
-            if cancel_token.is_cancelled() {
-                was_cancelled = true;
+        // Race the stream against cancellation so a cancel ends the turn immediately; dropping
+        // the stream aborts the in-flight provider request and tool calls.
+        loop {
+            let event = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                Some(notice) = retries.recv() => {
+                    cx.send_notification(SessionNotification::new(
+                        acp_session_id.clone(),
+                        SessionUpdate::AgentThoughtChunk(ContentChunk::new(ContentBlock::Text(
+                            TextContent::new(retry_notice_text(&notice)),
+                        ))),
+                    ))?;
+                    continue;
+                }
+                event = with_retry_scope(retry_scope.clone(), stream.next()) => event,
+            };
+            let Some(event) = event else {
                 break;
-            }
+            };
 
             match event {
                 Ok(goose::agents::AgentEvent::Message(message)) => {
-                    let mut sessions = self.sessions.lock().await;
-                    let session = sessions.get_mut(&session_id).ok_or_else(|| {
-                        sacp::Error::invalid_params()
-                            .data(format!("Session not found: {}", session_id))
-                    })?;
+                    if message.role == Role::Assistant {
+                        let now_answering = answering_model(&agent).await;
+                        if now_answering != answering {
+                            if let Some((provider, model)) = now_answering.clone() {
+                                cx.send_notification(SessionFailoverNotification {
+                                    session_id: acp_session_id.clone(),
+                                    provider,
+                                    model,
+                                })?;
+                            }
+                            answering = now_answering;
+                        }
+                    }
+                    let requested = message
+                        .content
+                        .iter()
+                        .filter(|content| matches!(content, MessageContent::ToolRequest(_)))
+                        .count() as u32;
+                    if message.role == Role::Assistant && requested > 0 {
+                        turns += 1;
+                        tool_calls += requested;
+                        if self.prompt_limits.exceeded(turns, tool_calls) {
+                            limit_reached = true;
+                            break;
+                        }
+                    }
 
-                    session.messages.push(message.clone());
+                    {
+                        let mut sessions = self.sessions.lock().await;
+                        let session = sessions.get_mut(&session_id).ok_or_else(|| {
+                            sacp::Error::invalid_params()
+                                .data(format!("Session not found: {}", session_id))
+                        })?;
 
-                    for content_item in &message.content {
-                        self.handle_message_content(content_item, &args.session_id, session, cx)
+                        session.messages.push(message.clone());
+
+                        for content_item in &message.content {
+                            // Command replies echo the user's text back, which the client already has.
+                            if message.role == Role::User
+                                && matches!(content_item, MessageContent::Text(_))
+                            {
+                                continue;
+                            }
+                            self.handle_message_content(
+                                content_item,
+                                &profile,
+                                acp_session_id,
+                                session,
+                                cx,
+                            )
                             .await?;
+                        }
+                    }
+
+                    // The agent waits for these results, so run them without holding the session
+                    // lock, and in the background since it may hand over several at once.
+                    for content_item in &message.content {
+                        if let MessageContent::FrontendToolRequest(request) = content_item {
+                            let request = request.clone();
+                            let agent = agent.clone();
+                            let session_id = acp_session_id.clone();
+                            let cancel_token = cancel_token.clone();
+                            let client_tool_slots = client_tool_slots.clone();
+                            let profile = profile.clone();
+                            let file_limits = self.file_limits.clone();
+                            let cwd = cwd.clone();
+                            let cx_clone = cx.clone();
+                            cx.spawn(async move {
+                                let _permit = client_tool_slots.acquire_owned().await;
+                                let result = client_tools::call(
+                                    &request,
+                                    &profile,
+                                    file_limits.as_ref(),
+                                    &cwd,
+                                    &session_id,
+                                    &cancel_token,
+                                    &cx_clone,
+                                )
+                                .await;
+                                agent.handle_tool_result(request.id, result).await;
+                                Ok(())
+                            })?;
+                        }
+                    }
+                }
+                Ok(goose::agents::AgentEvent::McpNotification((request_id, notification))) => {
+                    let Some(line) = tool_output_line(&notification) else {
+                        continue;
+                    };
+                    let mut sessions = self.sessions.lock().await;
+                    let Some(session) = sessions.get_mut(&session_id) else {
+                        continue;
+                    };
+                    let output = session.tool_output.entry(request_id.clone()).or_default();
+                    append_tool_output(output, line);
+                    cx.send_notification(SessionNotification::new(
+                        acp_session_id.clone(),
+                        SessionUpdate::ToolCallUpdate(ToolCallUpdate::new(
+                            ToolCallId::new(request_id),
+                            ToolCallUpdateFields::new()
+                                .status(ToolCallStatus::InProgress)
+                                .content(vec![ToolCallContent::Content(Content::new(
+                                    ContentBlock::Text(TextContent::new(output.clone())),
+                                ))]),
+                        )),
+                    ))?;
+                }
+                Ok(goose::agents::AgentEvent::HistoryReplaced(conversation)) => {
+                    let mut sessions = self.sessions.lock().await;
+                    let Some(session) = sessions.get_mut(&session_id) else {
+                        continue;
+                    };
+                    let messages_before = session.messages.len();
+                    let messages_after = conversation.len();
+                    session.messages = conversation;
+                    // `/clear` also replaces the history, but with nothing.
+                    if messages_after > 0 {
+                        let context_tokens = self
+                            .session_manager
+                            .get_session(&session_id, false)
+                            .await
+                            .ok()
+                            .and_then(|session| session.total_tokens);
+                        cx.send_notification(SessionCompactedNotification {
+                            session_id: acp_session_id.clone(),
+                            messages_before,
+                            messages_after,
+                            context_tokens,
+                        })?;
                     }
                 }
                 Ok(_) => {}
@@ -908,16 +3235,183 @@ impl GooseAcpAgent {
             }
         }
 
-        let mut sessions = self.sessions.lock().await;
-        if let Some(session) = sessions.get_mut(&session_id) {
-            session.cancel_token = None;
+        drop(stream);
+        let was_cancelled = cancel_token.is_cancelled();
+
+        {
+            let mut sessions = self.sessions.lock().await;
+            if let Some(session) = sessions.get_mut(&session_id) {
+                session.cancel_token = None;
+            }
         }
 
-        Ok(PromptResponse::new(if was_cancelled {
+        let response = PromptResponse::new(if was_cancelled {
             StopReason::Cancelled
+        } else if limit_reached {
+            StopReason::MaxTurnRequests
         } else {
             StopReason::EndTurn
-        }))
+        });
+        let usage_after = self.session_manager.get_session(&session_id, false).await;
+        let name_before = usage_before
+            .as_ref()
+            .ok()
+            .map(|session| session.name.clone());
+        self.send_title_when_named(agent, acp_session_id.clone(), name_before, cx)?;
+        Ok(match (usage_before, usage_after) {
+            (Ok(before), Ok(after)) => response.meta(usage_meta(&before, &after)),
+            _ => response,
+        })
+    }
+
+    /// goose names sessions in the background while the first prompts run, so the new title is
+    /// sent whenever that finishes, which may be after the prompt has returned.
+    fn send_title_when_named(
+        &self,
+        agent: Arc<Agent>,
+        session_id: SessionId,
+        name_before: Option<String>,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<(), sacp::Error> {
+        let session_manager = Arc::clone(&self.session_manager);
+        let cx_clone = cx.clone();
+        cx.spawn(async move {
+            agent.wait_for_session_name().await;
+            let session = match session_manager.get_session(&session_id.0, false).await {
+                Ok(session) => session,
+                Err(e) => {
+                    warn!(error = %e, "failed to read session name");
+                    return Ok(());
+                }
+            };
+            if name_before.as_deref() != Some(session.name.as_str()) {
+                cx_clone.send_notification(SessionNotification::new(
+                    session_id,
+                    session_info_update(&session),
+                ))?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn on_read_audit_log(
+        &self,
+        args: ReadAuditLogRequest,
+    ) -> Result<ReadAuditLogResponse, sacp::Error> {
+        self.ensure_authenticated().await?;
+        let records = self
+            .audit_log
+            .read(&args.session_id.0)
+            .map_err(|e| sacp::Error::invalid_params().data(e.to_string()))?;
+        Ok(ReadAuditLogResponse { records })
+    }
+
+    async fn on_read_permission_audit(
+        &self,
+        args: ReadPermissionAuditRequest,
+    ) -> Result<ReadPermissionAuditResponse, sacp::Error> {
+        self.ensure_authenticated().await?;
+        let records = self
+            .audit_log
+            .query_permissions(&args.query)
+            .map_err(|e| sacp::Error::internal_error().data(e.to_string()))?;
+        Ok(ReadPermissionAuditResponse { records })
+    }
+
+    async fn on_set_wire_log(
+        &self,
+        args: SetWireLogRequest,
+    ) -> Result<SetWireLogResponse, sacp::Error> {
+        self.ensure_authenticated().await?;
+        info!(enabled = args.enabled, "wire log toggled");
+        wire_log::set_enabled(args.enabled);
+        Ok(SetWireLogResponse {})
+    }
+
+    async fn on_cancel_tool_call(
+        &self,
+        args: CancelToolCallRequest,
+    ) -> Result<CancelToolCallResponse, sacp::Error> {
+        let (agent, _) = self.session_agent_and_cwd(&args.session_id.0).await?;
+        if !agent.cancel_tool_call(&args.tool_call_id.0) {
+            return Err(sacp::Error::invalid_params()
+                .data(format!("Tool call {} is not running", args.tool_call_id.0)));
+        }
+        Ok(CancelToolCallResponse::default())
+    }
+
+    async fn on_export_session(
+        &self,
+        args: ExportSessionRequest,
+    ) -> Result<ExportSessionResponse, sacp::Error> {
+        self.ensure_authenticated().await?;
+        let session = self
+            .session_manager
+            .get_session(&args.session_id.0, true)
+            .await
+            .map_err(|e| {
+                sacp::Error::invalid_params().data(format!(
+                    "Failed to load session {}: {}",
+                    args.session_id.0, e
+                ))
+            })?;
+        let content = export_session(&session, args.format)
+            .map_err(|e| sacp::Error::internal_error().data(e.to_string()))?;
+        Ok(ExportSessionResponse { content })
+    }
+
+    async fn on_list_sessions(
+        &self,
+        args: ListSessionsRequest,
+    ) -> Result<ListSessionsResponse, sacp::Error> {
+        self.ensure_authenticated().await?;
+        let tags = requested_tags(args.meta.as_ref())?;
+        let sessions = self
+            .session_manager
+            .list_sessions_with_tags(&tags)
+            .await
+            .map_err(|e| {
+                sacp::Error::internal_error().data(format!("Failed to list sessions: {}", e))
+            })?;
+        let sessions = sessions
+            .into_iter()
+            .filter(|session| {
+                args.cwd
+                    .as_ref()
+                    .is_none_or(|cwd| &session.working_dir == cwd)
+            })
+            .map(|session| {
+                let meta = Meta::from_iter([(
+                    "goose".to_string(),
+                    serde_json::json!({ "tags": session.tags }),
+                )]);
+                SessionInfo::new(SessionId::new(session.id), session.working_dir)
+                    .title(session.name)
+                    .updated_at(session.updated_at.to_rfc3339())
+                    .meta(meta)
+            })
+            .collect();
+        Ok(ListSessionsResponse::new(sessions))
+    }
+
+    async fn on_fork_session(
+        &self,
+        args: ForkSessionRequest,
+    ) -> Result<ForkSessionResponse, sacp::Error> {
+        self.ensure_authenticated().await?;
+        let forked = self
+            .session_manager
+            .fork(&args.session_id.0, &args.message_id)
+            .await
+            .map_err(|e| {
+                sacp::Error::invalid_params().data(format!(
+                    "Failed to fork session {}: {}",
+                    args.session_id.0, e
+                ))
+            })?;
+        Ok(ForkSessionResponse {
+            session_id: SessionId::new(forked.id),
+        })
     }
 
     async fn on_cancel(&self, args: CancelNotification) -> Result<(), sacp::Error> {
@@ -943,6 +3437,20 @@ pub struct GooseAcpHandler {
     pub agent: Arc<GooseAcpAgent>,
 }
 
+/// Marks the current `acp.rpc` span as failed when `result` is an error.
+fn traced<T>(result: Result<T, sacp::Error>) -> Result<T, sacp::Error> {
+    if let Err(e) = &result {
+        let span = Span::current();
+        span.record("otel.status_code", "ERROR");
+        span.record("error", field::display(e));
+    }
+    result
+}
+
+fn record_session(session_id: &SessionId) {
+    Span::current().record("session.id", field::display(&session_id.0));
+}
+
 impl JrMessageHandler for GooseAcpHandler {
     type Link = AgentToClient;
 
@@ -950,10 +3458,33 @@ impl JrMessageHandler for GooseAcpHandler {
         "goose-acp"
     }
 
+    /// Each message runs in an `acp.rpc` span carrying its method, session and outcome, so
+    /// OTLP exporters show how long every request between goose and its client took.
     async fn handle_message(
         &mut self,
         message: MessageCx,
         cx: JrConnectionCx<AgentToClient>,
+    ) -> Result<Handled<MessageCx>, sacp::Error> {
+        let span = info_span!(
+            "acp.rpc",
+            otel.kind = "server",
+            rpc.system = "jsonrpc",
+            rpc.method = %message.method(),
+            session.id = field::Empty,
+            otel.status_code = field::Empty,
+            error = field::Empty,
+        );
+        async { traced(self.dispatch(message, cx).await) }
+            .instrument(span)
+            .await
+    }
+}
+
+impl GooseAcpHandler {
+    async fn dispatch(
+        &mut self,
+        message: MessageCx,
+        cx: JrConnectionCx<AgentToClient>,
     ) -> Result<Handled<MessageCx>, sacp::Error> {
         use sacp::util::MatchMessageFrom;
         use sacp::JrRequestCx;
@@ -961,25 +3492,125 @@ impl JrMessageHandler for GooseAcpHandler {
         MatchMessageFrom::new(message, &cx)
             .if_request(
                 |req: InitializeRequest, req_cx: JrRequestCx<InitializeResponse>| async {
-                    req_cx.respond(self.agent.on_initialize(req).await?)
+                    req_cx.respond_with_result(traced(self.agent.on_initialize(req).await))
                 },
             )
             .await
             .if_request(
-                |_req: AuthenticateRequest, req_cx: JrRequestCx<AuthenticateResponse>| async {
-                    req_cx.respond(AuthenticateResponse::new())
+                |req: AuthenticateRequest, req_cx: JrRequestCx<AuthenticateResponse>| async {
+                    req_cx.respond_with_result(traced(self.agent.on_authenticate(req).await))
                 },
             )
             .await
             .if_request(
                 |req: NewSessionRequest, req_cx: JrRequestCx<NewSessionResponse>| async {
-                    req_cx.respond(self.agent.on_new_session(req).await?)
+                    let response = Box::pin(self.agent.on_new_session(req, &cx)).await;
+                    let session_id = response.as_ref().ok().map(|r| r.session_id.clone());
+                    if let Some(session_id) = &session_id {
+                        record_session(session_id);
+                    }
+                    req_cx.respond_with_result(traced(response))?;
+                    match session_id {
+                        Some(session_id) => self.agent.send_available_commands(session_id, &cx),
+                        None => Ok(()),
+                    }
                 },
             )
             .await
             .if_request(
                 |req: LoadSessionRequest, req_cx: JrRequestCx<LoadSessionResponse>| async {
-                    req_cx.respond(self.agent.on_load_session(req, &cx).await?)
+                    let session_id = req.session_id.clone();
+                    record_session(&session_id);
+                    let response = Box::pin(self.agent.on_load_session(req, &cx)).await;
+                    let loaded = response.is_ok();
+                    req_cx.respond_with_result(traced(response))?;
+                    if loaded {
+                        self.agent.send_available_commands(session_id, &cx)?;
+                    }
+                    Ok(())
+                },
+            )
+            .await
+            .if_request(
+                |req: SetSessionModeRequest, req_cx: JrRequestCx<SetSessionModeResponse>| async {
+                    record_session(&req.session_id);
+                    req_cx.respond_with_result(traced(self.agent.on_set_mode(req, &cx).await))
+                },
+            )
+            .await
+            .if_request(
+                |req: SetModelRequest, req_cx: JrRequestCx<SetModelResponse>| async {
+                    record_session(&req.0.session_id);
+                    req_cx.respond_with_result(traced(
+                        Box::pin(self.agent.on_set_model(req.0))
+                            .await
+                            .map(SetModelResponse),
+                    ))
+                },
+            )
+            .await
+            .if_request(
+                |req: AddExtensionRequest, req_cx: JrRequestCx<AddExtensionResponse>| async {
+                    req_cx.respond_with_result(traced(
+                        Box::pin(self.agent.on_add_extension(req, &cx)).await,
+                    ))
+                },
+            )
+            .await
+            .if_request(
+                |req: RemoveExtensionRequest, req_cx: JrRequestCx<RemoveExtensionResponse>| async {
+                    req_cx
+                        .respond_with_result(traced(self.agent.on_remove_extension(req, &cx).await))
+                },
+            )
+            .await
+            .if_request(
+                |req: ReadAuditLogRequest, req_cx: JrRequestCx<ReadAuditLogResponse>| async {
+                    req_cx.respond_with_result(traced(self.agent.on_read_audit_log(req).await))
+                },
+            )
+            .await
+            .if_request(
+                |req: ReadPermissionAuditRequest,
+                 req_cx: JrRequestCx<ReadPermissionAuditResponse>| async {
+                    req_cx
+                        .respond_with_result(traced(self.agent.on_read_permission_audit(req).await))
+                },
+            )
+            .await
+            .if_request(
+                |req: SetWireLogRequest, req_cx: JrRequestCx<SetWireLogResponse>| async {
+                    req_cx.respond_with_result(traced(self.agent.on_set_wire_log(req).await))
+                },
+            )
+            .await
+            .if_request(
+                |req: CancelToolCallRequest, req_cx: JrRequestCx<CancelToolCallResponse>| async {
+                    req_cx.respond_with_result(traced(self.agent.on_cancel_tool_call(req).await))
+                },
+            )
+            .await
+            .if_request(
+                |req: ExportSessionRequest, req_cx: JrRequestCx<ExportSessionResponse>| async {
+                    req_cx.respond_with_result(traced(self.agent.on_export_session(req).await))
+                },
+            )
+            .await
+            .if_request(
+                |req: SessionListRequest, req_cx: JrRequestCx<SessionListResponse>| async {
+                    req_cx.respond_with_result(traced(
+                        Box::pin(self.agent.on_list_sessions(req.0))
+                            .await
+                            .map(SessionListResponse),
+                    ))
+                },
+            )
+            .await
+            .if_request(
+                |req: ForkSessionRequest, req_cx: JrRequestCx<ForkSessionResponse>| async {
+                    req_cx.respond_with_result(traced(
+                        Box::pin(self.agent.on_fork_session(req)).await,
+                    ))
                 },
             )
             .await
@@ -987,25 +3618,31 @@ impl JrMessageHandler for GooseAcpHandler {
                 |req: PromptRequest, req_cx: JrRequestCx<PromptResponse>| async {
                     // Spawn the prompt processing in a task so we don't block the event loop.
                     // This allows permission responses to be processed while the agent is working.
+                    // The span moves into the task so it covers the whole turn.
+                    record_session(&req.session_id);
                     let agent = self.agent.clone();
                     let cx_clone = cx.clone();
-                    cx.spawn(async move {
-                        match agent.on_prompt(req, &cx_clone).await {
-                            Ok(response) => {
-                                req_cx.respond(response)?;
-                            }
-                            Err(e) => {
-                                req_cx.respond_with_error(e)?;
+                    cx.spawn(
+                        async move {
+                            match traced(Box::pin(agent.on_prompt(req, &cx_clone)).await) {
+                                Ok(response) => {
+                                    req_cx.respond(response)?;
+                                }
+                                Err(e) => {
+                                    req_cx.respond_with_error(e)?;
+                                }
                             }
+                            Ok(())
                         }
-                        Ok(())
-                    })?;
+                        .instrument(Span::current()),
+                    )?;
                     Ok(())
                 },
             )
             .await
             .if_notification(|notif: CancelNotification| async {
-                self.agent.on_cancel(notif).await
+                record_session(&notif.session_id);
+                traced(self.agent.on_cancel(notif).await)
             })
             .await
             .done()
@@ -1023,7 +3660,10 @@ where
     AgentToClient::builder()
         .name("goose-acp")
         .with_handler(handler)
-        .serve(ByteStreams::new(write, read))
+        .serve(ByteStreams::new(
+            WireLogged::new(write, Direction::Sent),
+            WireLogged::new(read, Direction::Received),
+        ))
         .await?;
 
     Ok(())
@@ -1043,8 +3683,8 @@ pub async fn run(builtins: Vec<String>) -> Result<()> {
 mod tests {
     use super::*;
     use sacp::schema::{
-        EnvVariable, HttpHeader, McpServer, McpServerHttp, McpServerSse, McpServerStdio,
-        PermissionOptionId, ResourceLink, SelectedPermissionOutcome,
+        EnvVariable, HttpHeader, McpServer, McpServerHttp, McpServerStdio, PermissionOptionId,
+        ResourceLink, SelectedPermissionOutcome,
     };
     use std::io::Write;
     use tempfile::NamedTempFile;
@@ -1094,15 +3734,62 @@ mod tests {
             available_tools: vec![],
         })
     )]
-    #[test_case(
-        McpServer::Sse(McpServerSse::new("test-sse", "https://agent-fin.biodnd.com/sse")),
-        Err("SSE is unsupported, migrate to streamable_http".to_string())
-    )]
     fn test_mcp_server_to_extension_config(
         input: McpServer,
         expected: Result<ExtensionConfig, String>,
     ) {
-        assert_eq!(mcp_server_to_extension_config(input), expected);
+        assert_eq!(
+            mcp_server_to_extension_config(input, &HashMap::new()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_mcp_server_env_merges_session_env() {
+        let session_env = HashMap::from([
+            ("API_KEY".to_string(), "session".to_string()),
+            ("REGION".to_string(), "eu".to_string()),
+        ]);
+        let server = McpServer::Stdio(
+            McpServerStdio::new("github", "github-mcp-server")
+                .env(vec![EnvVariable::new("API_KEY", "server")]),
+        );
+        let Ok(ExtensionConfig::Stdio { envs, .. }) =
+            mcp_server_to_extension_config(server, &session_env)
+        else {
+            panic!("expected a stdio config");
+        };
+        assert_eq!(
+            envs.get_env(),
+            HashMap::from([
+                ("API_KEY".to_string(), "server".to_string()),
+                ("REGION".to_string(), "eu".to_string()),
+            ])
+        );
+    }
+
+    #[test_case(serde_json::json!({"goose": {"mcpEnv": {"API_KEY": "secret"}}}), Ok(vec![("API_KEY", "secret")]); "env")]
+    #[test_case(serde_json::json!({"goose": {}}), Ok(vec![]); "none")]
+    #[test_case(serde_json::json!({"goose": {"mcpEnv": {"PATH": "/tmp"}}}), Err(()); "disallowed")]
+    #[test_case(serde_json::json!({"goose": {"mcpEnv": {"API_KEY": 1}}}), Err(()); "invalid")]
+    fn test_requested_mcp_env(meta: serde_json::Value, expected: Result<Vec<(&str, &str)>, ()>) {
+        let meta = meta.as_object().unwrap().clone();
+        let expected = expected.map(|env| {
+            env.into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>()
+        });
+        assert_eq!(requested_mcp_env(Some(&meta)).map_err(|_| ()), expected);
+    }
+
+    #[test]
+    fn test_requested_mcp_env_explains_path() {
+        let meta = serde_json::json!({"goose": {"mcpEnv": {"Path": "/opt/bin"}}});
+        let err = requested_mcp_env(Some(meta.as_object().unwrap())).unwrap_err();
+        assert!(err
+            .data
+            .and_then(|data| data.as_str().map(str::to_string))
+            .is_some_and(|data| data.contains("absolute path")));
     }
 
     fn new_resource_link(content: &str) -> anyhow::Result<(ResourceLink, NamedTempFile)> {
@@ -1166,6 +3853,261 @@ print(\"hello, world\")
         assert_eq!(format_tool_name("__tool"), ": Tool");
     }
 
+    #[test_case(
+        "- [x] Read the code\n- [~] Write the fix\n  - [ ] Add a test\nNotes are ignored",
+        vec![
+            PlanEntry::new("Read the code", PlanEntryPriority::Medium, PlanEntryStatus::Completed),
+            PlanEntry::new("Write the fix", PlanEntryPriority::Medium, PlanEntryStatus::InProgress),
+            PlanEntry::new("Add a test", PlanEntryPriority::Medium, PlanEntryStatus::Pending),
+        ]
+        ; "checklist"
+    )]
+    #[test_case("", vec![] ; "empty")]
+    fn test_plan_from_todo(content: &str, expected: Vec<PlanEntry>) {
+        assert_eq!(plan_from_todo(content), Plan::new(expected));
+    }
+
+    #[test]
+    fn test_request_locations() {
+        let cwd = tempfile::tempdir().unwrap();
+        fs::write(cwd.path().join("main.rs"), "").unwrap();
+        let tool_call = |name: &'static str, arguments: serde_json::Value| CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: name.into(),
+            arguments: arguments.as_object().cloned(),
+        };
+
+        assert_eq!(
+            request_locations(
+                &tool_call(
+                    "developer__text_editor",
+                    serde_json::json!({"command": "insert", "path": "/src/lib.rs", "insert_line": 4}),
+                ),
+                cwd.path(),
+            ),
+            vec![ToolCallLocation::new("/src/lib.rs").line(4)]
+        );
+        assert_eq!(
+            request_locations(
+                &tool_call(
+                    "developer__shell",
+                    serde_json::json!({"command": "cat -n main.rs missing.rs | head"}),
+                ),
+                cwd.path(),
+            ),
+            vec![ToolCallLocation::new(cwd.path().join("main.rs"))]
+        );
+        assert!(request_locations(
+            &tool_call("todo__todo_read", serde_json::json!({})),
+            cwd.path()
+        )
+        .is_empty());
+    }
+
+    #[test_case(
+        serde_json::json!({"command": "str_replace", "path": "/src/lib.rs", "old_str": "a", "new_str": "b"}),
+        vec![Diff::new("/src/lib.rs", "b").old_text("a".to_string())]
+        ; "str_replace"
+    )]
+    #[test_case(
+        serde_json::json!({
+            "command": "str_replace",
+            "path": "/repo",
+            "diff": "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n fn main() {\n-    old();\n+    new();\n"
+        }),
+        vec![Diff::new("/repo/src/lib.rs", "fn main() {\n    new();\n")
+            .old_text("fn main() {\n    old();\n".to_string())]
+        ; "unified_diff"
+    )]
+    #[test_case(
+        serde_json::json!({"command": "write", "path": "/src/new.rs", "file_text": "new"}),
+        vec![Diff::new("/src/new.rs", "new")]
+        ; "write"
+    )]
+    #[test_case(
+        serde_json::json!({"command": "view", "path": "/src/lib.rs"}),
+        vec![]
+        ; "view"
+    )]
+    fn test_edit_diffs(arguments: serde_json::Value, expected: Vec<Diff>) {
+        let tool_call = CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "developer__text_editor".into(),
+            arguments: arguments.as_object().cloned(),
+        };
+        assert_eq!(edit_diffs(&tool_call, Path::new("/work")), expected);
+    }
+
+    #[test]
+    fn test_edit_diffs_resolve_against_cwd() {
+        let cwd = tempfile::tempdir().unwrap();
+        std::fs::write(cwd.path().join("main.rs"), "old").unwrap();
+        let tool_call = |name: &str, arguments: serde_json::Value| CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: name.to_string().into(),
+            arguments: arguments.as_object().cloned(),
+        };
+
+        assert_eq!(
+            edit_diffs(
+                &tool_call(
+                    "developer__text_editor",
+                    serde_json::json!({"command": "write", "path": "main.rs", "file_text": "new"}),
+                ),
+                cwd.path(),
+            ),
+            vec![Diff::new(cwd.path().join("main.rs"), "new").old_text("old".to_string())]
+        );
+        assert_eq!(
+            edit_diffs(
+                &tool_call(
+                    "editor__write_text_file",
+                    serde_json::json!({"path": "lib.rs", "content": "new"}),
+                ),
+                cwd.path(),
+            ),
+            vec![Diff::new(cwd.path().join("lib.rs"), "new")]
+        );
+        assert_eq!(
+            edit_diffs(
+                &tool_call(
+                    "developer__text_editor",
+                    serde_json::json!({
+                        "command": "str_replace",
+                        "path": "main.rs",
+                        "diff": "--- a/lib.rs\n+++ b/lib.rs\n@@ -1 +1 @@\n-old\n+new\n"
+                    }),
+                ),
+                cwd.path(),
+            ),
+            vec![Diff::new(cwd.path().join("lib.rs"), "new\n").old_text("old\n".to_string())]
+        );
+    }
+
+    #[test_case(
+        rmcp::model::Content::image("aW1n", "image/png"),
+        ToolCallContent::Content(Content::new(ContentBlock::Image(ImageContent::new("aW1n", "image/png"))))
+        ; "image"
+    )]
+    #[test_case(
+        rmcp::model::Content::resource(ResourceContents::BlobResourceContents {
+            uri: "file:///tmp/chart.png".into(),
+            mime_type: Some("image/png".into()),
+            blob: "aW1n".into(),
+            meta: None,
+        }),
+        ToolCallContent::Content(Content::new(ContentBlock::Image(
+            ImageContent::new("aW1n", "image/png").uri("file:///tmp/chart.png".to_string())
+        )))
+        ; "image_blob_resource"
+    )]
+    fn test_build_tool_call_content(content: rmcp::model::Content, expected: ToolCallContent) {
+        assert_eq!(
+            build_tool_call_content(&Ok(CallToolResult::success(vec![content])), true),
+            vec![expected]
+        );
+    }
+
+    #[test]
+    fn test_build_tool_call_content_leaves_out_images() {
+        let result = Ok(CallToolResult::success(vec![
+            rmcp::model::Content::text("chart"),
+            rmcp::model::Content::image("aW1n", "image/png"),
+        ]));
+        assert_eq!(
+            build_tool_call_content(&result, false),
+            vec![ToolCallContent::Content(Content::new(ContentBlock::Text(
+                TextContent::new("chart")
+            )))]
+        );
+    }
+
+    #[test_case("relative/dir", false; "relative")]
+    #[test_case("/definitely/not/a/real/dir", false; "missing")]
+    #[test_case("/", true; "existing")]
+    fn test_validate_cwd(cwd: &str, valid: bool) {
+        assert_eq!(validate_cwd(Path::new(cwd)).is_ok(), valid);
+    }
+
+    #[test_case(PromptLimits::default(), 100, 100, false; "unlimited")]
+    #[test_case(PromptLimits { max_turns: Some(2), max_tool_calls: None }, 2, 9, false; "at_max_turns")]
+    #[test_case(PromptLimits { max_turns: Some(2), max_tool_calls: None }, 3, 3, true; "over_max_turns")]
+    #[test_case(PromptLimits { max_turns: None, max_tool_calls: Some(4) }, 2, 5, true; "over_max_tool_calls")]
+    fn test_prompt_limits_exceeded(
+        limits: PromptLimits,
+        turns: u32,
+        tool_calls: u32,
+        expected: bool,
+    ) {
+        assert_eq!(limits.exceeded(turns, tool_calls), expected);
+    }
+
+    #[test_case(serde_json::json!({"type": "shell_output", "stream": "stdout", "output": "compiling"}), Some("compiling"); "shell_output")]
+    #[test_case(serde_json::json!({"type": "notification", "output": "compiling"}), None; "other_type")]
+    #[test_case(serde_json::json!("compiling"), None; "plain_string")]
+    fn test_tool_output_line(data: serde_json::Value, expected: Option<&str>) {
+        let notification = ServerNotification::LoggingMessageNotification(
+            rmcp::model::LoggingMessageNotification::new(
+                rmcp::model::LoggingMessageNotificationParam {
+                    level: rmcp::model::LoggingLevel::Info,
+                    logger: None,
+                    data,
+                },
+            ),
+        );
+        assert_eq!(tool_output_line(&notification), expected);
+    }
+
+    #[test]
+    fn test_append_tool_output_keeps_tail() {
+        let mut output = String::new();
+        let line = "é".repeat(1000);
+        for _ in 0..20 {
+            append_tool_output(&mut output, &line);
+        }
+        assert!(output.len() <= MAX_STREAMED_TOOL_OUTPUT);
+        assert!(output.ends_with(&format!("{}\n", line)));
+    }
+
+    #[test]
+    fn test_switched_model_config_keeps_session_settings() {
+        let current = ModelConfig::new("gpt-5-nano")
+            .unwrap()
+            .with_temperature(Some(0.2))
+            .with_max_tokens(Some(512))
+            .with_stop_sequences(Some(vec!["END".to_string()]));
+
+        let switched = switched_model_config(current, "gpt-5-mini");
+        assert_eq!(switched.model_name, "gpt-5-mini");
+        assert_eq!(switched.temperature, Some(0.2));
+        assert_eq!(switched.max_tokens, Some(512));
+        assert_eq!(switched.stop_sequences, Some(vec!["END".to_string()]));
+    }
+
+    #[test]
+    fn test_session_mode_state_round_trips_goose_modes() {
+        let state = session_mode_state(GooseMode::SmartApprove);
+
+        assert_eq!(state.current_mode_id, SessionModeId::new("smart_approve"));
+        let modes: Vec<GooseMode> = state
+            .available_modes
+            .iter()
+            .map(|mode| mode.id.0.parse().unwrap())
+            .collect();
+        assert_eq!(
+            modes,
+            vec![
+                GooseMode::Auto,
+                GooseMode::Approve,
+                GooseMode::SmartApprove,
+                GooseMode::Chat
+            ]
+        );
+    }
+
     #[test_case(
         RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(PermissionOptionId::from("allow_once".to_string()))),
         PermissionConfirmation { principal_type: PrincipalType::Tool, permission: Permission::AllowOnce };
@@ -1176,6 +4118,21 @@ print(\"hello, world\")
         PermissionConfirmation { principal_type: PrincipalType::Tool, permission: Permission::AlwaysAllow };
         "allow_always_maps_to_always_allow"
     )]
+    #[test_case(
+        RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(PermissionOptionId::from("allow_session".to_string()))),
+        PermissionConfirmation { principal_type: PrincipalType::Tool, permission: Permission::AllowOnce };
+        "allow_session_maps_to_allow_once"
+    )]
+    #[test_case(
+        RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(PermissionOptionId::from("allow_hour".to_string()))),
+        PermissionConfirmation { principal_type: PrincipalType::Tool, permission: Permission::AllowOnce };
+        "allow_hour_maps_to_allow_once"
+    )]
+    #[test_case(
+        RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(PermissionOptionId::from("allow_prefix".to_string()))),
+        PermissionConfirmation { principal_type: PrincipalType::Tool, permission: Permission::AllowOnce };
+        "allow_prefix_maps_to_allow_once"
+    )]
     #[test_case(
         RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(PermissionOptionId::from("reject_once".to_string()))),
         PermissionConfirmation { principal_type: PrincipalType::Tool, permission: Permission::DenyOnce };
@@ -1202,4 +4159,151 @@ print(\"hello, world\")
     ) {
         assert_eq!(outcome_to_confirmation(&input), expected);
     }
+
+    #[test_case("developer__shell", serde_json::json!({"command": "cargo test -p goose"}), Some("cargo test"); "shell")]
+    #[test_case("developer__shell", serde_json::json!({"command": "  git status"}), Some("git status"); "leading_space")]
+    #[test_case("developer__shell", serde_json::json!({"command": "ls -la"}), Some("ls"); "flag")]
+    #[test_case("developer__shell", serde_json::json!({"command": "cat code.txt"}), Some("cat"); "path")]
+    #[test_case("developer__shell", serde_json::json!({}), None; "no_command")]
+    #[test_case("developer__text_editor", serde_json::json!({"command": "view"}), None; "not_shell")]
+    fn test_command_prefix(tool_name: &str, arguments: serde_json::Value, expected: Option<&str>) {
+        let arguments = arguments.as_object().unwrap();
+        assert_eq!(command_prefix(tool_name, arguments).as_deref(), expected);
+    }
+
+    #[test_case(
+        Ok(CreateElicitationResponse {
+            action: ElicitationAction::Accept,
+            content: Some(serde_json::json!({"branch": "main"})),
+        }),
+        Some(serde_json::json!({"branch": "main"}));
+        "accept"
+    )]
+    #[test_case(
+        Ok(CreateElicitationResponse { action: ElicitationAction::Accept, content: None }),
+        Some(serde_json::json!({}));
+        "accept_without_content"
+    )]
+    #[test_case(
+        Ok(CreateElicitationResponse { action: ElicitationAction::Decline, content: None }),
+        None;
+        "decline"
+    )]
+    #[test_case(Err(sacp::Error::method_not_found()), None; "unsupported")]
+    fn test_elicitation_answer(
+        result: Result<CreateElicitationResponse, sacp::Error>,
+        expected: Option<serde_json::Value>,
+    ) {
+        assert_eq!(elicitation_answer(result), expected);
+    }
+
+    #[test_case(
+        serde_json::json!({"goose": {"temperature": 0.2, "maxTokens": 512, "reasoningEffort": "high"}}),
+        Ok(Some(GenerationOverrides {
+            temperature: Some(0.2),
+            max_tokens: Some(512),
+            reasoning_effort: Some("high".to_string()),
+        }));
+        "all"
+    )]
+    #[test_case(serde_json::json!({"goose": {"provider": "openai"}}), Ok(None); "none")]
+    #[test_case(serde_json::json!({}), Ok(None); "no_goose_meta")]
+    #[test_case(serde_json::json!({"goose": {"reasoningEffort": "max"}}), Err(()); "unknown_effort")]
+    #[test_case(serde_json::json!({"goose": {"maxTokens": "lots"}}), Err(()); "invalid_type")]
+    fn test_requested_overrides(
+        meta: serde_json::Value,
+        expected: Result<Option<GenerationOverrides>, ()>,
+    ) {
+        let meta = meta.as_object().unwrap().clone();
+        assert_eq!(requested_overrides(Some(&meta)).map_err(|_| ()), expected);
+    }
+
+    #[test_case(
+        serde_json::json!({"goose": {"tools": {"allow": ["developer"], "deny": ["developer__shell"]}}}),
+        Ok(Some(ToolFilter {
+            allow: vec!["developer".to_string()],
+            deny: vec!["developer__shell".to_string()],
+        }));
+        "allow_and_deny"
+    )]
+    #[test_case(serde_json::json!({"goose": {"tools": {}}}), Ok(None); "empty")]
+    #[test_case(serde_json::json!({"goose": {}}), Ok(None); "none")]
+    #[test_case(serde_json::json!({"goose": {"tools": {"allow": "developer"}}}), Err(()); "invalid")]
+    fn test_requested_tool_filter(
+        meta: serde_json::Value,
+        expected: Result<Option<ToolFilter>, ()>,
+    ) {
+        let meta = meta.as_object().unwrap().clone();
+        let filter = requested_tool_filter(Some(&meta)).map(|filter| filter.map(|filter| filter.0));
+        assert_eq!(filter.map_err(|_| ()), expected);
+    }
+
+    #[test_case(serde_json::json!({"goose": {"permissionScope": "session"}}), Ok(PermissionScope::Session); "session")]
+    #[test_case(serde_json::json!({"goose": {"permissionScope": "workspace"}}), Ok(PermissionScope::Workspace); "workspace")]
+    #[test_case(serde_json::json!({"goose": {}}), Ok(PermissionScope::Workspace); "default")]
+    #[test_case(serde_json::json!({"goose": {"permissionScope": "global"}}), Err(()); "unknown")]
+    fn test_requested_permission_scope(
+        meta: serde_json::Value,
+        expected: Result<PermissionScope, ()>,
+    ) {
+        let meta = meta.as_object().unwrap().clone();
+        assert_eq!(
+            requested_permission_scope(Some(&meta)).map_err(|_| ()),
+            expected
+        );
+    }
+
+    #[test_case(serde_json::json!({"goose": {"tags": {"project": "web"}}}), Ok(HashMap::from([("project".to_string(), "web".to_string())])); "tags")]
+    #[test_case(serde_json::json!({"goose": {}}), Ok(HashMap::new()); "none")]
+    #[test_case(serde_json::json!({"goose": {"tags": ["web"]}}), Err(()); "invalid")]
+    fn test_requested_tags(meta: serde_json::Value, expected: Result<HashMap<String, String>, ()>) {
+        let meta = meta.as_object().unwrap().clone();
+        assert_eq!(requested_tags(Some(&meta)).map_err(|_| ()), expected);
+    }
+
+    #[test_case(true, 20_000, "Rate limited, retrying in 20s (attempt 1 of 3)"; "rate_limited")]
+    #[test_case(false, 300, "Model request failed, retrying in 1s (attempt 1 of 3)"; "sub_second")]
+    fn test_retry_notice_text(rate_limited: bool, delay_ms: u64, expected: &str) {
+        let notice = RetryNotice {
+            attempt: 1,
+            max_retries: 3,
+            delay: Duration::from_millis(delay_ms),
+            rate_limited,
+            error: "boom".to_string(),
+        };
+        assert_eq!(retry_notice_text(&notice), expected);
+    }
+
+    #[test]
+    fn test_permission_options() {
+        let ids = |options: Vec<PermissionOption>| -> Vec<String> {
+            options
+                .into_iter()
+                .map(|option| option.option_id.0.to_string())
+                .collect()
+        };
+        assert_eq!(
+            ids(permission_options("Developer: Shell", Some("cargo"))),
+            vec![
+                "allow_always",
+                "allow_session",
+                "allow_hour",
+                "allow_prefix",
+                "allow_once",
+                "reject_once",
+                "reject_always"
+            ]
+        );
+        assert_eq!(
+            ids(permission_options("Developer: Text Editor", None)),
+            vec![
+                "allow_always",
+                "allow_session",
+                "allow_hour",
+                "allow_once",
+                "reject_once",
+                "reject_always"
+            ]
+        );
+    }
 }
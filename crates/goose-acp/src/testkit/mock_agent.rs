@@ -0,0 +1,341 @@
+//! A scripted in-process ACP agent for testing client code without starting a real agent,
+//! available through the `test-utils` (or `testkit`) feature.
+//! Each prompt plays the next [`MockTurn`]; what the client sent back is recorded for
+//! assertions.
+
+use sacp::schema::{
+    CancelNotification, ContentBlock, ContentChunk, InitializeRequest, InitializeResponse,
+    NewSessionRequest, NewSessionResponse, PermissionOption, PromptRequest, PromptResponse,
+    RequestPermissionOutcome, RequestPermissionRequest, SessionId, SessionNotification,
+    SessionUpdate, StopReason, TextContent, ToolCall, ToolCallUpdate,
+};
+use sacp::{
+    AgentToClient, ClientToAgent, Component, Handled, JrConnectionCx, JrMessageHandler,
+    JrRequestCx, MessageCx,
+};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug)]
+enum MockStep {
+    Update(SessionUpdate),
+    RequestPermission {
+        tool_call: ToolCallUpdate,
+        options: Vec<PermissionOption>,
+    },
+}
+
+/// What the agent does in response to one prompt.
+#[derive(Debug)]
+pub struct MockTurn {
+    steps: Vec<MockStep>,
+    result: Result<StopReason, sacp::Error>,
+}
+
+impl Default for MockTurn {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockTurn {
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            result: Ok(StopReason::EndTurn),
+        }
+    }
+
+    pub fn update(mut self, update: SessionUpdate) -> Self {
+        self.steps.push(MockStep::Update(update));
+        self
+    }
+
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.update(SessionUpdate::AgentMessageChunk(ContentChunk::new(
+            ContentBlock::Text(TextContent::new(text)),
+        )))
+    }
+
+    pub fn tool_call(self, tool_call: ToolCall) -> Self {
+        self.update(SessionUpdate::ToolCall(tool_call))
+    }
+
+    pub fn tool_call_update(self, update: ToolCallUpdate) -> Self {
+        self.update(SessionUpdate::ToolCallUpdate(update))
+    }
+
+    /// Asks the client to approve `tool_call`, waiting for its answer before the next step.
+    /// Answers are available from [`MockAcpAgent::permission_outcomes`].
+    pub fn request_permission(
+        mut self,
+        tool_call: ToolCallUpdate,
+        options: Vec<PermissionOption>,
+    ) -> Self {
+        self.steps
+            .push(MockStep::RequestPermission { tool_call, options });
+        self
+    }
+
+    pub fn stop(mut self, stop_reason: StopReason) -> Self {
+        self.result = Ok(stop_reason);
+        self
+    }
+
+    /// Fails the prompt with `error` once the steps have played.
+    pub fn fail(mut self, error: sacp::Error) -> Self {
+        self.result = Err(error);
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    turns: VecDeque<MockTurn>,
+    new_session_error: Option<sacp::Error>,
+    sessions: usize,
+    prompts: Vec<PromptRequest>,
+    permission_outcomes: Vec<RequestPermissionOutcome>,
+    cancellations: Vec<SessionId>,
+}
+
+/// Clones share the script and the recordings, so a test can keep one to inspect after
+/// handing another to the connection.
+#[derive(Debug, Clone, Default)]
+pub struct MockAcpAgent {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockAcpAgent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a turn; prompts play queued turns in order and fail once they run out.
+    pub fn turn(self, turn: MockTurn) -> Self {
+        self.state.lock().unwrap().turns.push_back(turn);
+        self
+    }
+
+    /// Fails the next `session/new` with `error`.
+    pub fn fail_new_session(self, error: sacp::Error) -> Self {
+        self.state.lock().unwrap().new_session_error = Some(error);
+        self
+    }
+
+    pub fn prompts(&self) -> Vec<PromptRequest> {
+        self.state.lock().unwrap().prompts.clone()
+    }
+
+    pub fn permission_outcomes(&self) -> Vec<RequestPermissionOutcome> {
+        self.state.lock().unwrap().permission_outcomes.clone()
+    }
+
+    pub fn cancellations(&self) -> Vec<SessionId> {
+        self.state.lock().unwrap().cancellations.clone()
+    }
+
+    fn on_new_session(&self) -> Result<NewSessionResponse, sacp::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(error) = state.new_session_error.take() {
+            return Err(error);
+        }
+        state.sessions += 1;
+        Ok(NewSessionResponse::new(format!(
+            "mock-session-{}",
+            state.sessions
+        )))
+    }
+
+    async fn on_prompt(
+        &self,
+        req: PromptRequest,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<PromptResponse, sacp::Error> {
+        let session_id = req.session_id.clone();
+        let turn = {
+            let mut state = self.state.lock().unwrap();
+            state.prompts.push(req);
+            state.turns.pop_front()
+        };
+        let Some(turn) = turn else {
+            return Err(sacp::Error::internal_error().data("No scripted turn left"));
+        };
+
+        for step in turn.steps {
+            match step {
+                MockStep::Update(update) => {
+                    cx.send_notification(SessionNotification::new(session_id.clone(), update))?;
+                }
+                MockStep::RequestPermission { tool_call, options } => {
+                    let response = cx
+                        .send_request(RequestPermissionRequest::new(
+                            session_id.clone(),
+                            tool_call,
+                            options,
+                        ))
+                        .block_task()
+                        .await?;
+                    self.state
+                        .lock()
+                        .unwrap()
+                        .permission_outcomes
+                        .push(response.outcome);
+                }
+            }
+        }
+
+        turn.result.map(PromptResponse::new)
+    }
+}
+
+struct MockAcpHandler {
+    agent: MockAcpAgent,
+}
+
+impl JrMessageHandler for MockAcpHandler {
+    type Link = AgentToClient;
+
+    fn describe_chain(&self) -> impl std::fmt::Debug {
+        "mock-acp-agent"
+    }
+
+    async fn handle_message(
+        &mut self,
+        message: MessageCx,
+        cx: JrConnectionCx<AgentToClient>,
+    ) -> Result<Handled<MessageCx>, sacp::Error> {
+        use sacp::util::MatchMessageFrom;
+
+        MatchMessageFrom::new(message, &cx)
+            .if_request(
+                |req: InitializeRequest, req_cx: JrRequestCx<InitializeResponse>| async move {
+                    req_cx.respond(InitializeResponse::new(req.protocol_version))
+                },
+            )
+            .await
+            .if_request(
+                |_req: NewSessionRequest, req_cx: JrRequestCx<NewSessionResponse>| async {
+                    req_cx.respond_with_result(self.agent.on_new_session())
+                },
+            )
+            .await
+            .if_request(
+                |req: PromptRequest, req_cx: JrRequestCx<PromptResponse>| async {
+                    // Permission requests are answered through the event loop, so turns can't
+                    // run on it.
+                    let agent = self.agent.clone();
+                    let cx_clone = cx.clone();
+                    cx.spawn(async move {
+                        req_cx.respond_with_result(agent.on_prompt(req, &cx_clone).await)
+                    })
+                },
+            )
+            .await
+            .if_notification(|notif: CancelNotification| async {
+                self.agent
+                    .state
+                    .lock()
+                    .unwrap()
+                    .cancellations
+                    .push(notif.session_id);
+                Ok(())
+            })
+            .await
+            .done()
+    }
+}
+
+impl Component<AgentToClient> for MockAcpAgent {
+    async fn serve(self, client: impl Component<ClientToAgent>) -> Result<(), sacp::Error> {
+        AgentToClient::builder()
+            .name("mock-acp-agent")
+            .with_handler(MockAcpHandler { agent: self })
+            .serve(client)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sacp::schema::{
+        PermissionOptionKind, ProtocolVersion, RequestPermissionResponse,
+        SelectedPermissionOutcome, ToolCallUpdateFields,
+    };
+
+    #[tokio::test]
+    async fn test_mock_agent_plays_turns() {
+        let agent = MockAcpAgent::new()
+            .turn(
+                MockTurn::new()
+                    .tool_call(ToolCall::new("call_1", "Read file"))
+                    .request_permission(
+                        ToolCallUpdate::new("call_1", ToolCallUpdateFields::new()),
+                        vec![PermissionOption::new(
+                            "allow",
+                            "Allow",
+                            PermissionOptionKind::AllowOnce,
+                        )],
+                    )
+                    .text("done"),
+            )
+            .turn(MockTurn::new().fail(sacp::Error::internal_error()));
+        let updates = Arc::new(Mutex::new(Vec::new()));
+
+        ClientToAgent::builder()
+            .on_receive_notification(
+                {
+                    let updates = updates.clone();
+                    async move |notification: SessionNotification, _cx| {
+                        updates.lock().unwrap().push(notification.update);
+                        Ok(())
+                    }
+                },
+                sacp::on_receive_notification!(),
+            )
+            .on_receive_request(
+                async move |req: RequestPermissionRequest, request_cx, _connection_cx| {
+                    request_cx.respond(RequestPermissionResponse::new(
+                        RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(
+                            req.options[0].option_id.clone(),
+                        )),
+                    ))
+                },
+                sacp::on_receive_request!(),
+            )
+            .connect_to(agent.clone())
+            .unwrap()
+            .run_until(async |cx: JrConnectionCx<ClientToAgent>| {
+                cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                    .block_task()
+                    .await?;
+                let session = cx
+                    .send_request(NewSessionRequest::new("/tmp"))
+                    .block_task()
+                    .await?;
+                let prompt = PromptRequest::new(session.session_id, vec![]);
+
+                let response = cx.send_request(prompt.clone()).block_task().await?;
+                assert_eq!(response.stop_reason, StopReason::EndTurn);
+                assert!(cx.send_request(prompt).block_task().await.is_err());
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(agent.prompts().len(), 2);
+        assert!(matches!(
+            agent.permission_outcomes()[..],
+            [RequestPermissionOutcome::Selected(_)]
+        ));
+        let updates = updates.lock().unwrap();
+        assert!(matches!(
+            updates[..],
+            [
+                SessionUpdate::ToolCall(_),
+                SessionUpdate::AgentMessageChunk(_)
+            ]
+        ));
+    }
+}
@@ -0,0 +1,383 @@
+//! goose's ACP conformance scenarios, usable against any agent. Enable the `testkit` feature,
+//! implement [`AcpTarget`] to start the agent under test, and call the scenario functions from
+//! your own tests.
+//!
+//! Scenarios script the model with [`OpenAiFixture`], so the agent must be pointed at
+//! [`AgentSetup::model_url`] as an OpenAI-compatible chat completions endpoint. MCP tools are
+//! served over streamable HTTP and are expected to reach the model as `<server>__<tool>`.
+//!
+//! For the other side, [`MockAcpAgent`] stands in for an agent when testing client code.
+
+mod fixtures;
+mod mock_agent;
+
+pub use fixtures::{
+    ExpectedSessionId, Lookup, McpFixture, OpenAiFixture, ValidatingService, FAKE_CODE,
+};
+pub use mock_agent::{MockAcpAgent, MockTurn};
+
+use async_trait::async_trait;
+use sacp::schema::{
+    ClientCapabilities, ContentBlock, ContentChunk, FileSystemCapability, InitializeRequest,
+    McpServer, McpServerHttp, NewSessionRequest, PermissionOptionKind, PromptRequest,
+    ProtocolVersion, ReadTextFileRequest, ReadTextFileResponse, RequestPermissionOutcome,
+    RequestPermissionRequest, RequestPermissionResponse, SelectedPermissionOutcome, SessionId,
+    SessionNotification, SessionUpdate, StopReason, TextContent, ToolCallId, ToolCallStatus,
+    ToolCallUpdate, ToolCallUpdateFields,
+};
+use sacp::{AgentToClient, ClientToAgent, DynComponent, JrConnectionCx};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const BASIC_RESPONSE: &str = include_str!("../../tests/test_data/openai_basic_response.txt");
+pub const TOOL_CALL_RESPONSE: &str =
+    include_str!("../../tests/test_data/openai_tool_call_response.txt");
+pub const TOOL_RESULT_RESPONSE: &str =
+    include_str!("../../tests/test_data/openai_tool_result_response.txt");
+
+const TOOL_PROMPT: &str = "Use the get_code tool and output only its result.";
+
+/// How a scenario needs the agent under test configured.
+#[derive(Debug, Clone)]
+pub struct AgentSetup {
+    /// Base URL of the mock model; requests go to `{model_url}/v1/chat/completions`.
+    pub model_url: String,
+    /// Empty directory the agent may keep its state in.
+    pub data_dir: PathBuf,
+    /// Whether the agent must ask the client before running a tool.
+    pub approve_tools: bool,
+}
+
+#[async_trait]
+pub trait AcpTarget: Send + Sync {
+    /// Starts a fresh agent for one scenario.
+    async fn connect(&self, setup: AgentSetup) -> anyhow::Result<DynComponent<AgentToClient>>;
+
+    /// Whether the agent sends the ACP session ID on model and MCP requests, as goose does.
+    /// When it does, scenarios fail on requests carrying any other ID.
+    fn sends_session_id(&self) -> bool {
+        false
+    }
+}
+
+fn expected_session_id(target: &impl AcpTarget) -> ExpectedSessionId {
+    if target.sends_session_id() {
+        ExpectedSessionId::default()
+    } else {
+        ExpectedSessionId::unchecked()
+    }
+}
+
+async fn prompt(
+    cx: &JrConnectionCx<ClientToAgent>,
+    session_id: SessionId,
+    text: &str,
+) -> StopReason {
+    cx.send_request(PromptRequest::new(
+        session_id,
+        vec![ContentBlock::Text(TextContent::new(text))],
+    ))
+    .block_task()
+    .await
+    .unwrap()
+    .stop_reason
+}
+
+/// A prompt answered with plain text.
+pub async fn basic_completion(target: &impl AcpTarget) {
+    let data_dir = tempfile::tempdir().unwrap();
+    let expected_session_id = expected_session_id(target);
+    let openai = OpenAiFixture::new(
+        vec![(r#"what is 1+1""#.to_string(), BASIC_RESPONSE)],
+        expected_session_id.clone(),
+    )
+    .await;
+    let agent = target
+        .connect(AgentSetup {
+            model_url: openai.server.uri(),
+            data_dir: data_dir.path().to_path_buf(),
+            approve_tools: false,
+        })
+        .await
+        .unwrap();
+
+    run_session(
+        agent,
+        vec![],
+        None,
+        expected_session_id.clone(),
+        |cx, session_id, updates| async move {
+            assert_eq!(
+                prompt(&cx, session_id, "what is 1+1").await,
+                StopReason::EndTurn
+            );
+            wait_for_text(&updates, "2").await;
+        },
+    )
+    .await;
+
+    expected_session_id.assert_no_errors();
+}
+
+/// A prompt whose answer comes from a tool on an MCP server the client passed to the session.
+pub async fn mcp_tool_call(target: &impl AcpTarget) {
+    tool_call(target, false, None, ToolCallStatus::Completed).await;
+}
+
+/// A tool call the client is asked to approve, answered with an option of `kind`, or cancelled
+/// when `kind` is `None`. The tool must run only when the answer allows it.
+pub async fn permission(target: &impl AcpTarget, kind: Option<PermissionOptionKind>) {
+    let expected_status = match kind {
+        Some(PermissionOptionKind::AllowOnce | PermissionOptionKind::AllowAlways) => {
+            ToolCallStatus::Completed
+        }
+        _ => ToolCallStatus::Failed,
+    };
+    tool_call(target, true, kind, expected_status).await;
+}
+
+async fn tool_call(
+    target: &impl AcpTarget,
+    approve_tools: bool,
+    select: Option<PermissionOptionKind>,
+    expected_status: ToolCallStatus,
+) {
+    let data_dir = tempfile::tempdir().unwrap();
+    let expected_session_id = expected_session_id(target);
+    let mcp = McpFixture::new(expected_session_id.clone()).await;
+    let openai = OpenAiFixture::new(
+        vec![
+            (format!(r#"{TOOL_PROMPT}""#), TOOL_CALL_RESPONSE),
+            (format!(r#""content":"{FAKE_CODE}""#), TOOL_RESULT_RESPONSE),
+        ],
+        expected_session_id.clone(),
+    )
+    .await;
+    let agent = target
+        .connect(AgentSetup {
+            model_url: openai.server.uri(),
+            data_dir: data_dir.path().to_path_buf(),
+            approve_tools,
+        })
+        .await
+        .unwrap();
+
+    run_session(
+        agent,
+        vec![McpServer::Http(McpServerHttp::new("lookup", &mcp.url))],
+        select,
+        expected_session_id.clone(),
+        |cx, session_id, updates| async move {
+            let stop_reason = prompt(&cx, session_id, TOOL_PROMPT).await;
+            wait_for(
+                &updates,
+                &SessionUpdate::ToolCallUpdate(ToolCallUpdate::new(
+                    ToolCallId::new(""),
+                    ToolCallUpdateFields::new().status(Some(expected_status)),
+                )),
+            )
+            .await;
+            if expected_status == ToolCallStatus::Completed {
+                assert_eq!(stop_reason, StopReason::EndTurn);
+                wait_for_text(&updates, FAKE_CODE).await;
+            }
+        },
+    )
+    .await;
+
+    expected_session_id.assert_no_errors();
+}
+
+/// Runs `test_fn` against a new session on `agent`. The client answers permission requests with
+/// the `select` option, or cancels them, and serves [`FAKE_CODE`] for every file it's asked to
+/// read.
+pub async fn run_session<F, Fut>(
+    agent: DynComponent<AgentToClient>,
+    mcp_servers: Vec<McpServer>,
+    select: Option<PermissionOptionKind>,
+    expected_session_id: ExpectedSessionId,
+    test_fn: F,
+) where
+    F: FnOnce(
+        JrConnectionCx<ClientToAgent>,
+        SessionId,
+        Arc<Mutex<Vec<SessionNotification>>>,
+    ) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let work_dir = tempfile::tempdir().unwrap();
+    let updates = Arc::new(Mutex::new(Vec::new()));
+
+    ClientToAgent::builder()
+        .on_receive_notification(
+            {
+                let updates = updates.clone();
+                async move |notification: SessionNotification, _cx| {
+                    updates.lock().unwrap().push(notification);
+                    Ok(())
+                }
+            },
+            sacp::on_receive_notification!(),
+        )
+        .on_receive_request(
+            async move |req: RequestPermissionRequest, request_cx, _connection_cx| {
+                let response = match select {
+                    Some(kind) => {
+                        let id = req
+                            .options
+                            .iter()
+                            .find(|o| o.kind == kind)
+                            .unwrap()
+                            .option_id
+                            .clone();
+                        RequestPermissionResponse::new(RequestPermissionOutcome::Selected(
+                            SelectedPermissionOutcome::new(id),
+                        ))
+                    }
+                    None => RequestPermissionResponse::new(RequestPermissionOutcome::Cancelled),
+                };
+                request_cx.respond(response)
+            },
+            sacp::on_receive_request!(),
+        )
+        .on_receive_request(
+            async move |_req: ReadTextFileRequest, request_cx, _connection_cx| {
+                request_cx.respond(ReadTextFileResponse::new(FAKE_CODE))
+            },
+            sacp::on_receive_request!(),
+        )
+        .connect_to(agent)
+        .unwrap()
+        .run_until({
+            let updates = updates.clone();
+            let expected_session_id = expected_session_id.clone();
+            move |cx: JrConnectionCx<ClientToAgent>| async move {
+                cx.send_request(
+                    InitializeRequest::new(ProtocolVersion::LATEST).client_capabilities(
+                        ClientCapabilities::new()
+                            .fs(FileSystemCapability::new().read_text_file(true)),
+                    ),
+                )
+                .block_task()
+                .await
+                .unwrap();
+
+                let session = cx
+                    .send_request(NewSessionRequest::new(work_dir.path()).mcp_servers(mcp_servers))
+                    .block_task()
+                    .await
+                    .unwrap();
+
+                expected_session_id.set(&session.session_id);
+
+                test_fn(cx.clone(), session.session_id, updates).await;
+                Ok(())
+            }
+        })
+        .await
+        .unwrap();
+}
+
+async fn wait_for_text(updates: &Arc<Mutex<Vec<SessionNotification>>>, text: &str) {
+    wait_for(
+        updates,
+        &SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Text(TextContent::new(
+            text,
+        )))),
+    )
+    .await;
+}
+
+/// Waits briefly for a notification matching `expected`, panicking with what arrived instead.
+/// Message chunks match on their accumulated text, tool call updates on their status and other
+/// updates on the fields that identify them.
+pub async fn wait_for(updates: &Arc<Mutex<Vec<SessionNotification>>>, expected: &SessionUpdate) {
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(500);
+    let mut context = String::new();
+
+    loop {
+        let matched = {
+            let guard = updates.lock().unwrap();
+            context.clear();
+
+            match expected {
+                SessionUpdate::AgentMessageChunk(chunk) => {
+                    let expected_text = match &chunk.content {
+                        ContentBlock::Text(t) => &t.text,
+                        other => panic!("wait_for: unhandled content {:?}", other),
+                    };
+                    for n in guard.iter() {
+                        if let SessionUpdate::AgentMessageChunk(c) = &n.update {
+                            if let ContentBlock::Text(t) = &c.content {
+                                if t.text.is_empty() {
+                                    context.clear();
+                                } else {
+                                    context.push_str(&t.text);
+                                }
+                            }
+                        }
+                    }
+                    context.contains(expected_text)
+                }
+                SessionUpdate::UserMessageChunk(chunk) => {
+                    let expected_text = match &chunk.content {
+                        ContentBlock::Text(t) => &t.text,
+                        other => panic!("wait_for: unhandled content {:?}", other),
+                    };
+                    for n in guard.iter() {
+                        if let SessionUpdate::UserMessageChunk(c) = &n.update {
+                            if let ContentBlock::Text(t) = &c.content {
+                                context.push_str(&t.text);
+                            }
+                        }
+                    }
+                    context.contains(expected_text)
+                }
+                SessionUpdate::ToolCallUpdate(expected_update) => {
+                    for n in guard.iter() {
+                        if let SessionUpdate::ToolCallUpdate(u) = &n.update {
+                            context.push_str(&format!("{:?}\n", u));
+                            if u.fields.status == expected_update.fields.status {
+                                return;
+                            }
+                        }
+                    }
+                    false
+                }
+                SessionUpdate::AvailableCommandsUpdate(expected_update) => {
+                    guard.iter().any(|n| match &n.update {
+                        SessionUpdate::AvailableCommandsUpdate(u) => {
+                            context.push_str(&format!("{:?}\n", u));
+                            expected_update.available_commands.iter().all(|expected| {
+                                u.available_commands.iter().any(|c| c.name == expected.name)
+                            })
+                        }
+                        _ => false,
+                    })
+                }
+                SessionUpdate::SessionInfoUpdate(expected_update) => {
+                    guard.iter().any(|n| match &n.update {
+                        SessionUpdate::SessionInfoUpdate(u) => {
+                            context.push_str(&format!("{:?}\n", u));
+                            u.title == expected_update.title
+                        }
+                        _ => false,
+                    })
+                }
+                SessionUpdate::CurrentModeUpdate(expected_update) => guard
+                    .iter()
+                    .any(|n| matches!(&n.update, SessionUpdate::CurrentModeUpdate(u) if u == expected_update)),
+                other => panic!("wait_for: unhandled update {:?}", other),
+            }
+        };
+
+        if matched {
+            return;
+        }
+        if tokio::time::Instant::now() > deadline {
+            panic!("Timeout waiting for {:?}\n\n{}", expected, context);
+        }
+        tokio::task::yield_now().await;
+    }
+}
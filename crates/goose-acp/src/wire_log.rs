@@ -0,0 +1,149 @@
+//! Feeds the ACP byte streams to goose's wire log one JSON-RPC frame per line.
+
+use futures::{AsyncRead, AsyncWrite};
+use goose::security::wire_log::{self, Direction};
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// A frame still unterminated at this size is logged as far as it got, so a peer that never
+/// sends a newline can't grow the buffer without bound.
+const MAX_PENDING_BYTES: usize = 1024 * 1024;
+
+pub struct WireLogged<S> {
+    inner: S,
+    direction: Direction,
+    pending: Vec<u8>,
+}
+
+impl<S> WireLogged<S> {
+    pub fn new(inner: S, direction: Direction) -> Self {
+        Self {
+            inner,
+            direction,
+            pending: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, bytes: &[u8]) {
+        if !wire_log::is_enabled() {
+            self.pending.clear();
+            return;
+        }
+        self.pending.extend_from_slice(bytes);
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.log(&line);
+        }
+        if self.pending.len() >= MAX_PENDING_BYTES {
+            let line = std::mem::take(&mut self.pending);
+            self.log(&line);
+        }
+    }
+
+    fn log(&self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        if !line.is_empty() {
+            wire_log::log_line("acp", self.direction, line);
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for WireLogged<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let read = ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.record(&buf[..read]);
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for WireLogged<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let written = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.record(&buf[..written]);
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use std::sync::{Mutex, MutexGuard};
+
+    static WIRE_LOG_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Turns the wire log on for one test and back to how it was afterwards. Tests holding one
+    /// run one at a time since the switch is global.
+    struct WireLogEnabled {
+        previous: bool,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl WireLogEnabled {
+        fn new() -> Self {
+            let lock = WIRE_LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let previous = wire_log::is_enabled();
+            wire_log::set_enabled(true);
+            Self {
+                previous,
+                _lock: lock,
+            }
+        }
+    }
+
+    impl Drop for WireLogEnabled {
+        fn drop(&mut self) {
+            wire_log::set_enabled(self.previous);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_frames_pass_through_unchanged() {
+        let _wire_log = WireLogEnabled::new();
+        let frames = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\"}\n{\"partial\":";
+
+        let mut read = String::new();
+        WireLogged::new(Cursor::new(frames.as_bytes().to_vec()), Direction::Received)
+            .read_to_string(&mut read)
+            .await
+            .unwrap();
+        assert_eq!(read, frames);
+
+        let mut writer = WireLogged::new(Cursor::new(Vec::new()), Direction::Sent);
+        writer.write_all(frames.as_bytes()).await.unwrap();
+        assert_eq!(writer.inner.into_inner(), frames.as_bytes());
+        assert_eq!(writer.pending, b"{\"partial\":");
+    }
+
+    #[test]
+    fn test_unterminated_frame_is_capped() {
+        let _wire_log = WireLogEnabled::new();
+        let mut logged = WireLogged::new((), Direction::Received);
+
+        logged.record(&vec![b'x'; MAX_PENDING_BYTES - 1]);
+        assert_eq!(logged.pending.len(), MAX_PENDING_BYTES - 1);
+        logged.record(b"x");
+        assert!(logged.pending.is_empty());
+    }
+}
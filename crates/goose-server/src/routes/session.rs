@@ -1,7 +1,7 @@
 use crate::routes::errors::ErrorResponse;
 use crate::routes::recipe_utils::{apply_recipe_to_agent, build_recipe_with_parameter_values};
 use crate::state::AppState;
-use axum::extract::{DefaultBodyLimit, State};
+use axum::extract::{DefaultBodyLimit, Query, State};
 use axum::routing::post;
 use axum::{
     extract::Path,
@@ -67,11 +67,30 @@ pub struct ForkResponse {
 
 const MAX_NAME_LENGTH: usize = 200;
 
+#[derive(Deserialize, utoipa::IntoParams)]
+pub struct SessionListQuery {
+    /// Only list sessions carrying all of these tags, written as `key:value` pairs separated by
+    /// commas, e.g. `project:web,customer:acme`.
+    tags: Option<String>,
+}
+
+fn parse_tags(tags: &str) -> Option<HashMap<String, String>> {
+    tags.split(',')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            pair.split_once(':')
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
 #[utoipa::path(
     get,
     path = "/sessions",
+    params(SessionListQuery),
     responses(
         (status = 200, description = "List of available sessions retrieved successfully", body = SessionListResponse),
+        (status = 400, description = "Bad request - Malformed tags"),
         (status = 401, description = "Unauthorized - Invalid or missing API key"),
         (status = 500, description = "Internal server error")
     ),
@@ -82,10 +101,15 @@ const MAX_NAME_LENGTH: usize = 200;
 )]
 async fn list_sessions(
     State(state): State<Arc<AppState>>,
+    Query(query): Query<SessionListQuery>,
 ) -> Result<Json<SessionListResponse>, StatusCode> {
+    let tags = match query.tags.as_deref() {
+        Some(tags) => parse_tags(tags).ok_or(StatusCode::BAD_REQUEST)?,
+        None => HashMap::new(),
+    };
     let sessions = state
         .session_manager()
-        .list_sessions()
+        .list_sessions_with_tags(&tags)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -96,4 +96,18 @@ impl ActionRequiredManager {
 
         Ok(())
     }
+
+    /// Ends a pending request without an answer, so the caller waiting on it fails right away
+    /// instead of at its timeout.
+    pub async fn cancel(&self, request_id: &str) -> Result<()> {
+        let pending_arc = {
+            let pending = self.pending.read().await;
+            pending
+                .get(request_id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Request not found: {}", request_id))?
+        };
+        pending_arc.lock().await.response_tx.take();
+        Ok(())
+    }
 }
@@ -11,11 +11,15 @@ use uuid::Uuid;
 use super::container::Container;
 use super::final_output_tool::FinalOutputTool;
 use super::platform_tools;
-use super::tool_execution::{ToolCallResult, CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE};
+use super::tool_execution::{
+    limit_parallel_tools, with_timeout, RunningToolCalls, ToolCallResult,
+    CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE,
+};
 use crate::action_required_manager::ActionRequiredManager;
 use crate::agents::extension::{ExtensionConfig, ExtensionResult, ToolInfo};
 use crate::agents::extension_manager::{get_parameter_names, ExtensionManager};
 use crate::agents::extension_manager_extension::MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE;
+use crate::agents::file_limits::FileLimits;
 use crate::agents::final_output_tool::{FINAL_OUTPUT_CONTINUATION_MESSAGE, FINAL_OUTPUT_TOOL_NAME};
 use crate::agents::platform_tools::PLATFORM_MANAGE_SCHEDULE_TOOL_NAME;
 use crate::agents::prompt_manager::PromptManager;
@@ -24,9 +28,11 @@ use crate::agents::subagent_task_config::TaskConfig;
 use crate::agents::subagent_tool::{
     create_subagent_tool, handle_subagent_tool, SUBAGENT_TOOL_NAME,
 };
-use crate::agents::types::{FrontendTool, SessionConfig, SharedProvider, ToolResultReceiver};
+use crate::agents::types::{
+    FrontendTool, SessionConfig, SharedProvider, ToolFilter, ToolResultReceiver, ToolTimeouts,
+};
 use crate::config::permission::PermissionManager;
-use crate::config::{get_enabled_extensions, Config, GooseMode};
+use crate::config::{get_enabled_extensions, Config, ExtensionModes, GooseMode};
 use crate::context_mgmt::{
     check_if_compaction_needed, compact_messages, DEFAULT_COMPACTION_THRESHOLD,
 };
@@ -39,6 +45,7 @@ use crate::conversation::{debug_conversation_fix, fix_conversation, Conversation
 use crate::mcp_utils::ToolResult;
 use crate::permission::permission_inspector::PermissionInspector;
 use crate::permission::permission_judge::PermissionCheckResult;
+use crate::permission::risk_classifier::{RiskClassifier, RulePackClassifier};
 use crate::permission::PermissionConfirmation;
 use crate::providers::base::Provider;
 use crate::providers::errors::ProviderError;
@@ -57,8 +64,9 @@ use rmcp::model::{
 };
 use serde_json::Value;
 use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info, instrument, warn};
+use tracing::{debug, error, info, instrument, warn, Instrument};
 
 const DEFAULT_MAX_TURNS: u32 = 1000;
 const COMPACTION_THINKING_TEXT: &str = "goose is compacting the conversation...";
@@ -94,6 +102,15 @@ pub struct AgentConfig {
     pub permission_manager: Arc<PermissionManager>,
     pub scheduler_service: Option<Arc<dyn SchedulerTrait>>,
     pub goose_mode: GooseMode,
+    /// How many tool calls from one model response may run at once; unlimited when unset.
+    pub max_parallel_tool_calls: Option<usize>,
+    pub tool_timeouts: ToolTimeouts,
+    /// The model is only offered, and can only call, tools that every filter allows.
+    pub tool_filters: Vec<ToolFilter>,
+    /// Checked before each builtin tool call that touches files.
+    pub file_limits: Option<FileLimits>,
+    /// Consulted in smart approve mode, alongside the rule pack from config.
+    pub risk_classifiers: Vec<Arc<dyn RiskClassifier>>,
 }
 
 impl AgentConfig {
@@ -108,8 +125,44 @@ impl AgentConfig {
             permission_manager,
             scheduler_service,
             goose_mode,
+            max_parallel_tool_calls: None,
+            tool_timeouts: ToolTimeouts::default(),
+            tool_filters: Vec::new(),
+            file_limits: None,
+            risk_classifiers: Vec::new(),
         }
     }
+
+    pub fn with_max_parallel_tool_calls(mut self, max_parallel_tool_calls: usize) -> Self {
+        self.max_parallel_tool_calls = Some(max_parallel_tool_calls);
+        self
+    }
+
+    pub fn with_tool_timeouts(mut self, tool_timeouts: ToolTimeouts) -> Self {
+        self.tool_timeouts = tool_timeouts;
+        self
+    }
+
+    pub fn with_tool_filter(mut self, tool_filter: ToolFilter) -> Self {
+        self.tool_filters.push(tool_filter);
+        self
+    }
+
+    pub fn with_file_limits(mut self, file_limits: FileLimits) -> Self {
+        self.file_limits = Some(file_limits);
+        self
+    }
+
+    pub fn with_risk_classifier(mut self, classifier: Arc<dyn RiskClassifier>) -> Self {
+        self.risk_classifiers.push(classifier);
+        self
+    }
+
+    pub fn allows_tool(&self, tool_name: &str) -> bool {
+        self.tool_filters
+            .iter()
+            .all(|filter| filter.allows(tool_name))
+    }
 }
 
 /// The main goose Agent
@@ -130,7 +183,10 @@ pub struct Agent {
 
     pub(super) retry_manager: RetryManager,
     pub(super) tool_inspection_manager: ToolInspectionManager,
+    running_tool_calls: RunningToolCalls,
     container: Mutex<Option<Container>>,
+    goose_mode: Mutex<GooseMode>,
+    session_naming: Mutex<Option<JoinHandle<()>>>,
 }
 
 #[derive(Clone, Debug)]
@@ -200,6 +256,8 @@ impl Agent {
 
         let session_manager = Arc::clone(&config.session_manager);
         let permission_manager = Arc::clone(&config.permission_manager);
+        let goose_mode = config.goose_mode;
+        let risk_classifiers = config.risk_classifiers.clone();
         Self {
             provider: provider.clone(),
             config,
@@ -214,14 +272,21 @@ impl Agent {
             tool_result_tx: tool_tx,
             tool_result_rx: Arc::new(Mutex::new(tool_rx)),
             retry_manager: RetryManager::new(),
-            tool_inspection_manager: Self::create_tool_inspection_manager(permission_manager),
+            tool_inspection_manager: Self::create_tool_inspection_manager(
+                permission_manager,
+                risk_classifiers,
+            ),
+            running_tool_calls: RunningToolCalls::default(),
             container: Mutex::new(None),
+            goose_mode: Mutex::new(goose_mode),
+            session_naming: Mutex::new(None),
         }
     }
 
     /// Create a tool inspection manager with default inspectors
     fn create_tool_inspection_manager(
         permission_manager: Arc<PermissionManager>,
+        risk_classifiers: Vec<Arc<dyn RiskClassifier>>,
     ) -> ToolInspectionManager {
         let mut tool_inspection_manager = ToolInspectionManager::new();
 
@@ -229,11 +294,19 @@ impl Agent {
         tool_inspection_manager.add_inspector(Box::new(SecurityInspector::new()));
 
         // Add permission inspector (medium-high priority)
-        tool_inspection_manager.add_inspector(Box::new(PermissionInspector::new(
+        let mut permission_inspector = PermissionInspector::new(
             std::collections::HashSet::new(), // readonly tools - will be populated from extension manager
             std::collections::HashSet::new(), // regular tools - will be populated from extension manager
             permission_manager,
-        )));
+        )
+        .with_extension_modes(ExtensionModes::from_config(Config::global()));
+        if let Some(rule_pack) = RulePackClassifier::from_config(Config::global()) {
+            permission_inspector = permission_inspector.with_risk_classifier(Arc::new(rule_pack));
+        }
+        for classifier in risk_classifiers {
+            permission_inspector = permission_inspector.with_risk_classifier(classifier);
+        }
+        tool_inspection_manager.add_inspector(Box::new(permission_inspector));
 
         // Add repetition inspector (lower priority - basic repetition checking)
         tool_inspection_manager.add_inspector(Box::new(RepetitionInspector::new(None)));
@@ -324,7 +397,7 @@ impl Agent {
             tools,
             toolshim_tools,
             system_prompt,
-            goose_mode: self.config.goose_mode,
+            goose_mode: self.goose_mode().await,
             tool_call_cut_off: Config::global()
                 .get_param::<usize>("GOOSE_TOOL_CALL_CUTOFF")
                 .unwrap_or(10),
@@ -428,6 +501,15 @@ impl Agent {
         self.container.lock().await.clone()
     }
 
+    pub async fn goose_mode(&self) -> GooseMode {
+        *self.goose_mode.lock().await
+    }
+
+    /// Change the mode used for subsequent replies, starting from the initial `AgentConfig` mode.
+    pub async fn update_goose_mode(&self, goose_mode: GooseMode) {
+        *self.goose_mode.lock().await = goose_mode;
+    }
+
     /// Check if a tool is a frontend tool
     pub async fn is_frontend_tool(&self, name: &str) -> bool {
         self.frontend_tools.lock().await.contains_key(name)
@@ -491,6 +573,17 @@ impl Agent {
             );
         }
 
+        if !self.config.allows_tool(&tool_call.name) {
+            return (
+                request_id,
+                Err(ErrorData::new(
+                    ErrorCode::INVALID_REQUEST,
+                    format!("Tool {} is not available in this session", tool_call.name),
+                    None,
+                )),
+            );
+        }
+
         if tool_call.name == PLATFORM_MANAGE_SCHEDULE_TOOL_NAME {
             let arguments = tool_call
                 .arguments
@@ -525,9 +618,10 @@ impl Agent {
         }
 
         debug!("WAITING_TOOL_START: {}", tool_call.name);
-        let result: ToolCallResult = if tool_call.name == SUBAGENT_TOOL_NAME {
-            let provider = match self.provider().await {
-                Ok(p) => p,
+        // Checked before the call is registered as running, so failing here leaves nothing behind
+        let subagent_provider = if tool_call.name == SUBAGENT_TOOL_NAME {
+            match self.provider().await {
+                Ok(provider) => Some(provider),
                 Err(_) => {
                     return (
                         request_id,
@@ -538,8 +632,14 @@ impl Agent {
                         )),
                     );
                 }
-            };
-
+            }
+        } else {
+            None
+        };
+        let call_token = self
+            .running_tool_calls
+            .start(&request_id, cancellation_token.as_ref());
+        let result: ToolCallResult = if let Some(provider) = subagent_provider {
             let extensions = self.get_extension_configs().await;
 
             let max_turns_from_recipe = session
@@ -565,7 +665,7 @@ impl Agent {
                 task_config,
                 sub_recipes,
                 session.working_dir.clone(),
-                cancellation_token,
+                Some(call_token.clone()),
             )
         } else if self.is_frontend_tool(&tool_call.name).await {
             // For frontend tools, return an error indicating we need frontend execution
@@ -574,15 +674,13 @@ impl Agent {
                 "Frontend tool execution required".to_string(),
                 None,
             )))
+        } else if let Some(reason) = self.file_limit_violation(&tool_call, session).await {
+            ToolCallResult::from(Err(ErrorData::new(ErrorCode::INVALID_PARAMS, reason, None)))
         } else {
             // Clone the result to ensure no references to extension_manager are returned
             let result = self
                 .extension_manager
-                .dispatch_tool_call(
-                    &session.id,
-                    tool_call.clone(),
-                    cancellation_token.unwrap_or_default(),
-                )
+                .dispatch_tool_call(&session.id, tool_call.clone(), call_token.clone())
                 .await;
             result.unwrap_or_else(|e| {
                 crate::posthog::emit_error(
@@ -599,6 +697,20 @@ impl Agent {
 
         debug!("WAITING_TOOL_END: {}", tool_call.name);
 
+        let result = self
+            .running_tool_calls
+            .track(request_id.clone(), call_token.clone(), result);
+        let result = with_timeout(
+            result,
+            self.config.tool_timeouts.for_tool(&tool_call.name),
+            call_token,
+        );
+        let span = tracing::info_span!(
+            "tool_execution",
+            session.id = %session.id,
+            tool.name = %tool_call.name,
+            tool.request_id = %request_id
+        );
         (
             request_id,
             Ok(ToolCallResult {
@@ -606,7 +718,8 @@ impl Agent {
                 result: Box::new(
                     result
                         .result
-                        .map(super::large_response_handler::process_tool_response),
+                        .map(super::large_response_handler::process_tool_response)
+                        .instrument(span),
                 ),
             }),
         )
@@ -781,7 +894,7 @@ impl Agent {
     }
 
     pub async fn subagents_enabled(&self, session_id: &str) -> bool {
-        if self.config.goose_mode != GooseMode::Auto {
+        if self.goose_mode().await != GooseMode::Auto {
             return false;
         }
         let context = self.extension_manager.get_context();
@@ -804,6 +917,24 @@ impl Agent {
             .unwrap_or(true)
     }
 
+    async fn file_limit_violation(
+        &self,
+        tool_call: &CallToolRequestParams,
+        session: &Session,
+    ) -> Option<String> {
+        let limits = self.config.file_limits.as_ref()?;
+        if !self
+            .extension_manager
+            .is_builtin_tool(&tool_call.name)
+            .await
+        {
+            return None;
+        }
+        limits
+            .check(tool_call.arguments.as_ref(), &session.working_dir)
+            .err()
+    }
+
     pub async fn list_tools(&self, session_id: &str, extension_name: Option<String>) -> Vec<Tool> {
         let mut prefixed_tools = self
             .extension_manager
@@ -830,9 +961,16 @@ impl Agent {
             }
         }
 
+        prefixed_tools.retain(|tool| self.config.allows_tool(&tool.name));
         prefixed_tools
     }
 
+    /// Stops one running tool call, which then fails with a cancellation error while the rest of
+    /// the turn carries on. False when no call with that id is running.
+    pub fn cancel_tool_call(&self, request_id: &str) -> bool {
+        self.running_tool_calls.cancel(request_id)
+    }
+
     pub async fn remove_extension(&self, name: &str) -> Result<()> {
         self.extension_manager.remove_extension(name).await?;
         Ok(())
@@ -849,6 +987,15 @@ impl Agent {
         self.extension_manager.get_extension_configs().await
     }
 
+    /// Waits for the session name that the last `reply` generates in the background, if it
+    /// hasn't finished yet.
+    pub async fn wait_for_session_name(&self) {
+        let naming = self.session_naming.lock().await.take();
+        if let Some(naming) = naming {
+            let _ = naming.await;
+        }
+    }
+
     /// Handle a confirmation response for a tool request
     pub async fn handle_confirmation(
         &self,
@@ -1088,7 +1235,7 @@ impl Agent {
         let session_manager = self.config.session_manager.clone();
         let session_id = session_config.id.clone();
         let manager_for_spawn = session_manager.clone();
-        tokio::spawn(async move {
+        let naming = tokio::spawn(async move {
             if let Err(e) = manager_for_spawn
                 .maybe_update_name(&session_id, provider)
                 .await
@@ -1096,6 +1243,7 @@ impl Agent {
                 warn!("Failed to generate session description: {}", e);
             }
         });
+        *self.session_naming.lock().await = Some(naming);
 
         let working_dir = session.working_dir.clone();
         Ok(Box::pin(async_stream::try_stream! {
@@ -1150,6 +1298,7 @@ impl Agent {
                     conversation_with_moim.messages(),
                     &tools,
                     &toolshim_tools,
+                    cancel_token.clone().unwrap_or_default(),
                 ).await?;
 
                 let mut no_tools_called = true;
@@ -1218,19 +1367,9 @@ impl Agent {
                                     request_metadata.insert(request.id.clone(), request.metadata.clone());
                                 }
 
-                                for (idx, request) in frontend_requests.iter().enumerate() {
-                                    let mut frontend_tool_stream = self.handle_frontend_tool_request(
-                                        request,
-                                        tool_response_messages[idx].clone(),
-                                    );
-
-                                    while let Some(msg) = frontend_tool_stream.try_next().await? {
-                                        yield AgentEvent::Message(msg);
-                                    }
-                                }
                                 if goose_mode == GooseMode::Chat {
-                                    // Skip all remaining tool calls in chat mode
-                                    for request in remaining_requests.iter() {
+                                    // Skip all tool calls in chat mode, including the frontend's
+                                    for request in frontend_requests.iter().chain(remaining_requests.iter()) {
                                         if let Some(response_msg) = request_to_response_map.get(&request.id) {
                                             let mut response = response_msg.lock().await;
                                             *response = response.clone().with_tool_response_with_metadata(
@@ -1246,18 +1385,24 @@ impl Agent {
                                         }
                                     }
                                 } else {
-                                    // Run all tool inspectors
+                                    // Run all tool inspectors, over frontend tools as well since
+                                    // the client runs them with the user's own permissions
+                                    let inspected_requests: Vec<ToolRequest> = frontend_requests
+                                        .iter()
+                                        .chain(remaining_requests.iter())
+                                        .cloned()
+                                        .collect();
                                     let inspection_results = self.tool_inspection_manager
                                         .inspect_tools(
-                                            &remaining_requests,
+                                            &inspected_requests,
                                             conversation.messages(),
                                             goose_mode,
                                         )
                                         .await?;
 
-                                    let permission_check_result = self.tool_inspection_manager
+                                    let mut permission_check_result = self.tool_inspection_manager
                                         .process_inspection_results_with_permission_inspector(
-                                            &remaining_requests,
+                                            &inspected_requests,
                                             &inspection_results,
                                         )
                                         .unwrap_or_else(|| {
@@ -1266,10 +1411,18 @@ impl Agent {
                                                 needs_approval: vec![],
                                                 denied: vec![],
                                             };
-                                            result.needs_approval.extend(remaining_requests.iter().cloned());
+                                            result.needs_approval.extend(inspected_requests.iter().cloned());
                                             result
                                         });
 
+                                    // Approved frontend calls are handed to the client rather than dispatched
+                                    let (approved_frontend, approved): (Vec<_>, Vec<_>) = permission_check_result
+                                        .approved
+                                        .drain(..)
+                                        .partition(|request| frontend_requests.iter().any(|f| f.id == request.id));
+                                    permission_check_result.approved = approved;
+                                    let approved_frontend = Arc::new(Mutex::new(approved_frontend));
+
                                     // Track extension requests
                                     let mut enable_extension_request_ids = vec![];
                                     for request in &remaining_requests {
@@ -1292,6 +1445,7 @@ impl Agent {
                                     let mut tool_approval_stream = self.handle_approval_tool_requests(
                                         &permission_check_result.needs_approval,
                                         tool_futures_arc.clone(),
+                                        approved_frontend.clone(),
                                         &request_to_response_map,
                                         cancel_token.clone(),
                                         &session,
@@ -1302,12 +1456,21 @@ impl Agent {
                                         yield AgentEvent::Message(msg);
                                     }
 
+                                    let approved_frontend = approved_frontend.lock().await.clone();
+                                    let mut frontend_tool_stream = self.handle_frontend_tool_requests(
+                                        &approved_frontend,
+                                        &request_to_response_map,
+                                    );
+                                    while let Some(msg) = frontend_tool_stream.try_next().await? {
+                                        yield AgentEvent::Message(msg);
+                                    }
+
                                     tool_futures = {
                                         let mut futures_lock = tool_futures_arc.lock().await;
                                         futures_lock.drain(..).collect::<Vec<_>>()
                                     };
 
-                                    let with_id = tool_futures
+                                    let with_id = limit_parallel_tools(tool_futures, self.config.max_parallel_tool_calls)
                                         .into_iter()
                                         .map(|(request_id, stream)| {
                                             stream.map(move |item| (request_id.clone(), item))
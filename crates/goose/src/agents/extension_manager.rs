@@ -36,7 +36,7 @@ use crate::agents::extension::{Envs, ProcessExit};
 use crate::agents::extension_malware_check;
 use crate::agents::mcp_client::{McpClient, McpClientTrait};
 use crate::config::search_path::SearchPaths;
-use crate::config::{get_all_extensions, Config};
+use crate::config::{get_all_extensions, resolve_env_value, Config};
 use crate::oauth::oauth_flow;
 use crate::prompt_template;
 use crate::subprocess::configure_command_no_window;
@@ -302,7 +302,8 @@ fn extract_auth_error(
     }
 }
 
-/// Merge environment variables from direct envs and keychain-stored env_keys
+/// Merge environment variables from direct envs and keychain-stored env_keys, then resolve any
+/// `keyring:service/key` references among them
 async fn merge_environments(
     envs: &Envs,
     env_keys: &[String],
@@ -353,6 +354,15 @@ async fn merge_environments(
         }
     }
 
+    for (key, value) in all_envs.iter_mut() {
+        *value = resolve_env_value(value).map_err(|e| {
+            ExtensionError::ConfigError(format!(
+                "Failed to resolve env var '{}' for extension '{}': {}",
+                key, ext_name, e
+            ))
+        })?;
+    }
+
     Ok(all_envs)
 }
 
@@ -550,9 +560,11 @@ impl ExtensionManager {
                         "Starting stdio extension inside Docker container"
                     );
                     Command::new("docker").configure(|command| {
-                        command.arg("exec").arg("-i");
-                        for (key, value) in &all_envs {
-                            command.arg("-e").arg(format!("{}={}", key, value));
+                        // Pass only the names so values, which may be secrets, stay out of the
+                        // process listing; docker reads them from its own environment
+                        command.arg("exec").arg("-i").envs(&all_envs);
+                        for key in all_envs.keys() {
+                            command.arg("-e").arg(key);
                         }
                         command.arg(container_id);
                         command.arg(cmd);
@@ -775,6 +787,24 @@ impl ExtensionManager {
             .collect()
     }
 
+    /// Whether the tool comes from a builtin or platform extension, which run inside goose
+    /// rather than as a separate server.
+    pub async fn is_builtin_tool(&self, prefixed_name: &str) -> bool {
+        self.extensions
+            .lock()
+            .await
+            .iter()
+            .any(|(name, extension)| {
+                prefixed_name
+                    .strip_prefix(name.as_str())
+                    .is_some_and(|rest| rest.starts_with("__"))
+                    && matches!(
+                        extension.config,
+                        ExtensionConfig::Builtin { .. } | ExtensionConfig::Platform { .. }
+                    )
+            })
+    }
+
     /// Get all tools from all clients with proper prefixing
     pub async fn get_prefixed_tools(
         &self,
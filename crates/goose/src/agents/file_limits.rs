@@ -0,0 +1,190 @@
+use rmcp::model::JsonObject;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Arguments that carry content to be written to a file.
+const CONTENT_ARGUMENTS: [&str; 4] = ["file_text", "new_str", "diff", "content"];
+
+/// Arguments that name a file or directory the call works on.
+const PATH_ARGUMENTS: [&str; 2] = ["path", "cwd"];
+
+/// Limits on what file tools may do, checked against a tool call's path and content arguments
+/// before the tool runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileLimits {
+    /// Existing files larger than this can't be opened, except to be overwritten whole.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_read_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_write_bytes: Option<u64>,
+    /// Directories paths must fall under, with relative entries resolved against the session's
+    /// working directory. Empty allows any path.
+    pub allowed_roots: Vec<PathBuf>,
+}
+
+/// Makes `path` absolute and resolves `.`, `..` and `~` without requiring it to exist. The part
+/// that exists is canonicalized first, the way the OS would walk it, so neither a symlink nor a
+/// `..` after one can lead out of an allowed root.
+fn resolve(path: &Path, working_dir: &Path) -> PathBuf {
+    let expanded = PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).into_owned());
+    let full = working_dir.join(expanded);
+    let components: Vec<Component> = full.components().collect();
+
+    let existing = (1..=components.len())
+        .rev()
+        .map(|len| (len, components[..len].iter().collect::<PathBuf>()))
+        .find(|(_, prefix)| prefix.exists());
+    let (mut resolved, rest) = match existing {
+        Some((len, prefix)) => (prefix.canonicalize().unwrap_or(prefix), &components[len..]),
+        None => (PathBuf::new(), &components[..]),
+    };
+
+    // Nothing past here exists, so there are no links left to follow
+    for component in rest {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+impl FileLimits {
+    /// Why the call isn't allowed, if it isn't.
+    pub fn check(&self, arguments: Option<&JsonObject>, working_dir: &Path) -> Result<(), String> {
+        let Some(arguments) = arguments else {
+            return Ok(());
+        };
+
+        if let Some(limit) = self.max_write_bytes {
+            for name in CONTENT_ARGUMENTS {
+                if let Some(content) = arguments.get(name).and_then(|value| value.as_str()) {
+                    if content.len() as u64 > limit {
+                        return Err(format!(
+                            "{} is {} bytes, over the {} byte write limit",
+                            name,
+                            content.len(),
+                            limit
+                        ));
+                    }
+                }
+            }
+        }
+
+        for name in PATH_ARGUMENTS {
+            if let Some(path) = arguments.get(name).and_then(|value| value.as_str()) {
+                self.check_path(path, arguments, working_dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_path(
+        &self,
+        path: &str,
+        arguments: &JsonObject,
+        working_dir: &Path,
+    ) -> Result<(), String> {
+        let resolved = resolve(Path::new(path), working_dir);
+
+        if !self.allowed_roots.is_empty()
+            && !self
+                .allowed_roots
+                .iter()
+                .any(|root| resolved.starts_with(resolve(root, working_dir)))
+        {
+            return Err(format!(
+                "{} is outside the directories file tools may access",
+                path
+            ));
+        }
+
+        if let Some(limit) = self.max_read_bytes {
+            // Whole-file writes replace the file without reading it
+            let overwrite = ["file_text", "content"]
+                .iter()
+                .any(|name| arguments.contains_key(*name));
+            let size = std::fs::metadata(&resolved)
+                .ok()
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len());
+            if let Some(size) = size.filter(|size| *size > limit && !overwrite) {
+                return Err(format!(
+                    "{} is {} bytes, over the {} byte read limit",
+                    path, size, limit
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn check(limits: &FileLimits, arguments: serde_json::Value, dir: &Path) -> Result<(), String> {
+        limits.check(arguments.as_object(), dir)
+    }
+
+    #[test]
+    fn test_file_limits_allowed_roots() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cwd = temp_dir.path().join("project");
+        std::fs::create_dir_all(cwd.join("src")).unwrap();
+        let limits = FileLimits {
+            allowed_roots: vec![PathBuf::from(".")],
+            ..Default::default()
+        };
+
+        assert!(check(&limits, json!({"path": "src/main.rs"}), &cwd).is_ok());
+        assert!(check(&limits, json!({"path": cwd.join("new/file.rs")}), &cwd).is_ok());
+        assert!(check(&limits, json!({"path": "../secret"}), &cwd).is_err());
+        assert!(check(&limits, json!({"path": "src/../../secret"}), &cwd).is_err());
+        assert!(check(&limits, json!({"path": "/etc/passwd"}), &cwd).is_err());
+        assert!(check(&limits, json!({"command": "ls"}), &cwd).is_ok());
+
+        assert!(check(&limits, json!({"command": "ls", "cwd": "/"}), &cwd).is_err());
+        assert!(check(&limits, json!({"command": "ls", "cwd": "src"}), &cwd).is_ok());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path(), cwd.join("escape")).unwrap();
+            assert!(check(&limits, json!({"path": "escape/secret"}), &cwd).is_err());
+
+            // `..` after a link leaves from where the link points, not from the link itself
+            let outside = temp_dir.path().join("outside");
+            std::fs::create_dir_all(outside.join("deep")).unwrap();
+            std::os::unix::fs::symlink(outside.join("deep"), cwd.join("deep")).unwrap();
+            assert!(check(&limits, json!({"path": "deep/../secret"}), &cwd).is_err());
+        }
+    }
+
+    #[test]
+    fn test_file_limits_sizes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cwd = temp_dir.path();
+        std::fs::write(cwd.join("big.txt"), "x".repeat(100)).unwrap();
+        let limits = FileLimits {
+            max_read_bytes: Some(50),
+            max_write_bytes: Some(10),
+            ..Default::default()
+        };
+
+        let view = json!({"path": "big.txt", "command": "view"});
+        assert!(check(&limits, view, cwd).is_err());
+        let overwrite = json!({"path": "big.txt", "command": "write", "file_text": "small"});
+        assert!(check(&limits, overwrite, cwd).is_ok());
+        let write = json!({"path": "new.txt", "command": "write", "file_text": "x".repeat(11)});
+        assert!(check(&limits, write, cwd).is_err());
+        assert!(check(&limits, json!({"path": "missing.txt"}), cwd).is_ok());
+        let client_write = json!({"path": "big.txt", "content": "small"});
+        assert!(check(&limits, client_write, cwd).is_ok());
+    }
+}
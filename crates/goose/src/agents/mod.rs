@@ -9,6 +9,7 @@ pub mod extension;
 pub mod extension_malware_check;
 pub mod extension_manager;
 pub mod extension_manager_extension;
+pub mod file_limits;
 pub mod final_output_tool;
 mod large_response_handler;
 pub mod mcp_client;
@@ -32,6 +33,7 @@ pub use container::Container;
 pub use execute_commands::COMPACT_TRIGGERS;
 pub use extension::ExtensionConfig;
 pub use extension_manager::{normalize, ExtensionManager};
+pub use file_limits::FileLimits;
 pub use prompt_manager::PromptManager;
 pub use subagent_task_config::TaskConfig;
-pub use types::{FrontendTool, RetryConfig, SessionConfig, SuccessCheck};
+pub use types::{FrontendTool, RetryConfig, SessionConfig, SuccessCheck, ToolFilter, ToolTimeouts};
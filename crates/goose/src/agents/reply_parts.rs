@@ -4,7 +4,8 @@ use std::sync::Arc;
 use async_stream::try_stream;
 use futures::stream::StreamExt;
 use serde_json::{json, Value};
-use tracing::debug;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info_span, Instrument, Span};
 
 use super::super::agents::Agent;
 use crate::agents::code_execution_extension::EXTENSION_NAME as CODE_EXECUTION_EXTENSION;
@@ -14,6 +15,7 @@ use crate::conversation::message::{Message, MessageContent, ToolRequest};
 use crate::conversation::Conversation;
 use crate::providers::base::{stream_from_single_message, MessageStream, Provider, ProviderUsage};
 use crate::providers::errors::ProviderError;
+use crate::providers::multimodal::{normalize_messages, Modalities};
 use crate::providers::toolshim::{
     augment_message_with_tool_calls, convert_tool_messages_to_text,
     modify_system_prompt_for_tool_json, OllamaInterpreter,
@@ -106,6 +108,14 @@ async fn toolshim_postprocess(
         .map_err(|e| ProviderError::ExecutionError(format!("Failed to augment message: {}", e)))
 }
 
+/// Polls `stream` inside `span`, so the span lasts until the response has fully streamed in.
+fn in_span(mut stream: MessageStream, span: Span) -> MessageStream {
+    Box::pin(futures::stream::poll_fn(move |cx| {
+        let _entered = span.enter();
+        stream.as_mut().poll_next(cx)
+    }))
+}
+
 impl Agent {
     pub async fn prepare_tools_and_prompt(
         &self,
@@ -118,7 +128,9 @@ impl Agent {
         // Add frontend tools
         let frontend_tools = self.frontend_tools.lock().await;
         for frontend_tool in frontend_tools.values() {
-            tools.push(frontend_tool.tool.clone());
+            if self.config.allows_tool(&frontend_tool.name) {
+                tools.push(frontend_tool.tool.clone());
+            }
         }
 
         let code_execution_active = self
@@ -175,7 +187,8 @@ impl Agent {
     }
 
     /// Stream a response from the LLM provider.
-    /// Handles toolshim transformations if needed
+    /// Handles toolshim transformations if needed, and ends the stream when `cancel_token` is
+    /// cancelled
     pub(crate) async fn stream_response_from_provider(
         provider: Arc<dyn Provider>,
         session_id: &str,
@@ -183,14 +196,18 @@ impl Agent {
         messages: &[Message],
         tools: &[Tool],
         toolshim_tools: &[Tool],
+        cancel_token: CancellationToken,
     ) -> Result<MessageStream, ProviderError> {
         let config = provider.get_model_config();
 
+        let modalities = Modalities::for_model(provider.get_name(), &config.model_name);
+        let messages = normalize_messages(messages, modalities);
+
         // Convert tool messages to text if toolshim is enabled
         let messages_for_provider = if config.toolshim {
-            convert_tool_messages_to_text(messages)
+            convert_tool_messages_to_text(&messages)
         } else {
-            Conversation::new_unvalidated(messages.to_vec())
+            Conversation::new_unvalidated(messages)
         };
 
         // Clone owned data to move into the async stream
@@ -198,38 +215,50 @@ impl Agent {
         let tools = tools.to_owned();
         let toolshim_tools = toolshim_tools.to_owned();
         let provider = provider.clone();
+        let span = info_span!(
+            "provider_stream",
+            session.id = %session_id,
+            provider = %provider.get_name(),
+            model = %config.model_name
+        );
 
         // Capture errors during stream creation and return them as part of the stream
         // so they can be handled by the existing error handling logic in the agent
-        let stream_result = if provider.supports_streaming() {
-            debug!("WAITING_LLM_STREAM_START");
-            let result = provider
-                .stream(
-                    session_id,
-                    system_prompt.as_str(),
-                    messages_for_provider.messages(),
-                    &tools,
-                )
-                .await;
-            debug!("WAITING_LLM_STREAM_END");
-            result
-        } else {
-            debug!("WAITING_LLM_START");
-            let complete_result = provider
-                .complete(
-                    session_id,
-                    system_prompt.as_str(),
-                    messages_for_provider.messages(),
-                    &tools,
-                )
-                .await;
-            debug!("WAITING_LLM_END");
-
-            match complete_result {
-                Ok((message, usage)) => Ok(stream_from_single_message(message, usage)),
-                Err(e) => Err(e),
+        let stream_result = async {
+            if provider.supports_streaming() {
+                debug!("WAITING_LLM_STREAM_START");
+                let result = provider
+                    .stream(
+                        session_id,
+                        system_prompt.as_str(),
+                        messages_for_provider.messages(),
+                        &tools,
+                        cancel_token,
+                    )
+                    .await;
+                debug!("WAITING_LLM_STREAM_END");
+                result
+            } else {
+                debug!("WAITING_LLM_START");
+                let complete_result = cancel_token
+                    .run_until_cancelled(provider.complete(
+                        session_id,
+                        system_prompt.as_str(),
+                        messages_for_provider.messages(),
+                        &tools,
+                    ))
+                    .await;
+                debug!("WAITING_LLM_END");
+
+                match complete_result {
+                    Some(Ok((message, usage))) => Ok(stream_from_single_message(message, usage)),
+                    Some(Err(e)) => Err(e),
+                    None => Ok(Box::pin(futures::stream::empty()) as MessageStream),
+                }
             }
-        };
+        }
+        .instrument(span.clone())
+        .await;
 
         // If there was an error creating the stream, return a stream that yields that error
         let mut stream = match stream_result {
@@ -243,7 +272,7 @@ impl Agent {
             }
         };
 
-        Ok(Box::pin(try_stream! {
+        let stream: MessageStream = Box::pin(try_stream! {
             while let Some(result) = stream.next().await {
                 let (mut message, usage) = result?;
 
@@ -259,7 +288,8 @@ impl Agent {
 
                 yield (message, usage);
             }
-        }))
+        });
+        Ok(in_span(stream, span))
     }
 
     /// Categorize tool requests from the response into different types
@@ -365,6 +395,18 @@ impl Agent {
         let manager = self.config.session_manager.clone();
         let session = manager.get_session(session_id, false).await?;
 
+        let provider_name = match usage.provider.as_ref().or(session.provider_name.as_ref()) {
+            Some(name) => name.clone(),
+            None => self
+                .provider()
+                .await
+                .map(|provider| provider.get_name().to_string())
+                .unwrap_or_default(),
+        };
+        manager
+            .record_usage(session_id, &provider_name, usage)
+            .await?;
+
         let accumulate = |a: Option<i32>, b: Option<i32>| -> Option<i32> {
             match (a, b) {
                 (Some(x), Some(y)) => Some(x + y),
@@ -512,6 +554,99 @@ mod tests {
         Ok(())
     }
 
+    type SpanFields = Vec<(String, String)>;
+
+    #[derive(Clone, Default)]
+    struct SpanCapture {
+        spans: Arc<std::sync::Mutex<Vec<(String, SpanFields)>>>,
+        entered: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    struct FieldVisitor<'a>(&'a mut SpanFields);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    impl<S> tracing_subscriber::Layer<S> for SpanCapture
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = Vec::new();
+            attrs.record(&mut FieldVisitor(&mut fields));
+            self.spans
+                .lock()
+                .unwrap()
+                .push((attrs.metadata().name().to_string(), fields));
+        }
+
+        fn on_enter(&self, id: &tracing::span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+            if let Some(span) = ctx.span(id) {
+                self.entered.lock().unwrap().push(span.name().to_string());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provider_stream_span() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let capture = SpanCapture::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+
+        let provider = Arc::new(MockProvider {
+            model_config: ModelConfig::new("test-model").unwrap(),
+        });
+        let mut stream = Agent::stream_response_from_provider(
+            provider,
+            "session-1",
+            "system",
+            &[Message::user().with_text("hi")],
+            &[],
+            &[],
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        let spans = capture.spans.lock().unwrap().clone();
+        let (_, fields) = spans
+            .iter()
+            .find(|(name, _)| name == "provider_stream")
+            .expect("provider_stream span");
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(field("session.id"), Some("session-1"));
+        assert_eq!(field("provider"), Some("mock"));
+        assert_eq!(field("model"), Some("test-model"));
+
+        // The span is re-entered while the caller drains the stream
+        capture.entered.lock().unwrap().clear();
+        while let Some(item) = stream.next().await {
+            item.unwrap();
+        }
+        assert!(capture
+            .entered
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|name| name == "provider_stream"));
+    }
+
     #[tokio::test]
     async fn test_stream_error_propagation() {
         use futures::StreamExt;
@@ -1,17 +1,18 @@
 use std::collections::HashMap;
 use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_stream::try_stream;
 use futures::stream::{self, BoxStream};
-use futures::{Stream, StreamExt};
-use tokio::sync::Mutex;
+use futures::{FutureExt, Stream, StreamExt};
+use tokio::sync::{Mutex, Semaphore};
 use tokio_util::sync::CancellationToken;
 
 use crate::config::permission::PermissionLevel;
 use crate::mcp_utils::ToolResult;
 use crate::permission::Permission;
-use rmcp::model::{Content, ServerNotification};
+use rmcp::model::{Content, ErrorCode, ErrorData, ServerNotification};
 
 // ToolCallResult combines the result of a tool call with an optional notification stream that
 // can be used to receive notifications from the tool.
@@ -35,6 +36,139 @@ use crate::conversation::message::{Message, ToolRequest};
 use crate::session::Session;
 use crate::tool_inspection::get_security_finding_id_from_results;
 
+/// Holds each tool back until fewer than `limit` others are running.
+pub(crate) fn limit_parallel_tools(
+    tool_futures: Vec<(String, ToolStream)>,
+    limit: Option<usize>,
+) -> Vec<(String, ToolStream)> {
+    let Some(limit) = limit else {
+        return tool_futures;
+    };
+    let semaphore = Arc::new(Semaphore::new(limit.max(1)));
+    tool_futures
+        .into_iter()
+        .map(|(request_id, mut tool_stream)| {
+            let semaphore = semaphore.clone();
+            let limited: ToolStream = Box::pin(async_stream::stream! {
+                let _permit = semaphore.acquire_owned().await;
+                while let Some(item) = tool_stream.next().await {
+                    yield item;
+                }
+            });
+            (request_id, limited)
+        })
+        .collect()
+}
+
+/// Fails the call with a timeout error if it runs longer than `timeout`. The clock starts when
+/// the call does, so time spent waiting for a parallelism slot doesn't count. On timeout `token`
+/// is cancelled and the call is awaited once more so the extension can stop the work it started;
+/// pass a call wrapped by [`RunningToolCalls::track`], which bounds how long that takes.
+pub(crate) fn with_timeout(
+    result: ToolCallResult,
+    timeout: Option<Duration>,
+    token: CancellationToken,
+) -> ToolCallResult {
+    let Some(timeout) = timeout else {
+        return result;
+    };
+    let mut call = result.result;
+    ToolCallResult {
+        result: Box::new(
+            async move {
+                if let Ok(output) = tokio::time::timeout(timeout, &mut call).await {
+                    return output;
+                }
+                token.cancel();
+                let _ = call.await;
+                Err(ErrorData::new(
+                    ErrorCode::INTERNAL_ERROR,
+                    format!("Tool call timed out after {:?}", timeout),
+                    None,
+                ))
+            }
+            .boxed(),
+        ),
+        notification_stream: result.notification_stream,
+    }
+}
+
+/// How long a cancelled call gets to wind down, so extensions that watch their cancellation token
+/// can stop the work they started.
+const CANCEL_GRACE: Duration = Duration::from_secs(2);
+
+/// Cancellation tokens for the tool calls in flight, keyed by request id, so one call can be
+/// stopped without ending the turn.
+#[derive(Clone, Default)]
+pub(crate) struct RunningToolCalls(Arc<std::sync::Mutex<HashMap<String, CancellationToken>>>);
+
+impl RunningToolCalls {
+    pub(crate) fn start(
+        &self,
+        request_id: &str,
+        turn_token: Option<&CancellationToken>,
+    ) -> CancellationToken {
+        let token = turn_token
+            .map(CancellationToken::child_token)
+            .unwrap_or_default();
+        self.0
+            .lock()
+            .unwrap()
+            .insert(request_id.to_string(), token.clone());
+        token
+    }
+
+    pub(crate) fn cancel(&self, request_id: &str) -> bool {
+        match self.0.lock().unwrap().get(request_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Fails the call with a cancellation error once `token` is cancelled, and forgets it when
+    /// it finishes.
+    pub(crate) fn track(
+        &self,
+        request_id: String,
+        token: CancellationToken,
+        result: ToolCallResult,
+    ) -> ToolCallResult {
+        let running = self.clone();
+        let mut call = result.result;
+        ToolCallResult {
+            result: Box::new(
+                async move {
+                    let cancelled = || {
+                        Err(ErrorData::new(
+                            ErrorCode::INTERNAL_ERROR,
+                            "Tool call was cancelled by the user".to_string(),
+                            None,
+                        ))
+                    };
+                    let output = if token.is_cancelled() {
+                        cancelled()
+                    } else {
+                        tokio::select! {
+                            output = &mut call => output,
+                            _ = token.cancelled() => {
+                                let _ = tokio::time::timeout(CANCEL_GRACE, &mut call).await;
+                                cancelled()
+                            }
+                        }
+                    };
+                    running.0.lock().unwrap().remove(&request_id);
+                    output
+                }
+                .boxed(),
+            ),
+            notification_stream: result.notification_stream,
+        }
+    }
+}
+
 pub const DECLINED_RESPONSE: &str = "The user has declined to run this tool. \
     DO NOT attempt to call this tool again. \
     If there are no alternative methods to proceed, clearly explain the situation and STOP.";
@@ -49,10 +183,14 @@ pub const CHAT_MODE_TOOL_SKIPPED_RESPONSE: &str = "Let the user know the tool ca
                                         If needed, adjust the explanation based on user preferences or questions.";
 
 impl Agent {
+    /// Asks the user about each request and queues the approved ones: frontend tool calls go to
+    /// `approved_frontend` for the client to run, everything else to `tool_futures`.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn handle_approval_tool_requests<'a>(
         &'a self,
         tool_requests: &'a [ToolRequest],
         tool_futures: Arc<Mutex<Vec<(String, ToolStream)>>>,
+        approved_frontend: Arc<Mutex<Vec<ToolRequest>>>,
         request_to_response_map: &'a HashMap<String, Arc<Mutex<Message>>>,
         cancellation_token: Option<CancellationToken>,
         session: &'a Session,
@@ -97,19 +235,23 @@ impl Agent {
                         }
 
                         if confirmation.permission == Permission::AllowOnce || confirmation.permission == Permission::AlwaysAllow {
-                            let (req_id, tool_result) = self.dispatch_tool_call(tool_call.clone(), request.id.clone(), cancellation_token.clone(), session).await;
-                            let mut futures = tool_futures.lock().await;
-
-                            futures.push((req_id, match tool_result {
-                                Ok(result) => tool_stream(
-                                    result.notification_stream.unwrap_or_else(|| Box::new(stream::empty())),
-                                    result.result,
-                                ),
-                                Err(e) => tool_stream(
-                                    Box::new(stream::empty()),
-                                    futures::future::ready(Err(e)),
-                                ),
-                            }));
+                            if self.is_frontend_tool(&tool_call.name).await {
+                                approved_frontend.lock().await.push(request.clone());
+                            } else {
+                                let (req_id, tool_result) = self.dispatch_tool_call(tool_call.clone(), request.id.clone(), cancellation_token.clone(), session).await;
+                                let mut futures = tool_futures.lock().await;
+
+                                futures.push((req_id, match tool_result {
+                                    Ok(result) => tool_stream(
+                                        result.notification_stream.unwrap_or_else(|| Box::new(stream::empty())),
+                                        result.result,
+                                    ),
+                                    Err(e) => tool_stream(
+                                        Box::new(stream::empty()),
+                                        futures::future::ready(Err(e)),
+                                    ),
+                                }));
+                            }
 
                             // Update the shared permission manager when user selects "Always Allow"
                             if confirmation.permission == Permission::AlwaysAllow {
@@ -147,31 +289,141 @@ impl Agent {
     }.boxed()
     }
 
-    pub(crate) fn handle_frontend_tool_request<'a>(
+    /// Hands every frontend tool call to the client before waiting on any of them, so clients
+    /// can run them concurrently. Results are matched back to their requests by id.
+    pub(crate) fn handle_frontend_tool_requests<'a>(
         &'a self,
-        tool_request: &'a ToolRequest,
-        message_tool_response: Arc<Mutex<Message>>,
+        tool_requests: &'a [ToolRequest],
+        request_to_response_map: &'a HashMap<String, Arc<Mutex<Message>>>,
     ) -> BoxStream<'a, anyhow::Result<Message>> {
         try_stream! {
+            let mut pending = HashMap::new();
+            for tool_request in tool_requests {
                 if let Ok(tool_call) = tool_request.tool_call.clone() {
                     if self.is_frontend_tool(&tool_call.name).await {
-                        // Send frontend tool request and wait for response
                         yield Message::assistant().with_frontend_tool_request(
                             tool_request.id.clone(),
-                            Ok(tool_call.clone())
+                            Ok(tool_call)
                         );
-
-                        if let Some((id, result)) = self.tool_result_rx.lock().await.recv().await {
-                            let mut response = message_tool_response.lock().await;
-                            *response = response.clone().with_tool_response_with_metadata(
-                                id,
-                                result,
-                                tool_request.metadata.as_ref(),
-                            );
-                        }
+                        pending.insert(tool_request.id.clone(), tool_request);
                     }
+                }
+            }
+
+            let mut tool_result_rx = self.tool_result_rx.lock().await;
+            while !pending.is_empty() {
+                let Some((id, result)) = tool_result_rx.recv().await else {
+                    break;
+                };
+                // Late results for calls from an earlier, cancelled turn.
+                let Some(tool_request) = pending.remove(&id) else {
+                    continue;
+                };
+                if let Some(response_msg) = request_to_response_map.get(&id) {
+                    let mut response = response_msg.lock().await;
+                    *response = response.clone().with_tool_response_with_metadata(
+                        id,
+                        result,
+                        tool_request.metadata.as_ref(),
+                    );
+                }
             }
         }
         .boxed()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::CallToolResult;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_limit_parallel_tools() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let most_running = Arc::new(AtomicUsize::new(0));
+        let tool_futures = (0..4)
+            .map(|i| {
+                let running = running.clone();
+                let most_running = most_running.clone();
+                let done = async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most_running.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(CallToolResult::success(vec![]))
+                };
+                (i.to_string(), tool_stream(stream::empty(), done))
+            })
+            .collect();
+
+        let limited = limit_parallel_tools(tool_futures, Some(2))
+            .into_iter()
+            .map(|(_, tool_stream)| tool_stream);
+        let results: Vec<_> = stream::select_all(limited).collect().await;
+        assert_eq!(results.len(), 4);
+        assert_eq!(most_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_with_timeout() {
+        let running = RunningToolCalls::default();
+        let token = running.start("call_1", None);
+        let stopped = Arc::new(AtomicUsize::new(0));
+        // Hangs until cancelled, like an extension that watches its token
+        let hung = ToolCallResult {
+            result: Box::new(
+                {
+                    let token = token.clone();
+                    let stopped = stopped.clone();
+                    async move {
+                        token.cancelled().await;
+                        stopped.fetch_add(1, Ordering::SeqCst);
+                        Ok(CallToolResult::success(vec![]))
+                    }
+                }
+                .boxed(),
+            ),
+            notification_stream: None,
+        };
+        let tracked = running.track("call_1".to_string(), token.clone(), hung);
+        let error = with_timeout(tracked, Some(Duration::from_millis(10)), token.clone())
+            .result
+            .await
+            .unwrap_err();
+        assert_eq!(error.message, "Tool call timed out after 10ms");
+        assert!(token.is_cancelled());
+        assert_eq!(stopped.load(Ordering::SeqCst), 1);
+        assert!(!running.cancel("call_1"));
+
+        let quick = ToolCallResult::from(Ok(CallToolResult::success(vec![])));
+        assert!(with_timeout(
+            quick,
+            Some(Duration::from_secs(5)),
+            CancellationToken::new()
+        )
+        .result
+        .await
+        .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_running_tool_call_cancel() {
+        let running = RunningToolCalls::default();
+        let turn = CancellationToken::new();
+        let token = running.start("call_1", Some(&turn));
+        let hung = ToolCallResult {
+            result: Box::new(futures::future::pending().boxed()),
+            notification_stream: None,
+        };
+        let result = running.track("call_1".to_string(), token, hung);
+
+        assert!(running.cancel("call_1"));
+        assert!(!running.cancel("call_2"));
+        let error = result.result.await.unwrap_err();
+        assert_eq!(error.message, "Tool call was cancelled by the user");
+        assert!(!turn.is_cancelled());
+        assert!(!running.cancel("call_1"));
+    }
+}
@@ -2,7 +2,9 @@ use crate::mcp_utils::ToolResult;
 use crate::providers::base::Provider;
 use rmcp::model::{CallToolResult, Tool};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
 use utoipa::ToSchema;
 
@@ -61,6 +63,51 @@ impl RetryConfig {
     }
 }
 
+/// How long a single tool call may run before it fails with a timeout error. These apply on top
+/// of each extension's own request timeout.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolTimeouts {
+    /// For tools without their own entry; unset means no extra limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_seconds: Option<u64>,
+    /// Keyed by the full tool name, such as `developer__shell`.
+    #[serde(default)]
+    pub per_tool_seconds: HashMap<String, u64>,
+}
+
+impl ToolTimeouts {
+    pub fn for_tool(&self, tool_name: &str) -> Option<Duration> {
+        self.per_tool_seconds
+            .get(tool_name)
+            .copied()
+            .or(self.default_seconds)
+            .map(Duration::from_secs)
+    }
+}
+
+/// Which tools the model is offered. Entries name either a whole extension, such as
+/// `developer`, or a single tool by its full name, such as `developer__shell`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolFilter {
+    /// When non-empty, only these are offered.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl ToolFilter {
+    pub fn allows(&self, tool_name: &str) -> bool {
+        let matches = |entry: &String| {
+            tool_name == entry
+                || tool_name
+                    .strip_prefix(entry.as_str())
+                    .is_some_and(|rest| rest.starts_with("__"))
+        };
+        (self.allow.is_empty() || self.allow.iter().any(matches)) && !self.deny.iter().any(matches)
+    }
+}
+
 /// A single success check to validate recipe completion
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type")]
@@ -157,4 +204,39 @@ mod tests {
             "on_failure_timeout_seconds must be greater than 0 if specified"
         );
     }
+
+    #[test]
+    fn test_tool_timeouts_for_tool() {
+        let timeouts: ToolTimeouts = serde_json::from_value(serde_json::json!({
+            "default_seconds": 60,
+            "per_tool_seconds": {"developer__shell": 600}
+        }))
+        .unwrap();
+        assert_eq!(
+            timeouts.for_tool("developer__shell"),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(
+            timeouts.for_tool("developer__text_editor"),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(ToolTimeouts::default().for_tool("developer__shell"), None);
+    }
+
+    #[test]
+    fn test_tool_filter_allows() {
+        let filter = ToolFilter {
+            allow: vec![
+                "developer".to_string(),
+                "memory__remember_memory".to_string(),
+            ],
+            deny: vec!["developer__shell".to_string()],
+        };
+        assert!(filter.allows("developer__text_editor"));
+        assert!(filter.allows("memory__remember_memory"));
+        assert!(!filter.allows("developer__shell"));
+        assert!(!filter.allows("memory__retrieve_memories"));
+        assert!(!filter.allows("developerx__tool"));
+        assert!(ToolFilter::default().allows("developer__shell"));
+    }
 }
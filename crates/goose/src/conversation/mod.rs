@@ -1,4 +1,6 @@
+use crate::context_mgmt::{compact_conversation, CompactionStrategy};
 use crate::conversation::message::{Message, MessageContent, MessageMetadata};
+use crate::providers::base::{Provider, ProviderUsage};
 use rmcp::model::Role;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -122,6 +124,17 @@ impl Conversation {
         self.filtered_messages(|meta| meta.user_visible)
     }
 
+    /// Replaces older turns with a generated summary, as chosen by `strategy`. Cut points fall on
+    /// turn boundaries, so tool requests and their responses are never split up.
+    pub async fn compact(
+        &self,
+        provider: &dyn Provider,
+        session_id: &str,
+        strategy: CompactionStrategy,
+    ) -> anyhow::Result<(Conversation, Option<ProviderUsage>)> {
+        compact_conversation(provider, session_id, self, strategy).await
+    }
+
     fn validate(self) -> Result<Self, InvalidConversation> {
         let (_messages, issues) = fix_messages(self.0.clone());
         if !issues.is_empty() {
@@ -115,6 +115,33 @@ pub struct ModelConfig {
     /// Provider-specific request parameters (e.g., anthropic_beta headers)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub request_params: Option<HashMap<String, Value>>,
+    /// "low", "medium" or "high" for reasoning models. Only the OpenAI chat format uses it so far,
+    /// where it wins over a `-low`/`-high` suffix on the model name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
+    /// Asks for a reply that is JSON matching this schema. Set per request by
+    /// [`crate::providers::base::Provider::complete_structured`] on providers that support it.
+    #[serde(skip)]
+    pub response_schema: Option<Value>,
+    /// Sequences that end the reply when the model produces them. The OpenAI Responses API has
+    /// no such setting, so it ignores them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+/// Settings for a single request, layered over a session's [`ModelConfig`] with
+/// [`ModelConfig::with_overrides`] so one session can mix quick and thorough generations.
+/// Fields left unset keep the session's value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ModelOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -166,6 +193,9 @@ impl ModelConfig {
             toolshim_model,
             fast_model: None,
             request_params,
+            reasoning_effort: None,
+            response_schema: None,
+            stop_sequences: None,
         })
     }
 
@@ -345,6 +375,37 @@ impl ModelConfig {
         self
     }
 
+    pub fn with_reasoning_effort(mut self, effort: Option<String>) -> Self {
+        self.reasoning_effort = effort;
+        self
+    }
+
+    pub fn with_response_schema(mut self, schema: Option<Value>) -> Self {
+        self.response_schema = schema;
+        self
+    }
+
+    pub fn with_stop_sequences(mut self, stop_sequences: Option<Vec<String>>) -> Self {
+        self.stop_sequences = stop_sequences;
+        self
+    }
+
+    pub fn with_overrides(mut self, overrides: &ModelOverrides) -> Self {
+        if let Some(temperature) = overrides.temperature {
+            self.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            self.max_tokens = Some(max_tokens);
+        }
+        if let Some(effort) = &overrides.reasoning_effort {
+            self.reasoning_effort = Some(effort.clone());
+        }
+        if let Some(stop_sequences) = &overrides.stop_sequences {
+            self.stop_sequences = Some(stop_sequences.clone());
+        }
+        self
+    }
+
     pub fn use_fast_model(&self) -> Self {
         if let Some(fast_model) = &self.fast_model {
             let mut config = self.clone();
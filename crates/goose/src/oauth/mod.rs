@@ -8,6 +8,7 @@ use minijinja::render;
 use rmcp::transport::auth::{CredentialStore, OAuthState, StoredCredentials};
 use rmcp::transport::AuthorizationManager;
 use serde::Deserialize;
+use std::future::Future;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::{oneshot, Mutex};
@@ -17,6 +18,23 @@ use crate::oauth::persist::GooseCredentialStore;
 
 const CALLBACK_TEMPLATE: &str = include_str!("oauth_callback.html");
 
+/// Shows the user an MCP server's authorization URL, given the server's name and the URL, in
+/// place of opening a browser on this machine.
+pub type AuthorizationUrlHandler = Arc<dyn Fn(&str, &str) + Send + Sync>;
+
+tokio::task_local! {
+    static AUTHORIZATION_URL_HANDLER: AuthorizationUrlHandler;
+}
+
+/// Authorization flows started inside `future` hand their URL to `handler`, for callers like an
+/// ACP server whose user sits behind a client rather than at this machine.
+pub async fn with_authorization_url_handler<F: Future>(
+    handler: AuthorizationUrlHandler,
+    future: F,
+) -> F::Output {
+    AUTHORIZATION_URL_HANDLER.scope(handler, future).await
+}
+
 #[derive(Clone)]
 struct AppState {
     code_receiver: Arc<Mutex<Option<oneshot::Sender<CallbackParams>>>>,
@@ -84,7 +102,10 @@ pub async fn oauth_flow(
         .await?;
 
     let authorization_url = oauth_state.get_authorization_url().await?;
-    if webbrowser::open(authorization_url.as_str()).is_err() {
+    let handled = AUTHORIZATION_URL_HANDLER
+        .try_with(|handler| handler(name, &authorization_url))
+        .is_ok();
+    if !handled && webbrowser::open(authorization_url.as_str()).is_err() {
         eprintln!("Open the following URL to authorize {}:", name);
         eprintln!("  {}", authorization_url);
     }
@@ -2,8 +2,10 @@ pub mod permission_confirmation;
 pub mod permission_inspector;
 pub mod permission_judge;
 pub mod permission_store;
+pub mod risk_classifier;
 
 pub use permission_confirmation::{Permission, PermissionConfirmation};
 pub use permission_inspector::PermissionInspector;
 pub use permission_judge::detect_read_only_tools;
 pub use permission_store::ToolPermissionStore;
+pub use risk_classifier::{RiskAssessment, RiskClassifier, RiskLevel, RulePackClassifier};
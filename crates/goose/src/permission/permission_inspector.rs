@@ -1,19 +1,25 @@
 use crate::agents::extension_manager_extension::MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE;
 use crate::config::permission::PermissionLevel;
-use crate::config::{GooseMode, PermissionManager};
+use crate::config::{ExtensionModes, GooseMode, PermissionManager};
 use crate::conversation::message::{Message, ToolRequest};
 use crate::permission::permission_judge::PermissionCheckResult;
+use crate::permission::risk_classifier::{RiskAssessment, RiskClassifier, RiskLevel};
 use crate::tool_inspection::{InspectionAction, InspectionResult, ToolInspector};
 use anyhow::Result;
 use async_trait::async_trait;
+use rmcp::model::CallToolRequestParams;
 use std::collections::HashSet;
 use std::sync::Arc;
 
+const SHELL_TOOL_NAME: &str = "developer__shell";
+
 /// Permission Inspector that handles tool permission checking
 pub struct PermissionInspector {
     readonly_tools: HashSet<String>,
     regular_tools: HashSet<String>,
     pub permission_manager: Arc<PermissionManager>,
+    risk_classifiers: Vec<Arc<dyn RiskClassifier>>,
+    extension_modes: ExtensionModes,
 }
 
 impl PermissionInspector {
@@ -26,9 +32,41 @@ impl PermissionInspector {
             readonly_tools,
             regular_tools,
             permission_manager,
+            risk_classifiers: Vec::new(),
+            extension_modes: ExtensionModes::default(),
         }
     }
 
+    /// Runs the tools of the given extensions in their own mode rather than the session's.
+    pub fn with_extension_modes(mut self, extension_modes: ExtensionModes) -> Self {
+        self.extension_modes = extension_modes;
+        self
+    }
+
+    /// Adds a classifier consulted in smart approve mode.
+    pub fn with_risk_classifier(mut self, classifier: Arc<dyn RiskClassifier>) -> Self {
+        self.risk_classifiers.push(classifier);
+        self
+    }
+
+    /// The highest risk any classifier assigns to `tool_call`.
+    fn assess_risk(&self, tool_call: &CallToolRequestParams) -> Option<RiskAssessment> {
+        self.risk_classifiers
+            .iter()
+            .filter_map(|classifier| classifier.classify(tool_call))
+            .max_by_key(|assessment| assessment.level)
+    }
+
+    fn is_allowed_command(&self, tool_call: &CallToolRequestParams) -> bool {
+        tool_call.name == SHELL_TOOL_NAME
+            && tool_call
+                .arguments
+                .as_ref()
+                .and_then(|args| args.get("command"))
+                .and_then(|command| command.as_str())
+                .is_some_and(|command| self.permission_manager.is_user_command_allowed(command))
+    }
+
     /// Process inspection results into permission decisions
     /// This method takes all inspection results and converts them into a PermissionCheckResult
     /// that can be used by the agent to determine which tools to approve, deny, or ask for approval
@@ -115,13 +153,25 @@ impl ToolInspector for PermissionInspector {
         for request in tool_requests {
             if let Ok(tool_call) = &request.tool_call {
                 let tool_name = &tool_call.name;
+                let mode = self
+                    .extension_modes
+                    .mode_for_tool(tool_name.as_ref(), goose_mode);
+                let risk = if mode == GooseMode::SmartApprove {
+                    self.assess_risk(tool_call)
+                } else {
+                    None
+                };
 
-                let action = match goose_mode {
-                    GooseMode::Chat => continue,
+                let action = match mode {
+                    GooseMode::Chat if goose_mode == GooseMode::Chat => continue,
+                    // The session runs tools, but not this extension's
+                    GooseMode::Chat => InspectionAction::Deny,
                     GooseMode::Auto => InspectionAction::Allow,
                     GooseMode::Approve | GooseMode::SmartApprove => {
                         // 1. Check user-defined permission first
-                        if let Some(level) = permission_manager.get_user_permission(tool_name) {
+                        if let Some(level) =
+                            permission_manager.get_user_permission_for_call(tool_call)
+                        {
                             match level {
                                 PermissionLevel::AlwaysAllow => InspectionAction::Allow,
                                 PermissionLevel::NeverAllow => InspectionAction::Deny,
@@ -130,9 +180,20 @@ impl ToolInspector for PermissionInspector {
                                 }
                             }
                         }
-                        // 2. Check if it's a readonly or regular tool (both pre-approved)
+                        // 2. Let risk classifiers decide in smart approve mode
+                        else if let Some(assessment) = &risk {
+                            match assessment.level {
+                                RiskLevel::Low => InspectionAction::Allow,
+                                RiskLevel::High => {
+                                    InspectionAction::RequireApproval(assessment.reason.clone())
+                                }
+                            }
+                        }
+                        // 3. Check if it's a readonly or regular tool (both pre-approved), or a
+                        //    shell command matching one of the user's allowed prefixes
                         else if self.readonly_tools.contains(tool_name.as_ref())
                             || self.regular_tools.contains(tool_name.as_ref())
+                            || self.is_allowed_command(tool_call)
                         {
                             InspectionAction::Allow
                         }
@@ -151,8 +212,10 @@ impl ToolInspector for PermissionInspector {
 
                 let reason = match &action {
                     InspectionAction::Allow => {
-                        if goose_mode == GooseMode::Auto {
+                        if mode == GooseMode::Auto {
                             "Auto mode - all tools approved".to_string()
+                        } else if risk.as_ref().is_some_and(|a| a.level == RiskLevel::Low) {
+                            "Classified as low risk".to_string()
                         } else if self.readonly_tools.contains(tool_name.as_ref()) {
                             "Tool marked as read-only".to_string()
                         } else if self.regular_tools.contains(tool_name.as_ref()) {
@@ -161,10 +224,18 @@ impl ToolInspector for PermissionInspector {
                             "User permission allows this tool".to_string()
                         }
                     }
-                    InspectionAction::Deny => "User permission denies this tool".to_string(),
+                    InspectionAction::Deny => {
+                        if mode == GooseMode::Chat {
+                            "Extension runs in chat mode - tools disabled".to_string()
+                        } else {
+                            "User permission denies this tool".to_string()
+                        }
+                    }
                     InspectionAction::RequireApproval(_) => {
                         if tool_name == MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE {
                             "Extension management requires user approval".to_string()
+                        } else if risk.as_ref().is_some_and(|a| a.level == RiskLevel::High) {
+                            "Classified as high risk".to_string()
                         } else {
                             "Tool requires user approval".to_string()
                         }
@@ -185,3 +256,59 @@ impl ToolInspector for PermissionInspector {
         Ok(results)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::object;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn tool_request(id: &str, name: &str) -> ToolRequest {
+        ToolRequest {
+            id: id.to_string(),
+            tool_call: Ok(CallToolRequestParams {
+                meta: None,
+                task: None,
+                name: name.to_string().into(),
+                arguments: Some(object!({})),
+            }),
+            metadata: None,
+            tool_meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extension_mode_overrides_session_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let inspector = PermissionInspector::new(
+            HashSet::new(),
+            HashSet::new(),
+            Arc::new(PermissionManager::new(temp_dir.path().to_path_buf())),
+        )
+        .with_extension_modes(ExtensionModes::new(HashMap::from([
+            ("Developer".to_string(), GooseMode::Approve),
+            ("fetch".to_string(), GooseMode::Chat),
+        ])));
+        let requests = [
+            tool_request("1", "developer__shell"),
+            tool_request("2", "fetch__get"),
+            tool_request("3", "memory__remember"),
+        ];
+
+        let results = inspector
+            .inspect(&requests, &[], GooseMode::Auto)
+            .await
+            .unwrap();
+
+        let actions: Vec<_> = results.into_iter().map(|r| r.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                InspectionAction::RequireApproval(None),
+                InspectionAction::Deny,
+                InspectionAction::Allow,
+            ]
+        );
+    }
+}
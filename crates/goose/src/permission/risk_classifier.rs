@@ -0,0 +1,149 @@
+use crate::config::permission::{rule_matches, rule_matches_any_segment};
+use crate::config::{Config, ConfigError};
+use rmcp::model::CallToolRequestParams;
+use serde::{Deserialize, Serialize};
+
+/// Config key holding the rule pack applied in smart approve mode.
+pub const RISK_RULES_CONFIG_KEY: &str = "GOOSE_RISK_RULES";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    /// Safe to run without asking.
+    Low,
+    /// Always ask the user, whatever the built-in heuristics say.
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskAssessment {
+    pub level: RiskLevel,
+    pub reason: Option<String>,
+}
+
+/// Scores tool calls in smart approve mode. User permissions still apply first; a call no
+/// classifier has an opinion on falls through to the built-in read-only heuristics.
+pub trait RiskClassifier: Send + Sync {
+    fn classify(&self, tool_call: &CallToolRequestParams) -> Option<RiskAssessment>;
+}
+
+/// One rule of a rule pack. `tool` uses the permission rule syntax: a tool glob, optionally
+/// followed by `:` and a command glob, e.g. `developer__shell:*prod*`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskRule {
+    pub tool: String,
+    pub risk: RiskLevel,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// A classifier built from a list of rules. When several match, the highest risk wins.
+#[derive(Debug, Clone, Default)]
+pub struct RulePackClassifier {
+    rules: Vec<RiskRule>,
+}
+
+impl RulePackClassifier {
+    pub fn new(rules: Vec<RiskRule>) -> Self {
+        Self { rules }
+    }
+
+    /// The rules under `GOOSE_RISK_RULES`, or `None` if there aren't any.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        match config.get_param::<Vec<RiskRule>>(RISK_RULES_CONFIG_KEY) {
+            Ok(rules) if !rules.is_empty() => Some(Self::new(rules)),
+            Ok(_) => None,
+            Err(e) => {
+                if !matches!(e, ConfigError::NotFound(_)) {
+                    tracing::warn!(error = %e, "ignoring invalid {}", RISK_RULES_CONFIG_KEY);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl RiskClassifier for RulePackClassifier {
+    fn classify(&self, tool_call: &CallToolRequestParams) -> Option<RiskAssessment> {
+        let command = tool_call
+            .arguments
+            .as_ref()
+            .and_then(|args| args.get("command"))
+            .and_then(|command| command.as_str());
+        self.rules
+            .iter()
+            .filter(|rule| match rule.risk {
+                // Any part of a compound command can make it risky; only a whole command is safe
+                RiskLevel::High => rule_matches_any_segment(&rule.tool, &tool_call.name, command),
+                RiskLevel::Low => rule_matches(&rule.tool, &tool_call.name, command),
+            })
+            .max_by_key(|rule| rule.risk)
+            .map(|rule| RiskAssessment {
+                level: rule.risk,
+                reason: rule.reason.clone(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(command: &str) -> CallToolRequestParams {
+        CallToolRequestParams {
+            meta: None,
+            task: None,
+            name: "developer__shell".into(),
+            arguments: serde_json::json!({ "command": command })
+                .as_object()
+                .cloned(),
+        }
+    }
+
+    #[test]
+    fn test_rule_pack_highest_risk_wins() {
+        let rules: Vec<RiskRule> = serde_yaml::from_str(
+            "- tool: 'developer__shell:ls*'\n  risk: low\n\
+             - tool: 'developer__shell:*prod*'\n  risk: high\n  reason: Touches production credentials\n",
+        )
+        .unwrap();
+        let classifier = RulePackClassifier::new(rules);
+
+        assert_eq!(
+            classifier.classify(&shell("ls ~/.aws/prod")),
+            Some(RiskAssessment {
+                level: RiskLevel::High,
+                reason: Some("Touches production credentials".to_string()),
+            })
+        );
+        assert_eq!(
+            classifier.classify(&shell("ls")).map(|a| a.level),
+            Some(RiskLevel::Low)
+        );
+        assert_eq!(classifier.classify(&shell("cat notes.txt")), None);
+    }
+
+    #[test]
+    fn test_high_risk_rules_match_any_segment() {
+        let classifier = RulePackClassifier::new(vec![
+            RiskRule {
+                tool: "developer__shell:ls*".to_string(),
+                risk: RiskLevel::Low,
+                reason: None,
+            },
+            RiskRule {
+                tool: "developer__shell:kubectl delete*".to_string(),
+                risk: RiskLevel::High,
+                reason: None,
+            },
+        ]);
+
+        assert_eq!(
+            classifier
+                .classify(&shell("ls && kubectl delete ns prod"))
+                .map(|a| a.level),
+            Some(RiskLevel::High)
+        );
+        assert_eq!(classifier.classify(&shell("ls; echo done")), None);
+    }
+}
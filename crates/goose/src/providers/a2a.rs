@@ -0,0 +1,273 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use rmcp::model::{Role, Tool};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use super::api_client::{ApiClient, AuthMethod};
+use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::errors::ProviderError;
+use super::retry::ProviderRetry;
+use super::utils::{handle_response_openai_compat, RequestLog};
+use crate::config::Config;
+use crate::conversation::message::Message;
+use crate::model::ModelConfig;
+
+pub const A2A_DEFAULT_MODEL: &str = "default";
+pub const A2A_DOC_URL: &str = "https://a2a-protocol.org/latest/specification/";
+
+/// Drives a remote Agent2Agent agent. The agent runs its own model and tools, so goose only
+/// sends it the latest user message and keeps the A2A context id per session so the agent
+/// remembers earlier turns.
+#[derive(serde::Serialize)]
+pub struct A2aProvider {
+    #[serde(skip)]
+    api_client: ApiClient,
+    model: ModelConfig,
+    #[serde(skip)]
+    contexts: Mutex<HashMap<String, String>>,
+    #[serde(skip)]
+    name: String,
+}
+
+impl A2aProvider {
+    pub async fn from_env(model: ModelConfig) -> Result<Self> {
+        let config = Config::global();
+        let url: String = config.get_param("A2A_AGENT_URL")?;
+        let auth = match config.get_secret::<String>("A2A_API_KEY") {
+            Ok(key) => AuthMethod::BearerToken(key),
+            Err(_) => AuthMethod::NoAuth,
+        };
+
+        Ok(Self {
+            api_client: ApiClient::new(url, auth)?,
+            model,
+            contexts: Mutex::new(HashMap::new()),
+            name: Self::metadata().name,
+        })
+    }
+
+    fn send_request(&self, session_id: Option<&str>, messages: &[Message]) -> Value {
+        let text = messages
+            .iter()
+            .rev()
+            .find(|message| message.role == Role::User)
+            .map(|message| message.as_concat_text())
+            .unwrap_or_default();
+
+        let mut message = json!({
+            "kind": "message",
+            "role": "user",
+            "messageId": Uuid::new_v4().to_string(),
+            "parts": [{"kind": "text", "text": text}],
+        });
+        if let Some(context_id) =
+            session_id.and_then(|id| self.contexts.lock().unwrap().get(id).cloned())
+        {
+            message["contextId"] = json!(context_id);
+        }
+
+        json!({
+            "jsonrpc": "2.0",
+            "id": Uuid::new_v4().to_string(),
+            "method": "message/send",
+            "params": {
+                "message": message,
+                "configuration": {"blocking": true},
+            },
+        })
+    }
+}
+
+fn parts_text(parts: Option<&Value>) -> Vec<String> {
+    parts
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|part| part.get("kind").and_then(Value::as_str) == Some("text"))
+        .filter_map(|part| part.get("text").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Turns the result of `message/send`, either a message or a task, into an assistant
+/// message and the context id the agent filed it under.
+fn response_to_message(response: &Value) -> Result<(Message, Option<String>), ProviderError> {
+    if let Some(error) = response.get("error") {
+        return Err(ProviderError::RequestFailed(format!(
+            "A2A agent returned an error: {}",
+            error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+        )));
+    }
+    let result = response
+        .get("result")
+        .ok_or_else(|| ProviderError::RequestFailed("A2A response has no result".to_string()))?;
+    let context_id = result
+        .get("contextId")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let texts = match result.get("kind").and_then(Value::as_str) {
+        Some("message") => parts_text(result.get("parts")),
+        Some("task") => {
+            let status = result.get("status");
+            let state = status
+                .and_then(|status| status.get("state"))
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let status_text = parts_text(
+                status
+                    .and_then(|status| status.get("message"))
+                    .and_then(|message| message.get("parts")),
+            );
+            if matches!(state, "failed" | "rejected" | "canceled") {
+                return Err(ProviderError::ExecutionError(format!(
+                    "A2A task {}: {}",
+                    state,
+                    status_text.join("\n")
+                )));
+            }
+
+            let mut texts: Vec<String> = result
+                .get("artifacts")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .flat_map(|artifact| parts_text(artifact.get("parts")))
+                .collect();
+            texts.extend(status_text);
+            texts
+        }
+        other => {
+            return Err(ProviderError::RequestFailed(format!(
+                "Unexpected A2A result kind: {:?}",
+                other
+            )))
+        }
+    };
+
+    Ok((
+        Message::assistant().with_text(texts.join("\n\n")),
+        context_id,
+    ))
+}
+
+#[async_trait]
+impl Provider for A2aProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "a2a",
+            "A2A Agent",
+            "Delegate to a remote agent over the Agent2Agent protocol. The agent uses its own tools.",
+            A2A_DEFAULT_MODEL,
+            vec![A2A_DEFAULT_MODEL],
+            A2A_DOC_URL,
+            vec![
+                ConfigKey::new("A2A_AGENT_URL", true, false, None),
+                ConfigKey::new("A2A_API_KEY", false, true, None),
+            ],
+        )
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.model.clone()
+    }
+
+    #[tracing::instrument(
+        skip(self, _model_config, _system, messages, _tools),
+        fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
+    )]
+    async fn complete_with_model(
+        &self,
+        session_id: Option<&str>,
+        _model_config: &ModelConfig,
+        _system: &str,
+        messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let payload = self.send_request(session_id, messages);
+        let mut log = RequestLog::start(&self.model, &payload)?;
+
+        let response = self
+            .with_retry(|| async {
+                let response = self
+                    .api_client
+                    .response_post(session_id, "", &payload)
+                    .await?;
+                handle_response_openai_compat(response).await
+            })
+            .await
+            .inspect_err(|e| {
+                let _ = log.error(e);
+            })?;
+
+        let (message, context_id) = response_to_message(&response)?;
+        if let (Some(session_id), Some(context_id)) = (session_id, context_id) {
+            self.contexts
+                .lock()
+                .unwrap()
+                .insert(session_id.to_string(), context_id);
+        }
+
+        let usage = Usage::default();
+        log.write(&response, Some(&usage))?;
+        Ok((
+            message,
+            ProviderUsage::new(self.model.model_name.clone(), usage),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_result_collects_artifacts_and_context() {
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": "1",
+            "result": {
+                "kind": "task",
+                "id": "task-1",
+                "contextId": "ctx-1",
+                "status": {"state": "completed"},
+                "artifacts": [
+                    {"artifactId": "a", "parts": [{"kind": "text", "text": "first"}]},
+                    {"artifactId": "b", "parts": [
+                        {"kind": "file", "file": {"uri": "file:///x"}},
+                        {"kind": "text", "text": "second"},
+                    ]},
+                ],
+            },
+        });
+
+        let (message, context_id) = response_to_message(&response).unwrap();
+        assert_eq!(message.as_concat_text(), "first\n\nsecond");
+        assert_eq!(context_id.as_deref(), Some("ctx-1"));
+    }
+
+    #[test]
+    fn test_failed_task_and_rpc_error_are_errors() {
+        let failed = json!({"result": {
+            "kind": "task",
+            "status": {"state": "failed", "message": {"parts": [{"kind": "text", "text": "boom"}]}},
+        }});
+        assert!(matches!(
+            response_to_message(&failed),
+            Err(ProviderError::ExecutionError(msg)) if msg.contains("boom")
+        ));
+
+        let error = json!({"error": {"code": -32001, "message": "Task not found"}});
+        assert!(response_to_message(&error).is_err());
+    }
+}
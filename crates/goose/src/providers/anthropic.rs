@@ -14,7 +14,10 @@ use super::errors::ProviderError;
 use super::formats::anthropic::{
     create_request, get_usage, response_to_message, response_to_streaming_message,
 };
-use super::utils::{get_model, handle_status_openai_compat, map_http_error_to_provider_error};
+use super::utils::{
+    get_model, handle_status_openai_compat, map_http_error_to_provider_error,
+    map_http_error_with_backoff,
+};
 use crate::config::declarative_providers::DeclarativeProviderConfig;
 use crate::conversation::message::Message;
 use crate::model::ModelConfig;
@@ -159,9 +162,10 @@ impl AnthropicProvider {
                         }
                     }
                 }
-                Err(map_http_error_to_provider_error(
+                Err(map_http_error_with_backoff(
                     response.status,
                     response.payload,
+                    response.retry_after,
                 ))
             }
         }
@@ -263,21 +267,22 @@ impl Provider for AnthropicProvider {
         Ok(Some(models))
     }
 
-    async fn stream(
+    async fn stream_with_model(
         &self,
         session_id: &str,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<MessageStream, ProviderError> {
-        let mut payload = create_request(&self.model, system, messages, tools)?;
+        let mut payload = create_request(model_config, system, messages, tools)?;
         payload
             .as_object_mut()
             .unwrap()
             .insert("stream".to_string(), Value::Bool(true));
 
         let mut request = self.api_client.request(Some(session_id), "v1/messages");
-        let mut log = RequestLog::start(&self.model, &payload)?;
+        let mut log = RequestLog::start(model_config, &payload)?;
 
         for (key, value) in self.get_conditional_headers() {
             request = request.header(key, value)?;
@@ -168,6 +168,8 @@ pub trait AuthProvider: Send + Sync {
 pub struct ApiResponse {
     pub status: StatusCode,
     pub payload: Option<Value>,
+    /// See [`backoff_hint`].
+    pub retry_after: Option<Duration>,
 }
 
 impl fmt::Debug for AuthMethod {
@@ -189,11 +191,95 @@ impl fmt::Debug for AuthMethod {
 impl ApiResponse {
     pub async fn from_response(response: Response) -> Result<Self> {
         let status = response.status();
+        let retry_after = backoff_hint(response.headers());
         let payload = response.json().await.ok();
-        Ok(Self { status, payload })
+        Ok(Self {
+            status,
+            payload,
+            retry_after,
+        })
     }
 }
 
+/// The longest a backoff hint is honored for, so a bogus header can't park a request for days.
+pub const MAX_BACKOFF_HINT: Duration = Duration::from_secs(15 * 60);
+
+/// How long the server asked us to wait before trying again: `Retry-After` (seconds or an
+/// HTTP date), `retry-after-ms`, or else the reset time of whichever of OpenAI's or
+/// Anthropic's rate limit buckets has run out. Capped at [`MAX_BACKOFF_HINT`].
+pub fn backoff_hint(headers: &HeaderMap) -> Option<Duration> {
+    requested_backoff(headers).map(|wait| wait.min(MAX_BACKOFF_HINT))
+}
+
+fn requested_backoff(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.trim().parse::<f64>().ok()) {
+        if ms.is_finite() && ms >= 0.0 {
+            // Too large to represent is as good as forever.
+            return Some(Duration::try_from_secs_f64(ms / 1000.0).unwrap_or(Duration::MAX));
+        }
+    }
+    if let Some(value) = header("retry-after").map(str::trim) {
+        if let Ok(secs) = value.parse::<u64>() {
+            return Some(Duration::from_secs(secs));
+        }
+        if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+            return Some(until(date.with_timezone(&chrono::Utc)));
+        }
+    }
+
+    let exhausted = |prefix: &str, bucket: &str| {
+        header(&format!("{}-remaining-{}", prefix, bucket)).map(str::trim) == Some("0")
+    };
+    let openai = ["requests", "tokens"]
+        .into_iter()
+        .filter(|bucket| exhausted("x-ratelimit", bucket))
+        .filter_map(|bucket| header(&format!("x-ratelimit-reset-{}", bucket)))
+        .filter_map(parse_go_duration);
+    let anthropic = ["requests", "tokens", "input-tokens", "output-tokens"]
+        .into_iter()
+        .filter(|bucket| {
+            header(&format!("anthropic-ratelimit-{}-remaining", bucket)).map(str::trim) == Some("0")
+        })
+        .filter_map(|bucket| header(&format!("anthropic-ratelimit-{}-reset", bucket)))
+        .filter_map(|reset| chrono::DateTime::parse_from_rfc3339(reset.trim()).ok())
+        .map(|reset| until(reset.with_timezone(&chrono::Utc)));
+    openai.chain(anthropic).max()
+}
+
+fn until(time: chrono::DateTime<chrono::Utc>) -> Duration {
+    (time - chrono::Utc::now()).to_std().unwrap_or_default()
+}
+
+/// Parses durations like `1s`, `6m0s` or `20ms`, as OpenAI's reset headers use.
+fn parse_go_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut chars = value.trim().chars().peekable();
+    chars.peek()?;
+    while chars.peek().is_some() {
+        let mut number = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+            number.push(c);
+        }
+        let mut unit = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_ascii_digit() && *c != '.') {
+            unit.push(c);
+        }
+        let scale = match unit.as_str() {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "us" | "µs" => 1e-6,
+            "ns" => 1e-9,
+            _ => return None,
+        };
+        total += number.parse::<f64>().ok()? * scale;
+    }
+    Duration::try_from_secs_f64(total).ok()
+}
+
 pub struct ApiRequestBuilder<'a> {
     client: &'a ApiClient,
     path: &'a str,
@@ -461,4 +547,68 @@ mod tests {
             assert_eq!(actual, expected);
         });
     }
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                    HeaderValue::from_str(value).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test_case(&[("retry-after", "7")], Some(7_000); "retry after seconds")]
+    #[test_case(&[("retry-after-ms", "1500"), ("retry-after", "7")], Some(1_500); "milliseconds first")]
+    #[test_case(&[("x-ratelimit-remaining-tokens", "0"), ("x-ratelimit-reset-tokens", "6m0s"), ("x-ratelimit-remaining-requests", "3"), ("x-ratelimit-reset-requests", "1s")], Some(360_000); "openai exhausted bucket")]
+    #[test_case(&[("x-ratelimit-remaining-requests", "3"), ("x-ratelimit-reset-requests", "1s")], None; "openai bucket not exhausted")]
+    #[test_case(&[("retry-after", "soon")], None; "unparseable")]
+    #[test_case(&[("retry-after", "86400")], Some(MAX_BACKOFF_HINT.as_millis() as u64); "clamped")]
+    #[test_case(&[("retry-after-ms", "1e30")], Some(MAX_BACKOFF_HINT.as_millis() as u64); "milliseconds overflow")]
+    #[test_case(&[("x-ratelimit-remaining-requests", "0"), ("x-ratelimit-reset-requests", "99999999999999999999999h")], None; "unrepresentable reset")]
+    fn test_backoff_hint(pairs: &[(&str, &str)], expected_ms: Option<u64>) {
+        assert_eq!(
+            backoff_hint(&headers(pairs)),
+            expected_ms.map(Duration::from_millis)
+        );
+    }
+
+    #[test]
+    fn test_backoff_hint_from_reset_times() {
+        let reset = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc3339();
+        let hint = backoff_hint(&headers(&[
+            ("anthropic-ratelimit-input-tokens-remaining", "0"),
+            ("anthropic-ratelimit-input-tokens-reset", &reset),
+        ]))
+        .unwrap();
+        assert!(hint > Duration::from_secs(25) && hint <= Duration::from_secs(30));
+
+        assert_eq!(
+            backoff_hint(&headers(&[
+                ("anthropic-ratelimit-requests-remaining", "0"),
+                ("anthropic-ratelimit-requests-reset", "9999-12-31T23:59:59Z"),
+            ])),
+            Some(MAX_BACKOFF_HINT)
+        );
+
+        let past = chrono::Utc::now() - chrono::Duration::seconds(30);
+        assert_eq!(
+            backoff_hint(&headers(&[("retry-after", &past.to_rfc2822())])),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test_case("1s", Some(1_000); "seconds")]
+    #[test_case("1m30.5s", Some(90_500); "minutes and fractions")]
+    #[test_case("20ms", Some(20); "milliseconds")]
+    #[test_case("", None; "empty")]
+    #[test_case("5 days", None; "unknown unit")]
+    fn test_parse_go_duration(value: &str, expected_ms: Option<u64>) {
+        assert_eq!(
+            parse_go_duration(value),
+            expected_ms.map(Duration::from_millis)
+        );
+    }
 }
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 
 use super::canonical::{map_to_canonical_model, CanonicalModelRegistry};
@@ -11,6 +11,7 @@ use crate::conversation::Conversation;
 use crate::model::ModelConfig;
 use crate::utils::safe_truncate;
 use rmcp::model::Tool;
+use tokio_util::sync::CancellationToken;
 use utoipa::ToSchema;
 
 use once_cell::sync::Lazy;
@@ -243,11 +244,24 @@ impl ConfigKey {
 pub struct ProviderUsage {
     pub model: String,
     pub usage: Usage,
+    /// The provider that served the request, when a wrapper such as
+    /// [`super::fallback::FallbackProvider`] picked one of several.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
 }
 
 impl ProviderUsage {
     pub fn new(model: String, usage: Usage) -> Self {
-        Self { model, usage }
+        Self {
+            model,
+            usage,
+            provider: None,
+        }
+    }
+
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
     }
 
     /// Ensures this ProviderUsage has token counts, estimating them if necessary
@@ -275,6 +289,7 @@ impl ProviderUsage {
         ProviderUsage {
             model: self.model.clone(),
             usage: self.usage + other.usage,
+            provider: self.provider.clone(),
         }
     }
 }
@@ -432,6 +447,25 @@ pub trait Provider: Send + Sync {
         }
     }
 
+    /// Whether the API can be told to answer in JSON matching a schema, via
+    /// [`ModelConfig::response_schema`].
+    fn supports_structured_output(&self) -> bool {
+        false
+    }
+
+    /// A completion whose reply is JSON validated against `schema`. Uses the API's own
+    /// structured output where supported, and re-prompts with the validation errors when a
+    /// reply doesn't match. The usage covers every attempt.
+    async fn complete_structured(
+        &self,
+        session_id: &str,
+        system: &str,
+        messages: &[Message],
+        schema: &serde_json::Value,
+    ) -> Result<(serde_json::Value, ProviderUsage), ProviderError> {
+        super::structured::complete_structured(self, session_id, system, messages, schema).await
+    }
+
     /// Get the model config from the provider
     fn get_model_config(&self) -> ModelConfig;
 
@@ -514,9 +548,39 @@ pub trait Provider: Send + Sync {
         None
     }
 
+    /// Streams a reply with the provider's configured model. Cancelling `cancel_token` ends
+    /// the stream straight away, whether or not the reply has started, and drops the request
+    /// so the connection to the provider is closed rather than left to run.
     async fn stream(
+        &self,
+        session_id: &str,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        cancel_token: CancellationToken,
+    ) -> Result<MessageStream, ProviderError> {
+        let model_config = self.get_model_config();
+        let started = cancel_token
+            .run_until_cancelled(self.stream_with_model(
+                session_id,
+                &model_config,
+                system,
+                messages,
+                tools,
+            ))
+            .await;
+        match started {
+            Some(stream) => Ok(cancellable_stream(stream?, cancel_token)),
+            None => Ok(Box::pin(futures::stream::empty())),
+        }
+    }
+
+    /// Streaming counterpart of [`Provider::complete_with_model`]; providers that stream
+    /// override this rather than [`Provider::stream`].
+    async fn stream_with_model(
         &self,
         _session_id: &str,
+        _model_config: &ModelConfig,
         _system: &str,
         _messages: &[Message],
         _tools: &[Tool],
@@ -620,6 +684,11 @@ pub type MessageStream = Pin<
     Box<dyn Stream<Item = Result<(Option<Message>, Option<ProviderUsage>), ProviderError>> + Send>,
 >;
 
+/// Ends `stream` as soon as `cancel_token` is cancelled, dropping whatever it was waiting on.
+pub fn cancellable_stream(stream: MessageStream, cancel_token: CancellationToken) -> MessageStream {
+    Box::pin(stream.take_until(cancel_token.cancelled_owned()))
+}
+
 pub fn stream_from_single_message(message: Message, usage: ProviderUsage) -> MessageStream {
     let stream = futures::stream::once(async move { Ok((Some(message), Some(usage))) });
     Box::pin(stream)
@@ -629,6 +698,7 @@ pub fn stream_from_single_message(message: Message, usage: ProviderUsage) -> Mes
 mod tests {
     use super::*;
     use std::collections::HashMap;
+    use test_case::test_case;
 
     use serde_json::json;
     #[test]
@@ -753,4 +823,67 @@ mod tests {
         assert_eq!(info.output_token_cost, Some(0.00001));
         assert_eq!(info.currency, Some("$".to_string()));
     }
+
+    struct HangingProvider {
+        hang_before_start: bool,
+    }
+
+    #[async_trait]
+    impl Provider for HangingProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "hanging"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail("hanging-model")
+        }
+
+        async fn complete_with_model(
+            &self,
+            _session_id: Option<&str>,
+            _model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            futures::future::pending().await
+        }
+
+        async fn stream_with_model(
+            &self,
+            _session_id: &str,
+            _model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<MessageStream, ProviderError> {
+            if self.hang_before_start {
+                futures::future::pending::<()>().await;
+            }
+            Ok(Box::pin(futures::stream::pending()))
+        }
+    }
+
+    #[test_case(true; "before the reply starts")]
+    #[test_case(false; "while the reply streams")]
+    #[tokio::test]
+    async fn test_stream_ends_when_cancelled(hang_before_start: bool) {
+        let provider = HangingProvider { hang_before_start };
+        let cancel_token = CancellationToken::new();
+        let canceller = cancel_token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            canceller.cancel();
+        });
+
+        let mut stream = provider
+            .stream("session", "", &[], &[], cancel_token)
+            .await
+            .unwrap();
+        assert!(stream.next().await.is_none());
+    }
 }
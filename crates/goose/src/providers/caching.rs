@@ -0,0 +1,483 @@
+use anyhow::Result;
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use lru::LruCache;
+use rmcp::model::{Role, Tool};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::base::{
+    LeadWorkerProviderTrait, MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
+use super::errors::ProviderError;
+use super::retry::RetryConfig;
+use crate::config::Config;
+use crate::conversation::message::{Message, MessageContent};
+use crate::model::ModelConfig;
+
+/// Config key that turns the response cache on, holding the entry lifetime in seconds.
+pub const CACHE_TTL_CONFIG_KEY: &str = "GOOSE_PROVIDER_CACHE_TTL";
+pub const CACHE_MAX_ENTRIES_CONFIG_KEY: &str = "GOOSE_PROVIDER_CACHE_MAX_ENTRIES";
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 256;
+
+type StreamChunk = (Option<Message>, Option<ProviderUsage>);
+
+#[derive(Clone)]
+enum CachedReply {
+    Complete(Message, ProviderUsage),
+    Streamed(Vec<StreamChunk>),
+}
+
+impl CachedReply {
+    /// The reply as served from the cache. Nothing was spent on it, so its usage counts zero
+    /// tokens and the session isn't charged for the call a second time.
+    fn served(self) -> Self {
+        let free = |usage: ProviderUsage| ProviderUsage {
+            usage: Usage::new(Some(0), Some(0), Some(0)),
+            ..usage
+        };
+        match self {
+            CachedReply::Complete(message, usage) => CachedReply::Complete(message, free(usage)),
+            CachedReply::Streamed(chunks) => CachedReply::Streamed(
+                chunks
+                    .into_iter()
+                    .map(|(message, usage)| (message, usage.map(free)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+struct CacheEntry {
+    reply: CachedReply,
+    stored_at: Instant,
+}
+
+type Entries = Arc<Mutex<LruCache<[u8; 32], CacheEntry>>>;
+
+#[derive(Serialize)]
+struct CacheKey<'a> {
+    model_name: &'a str,
+    temperature: Option<f32>,
+    max_tokens: Option<i32>,
+    reasoning_effort: Option<&'a str>,
+    request_params: Option<BTreeMap<&'a String, &'a serde_json::Value>>,
+    response_schema: Option<&'a serde_json::Value>,
+    system: &'a str,
+    // Only role and content: ids, timestamps and visibility differ between otherwise
+    // identical runs.
+    messages: Vec<(&'a Role, &'a [MessageContent])>,
+    tools: &'a [Tool],
+    streamed: bool,
+}
+
+/// A provider that remembers successful completions and answers identical requests from
+/// memory, so re-running tests, batch jobs or recipes doesn't hit the network again.
+/// Requests match when the model settings, system prompt, message contents and tools do.
+/// Cached answers report zero tokens used, since no call was made for them.
+///
+/// Streamed requests are cached once the whole stream has been read without an error, and
+/// replayed chunk by chunk.
+pub struct CachingProvider {
+    inner: Arc<dyn Provider>,
+    ttl: Duration,
+    entries: Entries,
+}
+
+impl CachingProvider {
+    pub fn new(inner: Arc<dyn Provider>, ttl: Duration, max_entries: NonZeroUsize) -> Self {
+        Self {
+            inner,
+            ttl,
+            entries: Arc::new(Mutex::new(LruCache::new(max_entries))),
+        }
+    }
+
+    /// Wraps `provider` when `GOOSE_PROVIDER_CACHE_TTL` is set, and returns it unchanged
+    /// otherwise.
+    pub fn wrap_from_config(provider: Arc<dyn Provider>, config: &Config) -> Arc<dyn Provider> {
+        let Ok(ttl_secs) = config.get_param::<u64>(CACHE_TTL_CONFIG_KEY) else {
+            return provider;
+        };
+        let max_entries = config
+            .get_param::<usize>(CACHE_MAX_ENTRIES_CONFIG_KEY)
+            .ok()
+            .and_then(NonZeroUsize::new)
+            .unwrap_or(NonZeroUsize::new(DEFAULT_CACHE_MAX_ENTRIES).unwrap());
+        Arc::new(Self::new(
+            provider,
+            Duration::from_secs(ttl_secs),
+            max_entries,
+        ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn key(
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        streamed: bool,
+    ) -> Result<[u8; 32], ProviderError> {
+        let key = CacheKey {
+            model_name: &model_config.model_name,
+            temperature: model_config.temperature,
+            max_tokens: model_config.max_tokens,
+            reasoning_effort: model_config.reasoning_effort.as_deref(),
+            request_params: model_config
+                .request_params
+                .as_ref()
+                .map(|params| params.iter().collect()),
+            response_schema: model_config.response_schema.as_ref(),
+            system,
+            messages: messages
+                .iter()
+                .map(|m| (&m.role, m.content.as_slice()))
+                .collect(),
+            tools,
+            streamed,
+        };
+        let bytes = serde_json::to_vec(&key).map_err(|e| {
+            ProviderError::ExecutionError(format!("Failed to build cache key: {}", e))
+        })?;
+        Ok(Sha256::digest(bytes).into())
+    }
+
+    fn lookup(&self, key: &[u8; 32]) -> Option<CachedReply> {
+        let mut entries = self.entries.lock().unwrap();
+        let expired = entries.get(key)?.stored_at.elapsed() > self.ttl;
+        if expired {
+            entries.pop(key);
+            return None;
+        }
+        entries.get(key).map(|entry| entry.reply.clone().served())
+    }
+
+    fn store(entries: &Entries, key: [u8; 32], reply: CachedReply) {
+        entries.lock().unwrap().put(
+            key,
+            CacheEntry {
+                reply,
+                stored_at: Instant::now(),
+            },
+        );
+    }
+}
+
+#[async_trait]
+impl Provider for CachingProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "caching",
+            "Caching Provider",
+            "A provider that answers repeated identical requests from memory",
+            "",
+            vec![],
+            "",
+            vec![],
+        )
+    }
+
+    fn get_name(&self) -> &str {
+        self.inner.get_name()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete_with_model(
+        &self,
+        session_id: Option<&str>,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let key = Self::key(model_config, system, messages, tools, false)?;
+        if let Some(CachedReply::Complete(message, usage)) = self.lookup(&key) {
+            tracing::debug!(model = %model_config.model_name, "Serving completion from cache");
+            return Ok((message, usage));
+        }
+
+        let (message, usage) = self
+            .inner
+            .complete_with_model(session_id, model_config, system, messages, tools)
+            .await?;
+        Self::store(
+            &self.entries,
+            key,
+            CachedReply::Complete(message.clone(), usage.clone()),
+        );
+        Ok((message, usage))
+    }
+
+    async fn stream_with_model(
+        &self,
+        session_id: &str,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let key = Self::key(model_config, system, messages, tools, true)?;
+        if let Some(CachedReply::Streamed(chunks)) = self.lookup(&key) {
+            tracing::debug!(model = %model_config.model_name, "Serving stream from cache");
+            return Ok(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok))));
+        }
+
+        let mut stream = self
+            .inner
+            .stream_with_model(session_id, model_config, system, messages, tools)
+            .await?;
+        let entries = self.entries.clone();
+        Ok(Box::pin(try_stream! {
+            let mut chunks = Vec::new();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                chunks.push(chunk.clone());
+                yield chunk;
+            }
+            Self::store(&entries, key, CachedReply::Streamed(chunks));
+        }))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn retry_config(&self) -> RetryConfig {
+        self.inner.retry_config()
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.inner.fetch_supported_models().await
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        self.inner.supports_structured_output()
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.inner.supports_embeddings()
+    }
+
+    async fn supports_cache_control(&self) -> bool {
+        self.inner.supports_cache_control().await
+    }
+
+    async fn create_embeddings(
+        &self,
+        session_id: &str,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.inner.create_embeddings(session_id, texts).await
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.inner.as_lead_worker()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::Usage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Provider for CountingProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "counting"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail("counting-model")
+        }
+
+        async fn complete_with_model(
+            &self,
+            _session_id: Option<&str>,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok((
+                Message::assistant().with_text(format!("answer {}", call)),
+                ProviderUsage::new(
+                    model_config.model_name.clone(),
+                    Usage::new(Some(10), Some(5), None),
+                ),
+            ))
+        }
+
+        async fn stream_with_model(
+            &self,
+            _session_id: &str,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<MessageStream, ProviderError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            let usage = ProviderUsage::new(
+                model_config.model_name.clone(),
+                Usage::new(Some(10), Some(5), None),
+            );
+            Ok(Box::pin(futures::stream::iter([
+                Ok((Some(Message::assistant().with_text("streamed ")), None)),
+                Ok((
+                    Some(Message::assistant().with_text(format!("answer {}", call))),
+                    Some(usage),
+                )),
+            ])))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_requests_hit_cache() {
+        let inner = Arc::new(CountingProvider::default());
+        let provider = CachingProvider::new(
+            inner.clone(),
+            Duration::from_secs(60),
+            NonZeroUsize::new(1).unwrap(),
+        );
+        let complete = |text: &'static str| {
+            let provider = &provider;
+            async move {
+                // A fresh message each time, so ids and timestamps don't line up.
+                let messages = [Message::user().with_text(text)];
+                let (message, _) = provider
+                    .complete("session", "system", &messages, &[])
+                    .await
+                    .unwrap();
+                message.as_concat_text()
+            }
+        };
+
+        assert_eq!(complete("hi").await, "answer 1");
+        assert_eq!(complete("hi").await, "answer 1");
+        assert_eq!(complete("bye").await, "answer 2");
+        // The single slot now holds "bye", so "hi" has been evicted.
+        assert_eq!(complete("hi").await, "answer 3");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(provider.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_hits_report_no_usage() {
+        let inner = Arc::new(CountingProvider::default());
+        let provider = CachingProvider::new(
+            inner.clone(),
+            Duration::from_secs(60),
+            NonZeroUsize::new(8).unwrap(),
+        );
+        let messages = [Message::user().with_text("hi")];
+
+        let (_, first) = provider
+            .complete("session", "system", &messages, &[])
+            .await
+            .unwrap();
+        let (_, second) = provider
+            .complete("session", "system", &messages, &[])
+            .await
+            .unwrap();
+        assert_eq!(first.usage.total_tokens, Some(15));
+        assert_eq!(second.usage.input_tokens, Some(0));
+        assert_eq!(second.usage.output_tokens, Some(0));
+        assert_eq!(second.usage.total_tokens, Some(0));
+        assert_eq!(second.model, first.model);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_requests_are_cached() {
+        let inner = Arc::new(CountingProvider::default());
+        let provider = CachingProvider::new(
+            inner.clone(),
+            Duration::from_secs(60),
+            NonZeroUsize::new(8).unwrap(),
+        );
+        let stream = || {
+            let provider = &provider;
+            async move {
+                let messages = [Message::user().with_text("hi")];
+                let model_config = provider.get_model_config();
+                let chunks: Vec<_> = provider
+                    .stream_with_model("session", &model_config, "system", &messages, &[])
+                    .await
+                    .unwrap()
+                    .map(|chunk| chunk.unwrap())
+                    .collect()
+                    .await;
+                let text: String = chunks
+                    .iter()
+                    .filter_map(|(message, _)| message.as_ref())
+                    .map(|message| message.as_concat_text())
+                    .collect();
+                let tokens = chunks
+                    .iter()
+                    .find_map(|(_, usage)| usage.as_ref())
+                    .and_then(|usage| usage.usage.total_tokens);
+                (text, tokens)
+            }
+        };
+
+        assert_eq!(stream().await, ("streamed answer 1".to_string(), Some(15)));
+        assert_eq!(stream().await, ("streamed answer 1".to_string(), Some(0)));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        // A streamed reply doesn't answer a non-streamed request for the same messages
+        let messages = [Message::user().with_text("hi")];
+        let (message, _) = provider
+            .complete("session", "system", &messages, &[])
+            .await
+            .unwrap();
+        assert_eq!(message.as_concat_text(), "answer 2");
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_refetched() {
+        let inner = Arc::new(CountingProvider::default());
+        let provider =
+            CachingProvider::new(inner.clone(), Duration::ZERO, NonZeroUsize::new(8).unwrap());
+        let messages = [Message::user().with_text("hi")];
+
+        provider
+            .complete("session", "system", &messages, &[])
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        provider
+            .complete("session", "system", &messages, &[])
+            .await
+            .unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -26,3 +26,20 @@ pub fn maybe_get_canonical_model(provider: &str, model: &str) -> Option<Canonica
     let canonical_id = map_to_canonical_model(provider, model, registry)?;
     registry.get(&canonical_id).cloned()
 }
+
+/// Estimate the USD cost of a request from the bundled pricing table, if the model is priced.
+pub fn estimate_cost_usd(
+    provider: &str,
+    model: &str,
+    input_tokens: usize,
+    output_tokens: usize,
+) -> Option<f64> {
+    let canonical_model = maybe_get_canonical_model(provider, model)?;
+
+    let input_cost_per_token = canonical_model.pricing.prompt?;
+    let output_cost_per_token = canonical_model.pricing.completion?;
+
+    let input_cost = input_cost_per_token * input_tokens as f64;
+    let output_cost = output_cost_per_token * output_tokens as f64;
+    Some(input_cost + output_cost)
+}
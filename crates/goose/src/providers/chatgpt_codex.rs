@@ -927,14 +927,15 @@ impl Provider for ChatGptCodexProvider {
         true
     }
 
-    async fn stream(
+    async fn stream_with_model(
         &self,
         session_id: &str,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<MessageStream, ProviderError> {
-        let mut payload = create_codex_request(&self.model, system, messages, tools)
+        let mut payload = create_codex_request(model_config, system, messages, tools)
             .map_err(|e| ProviderError::ExecutionError(e.to_string()))?;
         payload["stream"] = serde_json::Value::Bool(true);
 
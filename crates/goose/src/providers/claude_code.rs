@@ -271,6 +271,8 @@ impl ClaudeCodeProvider {
 
         let mut cmd = Command::new(&self.command);
         configure_command_no_window(&mut cmd);
+        // A cancelled turn drops the future waiting on the CLI; take the process with it.
+        cmd.kill_on_drop(true);
         cmd.arg("-p")
             .arg(messages_json.to_string())
             .arg("--system-prompt")
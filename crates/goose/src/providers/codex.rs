@@ -175,6 +175,7 @@ impl CodexProvider {
 
         let mut cmd = Command::new(&self.command);
         configure_command_no_window(&mut cmd);
+        cmd.kill_on_drop(true);
 
         // Use 'exec' subcommand for non-interactive mode
         cmd.arg("exec");
@@ -198,6 +198,7 @@ impl CursorAgentProvider {
 
         let mut cmd = Command::new(&self.command);
         configure_command_no_window(&mut cmd);
+        cmd.kill_on_drop(true);
 
         if let Ok(path) = SearchPaths::builder().with_npm().path() {
             cmd.env("PATH", path);
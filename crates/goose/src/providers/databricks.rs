@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::time::Duration;
 
-use super::api_client::{ApiClient, AuthMethod, AuthProvider};
+use super::api_client::{backoff_hint, ApiClient, AuthMethod, AuthProvider};
 use super::base::{ConfigKey, MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::embedding::EmbeddingCapable;
 use super::errors::ProviderError;
@@ -12,8 +12,8 @@ use super::formats::databricks::{create_request, response_to_message};
 use super::oauth;
 use super::retry::ProviderRetry;
 use super::utils::{
-    get_model, handle_response_openai_compat, map_http_error_to_provider_error,
-    stream_openai_compat, ImageFormat, RequestLog,
+    get_model, handle_response_openai_compat, map_http_error_with_backoff, stream_openai_compat,
+    ImageFormat, RequestLog,
 };
 use crate::config::ConfigError;
 use crate::conversation::message::Message;
@@ -287,17 +287,16 @@ impl Provider for DatabricksProvider {
         Ok((message, ProviderUsage::new(response_model, usage)))
     }
 
-    async fn stream(
+    async fn stream_with_model(
         &self,
         session_id: &str,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<MessageStream, ProviderError> {
-        let model_config = self.model.clone();
-
         let mut payload =
-            create_request(&model_config, system, messages, tools, &self.image_format)?;
+            create_request(model_config, system, messages, tools, &self.image_format)?;
         payload
             .as_object_mut()
             .expect("payload should have model key")
@@ -309,7 +308,7 @@ impl Provider for DatabricksProvider {
             .insert("stream".to_string(), Value::Bool(true));
 
         let path = self.get_endpoint_path(&model_config.model_name, false);
-        let mut log = RequestLog::start(&self.model, &payload)?;
+        let mut log = RequestLog::start(model_config, &payload)?;
         let response = self
             .with_retry(|| async {
                 let resp = self
@@ -318,11 +317,16 @@ impl Provider for DatabricksProvider {
                     .await?;
                 if !resp.status().is_success() {
                     let status = resp.status();
+                    let retry_delay = backoff_hint(resp.headers());
                     let error_text = resp.text().await.unwrap_or_default();
 
-                    // Parse as JSON if possible to pass to map_http_error_to_provider_error
+                    // Parse as JSON if possible to pass to map_http_error_with_backoff
                     let json_payload = serde_json::from_str::<Value>(&error_text).ok();
-                    return Err(map_http_error_to_provider_error(status, json_payload));
+                    return Err(map_http_error_with_backoff(
+                        status,
+                        json_payload,
+                        retry_delay,
+                    ));
                 }
                 Ok(resp)
             })
@@ -1,6 +1,22 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use lru::LruCache;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use super::errors::ProviderError;
+use super::ollama::{OllamaProvider, OLLAMA_DEFAULT_MODEL};
+use super::openai::{OpenAiProvider, OPEN_AI_DEFAULT_MODEL};
+use crate::config::Config;
+use crate::model::ModelConfig;
+
+/// Config key naming the service used by [`create_embedding_provider`], `openai` or `ollama`.
+pub const EMBEDDING_PROVIDER_CONFIG_KEY: &str = "GOOSE_EMBEDDING_PROVIDER";
+/// Config key overriding the embedding model of whichever service is used.
+pub const EMBEDDING_MODEL_CONFIG_KEY: &str = "GOOSE_EMBEDDING_MODEL";
+pub const DEFAULT_EMBEDDING_CACHE_ENTRIES: usize = 4096;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingRequest {
@@ -26,3 +42,194 @@ pub trait EmbeddingCapable {
         texts: Vec<String>,
     ) -> Result<Vec<Vec<f32>>>;
 }
+
+/// Turns text into vectors for memory and retrieval features. Unlike [`EmbeddingCapable`],
+/// which is tied to a chat provider, an embedding provider stands on its own, so extensions
+/// can embed with one service whatever model the session chats with.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// The embedding model. Only vectors from the same model can be compared.
+    fn model(&self) -> &str;
+
+    /// The most texts a single request may carry.
+    fn max_batch_size(&self) -> usize {
+        256
+    }
+
+    /// Embeds `texts` in one request, returning one vector per text in the same order.
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError>;
+
+    /// Embeds any number of texts, split into batches the provider accepts.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.max_batch_size().max(1)) {
+            let batch_embeddings = self.embed_batch(batch).await?;
+            if batch_embeddings.len() != batch.len() {
+                return Err(ProviderError::ExecutionError(format!(
+                    "Expected {} embeddings but got {}",
+                    batch.len(),
+                    batch_embeddings.len()
+                )));
+            }
+            embeddings.extend(batch_embeddings);
+        }
+        Ok(embeddings)
+    }
+}
+
+/// Remembers the vectors of texts it has embedded before, so re-indexing mostly unchanged
+/// content only sends the new texts.
+pub struct CachingEmbeddingProvider {
+    inner: Arc<dyn EmbeddingProvider>,
+    entries: Mutex<LruCache<[u8; 32], Vec<f32>>>,
+}
+
+impl CachingEmbeddingProvider {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, max_entries: NonZeroUsize) -> Self {
+        Self {
+            inner,
+            entries: Mutex::new(LruCache::new(max_entries)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn key(&self, text: &str) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.inner.model().as_bytes());
+        hasher.update([0]);
+        hasher.update(text.as_bytes());
+        hasher.finalize().into()
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for CachingEmbeddingProvider {
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.inner.max_batch_size()
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let keys: Vec<_> = texts.iter().map(|text| self.key(text)).collect();
+        let mut embeddings: Vec<Option<Vec<f32>>> = {
+            let mut entries = self.entries.lock().unwrap();
+            keys.iter().map(|key| entries.get(key).cloned()).collect()
+        };
+
+        let missing: Vec<usize> = (0..texts.len())
+            .filter(|&i| embeddings[i].is_none())
+            .collect();
+        if !missing.is_empty() {
+            let missing_texts: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+            let fetched = self.inner.embed(&missing_texts).await?;
+            let mut entries = self.entries.lock().unwrap();
+            for (i, embedding) in missing.into_iter().zip(fetched) {
+                entries.put(keys[i], embedding.clone());
+                embeddings[i] = Some(embedding);
+            }
+        }
+
+        Ok(embeddings.into_iter().flatten().collect())
+    }
+}
+
+/// The model under `GOOSE_EMBEDDING_MODEL`, or `default`.
+pub(crate) fn configured_embedding_model(default: &str) -> String {
+    Config::global()
+        .get_param(EMBEDDING_MODEL_CONFIG_KEY)
+        .unwrap_or_else(|_| default.to_string())
+}
+
+/// The embedding provider named by `GOOSE_EMBEDDING_PROVIDER`, OpenAI when unset, behind a
+/// cache. It uses the same host and credentials as the chat provider of the same name.
+pub async fn create_embedding_provider(config: &Config) -> Result<Arc<dyn EmbeddingProvider>> {
+    let name = config
+        .get_param::<String>(EMBEDDING_PROVIDER_CONFIG_KEY)
+        .unwrap_or_else(|_| "openai".to_string());
+
+    let provider: Arc<dyn EmbeddingProvider> = match name.as_str() {
+        "openai" => {
+            Arc::new(OpenAiProvider::from_env(ModelConfig::new(OPEN_AI_DEFAULT_MODEL)?).await?)
+        }
+        "ollama" => {
+            Arc::new(OllamaProvider::from_env(ModelConfig::new(OLLAMA_DEFAULT_MODEL)?).await?)
+        }
+        other => anyhow::bail!("Unknown embedding provider: {}", other),
+    };
+    Ok(Arc::new(CachingEmbeddingProvider::new(
+        provider,
+        NonZeroUsize::new(DEFAULT_EMBEDDING_CACHE_ENTRIES).unwrap(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct LengthEmbeddings {
+        calls: AtomicUsize,
+        texts: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for LengthEmbeddings {
+        fn model(&self) -> &str {
+            "length"
+        }
+
+        fn max_batch_size(&self) -> usize {
+            2
+        }
+
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+            assert!(texts.len() <= 2);
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.texts.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts.iter().map(|text| vec![text.len() as f32]).collect())
+        }
+    }
+
+    fn texts(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_embed_splits_into_batches() {
+        let provider = LengthEmbeddings {
+            calls: AtomicUsize::new(0),
+            texts: AtomicUsize::new(0),
+        };
+
+        let embeddings = provider.embed(&texts(&["a", "bb", "ccc"])).await.unwrap();
+
+        assert_eq!(embeddings, vec![vec![1.0], vec![2.0], vec![3.0]]);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cache_only_embeds_new_texts() {
+        let inner = Arc::new(LengthEmbeddings {
+            calls: AtomicUsize::new(0),
+            texts: AtomicUsize::new(0),
+        });
+        let provider = CachingEmbeddingProvider::new(inner.clone(), NonZeroUsize::new(8).unwrap());
+
+        provider.embed(&texts(&["a", "bb"])).await.unwrap();
+        let embeddings = provider.embed(&texts(&["bb", "dddd", "a"])).await.unwrap();
+
+        assert_eq!(embeddings, vec![vec![2.0], vec![4.0], vec![1.0]]);
+        assert_eq!(inner.texts.load(Ordering::SeqCst), 3);
+        assert_eq!(provider.len(), 3);
+    }
+}
@@ -33,6 +33,14 @@ pub enum ProviderError {
 }
 
 impl ProviderError {
+    /// How long the provider asked us to wait before retrying, if it said.
+    pub fn retry_delay(&self) -> Option<Duration> {
+        match self {
+            ProviderError::RateLimitExceeded { retry_delay, .. } => *retry_delay,
+            _ => None,
+        }
+    }
+
     pub fn telemetry_type(&self) -> &'static str {
         match self {
             ProviderError::Authentication(_) => "auth",
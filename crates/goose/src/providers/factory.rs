@@ -1,10 +1,12 @@
 use std::sync::{Arc, RwLock};
 
 use super::{
+    a2a::A2aProvider,
     anthropic::AnthropicProvider,
     azure::AzureProvider,
     base::{Provider, ProviderMetadata},
     bedrock::BedrockProvider,
+    caching::CachingProvider,
     chatgpt_codex::ChatGptCodexProvider,
     claude_code::ClaudeCodeProvider,
     codex::CodexProvider,
@@ -16,10 +18,12 @@ use super::{
     google::GoogleProvider,
     lead_worker::LeadWorkerProvider,
     litellm::LiteLLMProvider,
+    middleware::MiddlewareProvider,
     ollama::OllamaProvider,
     openai::OpenAiProvider,
     openrouter::OpenRouterProvider,
     provider_registry::ProviderRegistry,
+    rate_limit::RateLimitedProvider,
     sagemaker_tgi::SageMakerTgiProvider,
     snowflake::SnowflakeProvider,
     tetrate::TetrateProvider,
@@ -43,6 +47,7 @@ static REGISTRY: OnceCell<RwLock<ProviderRegistry>> = OnceCell::const_new();
 
 async fn init_registry() -> RwLock<ProviderRegistry> {
     let mut registry = ProviderRegistry::new().with_providers(|registry| {
+        registry.register::<A2aProvider, _>(|m| Box::pin(A2aProvider::from_env(m)), false);
         registry
             .register::<AnthropicProvider, _>(|m| Box::pin(AnthropicProvider::from_env(m)), true);
         registry.register::<AzureProvider, _>(|m| Box::pin(AzureProvider::from_env(m)), false);
@@ -133,13 +138,17 @@ async fn get_from_registry(name: &str) -> Result<ProviderEntry> {
 pub async fn create(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
     let config = crate::config::Config::global();
 
-    if let Ok(lead_model_name) = config.get_param::<String>("GOOSE_LEAD_MODEL") {
+    let provider = if let Ok(lead_model_name) = config.get_param::<String>("GOOSE_LEAD_MODEL") {
         tracing::info!("Creating lead/worker provider from environment variables");
-        return create_lead_worker_from_env(name, &model, &lead_model_name).await;
-    }
+        create_lead_worker_from_env(name, &model, &lead_model_name).await?
+    } else {
+        let constructor = get_from_registry(name).await?.constructor.clone();
+        constructor(model).await?
+    };
 
-    let constructor = get_from_registry(name).await?.constructor.clone();
-    constructor(model).await
+    let provider = RateLimitedProvider::wrap_from_config(provider, name, config);
+    let provider = CachingProvider::wrap_from_config(provider, config);
+    MiddlewareProvider::wrap_from_config(provider, config)
 }
 
 pub async fn create_with_default_model(name: impl AsRef<str>) -> Result<Arc<dyn Provider>> {
@@ -0,0 +1,381 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::base::{
+    LeadWorkerProviderTrait, MessageStream, Provider, ProviderMetadata, ProviderUsage,
+};
+use super::errors::ProviderError;
+use crate::conversation::message::Message;
+use crate::model::ModelConfig;
+
+/// The kinds of [`ProviderError`] a [`FallbackProvider`] can be told to fall back on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    Authentication,
+    ContextLengthExceeded,
+    RateLimit,
+    Server,
+    Request,
+    Execution,
+    Usage,
+    NotImplemented,
+}
+
+impl ErrorClass {
+    pub fn of(error: &ProviderError) -> Self {
+        match error {
+            ProviderError::Authentication(_) => ErrorClass::Authentication,
+            ProviderError::ContextLengthExceeded(_) => ErrorClass::ContextLengthExceeded,
+            ProviderError::RateLimitExceeded { .. } => ErrorClass::RateLimit,
+            ProviderError::ServerError(_) => ErrorClass::Server,
+            ProviderError::RequestFailed(_) => ErrorClass::Request,
+            ProviderError::ExecutionError(_) => ErrorClass::Execution,
+            ProviderError::UsageError(_) => ErrorClass::Usage,
+            ProviderError::NotImplemented(_) => ErrorClass::NotImplemented,
+        }
+    }
+}
+
+/// Outages and throttling. Context length errors are left alone so the agent can compact.
+pub const DEFAULT_FALLBACK_ON: &[ErrorClass] = &[
+    ErrorClass::RateLimit,
+    ErrorClass::Server,
+    ErrorClass::Request,
+];
+
+/// A provider that tries an ordered list of providers, moving on to the next one when a
+/// request fails with one of the configured error classes. The provider that answered is
+/// recorded in the returned [`ProviderUsage`].
+pub struct FallbackProvider {
+    providers: Vec<Arc<dyn Provider>>,
+    fallback_on: Vec<ErrorClass>,
+}
+
+impl FallbackProvider {
+    /// The first provider is the primary; it supplies the name and model config this
+    /// provider reports.
+    pub fn new(providers: Vec<Arc<dyn Provider>>) -> Result<Self> {
+        if providers.is_empty() {
+            anyhow::bail!("A fallback provider needs at least one provider");
+        }
+        Ok(Self {
+            providers,
+            fallback_on: DEFAULT_FALLBACK_ON.to_vec(),
+        })
+    }
+
+    pub fn with_fallback_on(mut self, fallback_on: Vec<ErrorClass>) -> Self {
+        self.fallback_on = fallback_on;
+        self
+    }
+
+    fn primary(&self) -> &Arc<dyn Provider> {
+        &self.providers[0]
+    }
+
+    /// The requested model config belongs to the primary; the others use their own.
+    fn model_config_for(
+        index: usize,
+        provider: &Arc<dyn Provider>,
+        requested: &ModelConfig,
+    ) -> ModelConfig {
+        if index == 0 {
+            requested.clone()
+        } else {
+            provider
+                .get_model_config()
+                .with_response_schema(requested.response_schema.clone())
+        }
+    }
+
+    fn falls_back(&self, provider: &Arc<dyn Provider>, error: &ProviderError) -> bool {
+        if !self.fallback_on.contains(&ErrorClass::of(error)) {
+            return false;
+        }
+        tracing::warn!(
+            provider = provider.get_name(),
+            error = %error,
+            "Provider failed, falling back to the next one"
+        );
+        true
+    }
+}
+
+#[async_trait]
+impl Provider for FallbackProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "fallback",
+            "Fallback Provider",
+            "A provider that retries failed requests with the next provider in a list",
+            "",
+            vec![],
+            "",
+            vec![],
+        )
+    }
+
+    fn get_name(&self) -> &str {
+        self.primary().get_name()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.primary().get_model_config()
+    }
+
+    async fn complete_with_model(
+        &self,
+        session_id: Option<&str>,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let mut providers = self.providers.iter().enumerate().peekable();
+        while let Some((index, provider)) = providers.next() {
+            let model_config = Self::model_config_for(index, provider, model_config);
+            match provider
+                .complete_with_model(session_id, &model_config, system, messages, tools)
+                .await
+            {
+                Ok((message, usage)) => {
+                    return Ok((message, usage.with_provider(provider.get_name())));
+                }
+                Err(e) if providers.peek().is_some() && self.falls_back(provider, &e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("FallbackProvider always has at least one provider")
+    }
+
+    /// Falls back when a provider fails to start the stream or its first chunk is an error.
+    /// Once a reply has started, later errors are passed on as they are.
+    async fn stream_with_model(
+        &self,
+        session_id: &str,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let mut providers = self.providers.iter().enumerate().peekable();
+        while let Some((index, provider)) = providers.next() {
+            let model_config = Self::model_config_for(index, provider, model_config);
+            let started = match provider
+                .stream_with_model(session_id, &model_config, system, messages, tools)
+                .await
+            {
+                Ok(mut stream) => match stream.next().await {
+                    Some(Err(e)) => Err(e),
+                    first => Ok((first, stream)),
+                },
+                Err(e) => Err(e),
+            };
+            match started {
+                Ok((first, rest)) => {
+                    let name = provider.get_name().to_string();
+                    let stream = futures::stream::iter(first).chain(rest).map(move |chunk| {
+                        chunk.map(|(message, usage)| {
+                            (
+                                message,
+                                usage.map(|usage| usage.with_provider(name.clone())),
+                            )
+                        })
+                    });
+                    return Ok(Box::pin(stream));
+                }
+                Err(e) if providers.peek().is_some() && self.falls_back(provider, &e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("FallbackProvider always has at least one provider")
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.providers
+            .iter()
+            .all(|provider| provider.supports_streaming())
+    }
+
+    fn retry_config(&self) -> super::retry::RetryConfig {
+        self.primary().retry_config()
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.primary().fetch_supported_models().await
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        self.providers
+            .iter()
+            .all(|provider| provider.supports_structured_output())
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.primary().supports_embeddings()
+    }
+
+    async fn supports_cache_control(&self) -> bool {
+        self.primary().supports_cache_control().await
+    }
+
+    async fn create_embeddings(
+        &self,
+        session_id: &str,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.primary().create_embeddings(session_id, texts).await
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.primary().as_lead_worker()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::{stream_from_single_message, Usage};
+    use tokio_util::sync::CancellationToken;
+
+    struct MockProvider {
+        name: &'static str,
+        error: Option<fn() -> ProviderError>,
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            self.name
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail(self.name)
+        }
+
+        async fn complete_with_model(
+            &self,
+            _session_id: Option<&str>,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            match self.error {
+                Some(error) => Err(error()),
+                None => Ok((
+                    Message::assistant().with_text(self.name),
+                    ProviderUsage::new(model_config.model_name.clone(), Usage::default()),
+                )),
+            }
+        }
+
+        // Fails with the first chunk rather than before the stream starts.
+        async fn stream_with_model(
+            &self,
+            _session_id: &str,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<MessageStream, ProviderError> {
+            match self.error {
+                Some(error) => Ok(Box::pin(futures::stream::once(async move { Err(error()) }))),
+                None => Ok(stream_from_single_message(
+                    Message::assistant().with_text(self.name),
+                    ProviderUsage::new(model_config.model_name.clone(), Usage::default()),
+                )),
+            }
+        }
+
+        fn supports_streaming(&self) -> bool {
+            true
+        }
+    }
+
+    fn provider(name: &'static str, error: Option<fn() -> ProviderError>) -> Arc<dyn Provider> {
+        Arc::new(MockProvider { name, error })
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_configured_errors_only() {
+        let fallback = FallbackProvider::new(vec![
+            provider(
+                "primary",
+                Some(|| ProviderError::ServerError("down".into())),
+            ),
+            provider("backup", None),
+        ])
+        .unwrap();
+        let (message, usage) = fallback.complete("session", "", &[], &[]).await.unwrap();
+        assert_eq!(message.as_concat_text(), "backup");
+        assert_eq!(usage.provider.as_deref(), Some("backup"));
+        assert_eq!(usage.model, "backup");
+
+        let fallback = FallbackProvider::new(vec![
+            provider(
+                "primary",
+                Some(|| ProviderError::ContextLengthExceeded("too long".into())),
+            ),
+            provider("backup", None),
+        ])
+        .unwrap();
+        assert!(matches!(
+            fallback.complete("session", "", &[], &[]).await,
+            Err(ProviderError::ContextLengthExceeded(_))
+        ));
+
+        let fallback = FallbackProvider::new(vec![
+            provider(
+                "primary",
+                Some(|| ProviderError::ServerError("down".into())),
+            ),
+            provider(
+                "backup",
+                Some(|| ProviderError::ServerError("also down".into())),
+            ),
+        ])
+        .unwrap();
+        assert_eq!(
+            fallback
+                .complete("session", "", &[], &[])
+                .await
+                .unwrap_err(),
+            ProviderError::ServerError("also down".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_falls_back_on_first_chunk_error() {
+        let fallback = FallbackProvider::new(vec![
+            provider(
+                "primary",
+                Some(|| ProviderError::RateLimitExceeded {
+                    details: "slow down".into(),
+                    retry_delay: None,
+                }),
+            ),
+            provider("backup", None),
+        ])
+        .unwrap();
+        assert!(fallback.supports_streaming());
+
+        let mut stream = fallback
+            .stream("session", "", &[], &[], CancellationToken::new())
+            .await
+            .unwrap();
+        let (message, usage) = stream.next().await.unwrap().unwrap();
+        assert_eq!(message.unwrap().as_concat_text(), "backup");
+        assert_eq!(usage.unwrap().provider.as_deref(), Some("backup"));
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -101,7 +101,7 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::ActionRequired(_action_required) => {
                     // Skip action required messages - they're for UI only
                 }
-                MessageContent::SystemNotification(_) => {
+                MessageContent::SystemNotification(_) | MessageContent::Attachment(_) => {
                     // Skip
                 }
                 MessageContent::Thinking(thinking) => {
@@ -444,6 +444,13 @@ pub fn create_request(
             .insert("temperature".to_string(), json!(temp));
     }
 
+    if let Some(stop) = &model_config.stop_sequences {
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("stop_sequences".to_string(), json!(stop));
+    }
+
     // Add thinking parameters when CLAUDE_THINKING_ENABLED is set
     let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
     if is_thinking_enabled {
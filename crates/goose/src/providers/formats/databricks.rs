@@ -192,6 +192,7 @@ fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<Data
                     content_array.push(json!({"type": "text", "text": text}));
                 }
                 MessageContent::SystemNotification(_)
+                | MessageContent::Attachment(_)
                 | MessageContent::ToolConfirmationRequest(_)
                 | MessageContent::ActionRequired(_) => {}
             }
@@ -649,6 +650,13 @@ pub fn create_request(
         }
     }
 
+    if let Some(stop) = &model_config.stop_sequences {
+        payload
+            .as_object_mut()
+            .unwrap()
+            .insert("stop".to_string(), json!(stop));
+    }
+
     // Apply cache control for Claude models to enable prompt caching
     if is_claude_model(&model_config.model_name) {
         apply_cache_control_for_claude(&mut payload);
@@ -1043,6 +1051,9 @@ mod tests {
             toolshim_model: None,
             fast_model: None,
             request_params: None,
+            reasoning_effort: None,
+            response_schema: None,
+            stop_sequences: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1075,6 +1086,9 @@ mod tests {
             toolshim_model: None,
             fast_model: None,
             request_params: None,
+            reasoning_effort: None,
+            response_schema: None,
+            stop_sequences: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         assert_eq!(request["reasoning_effort"], "high");
@@ -1394,6 +1408,9 @@ mod tests {
             toolshim_model: None,
             fast_model: None,
             request_params: None,
+            reasoning_effort: None,
+            response_schema: None,
+            stop_sequences: None,
         };
 
         let messages = vec![
@@ -1446,6 +1463,9 @@ mod tests {
             toolshim_model: None,
             fast_model: None,
             request_params: None,
+            reasoning_effort: None,
+            response_schema: None,
+            stop_sequences: None,
         };
 
         let messages = vec![Message::user().with_text("Hello")];
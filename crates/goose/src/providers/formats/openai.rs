@@ -39,6 +39,19 @@ struct Delta {
     role: Option<String>,
     tool_calls: Option<Vec<DeltaToolCall>>,
     reasoning_details: Option<Vec<Value>>,
+    /// Reasoning text as streamed by DeepSeek, vLLM and similar servers.
+    reasoning_content: Option<String>,
+    /// Reasoning text as streamed by OpenRouter and Ollama.
+    reasoning: Option<String>,
+}
+
+impl Delta {
+    fn reasoning_text(&self) -> Option<&str> {
+        self.reasoning_content
+            .as_deref()
+            .or(self.reasoning.as_deref())
+            .filter(|text| !text.is_empty())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -97,7 +110,7 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                     // Redacted thinking blocks are not directly used in OpenAI format
                     continue;
                 }
-                MessageContent::SystemNotification(_) => {
+                MessageContent::SystemNotification(_) | MessageContent::Attachment(_) => {
                     continue;
                 }
                 MessageContent::ToolRequest(request) => match &request.tool_call {
@@ -406,10 +419,10 @@ fn extract_usage_with_output_tokens(chunk: &StreamingChunk) -> Option<ProviderUs
         .usage
         .as_ref()
         .and_then(|u| {
-            chunk.model.as_ref().map(|model| ProviderUsage {
-                usage: get_usage(u),
-                model: model.clone(),
-            })
+            chunk
+                .model
+                .as_ref()
+                .map(|model| ProviderUsage::new(model.clone(), get_usage(u)))
         })
         .filter(|u| u.usage.output_tokens.is_some())
 }
@@ -621,12 +634,24 @@ where
                     Some(msg),
                     usage,
                 )
-            } else if chunk.choices[0].delta.content.is_some() {
-                let text = chunk.choices[0].delta.content.as_ref().unwrap();
+            } else if chunk.choices[0].delta.content.is_some()
+                || chunk.choices[0].delta.reasoning_text().is_some()
+            {
+                let delta = &chunk.choices[0].delta;
+                let mut contents = Vec::new();
+                if let Some(reasoning) = delta.reasoning_text() {
+                    contents.push(MessageContent::thinking(reasoning, ""));
+                }
+                match &delta.content {
+                    Some(text) if contents.is_empty() || !text.is_empty() => {
+                        contents.push(MessageContent::text(text));
+                    }
+                    _ => {}
+                }
                 let mut msg = Message::new(
                     Role::Assistant,
                     chrono::Utc::now().timestamp(),
-                    vec![MessageContent::text(text)],
+                    contents,
                 );
 
                 // Add ID if present
@@ -704,6 +729,7 @@ pub fn create_request(
     });
 
     if let Some(effort) = reasoning_effort {
+        let effort = model_config.reasoning_effort.clone().unwrap_or(effort);
         payload["reasoning_effort"] = json!(effort);
     }
 
@@ -711,6 +737,13 @@ pub fn create_request(
         payload["tools"] = json!(tools_spec);
     }
 
+    if let Some(schema) = &model_config.response_schema {
+        payload["response_format"] = json!({
+            "type": "json_schema",
+            "json_schema": { "name": "response", "schema": schema },
+        });
+    }
+
     // o1, o3 models currently don't support temperature
     if !is_ox_model {
         if let Some(temp) = model_config.temperature {
@@ -731,6 +764,10 @@ pub fn create_request(
             .insert(key.to_string(), json!(tokens));
     }
 
+    if let Some(stop) = &model_config.stop_sequences {
+        payload["stop"] = json!(stop);
+    }
+
     if for_streaming {
         payload["stream"] = json!(true);
         payload["stream_options"] = json!({"include_usage": true});
@@ -743,6 +780,7 @@ pub fn create_request(
 mod tests {
     use super::*;
     use crate::conversation::message::Message;
+    use crate::model::ModelOverrides;
     use rmcp::model::CallToolResult;
     use rmcp::object;
     use serde_json::json;
@@ -1392,6 +1430,9 @@ mod tests {
             toolshim_model: None,
             fast_model: None,
             request_params: None,
+            reasoning_effort: None,
+            response_schema: None,
+            stop_sequences: None,
         };
         let request = create_request(
             &model_config,
@@ -1420,6 +1461,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_create_request_with_overrides() -> anyhow::Result<()> {
+        let model_config = ModelConfig::new_or_fail("gpt-4o")
+            .with_temperature(Some(0.7))
+            .with_max_tokens(Some(1024))
+            .with_overrides(&ModelOverrides {
+                temperature: Some(0.0),
+                stop_sequences: Some(vec!["END".to_string()]),
+                ..Default::default()
+            });
+        let request = create_request(
+            &model_config,
+            "system",
+            &[],
+            &[],
+            &ImageFormat::OpenAi,
+            false,
+        )?;
+
+        assert_eq!(request["temperature"], json!(0.0));
+        assert_eq!(request["max_tokens"], json!(1024));
+        assert_eq!(request["stop"], json!(["END"]));
+        Ok(())
+    }
+
     #[test]
     fn test_create_request_o1_default() -> anyhow::Result<()> {
         // Test default medium reasoning effort for O1 model
@@ -1432,6 +1498,9 @@ mod tests {
             toolshim_model: None,
             fast_model: None,
             request_params: None,
+            reasoning_effort: None,
+            response_schema: None,
+            stop_sequences: None,
         };
         let request = create_request(
             &model_config,
@@ -1473,6 +1542,9 @@ mod tests {
             toolshim_model: None,
             fast_model: None,
             request_params: None,
+            reasoning_effort: None,
+            response_schema: None,
+            stop_sequences: None,
         };
         let request = create_request(
             &model_config,
@@ -1502,11 +1574,42 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_create_request_reasoning_effort_override() -> anyhow::Result<()> {
+        let model_config =
+            ModelConfig::new_or_fail("o3-mini-high").with_reasoning_effort(Some("low".to_string()));
+        let request = create_request(
+            &model_config,
+            "system",
+            &[],
+            &[],
+            &ImageFormat::OpenAi,
+            false,
+        )?;
+        assert_eq!(request["model"], "o3-mini");
+        assert_eq!(request["reasoning_effort"], "low");
+
+        let model_config =
+            ModelConfig::new_or_fail("gpt-4o").with_reasoning_effort(Some("low".to_string()));
+        let request = create_request(
+            &model_config,
+            "system",
+            &[],
+            &[],
+            &ImageFormat::OpenAi,
+            false,
+        )?;
+        assert!(request.get("reasoning_effort").is_none());
+
+        Ok(())
+    }
+
     struct StreamingUsageTestResult {
         usage_count: usize,
         usage: Option<ProviderUsage>,
         tool_calls: Vec<String>,
         has_text_content: bool,
+        thinking: String,
     }
 
     async fn run_streaming_test(response_lines: &str) -> anyhow::Result<StreamingUsageTestResult> {
@@ -1520,6 +1623,7 @@ mod tests {
             usage: None,
             tool_calls: Vec::new(),
             has_text_content: false,
+            thinking: String::new(),
         };
 
         while let Some(Ok((message, usage))) = messages.next().await {
@@ -1538,6 +1642,9 @@ mod tests {
                         MessageContent::Text(text) if !text.text.is_empty() => {
                             result.has_text_content = true;
                         }
+                        MessageContent::Thinking(thinking) => {
+                            result.thinking.push_str(&thinking.thinking);
+                        }
                         _ => {}
                     }
                 }
@@ -1627,6 +1734,25 @@ data: [DONE]
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_streamed_reasoning_content_becomes_thinking() -> anyhow::Result<()> {
+        let response_lines = r#"
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","created":1768896871,"model":"deepseek-reasoner","choices":[{"index":0,"delta":{"role":"assistant","content":null,"reasoning_content":"The user wants"},"finish_reason":null}]}
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","created":1768896871,"model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":null,"reasoning_content":" a count."},"finish_reason":null}]}
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","created":1768896871,"model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":"There are 47 files.","reasoning_content":null},"finish_reason":null}]}
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","created":1768896871,"model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":""},"finish_reason":"stop"}],"usage":{"prompt_tokens":120,"completion_tokens":30,"total_tokens":150}}
+data: [DONE]
+"#;
+
+        let result = run_streaming_test(response_lines).await?;
+
+        assert_eq!(result.thinking, "The user wants a count.");
+        assert!(result.has_text_content, "Expected text content in response");
+        assert_usage_yielded_once(&result, 120, 30, 150);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_openai_gpt5_streaming_usage_yielded_once() -> anyhow::Result<()> {
         let response_lines = r#"
@@ -402,6 +402,15 @@ pub fn create_responses_request(
             .insert("tools".to_string(), json!(tools_spec));
     }
 
+    if let Some(schema) = &model_config.response_schema {
+        payload.as_object_mut().unwrap().insert(
+            "text".to_string(),
+            json!({
+                "format": { "type": "json_schema", "name": "response", "schema": schema },
+            }),
+        );
+    }
+
     if let Some(temp) = model_config.temperature {
         payload
             .as_object_mut()
@@ -653,10 +662,7 @@ where
                             Some(u.total_tokens),
                         ),
                     );
-                    final_usage = Some(ProviderUsage {
-                        usage,
-                        model: model.clone(),
-                    });
+                    final_usage = Some(ProviderUsage::new(model.clone(), usage));
 
                     // For complete output, use the response output items
                     if !response.output.is_empty() {
@@ -639,15 +639,15 @@ impl Provider for GcpVertexAIProvider {
         true
     }
 
-    async fn stream(
+    async fn stream_with_model(
         &self,
         session_id: &str,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<MessageStream, ProviderError> {
-        let model_config = self.get_model_config();
-        let (mut request, context) = create_request(&model_config, system, messages, tools)?;
+        let (mut request, context) = create_request(model_config, system, messages, tools)?;
 
         if matches!(context.provider(), ModelProvider::Anthropic) {
             if let Some(obj) = request.as_object_mut() {
@@ -655,7 +655,7 @@ impl Provider for GcpVertexAIProvider {
             }
         }
 
-        let mut log = RequestLog::start(&model_config, &request)?;
+        let mut log = RequestLog::start(model_config, &request)?;
 
         let response = self
             .post_stream(Some(session_id), &request, &context)
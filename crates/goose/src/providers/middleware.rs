@@ -0,0 +1,402 @@
+use anyhow::Result;
+use async_stream::try_stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use rmcp::model::Tool;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use super::base::{
+    LeadWorkerProviderTrait, MessageStream, Provider, ProviderMetadata, ProviderUsage,
+};
+use super::errors::ProviderError;
+use super::retry::RetryConfig;
+use crate::config::Config;
+use crate::conversation::message::Message;
+use crate::model::ModelConfig;
+use crate::security::redaction::Redactor;
+
+/// A provider call as the middleware sees it.
+#[derive(Debug, Clone)]
+pub struct ProviderRequest {
+    pub provider: String,
+    pub session_id: Option<String>,
+    pub model_config: ModelConfig,
+    pub system: String,
+    pub messages: Vec<Message>,
+    pub tools: Vec<Tool>,
+}
+
+/// Hooks around every call a provider makes. Each hook does nothing by default, so
+/// implementations only override the ones they need.
+pub trait ProviderMiddleware: Send + Sync {
+    /// Runs before the request is sent and may rewrite it. An error stops the request, and
+    /// is returned to the caller without reaching the provider.
+    fn on_request(&self, _request: &mut ProviderRequest) -> Result<(), ProviderError> {
+        Ok(())
+    }
+
+    /// Runs on each piece of a streamed reply before it is passed on.
+    fn on_chunk(
+        &self,
+        _request: &ProviderRequest,
+        _message: &mut Message,
+        _usage: Option<&ProviderUsage>,
+    ) {
+    }
+
+    /// Runs on a complete, non-streamed reply before it is returned.
+    fn on_response(
+        &self,
+        _request: &ProviderRequest,
+        _message: &mut Message,
+        _usage: &ProviderUsage,
+    ) {
+    }
+
+    /// Runs when the provider fails, whether before or during a stream.
+    fn on_error(&self, _request: &ProviderRequest, _error: &ProviderError) {}
+}
+
+static REGISTERED: LazyLock<RwLock<Vec<Arc<dyn ProviderMiddleware>>>> =
+    LazyLock::new(|| RwLock::new(Vec::new()));
+
+/// Adds middleware to every provider created by [`crate::providers::create`] from now on.
+pub fn register_middleware(middleware: Arc<dyn ProviderMiddleware>) {
+    REGISTERED.write().unwrap().push(middleware);
+}
+
+/// A provider that runs a chain of [`ProviderMiddleware`] around another provider. Hooks
+/// run in the order the middleware was added.
+pub struct MiddlewareProvider {
+    inner: Arc<dyn Provider>,
+    middleware: Arc<[Arc<dyn ProviderMiddleware>]>,
+}
+
+impl MiddlewareProvider {
+    pub fn new(inner: Arc<dyn Provider>, middleware: Vec<Arc<dyn ProviderMiddleware>>) -> Self {
+        Self {
+            inner,
+            middleware: middleware.into(),
+        }
+    }
+
+    /// Wraps `provider` with the configured [`Redactor`], if any, followed by the middleware
+    /// added through [`register_middleware`]. Returns it unchanged when there is none, and an
+    /// error when redaction is configured but can't be set up.
+    pub fn wrap_from_config(
+        provider: Arc<dyn Provider>,
+        config: &Config,
+    ) -> Result<Arc<dyn Provider>> {
+        let mut middleware: Vec<Arc<dyn ProviderMiddleware>> = Vec::new();
+        if let Some(redactor) = Redactor::from_config(config)? {
+            middleware.push(Arc::new(redactor));
+        }
+        middleware.extend(REGISTERED.read().unwrap().iter().cloned());
+        if middleware.is_empty() {
+            return Ok(provider);
+        }
+        Ok(Arc::new(Self::new(provider, middleware)))
+    }
+
+    fn start(
+        &self,
+        session_id: Option<&str>,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<ProviderRequest, ProviderError> {
+        let mut request = ProviderRequest {
+            provider: self.inner.get_name().to_string(),
+            session_id: session_id.map(str::to_string),
+            model_config: model_config.clone(),
+            system: system.to_string(),
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+        };
+        for middleware in self.middleware.iter() {
+            middleware.on_request(&mut request)?;
+        }
+        Ok(request)
+    }
+
+    fn fail(&self, request: &ProviderRequest, error: ProviderError) -> ProviderError {
+        for middleware in self.middleware.iter() {
+            middleware.on_error(request, &error);
+        }
+        error
+    }
+}
+
+#[async_trait]
+impl Provider for MiddlewareProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "middleware",
+            "Middleware Provider",
+            "A provider that runs request and response hooks around another provider",
+            "",
+            vec![],
+            "",
+            vec![],
+        )
+    }
+
+    fn get_name(&self) -> &str {
+        self.inner.get_name()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete_with_model(
+        &self,
+        session_id: Option<&str>,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let request = self.start(session_id, model_config, system, messages, tools)?;
+        let (mut message, usage) = self
+            .inner
+            .complete_with_model(
+                request.session_id.as_deref(),
+                &request.model_config,
+                &request.system,
+                &request.messages,
+                &request.tools,
+            )
+            .await
+            .map_err(|e| self.fail(&request, e))?;
+        for middleware in self.middleware.iter() {
+            middleware.on_response(&request, &mut message, &usage);
+        }
+        Ok((message, usage))
+    }
+
+    async fn stream_with_model(
+        &self,
+        session_id: &str,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let request = self.start(Some(session_id), model_config, system, messages, tools)?;
+        let mut stream = self
+            .inner
+            .stream_with_model(
+                session_id,
+                &request.model_config,
+                &request.system,
+                &request.messages,
+                &request.tools,
+            )
+            .await
+            .map_err(|e| self.fail(&request, e))?;
+
+        let middleware = self.middleware.clone();
+        Ok(Box::pin(try_stream! {
+            while let Some(result) = stream.next().await {
+                let (mut message, usage) = match result {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        for middleware in middleware.iter() {
+                            middleware.on_error(&request, &e);
+                        }
+                        Err(e)?
+                    }
+                };
+                if let Some(message) = message.as_mut() {
+                    for middleware in middleware.iter() {
+                        middleware.on_chunk(&request, message, usage.as_ref());
+                    }
+                }
+                yield (message, usage);
+            }
+        }))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn retry_config(&self) -> RetryConfig {
+        self.inner.retry_config()
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.inner.fetch_supported_models().await
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        self.inner.supports_structured_output()
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.inner.supports_embeddings()
+    }
+
+    async fn supports_cache_control(&self) -> bool {
+        self.inner.supports_cache_control().await
+    }
+
+    async fn create_embeddings(
+        &self,
+        session_id: &str,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.inner.create_embeddings(session_id, texts).await
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.inner.as_lead_worker()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::message::MessageContent;
+    use crate::providers::base::{stream_from_single_message, Usage};
+    use std::sync::Mutex;
+    use tokio_util::sync::CancellationToken;
+
+    struct EchoProvider {
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Provider for EchoProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "echo"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail("echo-model")
+        }
+
+        async fn complete_with_model(
+            &self,
+            _session_id: Option<&str>,
+            model_config: &ModelConfig,
+            system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            if self.fail {
+                return Err(ProviderError::ServerError("down".into()));
+            }
+            Ok((
+                Message::assistant().with_text(system),
+                ProviderUsage::new(model_config.model_name.clone(), Usage::default()),
+            ))
+        }
+
+        async fn stream_with_model(
+            &self,
+            session_id: &str,
+            model_config: &ModelConfig,
+            system: &str,
+            messages: &[Message],
+            tools: &[Tool],
+        ) -> Result<MessageStream, ProviderError> {
+            let (message, usage) = self
+                .complete_with_model(Some(session_id), model_config, system, messages, tools)
+                .await?;
+            Ok(stream_from_single_message(message, usage))
+        }
+
+        fn supports_streaming(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl ProviderMiddleware for Recorder {
+        fn on_request(&self, request: &mut ProviderRequest) -> Result<(), ProviderError> {
+            request.system = request.system.replace("secret", "[redacted]");
+            self.events.lock().unwrap().push("request".to_string());
+            Ok(())
+        }
+
+        fn on_chunk(
+            &self,
+            _request: &ProviderRequest,
+            message: &mut Message,
+            _usage: Option<&ProviderUsage>,
+        ) {
+            self.events.lock().unwrap().push("chunk".to_string());
+            message.content.push(MessageContent::text("!"));
+        }
+
+        fn on_response(
+            &self,
+            _request: &ProviderRequest,
+            _message: &mut Message,
+            usage: &ProviderUsage,
+        ) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("response from {}", usage.model));
+        }
+
+        fn on_error(&self, request: &ProviderRequest, error: &ProviderError) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{} failed: {}", request.provider, error));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_around_calls() {
+        let recorder = Arc::new(Recorder::default());
+        let provider = MiddlewareProvider::new(
+            Arc::new(EchoProvider { fail: false }),
+            vec![recorder.clone()],
+        );
+
+        let (message, _) = provider
+            .complete("session", "the secret is 42", &[], &[])
+            .await
+            .unwrap();
+        assert_eq!(message.as_concat_text(), "the [redacted] is 42");
+
+        let mut stream = provider
+            .stream("session", "hello", &[], &[], CancellationToken::new())
+            .await
+            .unwrap();
+        let (message, _) = stream.next().await.unwrap().unwrap();
+        assert_eq!(message.unwrap().as_concat_text(), "hello\n!");
+
+        let failing = MiddlewareProvider::new(
+            Arc::new(EchoProvider { fail: true }),
+            vec![recorder.clone()],
+        );
+        assert!(failing.complete("session", "", &[], &[]).await.is_err());
+
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            [
+                "request",
+                "response from echo-model",
+                "request",
+                "chunk",
+                "request",
+                "echo failed: Server error: down",
+            ]
+        );
+    }
+}
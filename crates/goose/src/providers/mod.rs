@@ -1,3 +1,4 @@
+pub mod a2a;
 pub mod anthropic;
 pub mod api_client;
 pub mod auto_detect;
@@ -5,6 +6,7 @@ pub mod azure;
 pub mod azureauth;
 pub mod base;
 pub mod bedrock;
+pub mod caching;
 pub mod canonical;
 pub mod chatgpt_codex;
 pub mod claude_code;
@@ -14,6 +16,7 @@ pub mod databricks;
 pub mod embedding;
 pub mod errors;
 mod factory;
+pub mod fallback;
 pub mod formats;
 mod gcpauth;
 pub mod gcpvertexai;
@@ -22,15 +25,19 @@ pub mod githubcopilot;
 pub mod google;
 pub mod lead_worker;
 pub mod litellm;
+pub mod middleware;
+pub mod multimodal;
 pub mod oauth;
 pub mod ollama;
 pub mod openai;
 pub mod openrouter;
 pub mod provider_registry;
 pub mod provider_test;
+pub mod rate_limit;
 mod retry;
 pub mod sagemaker_tgi;
 pub mod snowflake;
+mod structured;
 pub mod testprovider;
 pub mod tetrate;
 pub mod toolshim;
@@ -42,4 +49,4 @@ pub mod xai;
 pub use factory::{
     create, create_with_default_model, create_with_named_model, providers, refresh_custom_providers,
 };
-pub use retry::{retry_operation, RetryConfig};
+pub use retry::{retry_operation, with_retry_scope, RetryConfig, RetryNotice, RetryScope};
@@ -0,0 +1,255 @@
+use base64::Engine;
+use rmcp::model::{Annotated, RawContent, ResourceContents};
+
+use super::canonical::maybe_get_canonical_model;
+use crate::conversation::message::{AttachmentContent, Message, MessageContent};
+use crate::session::attachments::is_text_mime_type;
+
+/// The non-text input a model accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Modalities {
+    pub image: bool,
+}
+
+impl Modalities {
+    /// Looks the model up in the canonical registry. Models it doesn't know are assumed to
+    /// take images, which is how they were treated before this check existed.
+    pub fn for_model(provider: &str, model: &str) -> Self {
+        match maybe_get_canonical_model(provider, model) {
+            Some(canonical) => Self {
+                image: canonical.input_modalities.iter().any(|m| m == "image"),
+            },
+            None => Self { image: true },
+        }
+    }
+}
+
+fn image_placeholder(mime_type: &str) -> String {
+    format!(
+        "[An image ({}) was attached here, but this model can't read images.]",
+        mime_type
+    )
+}
+
+/// Rewrites content the model can't take into text placeholders, so every provider degrades
+/// the same way instead of failing the request or silently dropping it. Images are kept when
+/// the model reads them. Audio and binary resources always become placeholders, since no
+/// provider format carries them. Attachments are read from disk and become text, an image or
+/// a placeholder by the same rules.
+pub fn normalize_messages(messages: &[Message], modalities: Modalities) -> Vec<Message> {
+    messages
+        .iter()
+        .map(|message| {
+            let mut message = message.clone();
+            for content in &mut message.content {
+                match content {
+                    MessageContent::Image(image) if !modalities.image => {
+                        *content = MessageContent::text(image_placeholder(&image.mime_type));
+                    }
+                    MessageContent::Attachment(attachment) => {
+                        *content = resolve_attachment(attachment, modalities);
+                    }
+                    MessageContent::ToolResponse(response) => {
+                        if let Ok(result) = &mut response.tool_result {
+                            for item in &mut result.content {
+                                if let Some(text) = tool_content_placeholder(&item.raw, modalities)
+                                {
+                                    *item = Annotated {
+                                        raw: RawContent::text(text),
+                                        annotations: item.annotations.clone(),
+                                    };
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            message
+        })
+        .collect()
+}
+
+fn resolve_attachment(attachment: &AttachmentContent, modalities: Modalities) -> MessageContent {
+    let is_image = attachment.mime_type.starts_with("image/");
+    if is_image && !modalities.image {
+        return MessageContent::text(image_placeholder(&attachment.mime_type));
+    }
+    if !is_image && !is_text_mime_type(&attachment.mime_type) {
+        return MessageContent::text(format!(
+            "[The file {} ({}) was attached here, but files of this type can't be sent to this model.]",
+            attachment.name, attachment.mime_type
+        ));
+    }
+
+    let data = match std::fs::read(&attachment.path) {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::warn!(path = %attachment.path.display(), error = %e, "Failed to read attachment");
+            return MessageContent::text(format!(
+                "[The file {} was attached here, but it is no longer available.]",
+                attachment.name
+            ));
+        }
+    };
+    if is_image {
+        MessageContent::image(
+            base64::engine::general_purpose::STANDARD.encode(data),
+            &attachment.mime_type,
+        )
+    } else {
+        MessageContent::text(format!(
+            "--- Attachment: {} ({}) ---\n{}\n--- End of {} ---",
+            attachment.name,
+            attachment.mime_type,
+            String::from_utf8_lossy(&data),
+            attachment.name
+        ))
+    }
+}
+
+fn tool_content_placeholder(content: &RawContent, modalities: Modalities) -> Option<String> {
+    match content {
+        RawContent::Image(image) if !modalities.image => Some(image_placeholder(&image.mime_type)),
+        RawContent::Audio(audio) => Some(format!(
+            "[An audio clip ({}) was returned here, but audio can't be sent to this model.]",
+            audio.mime_type
+        )),
+        RawContent::Resource(resource) => match &resource.resource {
+            ResourceContents::BlobResourceContents { uri, mime_type, .. } => Some(format!(
+                "[The file {} ({}) was returned here, but files can't be sent to this model.]",
+                uri,
+                mime_type.as_deref().unwrap_or("unknown type")
+            )),
+            ResourceContents::TextResourceContents { .. } => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{
+        AnnotateAble, CallToolResult, Content, RawAudioContent, RawEmbeddedResource,
+    };
+    use std::path::PathBuf;
+
+    fn tool_output() -> Vec<Content> {
+        vec![
+            Content::text("done"),
+            Content::image("aW1n", "image/png"),
+            RawContent::Audio(RawAudioContent {
+                data: "YXVkaW8=".to_string(),
+                mime_type: "audio/wav".to_string(),
+            })
+            .no_annotation(),
+            RawContent::Resource(RawEmbeddedResource {
+                meta: None,
+                resource: ResourceContents::BlobResourceContents {
+                    uri: "file:///report.pdf".to_string(),
+                    mime_type: Some("application/pdf".to_string()),
+                    blob: "cGRm".to_string(),
+                    meta: None,
+                },
+            })
+            .no_annotation(),
+        ]
+    }
+
+    fn messages() -> Vec<Message> {
+        vec![
+            Message::user()
+                .with_text("look at this")
+                .with_image("aW1n", "image/jpeg"),
+            Message::user().with_tool_response(
+                "call_1",
+                Ok(CallToolResult {
+                    content: tool_output(),
+                    structured_content: None,
+                    is_error: Some(false),
+                    meta: None,
+                }),
+            ),
+        ]
+    }
+
+    fn tool_kinds(message: &Message) -> Vec<&'static str> {
+        let MessageContent::ToolResponse(response) = &message.content[0] else {
+            panic!("expected a tool response");
+        };
+        response
+            .tool_result
+            .as_ref()
+            .unwrap()
+            .content
+            .iter()
+            .map(|item| match &item.raw {
+                RawContent::Text(_) => "text",
+                RawContent::Image(_) => "image",
+                _ => "other",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_text_only_model_gets_placeholders() {
+        let normalized = normalize_messages(&messages(), Modalities { image: false });
+
+        assert!(matches!(normalized[0].content[0], MessageContent::Text(_)));
+        assert!(normalized[0].content[1]
+            .as_text()
+            .unwrap()
+            .contains("image/jpeg"));
+        assert_eq!(tool_kinds(&normalized[1]), ["text", "text", "text", "text"]);
+    }
+
+    #[test]
+    fn test_attachments_become_text_or_placeholders() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let csv = dir.path().join("sales.csv");
+        std::fs::write(&csv, "region,total\nnorth,3").unwrap();
+        let attachment = |name: &str, mime_type: &str, path: PathBuf| AttachmentContent {
+            name: name.to_string(),
+            mime_type: mime_type.to_string(),
+            size: 0,
+            path,
+        };
+        let message = Message::user()
+            .with_attachment(attachment("sales.csv", "text/csv", csv))
+            .with_attachment(attachment(
+                "report.pdf",
+                "application/pdf",
+                dir.path().join("report.pdf"),
+            ))
+            .with_attachment(attachment(
+                "gone.txt",
+                "text/plain",
+                dir.path().join("gone"),
+            ));
+
+        let normalized = normalize_messages(&[message], Modalities { image: true });
+        let texts: Vec<&str> = normalized[0]
+            .content
+            .iter()
+            .map(|content| content.as_text().unwrap())
+            .collect();
+        assert_eq!(
+            texts[0],
+            "--- Attachment: sales.csv (text/csv) ---\nregion,total\nnorth,3\n--- End of sales.csv ---"
+        );
+        assert!(texts[1].contains("can't be sent"));
+        assert!(texts[2].contains("no longer available"));
+    }
+
+    #[test]
+    fn test_vision_model_keeps_images() {
+        let normalized = normalize_messages(&messages(), Modalities { image: true });
+
+        assert!(matches!(normalized[0].content[1], MessageContent::Image(_)));
+        assert_eq!(
+            tool_kinds(&normalized[1]),
+            ["text", "image", "text", "text"]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use super::api_client::{ApiClient, AuthMethod};
 use super::base::{ConfigKey, MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::embedding::{configured_embedding_model, EmbeddingProvider};
 use super::errors::ProviderError;
 use super::retry::ProviderRetry;
 use super::utils::{
@@ -18,7 +19,7 @@ use anyhow::Result;
 use async_trait::async_trait;
 use regex::Regex;
 use rmcp::model::Tool;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::time::Duration;
 use url::Url;
 
@@ -26,6 +27,7 @@ pub const OLLAMA_HOST: &str = "localhost";
 pub const OLLAMA_TIMEOUT: u64 = 600;
 pub const OLLAMA_DEFAULT_PORT: u16 = 11434;
 pub const OLLAMA_DEFAULT_MODEL: &str = "qwen3";
+pub const OLLAMA_DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
 pub const OLLAMA_KNOWN_MODELS: &[&str] = &[
     OLLAMA_DEFAULT_MODEL,
     "qwen3-coder:30b",
@@ -40,6 +42,7 @@ pub struct OllamaProvider {
     model: ModelConfig,
     supports_streaming: bool,
     name: String,
+    embedding_model: String,
 }
 
 impl OllamaProvider {
@@ -79,6 +82,7 @@ impl OllamaProvider {
             model,
             supports_streaming: true,
             name: Self::metadata().name,
+            embedding_model: configured_embedding_model(OLLAMA_DEFAULT_EMBEDDING_MODEL),
         })
     }
 
@@ -126,6 +130,7 @@ impl OllamaProvider {
             model,
             supports_streaming: config.supports_streaming.unwrap_or(true),
             name: config.name.clone(),
+            embedding_model: configured_embedding_model(OLLAMA_DEFAULT_EMBEDDING_MODEL),
         })
     }
 
@@ -140,6 +145,41 @@ impl OllamaProvider {
             .await?;
         handle_response_openai_compat(response).await
     }
+
+    async fn post_embeddings(
+        &self,
+        session_id: Option<&str>,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, ProviderError> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+        let payload = json!({"model": self.embedding_model, "input": texts});
+        let response = self
+            .with_retry(|| async {
+                let response = self
+                    .api_client
+                    .response_post(session_id, "api/embed", &payload)
+                    .await?;
+                handle_response_openai_compat(response).await
+            })
+            .await?;
+        let embeddings = response.get("embeddings").cloned().ok_or_else(|| {
+            ProviderError::ExecutionError("No embeddings in response".to_string())
+        })?;
+        serde_json::from_value(embeddings).map_err(|e| ProviderError::ExecutionError(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaProvider {
+    fn model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.post_embeddings(None, texts).await
+    }
 }
 
 #[async_trait]
@@ -245,13 +285,26 @@ impl Provider for OllamaProvider {
         Ok(safe_truncate(&description, 100))
     }
 
+    fn supports_embeddings(&self) -> bool {
+        true
+    }
+
+    async fn create_embeddings(
+        &self,
+        session_id: &str,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.post_embeddings(Some(session_id), &texts).await
+    }
+
     fn supports_streaming(&self) -> bool {
         self.supports_streaming
     }
 
-    async fn stream(
+    async fn stream_with_model(
         &self,
         session_id: &str,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
@@ -265,14 +318,14 @@ impl Provider for OllamaProvider {
         };
 
         let payload = create_request(
-            &self.model,
+            model_config,
             system,
             messages,
             filtered_tools,
             &super::utils::ImageFormat::OpenAi,
             true,
         )?;
-        let mut log = RequestLog::start(&self.model, &payload)?;
+        let mut log = RequestLog::start(model_config, &payload)?;
 
         let response = self
             .with_retry(|| async {
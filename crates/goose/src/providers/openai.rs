@@ -1,6 +1,9 @@
 use super::api_client::{ApiClient, AuthMethod};
 use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
-use super::embedding::{EmbeddingCapable, EmbeddingRequest, EmbeddingResponse};
+use super::embedding::{
+    configured_embedding_model, EmbeddingCapable, EmbeddingProvider, EmbeddingRequest,
+    EmbeddingResponse,
+};
 use super::errors::ProviderError;
 use super::formats::openai::{create_request, get_usage, response_to_message};
 use super::formats::openai_responses::{
@@ -33,6 +36,7 @@ use rmcp::model::Tool;
 
 pub const OPEN_AI_DEFAULT_MODEL: &str = "gpt-4o";
 pub const OPEN_AI_DEFAULT_FAST_MODEL: &str = "gpt-4o-mini";
+pub const OPEN_AI_DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
 pub const OPEN_AI_KNOWN_MODELS: &[(&str, usize)] = &[
     ("gpt-4o", 128_000),
     ("gpt-4o-mini", 128_000),
@@ -60,6 +64,7 @@ pub struct OpenAiProvider {
     custom_headers: Option<HashMap<String, String>>,
     supports_streaming: bool,
     name: String,
+    embedding_model: String,
 }
 
 impl OpenAiProvider {
@@ -118,6 +123,7 @@ impl OpenAiProvider {
             custom_headers,
             supports_streaming: true,
             name: Self::metadata().name,
+            embedding_model: configured_embedding_model(OPEN_AI_DEFAULT_EMBEDDING_MODEL),
         })
     }
 
@@ -132,6 +138,7 @@ impl OpenAiProvider {
             custom_headers: None,
             supports_streaming: true,
             name: Self::metadata().name,
+            embedding_model: OPEN_AI_DEFAULT_EMBEDDING_MODEL.to_string(),
         }
     }
 
@@ -196,6 +203,7 @@ impl OpenAiProvider {
             custom_headers: config.headers,
             supports_streaming: config.supports_streaming.unwrap_or(true),
             name: config.name.clone(),
+            embedding_model: configured_embedding_model(OPEN_AI_DEFAULT_EMBEDDING_MODEL),
         })
     }
 
@@ -338,6 +346,10 @@ impl Provider for OpenAiProvider {
         }
     }
 
+    fn supports_structured_output(&self) -> bool {
+        true
+    }
+
     async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
         let models_path = self.base_path.replace("v1/chat/completions", "v1/models");
         let response = self
@@ -383,18 +395,19 @@ impl Provider for OpenAiProvider {
         self.supports_streaming
     }
 
-    async fn stream(
+    async fn stream_with_model(
         &self,
         session_id: &str,
+        model_config: &ModelConfig,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<MessageStream, ProviderError> {
-        if Self::uses_responses_api(&self.model.model_name) {
-            let mut payload = create_responses_request(&self.model, system, messages, tools)?;
+        if Self::uses_responses_api(&model_config.model_name) {
+            let mut payload = create_responses_request(model_config, system, messages, tools)?;
             payload["stream"] = serde_json::Value::Bool(true);
 
-            let mut log = RequestLog::start(&self.model, &payload)?;
+            let mut log = RequestLog::start(model_config, &payload)?;
 
             let response = self
                 .with_retry(|| async {
@@ -426,14 +439,14 @@ impl Provider for OpenAiProvider {
             }))
         } else {
             let payload = create_request(
-                &self.model,
+                model_config,
                 system,
                 messages,
                 tools,
                 &ImageFormat::OpenAi,
                 true,
             )?;
-            let mut log = RequestLog::start(&self.model, &payload)?;
+            let mut log = RequestLog::start(model_config, &payload)?;
 
             let response = self
                 .with_retry(|| async {
@@ -464,35 +477,27 @@ fn parse_custom_headers(s: String) -> HashMap<String, String> {
         .collect()
 }
 
-#[async_trait]
-impl EmbeddingCapable for OpenAiProvider {
-    async fn create_embeddings(
+impl OpenAiProvider {
+    async fn post_embeddings(
         &self,
-        session_id: &str,
-        texts: Vec<String>,
-    ) -> Result<Vec<Vec<f32>>> {
+        session_id: Option<&str>,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, ProviderError> {
         if texts.is_empty() {
             return Ok(vec![]);
         }
 
-        let embedding_model = std::env::var("GOOSE_EMBEDDING_MODEL")
-            .unwrap_or_else(|_| "text-embedding-3-small".to_string());
-
         let request = EmbeddingRequest {
-            input: texts,
-            model: embedding_model,
+            input: texts.to_vec(),
+            model: self.embedding_model.clone(),
         };
+        let request_value = serde_json::to_value(&request)
+            .map_err(|e| ProviderError::ExecutionError(e.to_string()))?;
 
         let response = self
             .with_retry(|| async {
-                let request_clone = EmbeddingRequest {
-                    input: request.input.clone(),
-                    model: request.model.clone(),
-                };
-                let request_value = serde_json::to_value(request_clone)
-                    .map_err(|e| ProviderError::ExecutionError(e.to_string()))?;
                 self.api_client
-                    .api_post(Some(session_id), "v1/embeddings", &request_value)
+                    .api_post(session_id, "v1/embeddings", &request_value)
                     .await
                     .map_err(|e| ProviderError::ExecutionError(e.to_string()))
             })
@@ -504,14 +509,18 @@ impl EmbeddingCapable for OpenAiProvider {
                 .as_ref()
                 .and_then(|p| p.as_str())
                 .unwrap_or("Unknown error");
-            return Err(anyhow::anyhow!("Embedding API error: {}", error_text));
+            return Err(ProviderError::ExecutionError(format!(
+                "Embedding API error: {}",
+                error_text
+            )));
         }
 
         let embedding_response: EmbeddingResponse = serde_json::from_value(
             response
                 .payload
-                .ok_or_else(|| anyhow::anyhow!("Empty response body"))?,
-        )?;
+                .ok_or_else(|| ProviderError::ExecutionError("Empty response body".to_string()))?,
+        )
+        .map_err(|e| ProviderError::ExecutionError(e.to_string()))?;
 
         Ok(embedding_response
             .data
@@ -520,3 +529,29 @@ impl EmbeddingCapable for OpenAiProvider {
             .collect())
     }
 }
+
+#[async_trait]
+impl EmbeddingCapable for OpenAiProvider {
+    async fn create_embeddings(
+        &self,
+        session_id: &str,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>> {
+        Ok(self.post_embeddings(Some(session_id), &texts).await?)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiProvider {
+    fn model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    fn max_batch_size(&self) -> usize {
+        2048
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.post_embeddings(None, texts).await
+    }
+}
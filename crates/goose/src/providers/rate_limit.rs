@@ -0,0 +1,560 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use super::base::{
+    LeadWorkerProviderTrait, MessageStream, Provider, ProviderMetadata, ProviderUsage,
+};
+use super::errors::ProviderError;
+use super::retry::RetryConfig;
+use crate::config::{Config, ConfigError};
+use crate::conversation::message::Message;
+use crate::model::ModelConfig;
+use crate::token_counter::create_token_counter_for_model;
+
+/// Config key holding [`RateLimits`] keyed by provider name.
+pub const RATE_LIMITS_CONFIG_KEY: &str = "GOOSE_PROVIDER_RATE_LIMITS";
+
+const WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_MAX_QUEUE_SECS: u64 = 120;
+
+/// What to do with a request that would go over a limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// Wait for room, up to `max_queue_secs`, then shed.
+    #[default]
+    Queue,
+    /// Fail straight away with a rate limit error carrying the time until there is room.
+    Shed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RateLimits {
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Counts input and output tokens. Input is estimated before sending and corrected
+    /// from the provider's usage afterwards.
+    #[serde(default)]
+    pub tokens_per_minute: Option<u64>,
+    #[serde(default)]
+    pub overflow: Overflow,
+    #[serde(default)]
+    pub max_queue_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RateLimitMetrics {
+    pub requests: u64,
+    pub queued: u64,
+    pub shed: u64,
+    pub queued_ms: u64,
+}
+
+struct Slot {
+    id: u64,
+    at: Instant,
+    tokens: u64,
+}
+
+/// Requests sent in the last minute.
+#[derive(Default)]
+struct Window {
+    slots: VecDeque<Slot>,
+    next_id: u64,
+}
+
+impl Window {
+    /// Takes a slot for a request of `tokens`, or says how long until one frees up.
+    fn try_acquire(
+        &mut self,
+        limits: &RateLimits,
+        now: Instant,
+        tokens: u64,
+    ) -> Result<u64, Duration> {
+        while self
+            .slots
+            .front()
+            .is_some_and(|slot| now.duration_since(slot.at) >= WINDOW)
+        {
+            self.slots.pop_front();
+        }
+        let expires = |slot: &Slot| (slot.at + WINDOW).saturating_duration_since(now);
+
+        let mut wait = Duration::ZERO;
+        if let Some(rpm) = limits.requests_per_minute {
+            let rpm = rpm.max(1) as usize;
+            if self.slots.len() >= rpm {
+                wait = wait.max(expires(&self.slots[self.slots.len() - rpm]));
+            }
+        }
+        if let Some(tpm) = limits.tokens_per_minute {
+            let mut used: u64 = self.slots.iter().map(|slot| slot.tokens).sum();
+            // A request bigger than the whole budget goes out alone once the window is empty.
+            let budget = tpm.max(tokens);
+            for slot in &self.slots {
+                if used + tokens <= budget {
+                    break;
+                }
+                used -= slot.tokens;
+                wait = wait.max(expires(slot));
+            }
+        }
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.slots.push_back(Slot {
+            id,
+            at: now,
+            tokens,
+        });
+        Ok(id)
+    }
+
+    fn settle(&mut self, id: u64, tokens: u64) {
+        if let Some(slot) = self.slots.iter_mut().find(|slot| slot.id == id) {
+            slot.tokens = tokens;
+        }
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    requests: AtomicU64,
+    queued: AtomicU64,
+    shed: AtomicU64,
+    queued_ms: AtomicU64,
+}
+
+struct RateLimiter {
+    limits: RateLimits,
+    window: Mutex<Window>,
+    metrics: Metrics,
+}
+
+impl RateLimiter {
+    fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            window: Mutex::new(Window::default()),
+            metrics: Metrics::default(),
+        }
+    }
+
+    async fn acquire(&self, provider: &str, tokens: u64) -> Result<u64, ProviderError> {
+        let start = Instant::now();
+        let max_queue =
+            Duration::from_secs(self.limits.max_queue_secs.unwrap_or(DEFAULT_MAX_QUEUE_SECS));
+        let mut queued = false;
+        loop {
+            let acquired =
+                self.window
+                    .lock()
+                    .unwrap()
+                    .try_acquire(&self.limits, Instant::now(), tokens);
+            let wait = match acquired {
+                Ok(id) => {
+                    self.metrics.requests.fetch_add(1, Ordering::Relaxed);
+                    if queued {
+                        self.metrics
+                            .queued_ms
+                            .fetch_add(start.elapsed().as_millis() as u64, Ordering::Relaxed);
+                    }
+                    return Ok(id);
+                }
+                Err(wait) => wait,
+            };
+
+            if self.limits.overflow == Overflow::Shed || start.elapsed() + wait > max_queue {
+                self.metrics.shed.fetch_add(1, Ordering::Relaxed);
+                return Err(ProviderError::RateLimitExceeded {
+                    details: format!("Client-side rate limit for {} reached", provider),
+                    retry_delay: Some(wait),
+                });
+            }
+            if !queued {
+                queued = true;
+                self.metrics.queued.fetch_add(1, Ordering::Relaxed);
+                tracing::info!(
+                    provider,
+                    wait_ms = wait.as_millis() as u64,
+                    "Queueing request behind rate limit"
+                );
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Replaces a slot's estimate with the tokens the provider reports it used.
+    fn settle(&self, slot: u64, estimate: u64, usage: &ProviderUsage) {
+        let input = usage
+            .usage
+            .input_tokens
+            .map_or(estimate, |t| t.max(0) as u64);
+        let output = usage.usage.output_tokens.unwrap_or(0).max(0) as u64;
+        self.window.lock().unwrap().settle(slot, input + output);
+    }
+
+    fn metrics(&self) -> RateLimitMetrics {
+        RateLimitMetrics {
+            requests: self.metrics.requests.load(Ordering::Relaxed),
+            queued: self.metrics.queued.load(Ordering::Relaxed),
+            shed: self.metrics.shed.load(Ordering::Relaxed),
+            queued_ms: self.metrics.queued_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Shared by every provider instance with the same name, so all sessions draw on one budget.
+static LIMITERS: LazyLock<Mutex<HashMap<String, Arc<RateLimiter>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn shared_limiter(provider: &str, limits: RateLimits) -> Arc<RateLimiter> {
+    let mut limiters = LIMITERS.lock().unwrap();
+    match limiters.get(provider) {
+        Some(limiter) if limiter.limits == limits => limiter.clone(),
+        _ => {
+            let limiter = Arc::new(RateLimiter::new(limits));
+            limiters.insert(provider.to_string(), limiter.clone());
+            limiter
+        }
+    }
+}
+
+/// Counters for the rate limiter of `provider`, if one is configured.
+pub fn rate_limit_metrics(provider: &str) -> Option<RateLimitMetrics> {
+    LIMITERS
+        .lock()
+        .unwrap()
+        .get(provider)
+        .map(|limiter| limiter.metrics())
+}
+
+/// A provider that holds requests back to stay under request and token budgets, instead of
+/// tripping the upstream API's 429s.
+pub struct RateLimitedProvider {
+    inner: Arc<dyn Provider>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitedProvider {
+    pub fn new(inner: Arc<dyn Provider>, limits: RateLimits) -> Self {
+        let limiter = shared_limiter(inner.get_name(), limits);
+        Self { inner, limiter }
+    }
+
+    /// Wraps `provider` when `GOOSE_PROVIDER_RATE_LIMITS` has an entry for `name`, and
+    /// returns it unchanged otherwise.
+    pub fn wrap_from_config(
+        provider: Arc<dyn Provider>,
+        name: &str,
+        config: &Config,
+    ) -> Arc<dyn Provider> {
+        match config.get_param::<HashMap<String, RateLimits>>(RATE_LIMITS_CONFIG_KEY) {
+            Ok(mut all) => match all.remove(name) {
+                Some(limits) => Arc::new(Self::new(provider, limits)),
+                None => provider,
+            },
+            Err(e) => {
+                if !matches!(e, ConfigError::NotFound(_)) {
+                    tracing::warn!(error = %e, "ignoring invalid {}", RATE_LIMITS_CONFIG_KEY);
+                }
+                provider
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> RateLimitMetrics {
+        self.limiter.metrics()
+    }
+
+    async fn estimate_input_tokens(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> u64 {
+        if self.limiter.limits.tokens_per_minute.is_none() {
+            return 0;
+        }
+        match create_token_counter_for_model(&model_config.model_name).await {
+            Ok(counter) => counter.count_chat_tokens(system, messages, tools) as u64,
+            Err(_) => 0,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for RateLimitedProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::new(
+            "rate_limited",
+            "Rate Limited Provider",
+            "A provider that keeps requests under per-minute request and token limits",
+            "",
+            vec![],
+            "",
+            vec![],
+        )
+    }
+
+    fn get_name(&self) -> &str {
+        self.inner.get_name()
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        self.inner.get_model_config()
+    }
+
+    async fn complete_with_model(
+        &self,
+        session_id: Option<&str>,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let estimate = self
+            .estimate_input_tokens(model_config, system, messages, tools)
+            .await;
+        let slot = self.limiter.acquire(self.get_name(), estimate).await?;
+
+        let result = self
+            .inner
+            .complete_with_model(session_id, model_config, system, messages, tools)
+            .await;
+        if let Ok((_, usage)) = &result {
+            self.limiter.settle(slot, estimate, usage);
+        }
+        result
+    }
+
+    async fn stream_with_model(
+        &self,
+        session_id: &str,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let estimate = self
+            .estimate_input_tokens(model_config, system, messages, tools)
+            .await;
+        let slot = self.limiter.acquire(self.get_name(), estimate).await?;
+
+        let stream = self
+            .inner
+            .stream_with_model(session_id, model_config, system, messages, tools)
+            .await?;
+        let limiter = self.limiter.clone();
+        Ok(Box::pin(stream.inspect(move |result| {
+            if let Ok((_, Some(usage))) = result {
+                limiter.settle(slot, estimate, usage);
+            }
+        })))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn retry_config(&self) -> RetryConfig {
+        self.inner.retry_config()
+    }
+
+    async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
+        self.inner.fetch_supported_models().await
+    }
+
+    fn supports_structured_output(&self) -> bool {
+        self.inner.supports_structured_output()
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        self.inner.supports_embeddings()
+    }
+
+    async fn supports_cache_control(&self) -> bool {
+        self.inner.supports_cache_control().await
+    }
+
+    async fn create_embeddings(
+        &self,
+        session_id: &str,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, ProviderError> {
+        self.limiter.acquire(self.get_name(), 0).await?;
+        self.inner.create_embeddings(session_id, texts).await
+    }
+
+    fn as_lead_worker(&self) -> Option<&dyn LeadWorkerProviderTrait> {
+        self.inner.as_lead_worker()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::{stream_from_single_message, Usage};
+    use tokio_util::sync::CancellationToken;
+
+    struct StreamingProvider;
+
+    #[async_trait]
+    impl Provider for StreamingProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "rate_limit_streaming_test"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail("streaming-model")
+        }
+
+        async fn complete_with_model(
+            &self,
+            _session_id: Option<&str>,
+            _model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Err(ProviderError::NotImplemented("stream only".into()))
+        }
+
+        async fn stream_with_model(
+            &self,
+            _session_id: &str,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<MessageStream, ProviderError> {
+            Ok(stream_from_single_message(
+                Message::assistant().with_text("hi"),
+                ProviderUsage::new(model_config.model_name.clone(), Usage::default()),
+            ))
+        }
+
+        fn supports_streaming(&self) -> bool {
+            true
+        }
+    }
+
+    fn limits(rpm: Option<u32>, tpm: Option<u64>) -> RateLimits {
+        RateLimits {
+            requests_per_minute: rpm,
+            tokens_per_minute: tpm,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_window_requests_per_minute() {
+        let limits = limits(Some(2), None);
+        let mut window = Window::default();
+        let start = Instant::now();
+
+        assert!(window.try_acquire(&limits, start, 0).is_ok());
+        assert!(window
+            .try_acquire(&limits, start + Duration::from_secs(10), 0)
+            .is_ok());
+        assert_eq!(
+            window.try_acquire(&limits, start + Duration::from_secs(20), 0),
+            Err(Duration::from_secs(40))
+        );
+        assert!(window.try_acquire(&limits, start + WINDOW, 0).is_ok());
+    }
+
+    #[test]
+    fn test_window_tokens_per_minute() {
+        let limits = limits(None, Some(1000));
+        let mut window = Window::default();
+        let start = Instant::now();
+
+        let first = window.try_acquire(&limits, start, 100).unwrap();
+        window.settle(first, 700);
+        window
+            .try_acquire(&limits, start + Duration::from_secs(30), 200)
+            .unwrap();
+        // 900 used; 300 more only fits once the first request's 700 expire.
+        assert_eq!(
+            window.try_acquire(&limits, start + Duration::from_secs(45), 300),
+            Err(Duration::from_secs(15))
+        );
+        // Too big for the budget: waits for an empty window, then goes alone.
+        assert_eq!(
+            window.try_acquire(&limits, start + Duration::from_secs(45), 5000),
+            Err(Duration::from_secs(45))
+        );
+        assert!(window
+            .try_acquire(&limits, start + Duration::from_secs(90), 5000)
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_shed_reports_retry_delay() {
+        let limiter = RateLimiter::new(RateLimits {
+            overflow: Overflow::Shed,
+            ..limits(Some(1), None)
+        });
+        limiter.acquire("test", 0).await.unwrap();
+        let err = limiter.acquire("test", 0).await.unwrap_err();
+        assert!(matches!(
+            err,
+            ProviderError::RateLimitExceeded {
+                retry_delay: Some(_),
+                ..
+            }
+        ));
+        assert_eq!(
+            limiter.metrics(),
+            RateLimitMetrics {
+                requests: 1,
+                queued: 0,
+                shed: 1,
+                queued_ms: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streams_take_a_slot_before_starting() {
+        let provider = RateLimitedProvider::new(
+            Arc::new(StreamingProvider),
+            RateLimits {
+                overflow: Overflow::Shed,
+                ..limits(Some(1), None)
+            },
+        );
+        assert!(provider.supports_streaming());
+
+        let mut stream = provider
+            .stream("session", "", &[], &[], CancellationToken::new())
+            .await
+            .unwrap();
+        assert!(stream.next().await.unwrap().is_ok());
+
+        let err = provider
+            .stream("session", "", &[], &[], CancellationToken::new())
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, ProviderError::RateLimitExceeded { .. }));
+        assert_eq!(provider.metrics().requests, 1);
+        assert_eq!(provider.metrics().shed, 1);
+    }
+}
@@ -1,8 +1,10 @@
 use super::errors::ProviderError;
 use crate::providers::base::Provider;
 use async_trait::async_trait;
+use serde::Deserialize;
 use std::future::Future;
 use std::time::Duration;
+use tokio::sync::mpsc;
 use tokio::time::sleep;
 
 pub const DEFAULT_MAX_RETRIES: usize = 3;
@@ -10,7 +12,8 @@ pub const DEFAULT_INITIAL_RETRY_INTERVAL_MS: u64 = 1000;
 pub const DEFAULT_BACKOFF_MULTIPLIER: f64 = 2.0;
 pub const DEFAULT_MAX_RETRY_INTERVAL_MS: u64 = 30_000;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct RetryConfig {
     /// Maximum number of retry attempts
     pub(crate) max_retries: usize,
@@ -71,6 +74,56 @@ impl RetryConfig {
     }
 }
 
+/// A retry that's about to wait out its delay.
+#[derive(Debug, Clone)]
+pub struct RetryNotice {
+    /// Starts at 1.
+    pub attempt: usize,
+    pub max_retries: usize,
+    pub delay: Duration,
+    pub rate_limited: bool,
+    pub error: String,
+}
+
+/// Overrides how provider requests made inside [`with_retry_scope`] retry, and reports each
+/// retry, so a caller can tell users why a response is taking a while.
+#[derive(Debug, Clone, Default)]
+pub struct RetryScope {
+    /// Replaces the provider's own retry config when set.
+    pub config: Option<RetryConfig>,
+    pub notices: Option<mpsc::UnboundedSender<RetryNotice>>,
+}
+
+tokio::task_local! {
+    static RETRY_SCOPE: RetryScope;
+}
+
+pub async fn with_retry_scope<F: Future>(scope: RetryScope, future: F) -> F::Output {
+    RETRY_SCOPE.scope(scope, future).await
+}
+
+fn scoped_config(default: RetryConfig) -> RetryConfig {
+    RETRY_SCOPE
+        .try_with(|scope| scope.config.clone())
+        .ok()
+        .flatten()
+        .unwrap_or(default)
+}
+
+fn notify_retry(attempt: usize, config: &RetryConfig, delay: Duration, error: &ProviderError) {
+    let _ = RETRY_SCOPE.try_with(|scope| {
+        if let Some(notices) = &scope.notices {
+            let _ = notices.send(RetryNotice {
+                attempt,
+                max_retries: config.max_retries,
+                delay,
+                rate_limited: matches!(error, ProviderError::RateLimitExceeded { .. }),
+                error: error.to_string(),
+            });
+        }
+    });
+}
+
 pub fn should_retry(error: &ProviderError) -> bool {
     matches!(
         error,
@@ -89,6 +142,7 @@ where
     Fut: Future<Output = Result<T, ProviderError>> + Send,
     T: Send,
 {
+    let config = scoped_config(config.clone());
     let mut attempts = 0;
 
     loop {
@@ -104,14 +158,11 @@ where
                         error
                     );
 
-                    let delay = match &error {
-                        ProviderError::RateLimitExceeded {
-                            retry_delay: Some(d),
-                            ..
-                        } => *d,
-                        _ => config.delay_for_attempt(attempts),
-                    };
+                    let delay = error
+                        .retry_delay()
+                        .unwrap_or_else(|| config.delay_for_attempt(attempts));
 
+                    notify_retry(attempts, &config, delay, &error);
                     sleep(delay).await;
                     continue;
                 }
@@ -135,7 +186,7 @@ pub trait ProviderRetry {
         T: Send,
     {
         let mut attempts = 0;
-        let config = self.retry_config();
+        let config = scoped_config(self.retry_config());
 
         loop {
             return match operation().await {
@@ -150,13 +201,9 @@ pub trait ProviderRetry {
                             error
                         );
 
-                        let delay = match &error {
-                            ProviderError::RateLimitExceeded {
-                                retry_delay: Some(provider_delay),
-                                ..
-                            } => *provider_delay,
-                            _ => config.delay_for_attempt(attempts),
-                        };
+                        let delay = error
+                            .retry_delay()
+                            .unwrap_or_else(|| config.delay_for_attempt(attempts));
 
                         let skip_backoff = std::env::var("GOOSE_PROVIDER_SKIP_BACKOFF")
                             .unwrap_or_default()
@@ -167,6 +214,7 @@ pub trait ProviderRetry {
                             tracing::info!("Skipping backoff due to GOOSE_PROVIDER_SKIP_BACKOFF");
                         } else {
                             tracing::info!("Backing off for {:?} before retry", delay);
+                            notify_retry(attempts, &config, delay, &error);
                             sleep(delay).await;
                         }
                         continue;
@@ -184,3 +232,46 @@ impl<P: Provider> ProviderRetry for P {
         Provider::retry_config(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_retry_scope_overrides_config_and_reports_retries() {
+        let (notices, mut received) = mpsc::unbounded_channel();
+        let scope = RetryScope {
+            config: Some(RetryConfig::new(2, 1, 1.0, 1)),
+            notices: Some(notices),
+        };
+        let calls = AtomicUsize::new(0);
+
+        let result: Result<(), _> = with_retry_scope(
+            scope,
+            retry_operation(&RetryConfig::default(), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(ProviderError::RateLimitExceeded {
+                    details: "slow down".to_string(),
+                    retry_delay: Some(Duration::from_millis(5)),
+                })
+            }),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        let first = received.recv().await.unwrap();
+        assert_eq!(
+            (
+                first.attempt,
+                first.max_retries,
+                first.delay,
+                first.rate_limited
+            ),
+            (1, 2, Duration::from_millis(5), true)
+        );
+        assert_eq!(received.recv().await.unwrap().attempt, 2);
+        assert!(received.try_recv().is_err());
+    }
+}
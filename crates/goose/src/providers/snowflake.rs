@@ -3,12 +3,12 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
-use super::api_client::{ApiClient, AuthMethod};
+use super::api_client::{backoff_hint, ApiClient, AuthMethod};
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
 use super::errors::ProviderError;
 use super::formats::snowflake::{create_request, get_usage, response_to_message};
 use super::retry::ProviderRetry;
-use super::utils::{get_model, map_http_error_to_provider_error, ImageFormat, RequestLog};
+use super::utils::{get_model, map_http_error_with_backoff, ImageFormat, RequestLog};
 use crate::config::ConfigError;
 use crate::conversation::message::Message;
 
@@ -118,6 +118,7 @@ impl SnowflakeProvider {
             .await?;
 
         let status = response.status();
+        let retry_delay = backoff_hint(response.headers());
         let payload_text: String = response.text().await.ok().unwrap_or_default();
 
         if status.is_success() {
@@ -287,7 +288,7 @@ impl SnowflakeProvider {
             Ok(answer_payload)
         } else {
             let error_json = serde_json::from_str::<Value>(&payload_text).ok();
-            Err(map_http_error_to_provider_error(status, error_json))
+            Err(map_http_error_with_backoff(status, error_json, retry_delay))
         }
     }
 }
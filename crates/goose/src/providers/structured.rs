@@ -0,0 +1,188 @@
+use serde_json::Value;
+
+use super::base::{Provider, ProviderUsage};
+use super::errors::ProviderError;
+use crate::conversation::message::Message;
+
+const MAX_ATTEMPTS: usize = 3;
+
+/// Asks for JSON matching `schema` and keeps asking, with the validation errors, until a reply
+/// matches. Providers with native support also get the schema on the request itself; the
+/// instructions go into the system prompt either way, since not every model honours it.
+pub(crate) async fn complete_structured<P: Provider + ?Sized>(
+    provider: &P,
+    session_id: &str,
+    system: &str,
+    messages: &[Message],
+    schema: &Value,
+) -> Result<(Value, ProviderUsage), ProviderError> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| ProviderError::ExecutionError(format!("Invalid JSON schema: {}", e)))?;
+
+    let mut model_config = provider.get_model_config();
+    if provider.supports_structured_output() {
+        model_config = model_config.with_response_schema(Some(schema.clone()));
+    }
+    let system = format!(
+        "{}\n\nRespond with only a JSON value, without any other text, that matches this JSON schema:\n{}",
+        system, schema
+    );
+
+    let mut messages = messages.to_vec();
+    let mut total_usage: Option<ProviderUsage> = None;
+    let mut problem = String::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        let (reply, usage) = provider
+            .complete_with_model(Some(session_id), &model_config, &system, &messages, &[])
+            .await?;
+        let usage = match total_usage {
+            Some(total) => total.combine_with(&usage),
+            None => usage,
+        };
+
+        problem = match extract_json(&reply.as_concat_text()) {
+            Some(value) => {
+                let errors: Vec<String> = validator
+                    .iter_errors(&value)
+                    .map(|e| e.to_string())
+                    .collect();
+                if errors.is_empty() {
+                    return Ok((value, usage));
+                }
+                errors.join("; ")
+            }
+            None => "the reply is not valid JSON".to_string(),
+        };
+        tracing::debug!(
+            attempt,
+            problem,
+            "Structured reply did not match the schema"
+        );
+        total_usage = Some(usage);
+
+        messages.push(reply);
+        messages.push(Message::user().with_text(format!(
+            "That reply doesn't match the schema: {}. Reply again with only the corrected JSON.",
+            problem
+        )));
+    }
+
+    Err(ProviderError::ExecutionError(format!(
+        "No reply matched the JSON schema after {} attempts: {}",
+        MAX_ATTEMPTS, problem
+    )))
+}
+
+/// Finds the JSON in a reply, allowing for code fences or a sentence around it.
+fn extract_json(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(value) = serde_json::from_str(text) {
+        return Some(value);
+    }
+    if let Some((_, fenced)) = text.split_once("```") {
+        let fenced = fenced.strip_prefix("json").unwrap_or(fenced);
+        if let Some((body, _)) = fenced.split_once("```") {
+            if let Ok(value) = serde_json::from_str(body.trim()) {
+                return Some(value);
+            }
+        }
+    }
+    let start = text.find(['{', '['])?;
+    let end = text.rfind(['}', ']'])?;
+    serde_json::from_str(text.get(start..=end)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{ProviderMetadata, Usage};
+    use async_trait::async_trait;
+    use rmcp::model::Tool;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    struct ScriptedProvider {
+        replies: Mutex<Vec<&'static str>>,
+        schemas: Mutex<Vec<Option<Value>>>,
+    }
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "scripted"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail("scripted-model")
+        }
+
+        fn supports_structured_output(&self) -> bool {
+            true
+        }
+
+        async fn complete_with_model(
+            &self,
+            _session_id: Option<&str>,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.schemas
+                .lock()
+                .unwrap()
+                .push(model_config.response_schema.clone());
+            let reply = self.replies.lock().unwrap().remove(0);
+            Ok((
+                Message::assistant().with_text(reply),
+                ProviderUsage::new(
+                    model_config.model_name.clone(),
+                    Usage::new(Some(10), Some(5), Some(15)),
+                ),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reprompts_until_reply_matches_schema() {
+        let provider = ScriptedProvider {
+            replies: Mutex::new(vec![
+                "Sure! Here you go.",
+                r#"{"name": "goose"}"#,
+                "```json\n{\"name\": \"goose\", \"legs\": 2}\n```",
+            ]),
+            schemas: Mutex::new(Vec::new()),
+        };
+        let schema = json!({
+            "type": "object",
+            "properties": { "name": { "type": "string" }, "legs": { "type": "integer" } },
+            "required": ["name", "legs"],
+        });
+
+        let (value, usage) = provider
+            .complete_structured("session", "You are helpful", &[], &schema)
+            .await
+            .unwrap();
+        assert_eq!(value, json!({ "name": "goose", "legs": 2 }));
+        assert_eq!(usage.usage.total_tokens, Some(45));
+        assert_eq!(
+            provider.schemas.lock().unwrap().as_slice(),
+            [Some(schema.clone()), Some(schema.clone()), Some(schema)]
+        );
+    }
+
+    #[test]
+    fn test_extract_json() {
+        assert_eq!(extract_json("[1, 2]"), Some(json!([1, 2])));
+        assert_eq!(
+            extract_json("The answer is {\"ok\": true}."),
+            Some(json!({ "ok": true }))
+        );
+        assert_eq!(extract_json("no json here"), None);
+    }
+}
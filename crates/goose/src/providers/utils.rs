@@ -1,9 +1,11 @@
+use super::api_client::backoff_hint;
 use super::base::{MessageStream, Usage};
 use super::errors::GoogleErrorCode;
 use crate::config::paths::Paths;
 use crate::model::ModelConfig;
 use crate::providers::errors::ProviderError;
 use crate::providers::formats::openai::response_to_streaming_message;
+use crate::security::wire_log::{self, Direction};
 use anyhow::{anyhow, Result};
 use async_stream::try_stream;
 use base64::Engine;
@@ -110,6 +112,17 @@ fn format_server_error_message(status_code: StatusCode, payload: Option<&Value>)
 pub fn map_http_error_to_provider_error(
     status: StatusCode,
     payload: Option<Value>,
+) -> ProviderError {
+    map_http_error_with_backoff(status, payload, None)
+}
+
+/// Like [`map_http_error_to_provider_error`], carrying the server's [`backoff_hint`] into the
+/// error. A 503 that says when to come back is throttling rather than an outage, so it becomes
+/// a rate limit error that retries wait out.
+pub fn map_http_error_with_backoff(
+    status: StatusCode,
+    payload: Option<Value>,
+    retry_delay: Option<Duration>,
 ) -> ProviderError {
     let extract_message = || -> String {
         payload
@@ -145,8 +158,14 @@ pub fn map_http_error_to_provider_error(
         }
         StatusCode::TOO_MANY_REQUESTS => ProviderError::RateLimitExceeded {
             details: extract_message(),
-            retry_delay: None,
+            retry_delay,
         },
+        StatusCode::SERVICE_UNAVAILABLE if retry_delay.is_some() => {
+            ProviderError::RateLimitExceeded {
+                details: format!("Service unavailable (503): {}", extract_message()),
+                retry_delay,
+            }
+        }
         _ if status.is_server_error() => {
             ProviderError::ServerError(format!("Server error ({}): {}", status, extract_message()))
         }
@@ -172,9 +191,10 @@ pub fn map_http_error_to_provider_error(
 pub async fn handle_status_openai_compat(response: Response) -> Result<Response, ProviderError> {
     let status = response.status();
     if !status.is_success() {
+        let retry_delay = backoff_hint(response.headers());
         let body = response.text().await.unwrap_or_default();
         let payload = serde_json::from_str::<Value>(&body).ok();
-        return Err(map_http_error_to_provider_error(status, payload));
+        return Err(map_http_error_with_backoff(status, payload, retry_delay));
     }
     Ok(response)
 }
@@ -280,6 +300,7 @@ fn parse_google_retry_delay(payload: &Value) -> Option<Duration> {
 /// - `Err(ProviderError)`: Describes the failure reason.
 pub async fn handle_response_google_compat(response: Response) -> Result<Value, ProviderError> {
     let status = response.status();
+    let header_delay = backoff_hint(response.headers());
     let payload: Option<Value> = response.json().await.ok();
     let final_status = get_google_final_status(status, payload.as_ref());
 
@@ -306,7 +327,10 @@ pub async fn handle_response_google_compat(response: Response) -> Result<Value,
             Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", final_status, error_msg)))
         }
         StatusCode::TOO_MANY_REQUESTS => {
-            let retry_delay = payload.as_ref().and_then(parse_google_retry_delay);
+            let retry_delay = payload
+                .as_ref()
+                .and_then(parse_google_retry_delay)
+                .or(header_delay);
             Err(ProviderError::RateLimitExceeded {
                 details: format!("{:?}", payload),
                 retry_delay,
@@ -498,6 +522,7 @@ impl RequestLog {
                 .open(&temp_path)?,
         );
 
+        wire_log::log_frame("provider", Direction::Sent, payload);
         let data = serde_json::json!({
             "model_config": model_config,
             "input": payload,
@@ -532,6 +557,7 @@ impl RequestLog {
     where
         Payload: Serialize,
     {
+        wire_log::log_frame("provider", Direction::Received, data);
         self.write_json(&serde_json::json!({
             "data": data,
             "usage": usage,
@@ -957,4 +983,22 @@ mod tests {
             Some(Duration::from_secs(42))
         );
     }
+
+    #[test]
+    fn test_map_http_error_with_backoff() {
+        let delay = Some(Duration::from_secs(5));
+        let payload = Some(json!({"error": {"message": "slow down"}}));
+
+        let error =
+            map_http_error_with_backoff(StatusCode::TOO_MANY_REQUESTS, payload.clone(), delay);
+        assert_eq!(error.retry_delay(), delay);
+
+        let error =
+            map_http_error_with_backoff(StatusCode::SERVICE_UNAVAILABLE, payload.clone(), delay);
+        assert!(matches!(error, ProviderError::RateLimitExceeded { .. }));
+        assert_eq!(error.retry_delay(), delay);
+
+        let error = map_http_error_with_backoff(StatusCode::SERVICE_UNAVAILABLE, payload, None);
+        assert!(matches!(error, ProviderError::ServerError(_)));
+    }
 }
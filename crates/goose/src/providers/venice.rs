@@ -4,11 +4,11 @@ use chrono::Utc;
 use serde::Serialize;
 use serde_json::{json, Value};
 
-use super::api_client::{ApiClient, AuthMethod};
+use super::api_client::{backoff_hint, ApiClient, AuthMethod};
 use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
 use super::errors::ProviderError;
 use super::retry::ProviderRetry;
-use super::utils::map_http_error_to_provider_error;
+use super::utils::map_http_error_with_backoff;
 use crate::conversation::message::{Message, MessageContent};
 
 use crate::mcp_utils::ToolResult;
@@ -128,6 +128,7 @@ impl VeniceProvider {
         tracing::debug!("Venice response status: {}", status);
 
         if !status.is_success() {
+            let retry_delay = backoff_hint(response.headers());
             // Read response body for more details on error
             let error_body = response.text().await.unwrap_or_default();
 
@@ -179,7 +180,7 @@ impl VeniceProvider {
 
             // Use the common error mapping function
             let error_json = serde_json::from_str::<Value>(&error_body).ok();
-            return Err(map_http_error_to_provider_error(status, error_json));
+            return Err(map_http_error_with_backoff(status, error_json, retry_delay));
         }
 
         let response_text = response.text().await?;
@@ -1,7 +1,9 @@
 pub mod classification_client;
 pub mod patterns;
+pub mod redaction;
 pub mod scanner;
 pub mod security_inspector;
+pub mod wire_log;
 
 use crate::config::Config;
 use crate::conversation::message::{Message, ToolRequest};
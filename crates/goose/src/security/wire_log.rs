@@ -0,0 +1,125 @@
+//! Debug dump of the JSON goose exchanges with ACP clients and model providers, with secrets
+//! masked so the output can go into a bug report. Frames are logged at debug level under the
+//! `goose::wire` target. `GOOSE_WIRE_LOG` turns it on at startup; [`set_enabled`] switches it
+//! while running.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+
+use super::redaction::{
+    RedactionConfig, RedactionReport, Redactor, DEFAULT_ENTROPY_THRESHOLD, REDACTION_CONFIG_KEY,
+};
+use crate::config::Config;
+
+pub const WIRE_LOG_CONFIG_KEY: &str = "GOOSE_WIRE_LOG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+static ENABLED: LazyLock<AtomicBool> = LazyLock::new(|| {
+    AtomicBool::new(
+        Config::global()
+            .get_param(WIRE_LOG_CONFIG_KEY)
+            .unwrap_or(false),
+    )
+});
+
+/// The `GOOSE_REDACTION` patterns when configured, so anything masked before reaching a
+/// provider is masked here too. The built-in patterns otherwise.
+static REDACTOR: LazyLock<Redactor> = LazyLock::new(|| {
+    let defaults = RedactionConfig {
+        entropy_threshold: Some(DEFAULT_ENTROPY_THRESHOLD),
+        ..Default::default()
+    };
+    let config = Config::global()
+        .get_param::<RedactionConfig>(REDACTION_CONFIG_KEY)
+        .unwrap_or(defaults.clone());
+    Redactor::new(&config).unwrap_or_else(|e| {
+        tracing::warn!("Wire log uses the built-in redaction patterns: {}", e);
+        Redactor::new(&defaults).expect("built-in redaction patterns compile")
+    })
+});
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn redact_frame(frame: &Value) -> Value {
+    let mut frame = frame.clone();
+    REDACTOR.redact_value(&mut frame, &mut RedactionReport::default());
+    frame
+}
+
+/// Logs a frame when the wire log is on. `channel` says whose traffic it is, such as `acp` or
+/// a provider name.
+pub fn log_frame<T: Serialize + ?Sized>(channel: &str, direction: Direction, frame: &T) {
+    if !is_enabled() {
+        return;
+    }
+    match serde_json::to_value(frame) {
+        Ok(value) => tracing::debug!(
+            target: "goose::wire",
+            channel,
+            direction = ?direction,
+            frame = %redact_frame(&value),
+        ),
+        Err(e) => tracing::debug!(
+            target: "goose::wire",
+            channel,
+            direction = ?direction,
+            "unserializable frame: {}",
+            e
+        ),
+    }
+}
+
+/// Logs one line of a line-delimited stream, as JSON when it parses and as text otherwise.
+pub fn log_line(channel: &str, direction: Direction, line: &str) {
+    if !is_enabled() {
+        return;
+    }
+    match serde_json::from_str::<Value>(line) {
+        Ok(value) => log_frame(channel, direction, &value),
+        Err(_) => tracing::debug!(
+            target: "goose::wire",
+            channel,
+            direction = ?direction,
+            frame = %REDACTOR.redact_text(line, &mut RedactionReport::default()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_frame_masks_nested_secrets() {
+        let frame = json!({
+            "jsonrpc": "2.0",
+            "method": "session/new",
+            "params": {
+                "mcpServers": [{
+                    "env": [{"name": "OPENAI_API_KEY", "value": "sk-abcdefghijklmnopqrstuvwxyz"}],
+                    "headers": [{"name": "Authorization", "value": "Bearer abcdefghijklmnopqrstuvwxyz"}],
+                }],
+                "cwd": "/work",
+            },
+        });
+
+        let redacted = redact_frame(&frame).to_string();
+        assert!(!redacted.contains("sk-abcdefghijklmnopqrstuvwxyz"));
+        assert!(!redacted.contains("Bearer abcdefghijklmnopqrstuvwxyz"));
+        assert!(redacted.contains("\"cwd\":\"/work\""));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::session::attachments::{attachments_in, ATTACHMENTS_FOLDER};
+use crate::session::session_manager::Session;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+pub const ARCHIVE_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const SESSION_FILE: &str = "session.json";
+
+/// Describes what an archive holds, so importers can reject versions they don't understand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub version: u32,
+    pub app_version: String,
+    pub exported_at: DateTime<Utc>,
+    pub session_id: String,
+}
+
+/// How an imported session is identified on this machine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveIds {
+    /// Keep the exported session and message ids. Fails if the session id is already taken.
+    Preserve,
+    /// Give the session and its messages fresh ids.
+    #[default]
+    Remap,
+}
+
+/// Packs a session into a zip holding a manifest, the session itself and the files attached to
+/// its messages. Tool calls, tool outputs and images are inline in the messages.
+pub(crate) fn write_archive(session: &Session) -> Result<Vec<u8>> {
+    let manifest = ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Utc::now(),
+        session_id: session.id.clone(),
+    };
+
+    let mut buffer = Vec::new();
+    {
+        let mut zip = ZipWriter::new(Cursor::new(&mut buffer));
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+        zip.start_file(MANIFEST_FILE, options)?;
+        zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+
+        zip.start_file(SESSION_FILE, options)?;
+        zip.write_all(serde_json::to_string_pretty(session)?.as_bytes())?;
+
+        let messages = session
+            .conversation
+            .as_ref()
+            .map(|conversation| conversation.messages().as_slice())
+            .unwrap_or_default();
+        let mut written = HashSet::new();
+        for attachment in attachments_in(messages) {
+            let Some(file_name) = attachment.path.file_name() else {
+                continue;
+            };
+            let file_name = file_name.to_string_lossy().into_owned();
+            if !written.insert(file_name.clone()) {
+                continue;
+            }
+            let data = std::fs::read(&attachment.path)
+                .with_context(|| format!("Cannot read attachment {}", attachment.path.display()))?;
+            zip.start_file(format!("{}/{}", ATTACHMENTS_FOLDER, file_name), options)?;
+            zip.write_all(&data)?;
+        }
+
+        zip.finish()?;
+    }
+
+    Ok(buffer)
+}
+
+/// Attached files as (file name, contents).
+pub(crate) type ArchivedAttachments = Vec<(String, Vec<u8>)>;
+
+pub(crate) fn read_archive(
+    archive: &[u8],
+) -> Result<(ArchiveManifest, Session, ArchivedAttachments)> {
+    let mut zip = ZipArchive::new(Cursor::new(archive)).context("Not a session archive")?;
+
+    let manifest: ArchiveManifest = serde_json::from_str(&read_file(&mut zip, MANIFEST_FILE)?)?;
+    if manifest.version > ARCHIVE_VERSION {
+        anyhow::bail!(
+            "Session archive version {} is newer than the supported version {}",
+            manifest.version,
+            ARCHIVE_VERSION
+        );
+    }
+
+    let session = serde_json::from_str(&read_file(&mut zip, SESSION_FILE)?)?;
+
+    let prefix = format!("{}/", ATTACHMENTS_FOLDER);
+    let mut attachments = Vec::new();
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index)?;
+        let Some(file_name) = file.name().strip_prefix(&prefix).map(str::to_string) else {
+            continue;
+        };
+        if file_name.is_empty() || file_name.contains(['/', '\\']) || file_name.contains("..") {
+            continue;
+        }
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        attachments.push((file_name, data));
+    }
+    Ok((manifest, session, attachments))
+}
+
+fn read_file(zip: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<String> {
+    let mut file = zip
+        .by_name(name)
+        .with_context(|| format!("Session archive has no {}", name))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(contents)
+}
@@ -0,0 +1,151 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::conversation::message::{AttachmentContent, Message, MessageContent};
+
+pub const ATTACHMENTS_FOLDER: &str = "attachments";
+/// Config key for the largest file that can be attached, in bytes.
+pub const MAX_ATTACHMENT_SIZE_CONFIG_KEY: &str = "GOOSE_MAX_ATTACHMENT_SIZE";
+pub const DEFAULT_MAX_ATTACHMENT_SIZE: u64 = 20 * 1024 * 1024;
+
+/// Where an attachment's bytes come from.
+#[derive(Debug, Clone)]
+pub enum AttachmentSource {
+    Path(PathBuf),
+    Bytes { name: String, data: Vec<u8> },
+}
+
+/// Copies an attachment into `dir`, named by its hash so the same file is stored once however
+/// many sessions, copies and forks refer to it.
+pub(crate) fn store_attachment(
+    dir: &Path,
+    source: AttachmentSource,
+    mime_type: Option<String>,
+    max_size: u64,
+) -> Result<AttachmentContent> {
+    let (name, data) = match source {
+        AttachmentSource::Path(path) => {
+            let size = fs::metadata(&path)
+                .with_context(|| format!("Cannot read {}", path.display()))?
+                .len();
+            check_size(size, max_size)?;
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "attachment".to_string());
+            (name, fs::read(&path)?)
+        }
+        AttachmentSource::Bytes { name, data } => {
+            check_size(data.len() as u64, max_size)?;
+            (name, data)
+        }
+    };
+
+    let mime_type = mime_type.unwrap_or_else(|| guess_mime_type(&name, &data));
+    let mut file_name = format!("{:x}", Sha256::digest(&data));
+    if let Some(extension) = Path::new(&name).extension().and_then(|e| e.to_str()) {
+        if !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+            file_name = format!("{}.{}", file_name, extension.to_ascii_lowercase());
+        }
+    }
+
+    fs::create_dir_all(dir)?;
+    let path = dir.join(file_name);
+    if !path.exists() {
+        fs::write(&path, &data)?;
+    }
+
+    Ok(AttachmentContent {
+        name,
+        mime_type,
+        size: data.len() as u64,
+        path,
+    })
+}
+
+fn check_size(size: u64, max_size: u64) -> Result<()> {
+    if size > max_size {
+        anyhow::bail!(
+            "Attachment is {} bytes, over the {} byte limit",
+            size,
+            max_size
+        );
+    }
+    Ok(())
+}
+
+/// Guesses from the extension, falling back to `text/plain` for anything that is valid UTF-8.
+pub fn guess_mime_type(name: &str, data: &[u8]) -> String {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let mime_type = match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("pdf") => "application/pdf",
+        Some("csv") => "text/csv",
+        Some("tsv") => "text/tab-separated-values",
+        Some("md" | "markdown") => "text/markdown",
+        Some("html" | "htm") => "text/html",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("yaml" | "yml") => "application/yaml",
+        Some("toml") => "application/toml",
+        _ if std::str::from_utf8(data).is_ok() => "text/plain",
+        _ => "application/octet-stream",
+    };
+    mime_type.to_string()
+}
+
+/// Whether the attachment can be handed to a model as text.
+pub fn is_text_mime_type(mime_type: &str) -> bool {
+    mime_type.starts_with("text/")
+        || matches!(
+            mime_type,
+            "application/json" | "application/xml" | "application/yaml" | "application/toml"
+        )
+}
+
+pub(crate) fn attachments_in(messages: &[Message]) -> impl Iterator<Item = &AttachmentContent> {
+    messages
+        .iter()
+        .flat_map(|message| message.content.iter())
+        .filter_map(|content| match content {
+            MessageContent::Attachment(attachment) => Some(attachment),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_attachment() {
+        let dir = TempDir::new().unwrap();
+        let bytes = |data: &str| AttachmentSource::Bytes {
+            name: "sales.csv".to_string(),
+            data: data.as_bytes().to_vec(),
+        };
+
+        let first = store_attachment(dir.path(), bytes("a,b\n1,2\n"), None, 1024).unwrap();
+        assert_eq!(first.name, "sales.csv");
+        assert_eq!(first.mime_type, "text/csv");
+        assert_eq!(first.size, 8);
+        assert_eq!(fs::read_to_string(&first.path).unwrap(), "a,b\n1,2\n");
+
+        let again = store_attachment(dir.path(), bytes("a,b\n1,2\n"), None, 1024).unwrap();
+        assert_eq!(again.path, first.path);
+
+        let error = store_attachment(dir.path(), bytes(&"x".repeat(2048)), None, 1024)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("over the 1024 byte limit"), "{}", error);
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How [`crate::session::SessionManager::cost_totals`] groups recorded usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CostGrouping {
+    Session,
+    /// Calendar day in UTC.
+    Day,
+    Provider,
+}
+
+impl CostGrouping {
+    pub(crate) fn key_sql(self) -> &'static str {
+        match self {
+            CostGrouping::Session => "session_id",
+            CostGrouping::Day => "date(created_timestamp, 'unixepoch')",
+            CostGrouping::Provider => "provider_name",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CostTotal {
+    /// The session id, the day as `YYYY-MM-DD`, or the provider name.
+    pub key: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Estimated from the bundled pricing table. `None` when none of the models were priced.
+    pub cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ModelCost {
+    pub provider_name: String,
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: Option<f64>,
+}
+
+/// Everything a session has spent, broken down by the models that served it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SessionCostReport {
+    pub session_id: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: Option<f64>,
+    pub by_model: Vec<ModelCost>,
+}
+
+impl SessionCostReport {
+    pub(crate) fn new(session_id: String, by_model: Vec<ModelCost>) -> Self {
+        let cost_usd = by_model
+            .iter()
+            .filter_map(|model| model.cost_usd)
+            .reduce(|a, b| a + b);
+        Self {
+            session_id,
+            input_tokens: by_model.iter().map(|model| model.input_tokens).sum(),
+            output_tokens: by_model.iter().map(|model| model.output_tokens).sum(),
+            cost_usd,
+            by_model,
+        }
+    }
+}
@@ -1,11 +1,22 @@
+pub mod archive;
+pub mod attachments;
 mod chat_history_search;
+pub mod cost;
 mod diagnostics;
 pub mod extension_data;
 mod legacy;
+pub mod retention;
 pub mod session_manager;
+pub mod store;
 
+pub use archive::{ArchiveIds, ArchiveManifest};
+pub use attachments::AttachmentSource;
+pub use chat_history_search::{ChatRecallMessage, ChatRecallResult, ChatRecallResults};
+pub use cost::{CostGrouping, CostTotal, ModelCost, SessionCostReport};
 pub use diagnostics::{generate_diagnostics, get_system_info, SystemInfo};
 pub use extension_data::{EnabledExtensionsState, ExtensionData, ExtensionState, TodoState};
+pub use retention::{PrunedSession, RetentionPolicy};
 pub use session_manager::{
-    Session, SessionInsights, SessionManager, SessionType, SessionUpdateBuilder,
+    Session, SessionInsights, SessionManager, SessionType, SessionUpdate, SessionUpdateBuilder,
 };
+pub use store::{MetadataUpdate, SessionStore};
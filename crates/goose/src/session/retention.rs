@@ -0,0 +1,56 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Limits on how many stored sessions are kept. Sessions are ranked by when they were last
+/// updated; any limit a session falls outside of gets it deleted. Unset limits don't apply.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Sessions not updated for this many days are deleted.
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    /// Only the most recently updated sessions are kept.
+    #[serde(default)]
+    pub max_count: Option<usize>,
+    /// The most recently updated sessions are kept until their messages add up to this size.
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+}
+
+/// A session removed by [`crate::session::SessionManager::prune`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrunedSession {
+    pub id: String,
+    pub name: String,
+    pub updated_at: DateTime<Utc>,
+    /// The size of the session's stored messages.
+    pub size_bytes: u64,
+}
+
+impl RetentionPolicy {
+    pub fn is_unbounded(&self) -> bool {
+        self.max_age_days.is_none() && self.max_count.is_none() && self.max_bytes.is_none()
+    }
+
+    /// Picks the sessions to delete from `sessions`, which must be ordered newest first.
+    pub(crate) fn select(
+        &self,
+        sessions: Vec<PrunedSession>,
+        now: DateTime<Utc>,
+    ) -> Vec<PrunedSession> {
+        let cutoff = self
+            .max_age_days
+            .map(|days| now - Duration::days(i64::from(days)));
+        let mut total_bytes = 0u64;
+        sessions
+            .into_iter()
+            .enumerate()
+            .filter(|(index, session)| {
+                total_bytes = total_bytes.saturating_add(session.size_bytes);
+                cutoff.is_some_and(|cutoff| session.updated_at < cutoff)
+                    || self.max_count.is_some_and(|max| *index >= max)
+                    || self.max_bytes.is_some_and(|max| total_bytes > max)
+            })
+            .map(|(_, session)| session)
+            .collect()
+    }
+}
@@ -1,24 +1,36 @@
 use crate::config::paths::Paths;
-use crate::conversation::message::Message;
+use crate::config::Config;
+use crate::conversation::message::{AttachmentContent, Message, MessageContent, MessageMetadata};
 use crate::conversation::Conversation;
 use crate::model::ModelConfig;
-use crate::providers::base::{Provider, MSG_COUNT_FOR_SESSION_NAME_GENERATION};
+use crate::providers::base::{Provider, ProviderUsage, MSG_COUNT_FOR_SESSION_NAME_GENERATION};
+use crate::providers::canonical::estimate_cost_usd;
 use crate::recipe::Recipe;
+use crate::session::archive::{read_archive, write_archive, ArchiveIds};
+use crate::session::attachments::{
+    store_attachment, AttachmentSource, ATTACHMENTS_FOLDER, DEFAULT_MAX_ATTACHMENT_SIZE,
+    MAX_ATTACHMENT_SIZE_CONFIG_KEY,
+};
+use crate::session::chat_history_search::ChatRecallResults;
+use crate::session::cost::{CostGrouping, CostTotal, ModelCost, SessionCostReport};
 use crate::session::extension_data::ExtensionData;
+use crate::session::retention::{PrunedSession, RetentionPolicy};
+use crate::session::store::{MetadataUpdate, SessionStore};
 use anyhow::Result;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use rmcp::model::Role;
 use serde::{Deserialize, Serialize};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
-use sqlx::{Pool, Sqlite};
-use std::collections::HashMap;
+use sqlx::{Pool, Sqlite, SqliteConnection};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock};
 use tracing::{info, warn};
 use utoipa::ToSchema;
 
-pub const CURRENT_SCHEMA_VERSION: i32 = 7;
+pub const CURRENT_SCHEMA_VERSION: i32 = 10;
 pub const SESSIONS_FOLDER: &str = "sessions";
 pub const DB_NAME: &str = "sessions.db";
 
@@ -90,27 +102,38 @@ pub struct Session {
     pub message_count: usize,
     pub provider_name: Option<String>,
     pub model_config: Option<ModelConfig>,
+    /// Caller-defined labels, such as a project or CI run id, that listings can filter on.
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+/// The fields a [`SessionUpdateBuilder`] changes. `None` leaves a field as it is; for the
+/// nullable ones, `Some(None)` clears it.
+#[derive(Debug, Clone, Default)]
+pub struct SessionUpdate {
+    pub name: Option<String>,
+    pub user_set_name: Option<bool>,
+    pub session_type: Option<SessionType>,
+    pub working_dir: Option<PathBuf>,
+    pub extension_data: Option<ExtensionData>,
+    pub total_tokens: Option<Option<i32>>,
+    pub input_tokens: Option<Option<i32>>,
+    pub output_tokens: Option<Option<i32>>,
+    pub accumulated_total_tokens: Option<Option<i32>>,
+    pub accumulated_input_tokens: Option<Option<i32>>,
+    pub accumulated_output_tokens: Option<Option<i32>>,
+    pub schedule_id: Option<Option<String>>,
+    pub recipe: Option<Option<Recipe>>,
+    pub user_recipe_values: Option<Option<HashMap<String, String>>>,
+    pub provider_name: Option<Option<String>>,
+    pub model_config: Option<Option<ModelConfig>>,
+    pub tags: Option<HashMap<String, String>>,
 }
 
 pub struct SessionUpdateBuilder<'a> {
     session_manager: &'a SessionManager,
     session_id: String,
-    name: Option<String>,
-    user_set_name: Option<bool>,
-    session_type: Option<SessionType>,
-    working_dir: Option<PathBuf>,
-    extension_data: Option<ExtensionData>,
-    total_tokens: Option<Option<i32>>,
-    input_tokens: Option<Option<i32>>,
-    output_tokens: Option<Option<i32>>,
-    accumulated_total_tokens: Option<Option<i32>>,
-    accumulated_input_tokens: Option<Option<i32>>,
-    accumulated_output_tokens: Option<Option<i32>>,
-    schedule_id: Option<Option<String>>,
-    recipe: Option<Option<Recipe>>,
-    user_recipe_values: Option<Option<HashMap<String, String>>>,
-    provider_name: Option<Option<String>>,
-    model_config: Option<Option<ModelConfig>>,
+    update: SessionUpdate,
 }
 
 #[derive(Serialize, ToSchema, Debug)]
@@ -125,34 +148,22 @@ impl<'a> SessionUpdateBuilder<'a> {
         Self {
             session_manager,
             session_id,
-            name: None,
-            user_set_name: None,
-            session_type: None,
-            working_dir: None,
-            extension_data: None,
-            total_tokens: None,
-            input_tokens: None,
-            output_tokens: None,
-            accumulated_total_tokens: None,
-            accumulated_input_tokens: None,
-            accumulated_output_tokens: None,
-            schedule_id: None,
-            recipe: None,
-            user_recipe_values: None,
-            provider_name: None,
-            model_config: None,
+            update: SessionUpdate::default(),
         }
     }
 
     pub async fn apply(self) -> Result<()> {
-        self.session_manager.apply_update_inner(self).await
+        self.session_manager
+            .storage
+            .apply_update(&self.session_id, self.update)
+            .await
     }
 
     pub fn user_provided_name(mut self, name: impl Into<String>) -> Self {
         let name = name.into().trim().to_string();
         if !name.is_empty() {
-            self.name = Some(name);
-            self.user_set_name = Some(true);
+            self.update.name = Some(name);
+            self.update.user_set_name = Some(true);
         }
         self
     }
@@ -160,64 +171,64 @@ impl<'a> SessionUpdateBuilder<'a> {
     pub fn system_generated_name(mut self, name: impl Into<String>) -> Self {
         let name = name.into().trim().to_string();
         if !name.is_empty() {
-            self.name = Some(name);
-            self.user_set_name = Some(false);
+            self.update.name = Some(name);
+            self.update.user_set_name = Some(false);
         }
         self
     }
 
     pub fn session_type(mut self, session_type: SessionType) -> Self {
-        self.session_type = Some(session_type);
+        self.update.session_type = Some(session_type);
         self
     }
 
     pub fn working_dir(mut self, working_dir: PathBuf) -> Self {
-        self.working_dir = Some(working_dir);
+        self.update.working_dir = Some(working_dir);
         self
     }
 
     pub fn extension_data(mut self, data: ExtensionData) -> Self {
-        self.extension_data = Some(data);
+        self.update.extension_data = Some(data);
         self
     }
 
     pub fn total_tokens(mut self, tokens: Option<i32>) -> Self {
-        self.total_tokens = Some(tokens);
+        self.update.total_tokens = Some(tokens);
         self
     }
 
     pub fn input_tokens(mut self, tokens: Option<i32>) -> Self {
-        self.input_tokens = Some(tokens);
+        self.update.input_tokens = Some(tokens);
         self
     }
 
     pub fn output_tokens(mut self, tokens: Option<i32>) -> Self {
-        self.output_tokens = Some(tokens);
+        self.update.output_tokens = Some(tokens);
         self
     }
 
     pub fn accumulated_total_tokens(mut self, tokens: Option<i32>) -> Self {
-        self.accumulated_total_tokens = Some(tokens);
+        self.update.accumulated_total_tokens = Some(tokens);
         self
     }
 
     pub fn accumulated_input_tokens(mut self, tokens: Option<i32>) -> Self {
-        self.accumulated_input_tokens = Some(tokens);
+        self.update.accumulated_input_tokens = Some(tokens);
         self
     }
 
     pub fn accumulated_output_tokens(mut self, tokens: Option<i32>) -> Self {
-        self.accumulated_output_tokens = Some(tokens);
+        self.update.accumulated_output_tokens = Some(tokens);
         self
     }
 
     pub fn schedule_id(mut self, schedule_id: Option<String>) -> Self {
-        self.schedule_id = Some(schedule_id);
+        self.update.schedule_id = Some(schedule_id);
         self
     }
 
     pub fn recipe(mut self, recipe: Option<Recipe>) -> Self {
-        self.recipe = Some(recipe);
+        self.update.recipe = Some(recipe);
         self
     }
 
@@ -225,39 +236,51 @@ impl<'a> SessionUpdateBuilder<'a> {
         mut self,
         user_recipe_values: Option<HashMap<String, String>>,
     ) -> Self {
-        self.user_recipe_values = Some(user_recipe_values);
+        self.update.user_recipe_values = Some(user_recipe_values);
         self
     }
 
     pub fn provider_name(mut self, provider_name: impl Into<String>) -> Self {
-        self.provider_name = Some(Some(provider_name.into()));
+        self.update.provider_name = Some(Some(provider_name.into()));
         self
     }
 
     pub fn model_config(mut self, model_config: ModelConfig) -> Self {
-        self.model_config = Some(Some(model_config));
+        self.update.model_config = Some(Some(model_config));
+        self
+    }
+
+    /// Replaces all of the session's tags.
+    pub fn tags(mut self, tags: HashMap<String, String>) -> Self {
+        self.update.tags = Some(tags);
         self
     }
 }
 
 pub struct SessionManager {
-    storage: Arc<SessionStorage>,
+    storage: Arc<dyn SessionStore>,
 }
 
 impl SessionManager {
+    /// Sessions kept in the SQLite database under `data_dir`.
     pub fn new(data_dir: PathBuf) -> Self {
         Self {
             storage: Arc::new(SessionStorage::new(data_dir)),
         }
     }
 
+    /// Sessions kept in `store`, such as a database shared by several servers.
+    pub fn with_store(store: Arc<dyn SessionStore>) -> Self {
+        Self { storage: store }
+    }
+
     pub fn instance() -> Self {
         Self {
-            storage: Arc::clone(&SESSION_STORAGE),
+            storage: SESSION_STORAGE.clone(),
         }
     }
 
-    pub fn storage(&self) -> &Arc<SessionStorage> {
+    pub fn storage(&self) -> &Arc<dyn SessionStore> {
         &self.storage
     }
 
@@ -267,9 +290,12 @@ impl SessionManager {
         name: String,
         session_type: SessionType,
     ) -> Result<Session> {
-        self.storage
+        let session = self
+            .storage
             .create_session(working_dir, name, session_type)
-            .await
+            .await?;
+        crate::posthog::emit_session_started();
+        Ok(session)
     }
 
     pub async fn get_session(&self, id: &str, include_messages: bool) -> Result<Session> {
@@ -280,10 +306,6 @@ impl SessionManager {
         SessionUpdateBuilder::new(self, id.to_string())
     }
 
-    async fn apply_update_inner(&self, builder: SessionUpdateBuilder<'_>) -> Result<()> {
-        self.storage.apply_update(builder).await
-    }
-
     pub async fn add_message(&self, id: &str, message: &Message) -> Result<()> {
         self.storage.add_message(id, message).await
     }
@@ -300,24 +322,279 @@ impl SessionManager {
         self.storage.list_sessions_by_types(types).await
     }
 
+    /// Lists the sessions [`SessionManager::list_sessions`] would that carry every tag in `tags`.
+    pub async fn list_sessions_with_tags(
+        &self,
+        tags: &HashMap<String, String>,
+    ) -> Result<Vec<Session>> {
+        let mut sessions = self.list_sessions().await?;
+        sessions.retain(|session| session.has_tags(tags));
+        Ok(sessions)
+    }
+
     pub async fn delete_session(&self, id: &str) -> Result<()> {
         self.storage.delete_session(id).await
     }
 
+    /// Stores a file so it can be attached to the session's messages as
+    /// [`MessageContent::Attachment`]. Files
+    /// over `GOOSE_MAX_ATTACHMENT_SIZE` bytes are refused.
+    pub async fn add_attachment(
+        &self,
+        session_id: &str,
+        source: AttachmentSource,
+        mime_type: Option<String>,
+    ) -> Result<AttachmentContent> {
+        self.storage
+            .add_attachment(session_id, source, mime_type)
+            .await
+    }
+
     pub async fn get_insights(&self) -> Result<SessionInsights> {
         self.storage.get_insights().await
     }
 
+    /// Records one provider call against the session, priced from the bundled pricing table.
+    pub async fn record_usage(
+        &self,
+        session_id: &str,
+        provider_name: &str,
+        usage: &ProviderUsage,
+    ) -> Result<()> {
+        self.storage
+            .record_usage(session_id, provider_name, usage)
+            .await
+    }
+
+    pub async fn session_cost(&self, session_id: &str) -> Result<SessionCostReport> {
+        self.storage.session_cost(session_id).await
+    }
+
+    /// Recorded usage summed per session, day or provider, optionally only since `since`.
+    pub async fn cost_totals(
+        &self,
+        grouping: CostGrouping,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<CostTotal>> {
+        self.storage.cost_totals(grouping, since).await
+    }
+
+    /// Deletes the sessions that fall outside `policy`, except those in `keep`, and returns them.
+    pub async fn prune(
+        &self,
+        policy: &RetentionPolicy,
+        keep: &HashSet<String>,
+    ) -> Result<Vec<PrunedSession>> {
+        self.storage.prune(policy, keep).await
+    }
+
+    /// Prunes with `policy` now and then every `interval`, until the returned task is aborted.
+    /// The sessions `in_use` returns at each pass, such as the ones open on a server, are kept.
+    pub fn spawn_pruning<F, Fut>(
+        &self,
+        policy: RetentionPolicy,
+        interval: std::time::Duration,
+        in_use: F,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = HashSet<String>> + Send,
+    {
+        let session_manager = SessionManager {
+            storage: Arc::clone(&self.storage),
+        };
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let keep = in_use().await;
+                match session_manager.prune(&policy, &keep).await {
+                    Ok(pruned) if !pruned.is_empty() => {
+                        info!(count = pruned.len(), "pruned sessions past retention");
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(error = %e, "failed to prune sessions"),
+                }
+            }
+        })
+    }
+
+    /// Packs a session, with its messages and metadata, into an archive that
+    /// [`SessionManager::import_archive`] can restore on another machine.
+    pub async fn export_archive(&self, id: &str) -> Result<Vec<u8>> {
+        let session = self.get_session(id, true).await?;
+        write_archive(&session)
+    }
+
     pub async fn export_session(&self, id: &str) -> Result<String> {
-        self.storage.export_session(id).await
+        let session = self.get_session(id, true).await?;
+        serde_json::to_string_pretty(&session).map_err(Into::into)
     }
 
     pub async fn import_session(&self, json: &str) -> Result<Session> {
-        self.storage.import_session(self, json).await
+        let import: Session = serde_json::from_str(json)?;
+
+        let session = self
+            .create_session(
+                import.working_dir.clone(),
+                import.name.clone(),
+                import.session_type,
+            )
+            .await?;
+
+        if let Some(conversation) = self.restore_metadata(&session.id, import).await? {
+            self.replace_conversation(&session.id, &conversation)
+                .await?;
+        }
+
+        self.get_session(&session.id, true).await
+    }
+
+    pub async fn import_archive(&self, archive: &[u8], ids: ArchiveIds) -> Result<Session> {
+        let (manifest, mut import, attachments) = read_archive(archive)?;
+        let attachments_dir = self.storage.attachments_dir();
+        if !attachments.is_empty() {
+            fs::create_dir_all(&attachments_dir)?;
+        }
+        for (file_name, data) in attachments {
+            let path = attachments_dir.join(file_name);
+            if !path.exists() {
+                fs::write(path, data)?;
+            }
+        }
+
+        if let Some(conversation) = import.conversation.take() {
+            let mut messages = conversation.messages().clone();
+            for message in &mut messages {
+                if ids == ArchiveIds::Remap {
+                    message.id = None;
+                }
+                for content in &mut message.content {
+                    if let MessageContent::Attachment(attachment) = content {
+                        if let Some(file_name) = attachment.path.file_name() {
+                            attachment.path = attachments_dir.join(file_name);
+                        }
+                    }
+                }
+            }
+            import.conversation = Some(Conversation::new_unvalidated(messages));
+        }
+
+        let id = match ids {
+            ArchiveIds::Preserve => Some(manifest.session_id.as_str()),
+            ArchiveIds::Remap => None,
+        };
+        let session = self.storage.insert_session(id, &import).await?;
+        crate::posthog::emit_session_started();
+        Ok(session)
+    }
+
+    /// Copies everything but the conversation from an imported session, which is returned.
+    async fn restore_metadata(
+        &self,
+        session_id: &str,
+        import: Session,
+    ) -> Result<Option<Conversation>> {
+        let mut builder = self
+            .update(session_id)
+            .extension_data(import.extension_data)
+            .total_tokens(import.total_tokens)
+            .input_tokens(import.input_tokens)
+            .output_tokens(import.output_tokens)
+            .accumulated_total_tokens(import.accumulated_total_tokens)
+            .accumulated_input_tokens(import.accumulated_input_tokens)
+            .accumulated_output_tokens(import.accumulated_output_tokens)
+            .schedule_id(import.schedule_id)
+            .recipe(import.recipe)
+            .user_recipe_values(import.user_recipe_values)
+            .tags(import.tags);
+
+        if import.user_set_name {
+            builder = builder.user_provided_name(import.name.clone());
+        }
+
+        if let Some(provider_name) = import.provider_name {
+            builder = builder.provider_name(provider_name);
+        }
+
+        if let Some(model_config) = import.model_config {
+            builder = builder.model_config(model_config);
+        }
+
+        builder.apply().await?;
+        Ok(import.conversation)
     }
 
     pub async fn copy_session(&self, session_id: &str, new_name: String) -> Result<Session> {
-        self.storage.copy_session(self, session_id, new_name).await
+        let original_session = self.get_session(session_id, true).await?;
+        self.duplicate_session(original_session, new_name).await
+    }
+
+    /// Creates a new session that shares `session_id`'s history up to and including the message
+    /// with id `at_message`, so the conversation can continue differently from there. The
+    /// original session is left untouched.
+    pub async fn fork(&self, session_id: &str, at_message: &str) -> Result<Session> {
+        let mut original_session = self.get_session(session_id, true).await?;
+        let messages = original_session
+            .conversation
+            .take()
+            .map(|conversation| conversation.messages().clone())
+            .unwrap_or_default();
+        let Some(index) = messages
+            .iter()
+            .position(|message| message.id.as_deref() == Some(at_message))
+        else {
+            anyhow::bail!("Message {} not found in session {}", at_message, session_id);
+        };
+
+        let name = original_session.name.clone();
+        let forked = self.duplicate_session(original_session, name).await?;
+        // Added one by one so the fork keeps the original message ids
+        for message in &messages[..=index] {
+            self.add_message(&forked.id, message).await?;
+        }
+
+        self.get_session(&forked.id, true).await
+    }
+
+    async fn duplicate_session(
+        &self,
+        original_session: Session,
+        new_name: String,
+    ) -> Result<Session> {
+        let new_session = self
+            .create_session(
+                original_session.working_dir.clone(),
+                new_name,
+                original_session.session_type,
+            )
+            .await?;
+
+        let mut builder = self
+            .update(&new_session.id)
+            .extension_data(original_session.extension_data)
+            .schedule_id(original_session.schedule_id)
+            .recipe(original_session.recipe)
+            .user_recipe_values(original_session.user_recipe_values)
+            .tags(original_session.tags);
+
+        // Preserve provider and model config from original session
+        if let Some(provider_name) = original_session.provider_name {
+            builder = builder.provider_name(provider_name);
+        }
+
+        if let Some(model_config) = original_session.model_config {
+            builder = builder.model_config(model_config);
+        }
+
+        builder.apply().await?;
+
+        if let Some(conversation) = original_session.conversation {
+            self.replace_conversation(&new_session.id, &conversation)
+                .await?;
+        }
+
+        self.get_session(&new_session.id, true).await
     }
 
     pub async fn truncate_conversation(&self, session_id: &str, timestamp: i64) -> Result<()> {
@@ -358,7 +635,7 @@ impl SessionManager {
         after_date: Option<chrono::DateTime<chrono::Utc>>,
         before_date: Option<chrono::DateTime<chrono::Utc>>,
         exclude_session_id: Option<String>,
-    ) -> Result<crate::session::chat_history_search::ChatRecallResults> {
+    ) -> Result<ChatRecallResults> {
         self.storage
             .search_chat_history(query, limit, after_date, before_date, exclude_session_id)
             .await
@@ -366,13 +643,11 @@ impl SessionManager {
 
     pub async fn update_message_metadata<F>(id: &str, message_id: &str, f: F) -> Result<()>
     where
-        F: FnOnce(
-            crate::conversation::message::MessageMetadata,
-        ) -> crate::conversation::message::MessageMetadata,
+        F: FnOnce(MessageMetadata) -> MessageMetadata + Send + 'static,
     {
         Self::instance()
             .storage
-            .update_message_metadata(id, message_id, f)
+            .update_message_metadata(id, message_id, Box::new(f))
             .await
     }
 }
@@ -381,6 +656,8 @@ pub struct SessionStorage {
     pool: Pool<Sqlite>,
     initialized: tokio::sync::OnceCell<()>,
     session_dir: PathBuf,
+    // Held while a file is stored and claimed, and while unclaimed files are removed
+    attachments_lock: tokio::sync::Mutex<()>,
 }
 
 fn role_to_string(role: &Role) -> &'static str {
@@ -414,11 +691,17 @@ impl Default for Session {
             message_count: 0,
             provider_name: None,
             model_config: None,
+            tags: HashMap::new(),
         }
     }
 }
 
 impl Session {
+    pub fn has_tags(&self, tags: &HashMap<String, String>) -> bool {
+        tags.iter()
+            .all(|(key, value)| self.tags.get(key) == Some(value))
+    }
+
     pub fn without_messages(mut self) -> Self {
         self.conversation = None;
         self
@@ -439,6 +722,11 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for Session {
         let model_config_json: Option<String> = row.try_get("model_config_json").ok().flatten();
         let model_config = model_config_json.and_then(|json| serde_json::from_str(&json).ok());
 
+        let tags_json: Option<String> = row.try_get("tags_json").ok().flatten();
+        let tags = tags_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
         let name: String = {
             let name_val: String = row.try_get("name").unwrap_or_default();
             if !name_val.is_empty() {
@@ -478,6 +766,7 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for Session {
             message_count: row.try_get("message_count").unwrap_or(0) as usize,
             provider_name: row.try_get("provider_name").ok().flatten(),
             model_config,
+            tags,
         })
     }
 }
@@ -504,6 +793,7 @@ impl SessionStorage {
             pool: Self::create_pool(&db_path),
             initialized: tokio::sync::OnceCell::new(),
             session_dir,
+            attachments_lock: tokio::sync::Mutex::new(()),
         }
     }
 
@@ -576,7 +866,8 @@ impl SessionStorage {
                 recipe_json TEXT,
                 user_recipe_values_json TEXT,
                 provider_name TEXT,
-                model_config_json TEXT
+                model_config_json TEXT,
+                tags_json TEXT NOT NULL DEFAULT '{}'
             )
         "#,
         )
@@ -601,6 +892,9 @@ impl SessionStorage {
         .execute(pool)
         .await?;
 
+        Self::create_usage_table(pool).await?;
+        Self::create_attachments_table(pool).await?;
+
         sqlx::query("CREATE INDEX idx_messages_session ON messages(session_id)")
             .execute(pool)
             .await?;
@@ -620,6 +914,105 @@ impl SessionStorage {
         Ok(())
     }
 
+    // No foreign key on purpose: usage outlives deleted sessions so daily and per-provider
+    // totals keep covering everything that was spent.
+    async fn create_usage_table(pool: &Pool<Sqlite>) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE session_usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                provider_name TEXT NOT NULL,
+                model TEXT NOT NULL,
+                input_tokens INTEGER NOT NULL DEFAULT 0,
+                output_tokens INTEGER NOT NULL DEFAULT 0,
+                cost_usd REAL,
+                created_timestamp INTEGER NOT NULL
+            )
+        "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX idx_session_usage_session ON session_usage(session_id)")
+            .execute(pool)
+            .await?;
+        sqlx::query("CREATE INDEX idx_session_usage_created ON session_usage(created_timestamp)")
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Which sessions use which stored attachment. A session claims a file as soon as it's
+    /// stored, before any of its messages refer to it, so cleanup after another session is
+    /// deleted can't remove it in between.
+    async fn create_attachments_table(pool: &Pool<Sqlite>) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE session_attachments (
+                session_id TEXT NOT NULL,
+                file_name TEXT NOT NULL,
+                PRIMARY KEY (session_id, file_name)
+            )
+        "#,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert_message(
+        conn: &mut SqliteConnection,
+        session_id: &str,
+        message: &Message,
+    ) -> Result<()> {
+        let metadata_json = serde_json::to_string(&message.metadata)?;
+
+        let message_id = message
+            .id
+            .clone()
+            .unwrap_or_else(|| format!("msg_{}_{}", session_id, uuid::Uuid::new_v4()));
+
+        sqlx::query(
+            r#"
+            INSERT INTO messages (message_id, session_id, role, content_json, created_timestamp, metadata_json)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+        )
+        .bind(message_id)
+        .bind(session_id)
+        .bind(role_to_string(&message.role))
+        .bind(serde_json::to_string(&message.content)?)
+        .bind(message.created)
+        .bind(metadata_json)
+        .execute(&mut *conn)
+        .await?;
+        Self::claim_attachments(conn, session_id, &message.content).await
+    }
+
+    async fn claim_attachments(
+        conn: &mut SqliteConnection,
+        session_id: &str,
+        content: &[MessageContent],
+    ) -> Result<()> {
+        for content in content {
+            let MessageContent::Attachment(attachment) = content else {
+                continue;
+            };
+            let Some(file_name) = attachment.path.file_name() else {
+                continue;
+            };
+            sqlx::query(
+                "INSERT OR IGNORE INTO session_attachments (session_id, file_name) VALUES (?, ?)",
+            )
+            .bind(session_id)
+            .bind(file_name.to_string_lossy())
+            .execute(&mut *conn)
+            .await?;
+        }
+        Ok(())
+    }
+
     async fn import_legacy(pool: &Pool<Sqlite>, session_dir: &PathBuf) -> Result<()> {
         use crate::session::legacy;
 
@@ -878,6 +1271,33 @@ impl SessionStorage {
                     .execute(pool)
                     .await?;
             }
+            8 => {
+                sqlx::query(
+                    r#"
+                    ALTER TABLE sessions ADD COLUMN tags_json TEXT NOT NULL DEFAULT '{}'
+                "#,
+                )
+                .execute(pool)
+                .await?;
+            }
+            9 => {
+                Self::create_usage_table(pool).await?;
+            }
+            10 => {
+                Self::create_attachments_table(pool).await?;
+                let rows = sqlx::query_as::<_, (String, String)>(
+                    r#"SELECT session_id, content_json FROM messages WHERE content_json LIKE '%"type":"attachment"%'"#,
+                )
+                .fetch_all(pool)
+                .await?;
+                let mut conn = pool.acquire().await?;
+                for (session_id, content_json) in rows {
+                    if let Ok(content) = serde_json::from_str::<Vec<MessageContent>>(&content_json)
+                    {
+                        Self::claim_attachments(&mut conn, &session_id, &content).await?;
+                    }
+                }
+            }
             _ => {
                 anyhow::bail!("Unknown migration version: {}", version);
             }
@@ -886,15 +1306,120 @@ impl SessionStorage {
         Ok(())
     }
 
-    async fn create_session(
-        &self,
-        working_dir: PathBuf,
-        name: String,
-        session_type: SessionType,
-    ) -> Result<Session> {
+    async fn get_conversation(&self, session_id: &str) -> Result<Conversation> {
         let pool = self.pool().await?;
-        let mut tx = pool.begin().await?;
-
+        let rows = sqlx::query_as::<_, (String, String, i64, Option<String>, Option<String>)>(
+            "SELECT role, content_json, created_timestamp, metadata_json, message_id FROM messages WHERE session_id = ? ORDER BY timestamp",
+        )
+            .bind(session_id)
+            .fetch_all(pool)
+            .await?;
+
+        let mut messages = Vec::new();
+        for (role_str, content_json, created_timestamp, metadata_json, message_id) in
+            rows.into_iter()
+        {
+            let role = match role_str.as_str() {
+                "user" => Role::User,
+                "assistant" => Role::Assistant,
+                _ => continue,
+            };
+
+            let content = serde_json::from_str(&content_json)?;
+            let metadata = metadata_json
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
+
+            let mut message = Message::new(role, created_timestamp, content);
+            message.metadata = metadata;
+            if let Some(id) = message_id {
+                message = message.with_id(id);
+            }
+            messages.push(message);
+        }
+
+        Ok(Conversation::new_unvalidated(messages))
+    }
+
+    async fn replace_conversation_inner(
+        pool: &Pool<Sqlite>,
+        session_id: &str,
+        conversation: &Conversation,
+    ) -> Result<()> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query("DELETE FROM messages WHERE session_id = ?")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for message in conversation.messages() {
+            let metadata_json = serde_json::to_string(&message.metadata)?;
+
+            sqlx::query(
+                r#"
+            INSERT INTO messages (session_id, role, content_json, created_timestamp, metadata_json)
+            VALUES (?, ?, ?, ?, ?)
+        "#,
+            )
+            .bind(session_id)
+            .bind(role_to_string(&message.role))
+            .bind(serde_json::to_string(&message.content)?)
+            .bind(message.created)
+            .bind(metadata_json)
+            .execute(&mut *tx)
+            .await?;
+            Self::claim_attachments(&mut tx, session_id, &message.content).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Attachments are shared between sessions, so a file goes once no session claims it.
+    /// Failures only leave files behind, so they are logged rather than returned.
+    async fn remove_unreferenced_attachments(&self) {
+        let _guard = self.attachments_lock.lock().await;
+        let Ok(pool) = self.pool().await else {
+            return;
+        };
+        let claimed: HashSet<String> =
+            match sqlx::query_scalar("SELECT DISTINCT file_name FROM session_attachments")
+                .fetch_all(pool)
+                .await
+            {
+                Ok(claimed) => claimed.into_iter().collect(),
+                Err(e) => {
+                    warn!("Failed to list attachments in use: {}", e);
+                    return;
+                }
+            };
+        let Ok(entries) = fs::read_dir(self.attachments_dir()) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if claimed.contains(&file_name) {
+                continue;
+            }
+            if let Err(e) = fs::remove_file(entry.path()) {
+                warn!("Failed to remove attachment {}: {}", file_name, e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for SessionStorage {
+    async fn create_session(
+        &self,
+        working_dir: PathBuf,
+        name: String,
+        session_type: SessionType,
+    ) -> Result<Session> {
+        let pool = self.pool().await?;
+        let mut tx = pool.begin().await?;
+
         let today = chrono::Utc::now().format("%Y%m%d").to_string();
         let session = sqlx::query_as(
             r#"
@@ -923,10 +1448,104 @@ impl SessionStorage {
             .await?;
 
         tx.commit().await?;
-        crate::posthog::emit_session_started();
         Ok(session)
     }
 
+    async fn insert_session(&self, id: Option<&str>, session: &Session) -> Result<Session> {
+        let pool = self.pool().await?;
+        let mut tx = pool.begin().await?;
+
+        let id = match id {
+            Some(id) => {
+                let exists: bool =
+                    sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ?)")
+                        .bind(id)
+                        .fetch_one(&mut *tx)
+                        .await?;
+                if exists {
+                    anyhow::bail!("Session {} already exists", id);
+                }
+                id.to_string()
+            }
+            None => {
+                let today = chrono::Utc::now().format("%Y%m%d").to_string();
+                sqlx::query_scalar(
+                    r#"
+                    SELECT ? || '_' || CAST(COALESCE((
+                        SELECT MAX(CAST(SUBSTR(id, 10) AS INTEGER))
+                        FROM sessions
+                        WHERE id LIKE ? || '_%'
+                    ), 0) + 1 AS TEXT)
+                    "#,
+                )
+                .bind(&today)
+                .bind(&today)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (
+                id, name, user_set_name, session_type, working_dir, extension_data,
+                total_tokens, input_tokens, output_tokens,
+                accumulated_total_tokens, accumulated_input_tokens, accumulated_output_tokens,
+                schedule_id, recipe_json, user_recipe_values_json,
+                provider_name, model_config_json, tags_json
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&id)
+        .bind(&session.name)
+        .bind(session.user_set_name)
+        .bind(session.session_type.to_string())
+        .bind(session.working_dir.to_string_lossy().as_ref())
+        .bind(serde_json::to_string(&session.extension_data)?)
+        .bind(session.total_tokens)
+        .bind(session.input_tokens)
+        .bind(session.output_tokens)
+        .bind(session.accumulated_total_tokens)
+        .bind(session.accumulated_input_tokens)
+        .bind(session.accumulated_output_tokens)
+        .bind(&session.schedule_id)
+        .bind(
+            session
+                .recipe
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(
+            session
+                .user_recipe_values
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(&session.provider_name)
+        .bind(
+            session
+                .model_config
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(serde_json::to_string(&session.tags)?)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(conversation) = &session.conversation {
+            for message in conversation.messages() {
+                Self::insert_message(&mut tx, &id, message).await?;
+            }
+        }
+
+        tx.commit().await?;
+        self.get_session(&id, true).await
+    }
+
     async fn get_session(&self, id: &str, include_messages: bool) -> Result<Session> {
         let pool = self.pool().await?;
         let mut session = sqlx::query_as::<_, Session>(
@@ -935,7 +1554,7 @@ impl SessionStorage {
                total_tokens, input_tokens, output_tokens,
                accumulated_total_tokens, accumulated_input_tokens, accumulated_output_tokens,
                schedule_id, recipe_json, user_recipe_values_json,
-               provider_name, model_config_json
+               provider_name, model_config_json, tags_json
         FROM sessions
         WHERE id = ?
     "#,
@@ -962,7 +1581,7 @@ impl SessionStorage {
     }
 
     #[allow(clippy::too_many_lines)]
-    async fn apply_update(&self, builder: SessionUpdateBuilder<'_>) -> Result<()> {
+    async fn apply_update(&self, session_id: &str, update: SessionUpdate) -> Result<()> {
         let mut updates = Vec::new();
         let mut query = String::from("UPDATE sessions SET ");
 
@@ -979,25 +1598,26 @@ impl SessionStorage {
             };
         }
 
-        add_update!(builder.name, "name");
-        add_update!(builder.user_set_name, "user_set_name");
-        add_update!(builder.session_type, "session_type");
-        add_update!(builder.working_dir, "working_dir");
-        add_update!(builder.extension_data, "extension_data");
-        add_update!(builder.total_tokens, "total_tokens");
-        add_update!(builder.input_tokens, "input_tokens");
-        add_update!(builder.output_tokens, "output_tokens");
-        add_update!(builder.accumulated_total_tokens, "accumulated_total_tokens");
-        add_update!(builder.accumulated_input_tokens, "accumulated_input_tokens");
+        add_update!(update.name, "name");
+        add_update!(update.user_set_name, "user_set_name");
+        add_update!(update.session_type, "session_type");
+        add_update!(update.working_dir, "working_dir");
+        add_update!(update.extension_data, "extension_data");
+        add_update!(update.total_tokens, "total_tokens");
+        add_update!(update.input_tokens, "input_tokens");
+        add_update!(update.output_tokens, "output_tokens");
+        add_update!(update.accumulated_total_tokens, "accumulated_total_tokens");
+        add_update!(update.accumulated_input_tokens, "accumulated_input_tokens");
         add_update!(
-            builder.accumulated_output_tokens,
+            update.accumulated_output_tokens,
             "accumulated_output_tokens"
         );
-        add_update!(builder.schedule_id, "schedule_id");
-        add_update!(builder.recipe, "recipe_json");
-        add_update!(builder.user_recipe_values, "user_recipe_values_json");
-        add_update!(builder.provider_name, "provider_name");
-        add_update!(builder.model_config, "model_config_json");
+        add_update!(update.schedule_id, "schedule_id");
+        add_update!(update.recipe, "recipe_json");
+        add_update!(update.user_recipe_values, "user_recipe_values_json");
+        add_update!(update.provider_name, "provider_name");
+        add_update!(update.model_config, "model_config_json");
+        add_update!(update.tags, "tags_json");
 
         if updates.is_empty() {
             return Ok(());
@@ -1008,131 +1628,79 @@ impl SessionStorage {
 
         let mut q = sqlx::query(&query);
 
-        if let Some(name) = builder.name {
+        if let Some(name) = update.name {
             q = q.bind(name);
         }
-        if let Some(user_set_name) = builder.user_set_name {
+        if let Some(user_set_name) = update.user_set_name {
             q = q.bind(user_set_name);
         }
-        if let Some(session_type) = builder.session_type {
+        if let Some(session_type) = update.session_type {
             q = q.bind(session_type.to_string());
         }
-        if let Some(wd) = builder.working_dir {
+        if let Some(wd) = update.working_dir {
             q = q.bind(wd.to_string_lossy().to_string());
         }
-        if let Some(ed) = builder.extension_data {
+        if let Some(ed) = update.extension_data {
             q = q.bind(serde_json::to_string(&ed)?);
         }
-        if let Some(tt) = builder.total_tokens {
+        if let Some(tt) = update.total_tokens {
             q = q.bind(tt);
         }
-        if let Some(it) = builder.input_tokens {
+        if let Some(it) = update.input_tokens {
             q = q.bind(it);
         }
-        if let Some(ot) = builder.output_tokens {
+        if let Some(ot) = update.output_tokens {
             q = q.bind(ot);
         }
-        if let Some(att) = builder.accumulated_total_tokens {
+        if let Some(att) = update.accumulated_total_tokens {
             q = q.bind(att);
         }
-        if let Some(ait) = builder.accumulated_input_tokens {
+        if let Some(ait) = update.accumulated_input_tokens {
             q = q.bind(ait);
         }
-        if let Some(aot) = builder.accumulated_output_tokens {
+        if let Some(aot) = update.accumulated_output_tokens {
             q = q.bind(aot);
         }
-        if let Some(sid) = builder.schedule_id {
+        if let Some(sid) = update.schedule_id {
             q = q.bind(sid);
         }
-        if let Some(recipe) = builder.recipe {
+        if let Some(recipe) = update.recipe {
             let recipe_json = recipe.map(|r| serde_json::to_string(&r)).transpose()?;
             q = q.bind(recipe_json);
         }
-        if let Some(user_recipe_values) = builder.user_recipe_values {
+        if let Some(user_recipe_values) = update.user_recipe_values {
             let user_recipe_values_json = user_recipe_values
                 .map(|urv| serde_json::to_string(&urv))
                 .transpose()?;
             q = q.bind(user_recipe_values_json);
         }
-        if let Some(provider_name) = builder.provider_name {
+        if let Some(provider_name) = update.provider_name {
             q = q.bind(provider_name);
         }
-        if let Some(model_config) = builder.model_config {
+        if let Some(model_config) = update.model_config {
             let model_config_json = model_config
                 .map(|mc| serde_json::to_string(&mc))
                 .transpose()?;
             q = q.bind(model_config_json);
         }
+        if let Some(tags) = update.tags {
+            q = q.bind(serde_json::to_string(&tags)?);
+        }
 
         let pool = self.pool().await?;
         let mut tx = pool.begin().await?;
-        q = q.bind(&builder.session_id);
+        q = q.bind(session_id);
         q.execute(&mut *tx).await?;
 
         tx.commit().await?;
         Ok(())
     }
 
-    async fn get_conversation(&self, session_id: &str) -> Result<Conversation> {
-        let pool = self.pool().await?;
-        let rows = sqlx::query_as::<_, (String, String, i64, Option<String>, Option<String>)>(
-            "SELECT role, content_json, created_timestamp, metadata_json, message_id FROM messages WHERE session_id = ? ORDER BY timestamp",
-        )
-            .bind(session_id)
-            .fetch_all(pool)
-            .await?;
-
-        let mut messages = Vec::new();
-        for (role_str, content_json, created_timestamp, metadata_json, message_id) in
-            rows.into_iter()
-        {
-            let role = match role_str.as_str() {
-                "user" => Role::User,
-                "assistant" => Role::Assistant,
-                _ => continue,
-            };
-
-            let content = serde_json::from_str(&content_json)?;
-            let metadata = metadata_json
-                .and_then(|json| serde_json::from_str(&json).ok())
-                .unwrap_or_default();
-
-            let mut message = Message::new(role, created_timestamp, content);
-            message.metadata = metadata;
-            if let Some(id) = message_id {
-                message = message.with_id(id);
-            }
-            messages.push(message);
-        }
-
-        Ok(Conversation::new_unvalidated(messages))
-    }
-
     async fn add_message(&self, session_id: &str, message: &Message) -> Result<()> {
         let pool = self.pool().await?;
         let mut tx = pool.begin().await?;
 
-        let metadata_json = serde_json::to_string(&message.metadata)?;
-
-        let message_id = message
-            .id
-            .clone()
-            .unwrap_or_else(|| format!("msg_{}_{}", session_id, uuid::Uuid::new_v4()));
-
-        sqlx::query(
-            r#"
-            INSERT INTO messages (message_id, session_id, role, content_json, created_timestamp, metadata_json)
-            VALUES (?, ?, ?, ?, ?, ?)
-        "#,
-        )
-        .bind(message_id)
-        .bind(session_id)
-        .bind(role_to_string(&message.role))
-        .bind(serde_json::to_string(&message.content)?)
-        .bind(message.created)
-        .bind(metadata_json)
-        .execute(&mut *tx)
-        .await?;
+        Self::insert_message(&mut tx, session_id, message).await?;
 
         sqlx::query("UPDATE sessions SET updated_at = datetime('now') WHERE id = ?")
             .bind(session_id)
@@ -1143,41 +1711,7 @@ impl SessionStorage {
         Ok(())
     }
 
-    async fn replace_conversation_inner(
-        pool: &Pool<Sqlite>,
-        session_id: &str,
-        conversation: &Conversation,
-    ) -> Result<()> {
-        let mut tx = pool.begin().await?;
-
-        sqlx::query("DELETE FROM messages WHERE session_id = ?")
-            .bind(session_id)
-            .execute(&mut *tx)
-            .await?;
-
-        for message in conversation.messages() {
-            let metadata_json = serde_json::to_string(&message.metadata)?;
-
-            sqlx::query(
-                r#"
-            INSERT INTO messages (session_id, role, content_json, created_timestamp, metadata_json)
-            VALUES (?, ?, ?, ?, ?)
-        "#,
-            )
-            .bind(session_id)
-            .bind(role_to_string(&message.role))
-            .bind(serde_json::to_string(&message.content)?)
-            .bind(message.created)
-            .bind(metadata_json)
-            .execute(&mut *tx)
-            .await?;
-        }
-
-        tx.commit().await?;
-        Ok(())
-    }
-
-    pub async fn replace_conversation(
+    async fn replace_conversation(
         &self,
         session_id: &str,
         conversation: &Conversation,
@@ -1198,7 +1732,7 @@ impl SessionStorage {
                    s.total_tokens, s.input_tokens, s.output_tokens,
                    s.accumulated_total_tokens, s.accumulated_input_tokens, s.accumulated_output_tokens,
                    s.schedule_id, s.recipe_json, s.user_recipe_values_json,
-                   s.provider_name, s.model_config_json,
+                   s.provider_name, s.model_config_json, s.tags_json,
                    COUNT(m.id) as message_count
             FROM sessions s
             INNER JOIN messages m ON s.id = m.session_id
@@ -1218,11 +1752,6 @@ impl SessionStorage {
         q.fetch_all(pool).await.map_err(Into::into)
     }
 
-    async fn list_sessions(&self) -> Result<Vec<Session>> {
-        self.list_sessions_by_types(&[SessionType::User, SessionType::Scheduled])
-            .await
-    }
-
     async fn delete_session(&self, session_id: &str) -> Result<()> {
         let pool = self.pool().await?;
         let mut tx = pool.begin().await?;
@@ -1242,120 +1771,223 @@ impl SessionStorage {
             .execute(&mut *tx)
             .await?;
 
+        sqlx::query("DELETE FROM session_attachments WHERE session_id = ?")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+
         sqlx::query("DELETE FROM sessions WHERE id = ?")
             .bind(session_id)
             .execute(&mut *tx)
             .await?;
 
         tx.commit().await?;
+        self.remove_unreferenced_attachments().await;
         Ok(())
     }
 
-    async fn get_insights(&self) -> Result<SessionInsights> {
+    async fn prune(
+        &self,
+        policy: &RetentionPolicy,
+        keep: &HashSet<String>,
+    ) -> Result<Vec<PrunedSession>> {
+        if policy.is_unbounded() {
+            return Ok(Vec::new());
+        }
+
         let pool = self.pool().await?;
-        let row = sqlx::query_as::<_, (i64, Option<i64>)>(
+        let rows = sqlx::query_as::<_, (String, String, DateTime<Utc>, i64)>(
             r#"
-            SELECT COUNT(*) as total_sessions,
-                   COALESCE(SUM(COALESCE(accumulated_total_tokens, total_tokens, 0)), 0) as total_tokens
-            FROM sessions
+            SELECT s.id, s.name, s.updated_at,
+                   COALESCE(SUM(LENGTH(m.content_json) + COALESCE(LENGTH(m.metadata_json), 0)), 0)
+            FROM sessions s
+            LEFT JOIN messages m ON s.id = m.session_id
+            GROUP BY s.id
+            ORDER BY s.updated_at DESC, s.id DESC
             "#,
         )
-            .fetch_one(pool)
-            .await?;
+        .fetch_all(pool)
+        .await?;
 
-        Ok(SessionInsights {
-            total_sessions: row.0 as usize,
-            total_tokens: row.1.unwrap_or(0),
-        })
+        let sessions = rows
+            .into_iter()
+            .map(|(id, name, updated_at, size_bytes)| PrunedSession {
+                id,
+                name,
+                updated_at,
+                size_bytes: size_bytes.max(0) as u64,
+            })
+            .collect();
+        let mut pruned = policy.select(sessions, Utc::now());
+        pruned.retain(|session| !keep.contains(&session.id));
+
+        let mut tx = pool.begin().await?;
+        for session in &pruned {
+            sqlx::query("DELETE FROM messages WHERE session_id = ?")
+                .bind(&session.id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM session_attachments WHERE session_id = ?")
+                .bind(&session.id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM sessions WHERE id = ?")
+                .bind(&session.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        if !pruned.is_empty() {
+            self.remove_unreferenced_attachments().await;
+        }
+
+        Ok(pruned)
     }
 
-    async fn export_session(&self, id: &str) -> Result<String> {
-        let session = self.get_session(id, true).await?;
-        serde_json::to_string_pretty(&session).map_err(Into::into)
+    fn attachments_dir(&self) -> PathBuf {
+        self.session_dir.join(ATTACHMENTS_FOLDER)
     }
 
-    async fn import_session(
+    async fn add_attachment(
         &self,
-        session_manager: &SessionManager,
-        json: &str,
-    ) -> Result<Session> {
-        let import: Session = serde_json::from_str(json)?;
-
-        let session = self
-            .create_session(
-                import.working_dir.clone(),
-                import.name.clone(),
-                import.session_type,
-            )
-            .await?;
-
-        let mut builder = session_manager
-            .update(&session.id)
-            .extension_data(import.extension_data)
-            .total_tokens(import.total_tokens)
-            .input_tokens(import.input_tokens)
-            .output_tokens(import.output_tokens)
-            .accumulated_total_tokens(import.accumulated_total_tokens)
-            .accumulated_input_tokens(import.accumulated_input_tokens)
-            .accumulated_output_tokens(import.accumulated_output_tokens)
-            .schedule_id(import.schedule_id)
-            .recipe(import.recipe)
-            .user_recipe_values(import.user_recipe_values);
-
-        if import.user_set_name {
-            builder = builder.user_provided_name(import.name.clone());
-        }
-
-        builder.apply().await?;
-
-        if let Some(conversation) = import.conversation {
-            self.replace_conversation(&session.id, &conversation)
-                .await?;
-        }
+        session_id: &str,
+        source: AttachmentSource,
+        mime_type: Option<String>,
+    ) -> Result<AttachmentContent> {
+        self.get_session(session_id, false).await?;
+        let max_size = Config::global()
+            .get_param::<u64>(MAX_ATTACHMENT_SIZE_CONFIG_KEY)
+            .unwrap_or(DEFAULT_MAX_ATTACHMENT_SIZE);
+        let dir = self.attachments_dir();
+        let _guard = self.attachments_lock.lock().await;
+        let attachment = tokio::task::spawn_blocking(move || {
+            store_attachment(&dir, source, mime_type, max_size)
+        })
+        .await??;
 
-        self.get_session(&session.id, true).await
+        let mut conn = self.pool().await?.acquire().await?;
+        Self::claim_attachments(
+            &mut conn,
+            session_id,
+            &[MessageContent::Attachment(attachment.clone())],
+        )
+        .await?;
+        Ok(attachment)
     }
 
-    async fn copy_session(
+    async fn record_usage(
         &self,
-        session_manager: &SessionManager,
         session_id: &str,
-        new_name: String,
-    ) -> Result<Session> {
-        let original_session = self.get_session(session_id, true).await?;
+        provider_name: &str,
+        usage: &ProviderUsage,
+    ) -> Result<()> {
+        let input_tokens = usage.usage.input_tokens.unwrap_or(0).max(0);
+        let output_tokens = usage.usage.output_tokens.unwrap_or(0).max(0);
+        let cost_usd = estimate_cost_usd(
+            provider_name,
+            &usage.model,
+            input_tokens as usize,
+            output_tokens as usize,
+        );
 
-        let new_session = self
-            .create_session(
-                original_session.working_dir.clone(),
-                new_name,
-                original_session.session_type,
-            )
-            .await?;
+        let pool = self.pool().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO session_usage (session_id, provider_name, model, input_tokens, output_tokens, cost_usd, created_timestamp)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(session_id)
+        .bind(provider_name)
+        .bind(&usage.model)
+        .bind(input_tokens)
+        .bind(output_tokens)
+        .bind(cost_usd)
+        .bind(Utc::now().timestamp())
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
 
-        let mut builder = session_manager
-            .update(&new_session.id)
-            .extension_data(original_session.extension_data)
-            .schedule_id(original_session.schedule_id)
-            .recipe(original_session.recipe)
-            .user_recipe_values(original_session.user_recipe_values);
+    async fn session_cost(&self, session_id: &str) -> Result<SessionCostReport> {
+        let pool = self.pool().await?;
+        let rows = sqlx::query_as::<_, (String, String, i64, i64, Option<f64>)>(
+            r#"
+            SELECT provider_name, model, SUM(input_tokens), SUM(output_tokens), SUM(cost_usd)
+            FROM session_usage
+            WHERE session_id = ?
+            GROUP BY provider_name, model
+            ORDER BY provider_name, model
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(pool)
+        .await?;
 
-        // Preserve provider and model config from original session
-        if let Some(provider_name) = original_session.provider_name {
-            builder = builder.provider_name(provider_name);
-        }
+        let by_model = rows
+            .into_iter()
+            .map(
+                |(provider_name, model, input_tokens, output_tokens, cost_usd)| ModelCost {
+                    provider_name,
+                    model,
+                    input_tokens,
+                    output_tokens,
+                    cost_usd,
+                },
+            )
+            .collect();
+        Ok(SessionCostReport::new(session_id.to_string(), by_model))
+    }
 
-        if let Some(model_config) = original_session.model_config {
-            builder = builder.model_config(model_config);
-        }
+    async fn cost_totals(
+        &self,
+        grouping: CostGrouping,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<CostTotal>> {
+        let key = grouping.key_sql();
+        let query = format!(
+            r#"
+            SELECT {key}, SUM(input_tokens), SUM(output_tokens), SUM(cost_usd)
+            FROM session_usage
+            WHERE created_timestamp >= ?
+            GROUP BY {key}
+            ORDER BY {key}
+            "#
+        );
 
-        builder.apply().await?;
+        let pool = self.pool().await?;
+        let rows = sqlx::query_as::<_, (String, i64, i64, Option<f64>)>(&query)
+            .bind(since.map_or(i64::MIN, |since| since.timestamp()))
+            .fetch_all(pool)
+            .await?;
 
-        if let Some(conversation) = original_session.conversation {
-            self.replace_conversation(&new_session.id, &conversation)
-                .await?;
-        }
+        Ok(rows
+            .into_iter()
+            .map(|(key, input_tokens, output_tokens, cost_usd)| CostTotal {
+                key,
+                input_tokens,
+                output_tokens,
+                cost_usd,
+            })
+            .collect())
+    }
 
-        self.get_session(&new_session.id, true).await
+    async fn get_insights(&self) -> Result<SessionInsights> {
+        let pool = self.pool().await?;
+        let row = sqlx::query_as::<_, (i64, Option<i64>)>(
+            r#"
+            SELECT COUNT(*) as total_sessions,
+                   COALESCE(SUM(COALESCE(accumulated_total_tokens, total_tokens, 0)), 0) as total_tokens
+            FROM sessions
+            "#,
+        )
+            .fetch_one(pool)
+            .await?;
+
+        Ok(SessionInsights {
+            total_sessions: row.0 as usize,
+            total_tokens: row.1.unwrap_or(0),
+        })
     }
 
     async fn truncate_conversation(&self, session_id: &str, timestamp: i64) -> Result<()> {
@@ -1376,7 +2008,7 @@ impl SessionStorage {
         after_date: Option<chrono::DateTime<chrono::Utc>>,
         before_date: Option<chrono::DateTime<chrono::Utc>>,
         exclude_session_id: Option<String>,
-    ) -> Result<crate::session::chat_history_search::ChatRecallResults> {
+    ) -> Result<ChatRecallResults> {
         use crate::session::chat_history_search::ChatHistorySearch;
 
         let pool = self.pool().await?;
@@ -1392,17 +2024,12 @@ impl SessionStorage {
         .await
     }
 
-    async fn update_message_metadata<F>(
+    async fn update_message_metadata(
         &self,
         session_id: &str,
         message_id: &str,
-        f: F,
-    ) -> Result<()>
-    where
-        F: FnOnce(
-            crate::conversation::message::MessageMetadata,
-        ) -> crate::conversation::message::MessageMetadata,
-    {
+        f: MetadataUpdate,
+    ) -> Result<()> {
         let mut tx = self.pool.begin().await?;
 
         let current_metadata_json = sqlx::query_scalar::<_, String>(
@@ -1438,6 +2065,7 @@ impl SessionStorage {
 mod tests {
     use super::*;
     use crate::conversation::message::{Message, MessageContent};
+    use crate::session::attachments::AttachmentSource;
     use tempfile::TempDir;
 
     const NUM_CONCURRENT_SESSIONS: i32 = 10;
@@ -1601,6 +2229,352 @@ mod tests {
         assert_eq!(conversation.messages()[1].role, Role::Assistant);
     }
 
+    #[tokio::test]
+    async fn test_fork_at_message() {
+        let temp_dir = TempDir::new().unwrap();
+        let sm = SessionManager::new(temp_dir.path().to_path_buf());
+        let original = sm
+            .create_session(
+                PathBuf::from("/tmp/test"),
+                "Original".to_string(),
+                SessionType::User,
+            )
+            .await
+            .unwrap();
+        for (id, text) in [("m1", "first"), ("m2", "second"), ("m3", "third")] {
+            sm.add_message(&original.id, &Message::user().with_text(text).with_id(id))
+                .await
+                .unwrap();
+        }
+
+        let forked = sm.fork(&original.id, "m2").await.unwrap();
+
+        assert_ne!(forked.id, original.id);
+        assert_eq!(forked.name, "Original");
+        let ids: Vec<_> = forked
+            .conversation
+            .unwrap()
+            .messages()
+            .iter()
+            .map(|message| message.id.clone().unwrap())
+            .collect();
+        assert_eq!(ids, ["m1", "m2"]);
+        let original = sm.get_session(&original.id, true).await.unwrap();
+        assert_eq!(original.message_count, 3);
+        assert!(sm.fork(&original.id, "missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_archive_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let sm = SessionManager::new(temp_dir.path().join("a"));
+        let original = sm
+            .create_session(
+                PathBuf::from("/tmp/test"),
+                "Original".to_string(),
+                SessionType::User,
+            )
+            .await
+            .unwrap();
+        sm.update(&original.id)
+            .provider_name("openai")
+            .total_tokens(Some(42))
+            .apply()
+            .await
+            .unwrap();
+        sm.add_message(&original.id, &Message::user().with_text("hi").with_id("m1"))
+            .await
+            .unwrap();
+        let archive = sm.export_archive(&original.id).await.unwrap();
+
+        let remapped = sm
+            .import_archive(&archive, ArchiveIds::Remap)
+            .await
+            .unwrap();
+        assert_ne!(remapped.id, original.id);
+        assert_eq!(remapped.provider_name.as_deref(), Some("openai"));
+        assert_eq!(remapped.total_tokens, Some(42));
+        let message = remapped.conversation.unwrap().messages()[0].clone();
+        assert_ne!(message.id.as_deref(), Some("m1"));
+        assert!(sm
+            .import_archive(&archive, ArchiveIds::Preserve)
+            .await
+            .is_err());
+
+        let elsewhere = SessionManager::new(temp_dir.path().join("b"));
+        let preserved = elsewhere
+            .import_archive(&archive, ArchiveIds::Preserve)
+            .await
+            .unwrap();
+        assert_eq!(preserved.id, original.id);
+        assert_eq!(preserved.name, "Original");
+        let message = preserved.conversation.unwrap().messages()[0].clone();
+        assert_eq!(message.id.as_deref(), Some("m1"));
+        assert_eq!(message.as_concat_text(), "hi");
+    }
+
+    #[tokio::test]
+    async fn test_attachments_follow_archives_and_deletes() {
+        let temp_dir = TempDir::new().unwrap();
+        let sm = SessionManager::new(temp_dir.path().join("a"));
+        let session = sm
+            .create_session(PathBuf::from("/tmp"), "csv".to_string(), SessionType::User)
+            .await
+            .unwrap();
+        let attachment = sm
+            .add_attachment(
+                &session.id,
+                AttachmentSource::Bytes {
+                    name: "sales.csv".to_string(),
+                    data: b"region,total\nnorth,3\n".to_vec(),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(attachment.path.starts_with(temp_dir.path().join("a")));
+        sm.add_message(
+            &session.id,
+            &Message::user()
+                .with_text("analyze this CSV")
+                .with_attachment(attachment.clone()),
+        )
+        .await
+        .unwrap();
+        let archive = sm.export_archive(&session.id).await.unwrap();
+
+        sm.delete_session(&session.id).await.unwrap();
+        assert!(!attachment.path.exists());
+
+        let elsewhere = SessionManager::new(temp_dir.path().join("b"));
+        let imported = elsewhere
+            .import_archive(&archive, ArchiveIds::Remap)
+            .await
+            .unwrap();
+        let message = imported.conversation.unwrap().messages()[0].clone();
+        let MessageContent::Attachment(restored) = &message.content[1] else {
+            panic!("expected an attachment");
+        };
+        assert!(restored.path.starts_with(temp_dir.path().join("b")));
+        assert_eq!(
+            std::fs::read_to_string(&restored.path).unwrap(),
+            "region,total\nnorth,3\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attachment_is_kept_before_a_message_uses_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let sm = SessionManager::new(temp_dir.path().to_path_buf());
+        let csv = || AttachmentSource::Bytes {
+            name: "sales.csv".to_string(),
+            data: b"region,total\n".to_vec(),
+        };
+        let mut sessions = Vec::new();
+        for name in ["old", "new"] {
+            let session = sm
+                .create_session(PathBuf::from("/tmp"), name.to_string(), SessionType::User)
+                .await
+                .unwrap();
+            sessions.push(session.id);
+        }
+        sm.add_attachment(&sessions[0], csv(), None).await.unwrap();
+        let attachment = sm.add_attachment(&sessions[1], csv(), None).await.unwrap();
+
+        // The new session hasn't sent a message with the file yet
+        sm.delete_session(&sessions[0]).await.unwrap();
+        assert!(attachment.path.exists());
+
+        sm.delete_session(&sessions[1]).await.unwrap();
+        assert!(!attachment.path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_prune_by_count_bytes_and_age() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(SessionStorage::new(temp_dir.path().to_path_buf()));
+        let sm = SessionManager::with_store(storage.clone());
+        let mut ids = Vec::new();
+        for (index, updated_at) in [
+            "2020-01-01 00:00:00",
+            "2020-01-02 00:00:00",
+            "2099-01-01 00:00:00",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let session = sm
+                .create_session(
+                    PathBuf::from("/tmp"),
+                    format!("s{index}"),
+                    SessionType::User,
+                )
+                .await
+                .unwrap();
+            sm.add_message(&session.id, &Message::user().with_text("x".repeat(400)))
+                .await
+                .unwrap();
+            sqlx::query("UPDATE sessions SET updated_at = ? WHERE id = ?")
+                .bind(updated_at)
+                .bind(&session.id)
+                .execute(storage.pool().await.unwrap())
+                .await
+                .unwrap();
+            ids.push(session.id);
+        }
+        let pruned_ids = |pruned: Vec<PrunedSession>| -> Vec<String> {
+            pruned.into_iter().map(|session| session.id).collect()
+        };
+
+        let none = HashSet::new();
+
+        assert!(sm
+            .prune(&RetentionPolicy::default(), &none)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let policy = RetentionPolicy {
+            max_count: Some(2),
+            ..Default::default()
+        };
+        // A session in use is kept even when it falls outside the policy
+        let in_use = HashSet::from([ids[0].clone()]);
+        assert!(sm.prune(&policy, &in_use).await.unwrap().is_empty());
+        assert_eq!(
+            pruned_ids(sm.prune(&policy, &none).await.unwrap()),
+            [ids[0].clone()]
+        );
+
+        let policy = RetentionPolicy {
+            max_bytes: Some(600),
+            ..Default::default()
+        };
+        assert_eq!(
+            pruned_ids(sm.prune(&policy, &none).await.unwrap()),
+            [ids[1].clone()]
+        );
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            ..Default::default()
+        };
+        assert!(sm.prune(&policy, &none).await.unwrap().is_empty());
+        assert!(sm.get_session(&ids[2], false).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_with_tags() {
+        let temp_dir = TempDir::new().unwrap();
+        let sm = SessionManager::new(temp_dir.path().to_path_buf());
+        let mut ids = Vec::new();
+        for project in ["alpha", "beta"] {
+            let session = sm
+                .create_session(
+                    PathBuf::from("/tmp"),
+                    project.to_string(),
+                    SessionType::User,
+                )
+                .await
+                .unwrap();
+            sm.update(&session.id)
+                .tags(HashMap::from([
+                    ("project".to_string(), project.to_string()),
+                    ("customer".to_string(), "acme".to_string()),
+                ]))
+                .apply()
+                .await
+                .unwrap();
+            sm.add_message(&session.id, &Message::user().with_text("hi"))
+                .await
+                .unwrap();
+            ids.push(session.id);
+        }
+
+        let list = |tags: &[(&str, &str)]| {
+            let tags = tags
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            let sm = &sm;
+            async move {
+                let mut ids: Vec<String> = sm
+                    .list_sessions_with_tags(&tags)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|session| session.id)
+                    .collect();
+                ids.sort();
+                ids
+            }
+        };
+        assert_eq!(list(&[("customer", "acme")]).await, ids);
+        assert_eq!(list(&[("project", "beta")]).await, [ids[1].clone()]);
+        assert!(list(&[("project", "beta"), ("customer", "other")])
+            .await
+            .is_empty());
+
+        let session = sm.get_session(&ids[0], false).await.unwrap();
+        assert_eq!(session.tags["project"], "alpha");
+    }
+
+    #[tokio::test]
+    async fn test_cost_accounting() {
+        use crate::providers::base::Usage;
+
+        let temp_dir = TempDir::new().unwrap();
+        let sm = SessionManager::new(temp_dir.path().to_path_buf());
+        let session = sm
+            .create_session(PathBuf::from("/tmp"), "cost".to_string(), SessionType::User)
+            .await
+            .unwrap();
+
+        let usage = |model: &str, input, output| {
+            ProviderUsage::new(
+                model.to_string(),
+                Usage::new(Some(input), Some(output), None),
+            )
+        };
+        for (provider, usage) in [
+            ("openai", usage("gpt-4o", 1000, 100)),
+            ("openai", usage("gpt-4o", 2000, 200)),
+            ("custom", usage("in-house-model", 500, 50)),
+        ] {
+            sm.record_usage(&session.id, provider, &usage)
+                .await
+                .unwrap();
+        }
+
+        let report = sm.session_cost(&session.id).await.unwrap();
+        assert_eq!((report.input_tokens, report.output_tokens), (3500, 350));
+        assert_eq!(report.by_model.len(), 2);
+        let custom = &report.by_model[0];
+        assert_eq!(custom.model, "in-house-model");
+        assert_eq!(custom.cost_usd, None);
+        let gpt = &report.by_model[1];
+        assert_eq!((gpt.input_tokens, gpt.output_tokens), (3000, 300));
+        let expected = estimate_cost_usd("openai", "gpt-4o", 3000, 300).unwrap();
+        assert!((gpt.cost_usd.unwrap() - expected).abs() < 1e-9);
+        assert_eq!(report.cost_usd, gpt.cost_usd);
+
+        let by_provider = sm.cost_totals(CostGrouping::Provider, None).await.unwrap();
+        let keys: Vec<_> = by_provider.iter().map(|total| total.key.as_str()).collect();
+        assert_eq!(keys, ["custom", "openai"]);
+
+        let by_day = sm.cost_totals(CostGrouping::Day, None).await.unwrap();
+        assert_eq!(by_day.len(), 1);
+        assert_eq!(by_day[0].key, Utc::now().format("%Y-%m-%d").to_string());
+        assert_eq!(by_day[0].input_tokens, 3500);
+
+        let tomorrow = Utc::now() + chrono::Duration::days(1);
+        assert!(sm
+            .cost_totals(CostGrouping::Session, Some(tomorrow))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
     #[tokio::test]
     async fn test_import_session_with_description_field() {
         const OLD_FORMAT_JSON: &str = r#"{
@@ -1623,4 +2597,298 @@ mod tests {
         assert!(imported.user_set_name);
         assert_eq!(imported.working_dir, PathBuf::from("/tmp/test"));
     }
+
+    /// Keeps sessions in memory, to check that the manager only relies on the store's contract.
+    #[derive(Default)]
+    struct MemoryStore {
+        sessions: std::sync::Mutex<HashMap<String, Session>>,
+        next_id: std::sync::atomic::AtomicUsize,
+        attachments_dir: PathBuf,
+    }
+
+    impl MemoryStore {
+        fn next_id(&self, prefix: &str) -> String {
+            let n = self
+                .next_id
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            format!("{}{}", prefix, n)
+        }
+
+        fn with_session<T>(&self, id: &str, f: impl FnOnce(&mut Session) -> T) -> Result<T> {
+            let mut sessions = self.sessions.lock().unwrap();
+            let session = sessions
+                .get_mut(id)
+                .ok_or_else(|| anyhow::anyhow!("Session not found: {}", id))?;
+            Ok(f(session))
+        }
+    }
+
+    #[async_trait]
+    impl SessionStore for MemoryStore {
+        async fn create_session(
+            &self,
+            working_dir: PathBuf,
+            name: String,
+            session_type: SessionType,
+        ) -> Result<Session> {
+            let session = Session {
+                working_dir,
+                name,
+                session_type,
+                ..Default::default()
+            };
+            self.insert_session(None, &session).await
+        }
+
+        async fn insert_session(&self, id: Option<&str>, session: &Session) -> Result<Session> {
+            let id = id
+                .map(str::to_string)
+                .unwrap_or_else(|| self.next_id("session_"));
+            let mut messages = session
+                .conversation
+                .as_ref()
+                .map(|conversation| conversation.messages().clone())
+                .unwrap_or_default();
+            for message in &mut messages {
+                if message.id.is_none() {
+                    message.id = Some(self.next_id("msg_"));
+                }
+            }
+            let session = Session {
+                id: id.clone(),
+                message_count: messages.len(),
+                conversation: Some(Conversation::new_unvalidated(messages)),
+                ..session.clone()
+            };
+            let mut sessions = self.sessions.lock().unwrap();
+            if sessions.contains_key(&id) {
+                anyhow::bail!("Session {} already exists", id);
+            }
+            sessions.insert(id, session.clone());
+            Ok(session)
+        }
+
+        async fn get_session(&self, id: &str, include_messages: bool) -> Result<Session> {
+            let session = self.with_session(id, |session| session.clone())?;
+            Ok(if include_messages {
+                session
+            } else {
+                session.without_messages()
+            })
+        }
+
+        async fn apply_update(&self, session_id: &str, update: SessionUpdate) -> Result<()> {
+            self.with_session(session_id, |session| {
+                macro_rules! apply {
+                    ($($field:ident),*) => {
+                        $(if let Some(value) = update.$field {
+                            session.$field = value;
+                        })*
+                    };
+                }
+                apply!(
+                    name,
+                    user_set_name,
+                    session_type,
+                    working_dir,
+                    extension_data,
+                    total_tokens,
+                    input_tokens,
+                    output_tokens,
+                    accumulated_total_tokens,
+                    accumulated_input_tokens,
+                    accumulated_output_tokens,
+                    schedule_id,
+                    recipe,
+                    user_recipe_values,
+                    provider_name,
+                    model_config,
+                    tags
+                );
+            })
+        }
+
+        async fn add_message(&self, session_id: &str, message: &Message) -> Result<()> {
+            let mut message = message.clone();
+            if message.id.is_none() {
+                message.id = Some(self.next_id("msg_"));
+            }
+            self.with_session(session_id, |session| {
+                session
+                    .conversation
+                    .get_or_insert_with(Conversation::empty)
+                    .push(message);
+                session.message_count += 1;
+            })
+        }
+
+        async fn replace_conversation(
+            &self,
+            session_id: &str,
+            conversation: &Conversation,
+        ) -> Result<()> {
+            self.with_session(session_id, |session| {
+                session.message_count = conversation.messages().len();
+                session.conversation = Some(conversation.clone());
+            })
+        }
+
+        async fn truncate_conversation(&self, session_id: &str, timestamp: i64) -> Result<()> {
+            self.with_session(session_id, |session| {
+                let kept: Vec<_> = session
+                    .conversation
+                    .take()
+                    .map(|conversation| conversation.messages().clone())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|message| message.created < timestamp)
+                    .collect();
+                session.message_count = kept.len();
+                session.conversation = Some(Conversation::new_unvalidated(kept));
+            })
+        }
+
+        async fn update_message_metadata(
+            &self,
+            _session_id: &str,
+            _message_id: &str,
+            _f: MetadataUpdate,
+        ) -> Result<()> {
+            anyhow::bail!("not supported")
+        }
+
+        async fn list_sessions_by_types(&self, types: &[SessionType]) -> Result<Vec<Session>> {
+            let sessions = self.sessions.lock().unwrap();
+            Ok(sessions
+                .values()
+                .filter(|session| types.contains(&session.session_type))
+                .map(|session| session.clone().without_messages())
+                .collect())
+        }
+
+        async fn delete_session(&self, session_id: &str) -> Result<()> {
+            self.sessions.lock().unwrap().remove(session_id);
+            Ok(())
+        }
+
+        async fn prune(
+            &self,
+            _policy: &RetentionPolicy,
+            _keep: &HashSet<String>,
+        ) -> Result<Vec<PrunedSession>> {
+            anyhow::bail!("not supported")
+        }
+
+        fn attachments_dir(&self) -> PathBuf {
+            self.attachments_dir.clone()
+        }
+
+        async fn add_attachment(
+            &self,
+            _session_id: &str,
+            _source: AttachmentSource,
+            _mime_type: Option<String>,
+        ) -> Result<AttachmentContent> {
+            anyhow::bail!("not supported")
+        }
+
+        async fn record_usage(
+            &self,
+            _session_id: &str,
+            _provider_name: &str,
+            _usage: &ProviderUsage,
+        ) -> Result<()> {
+            anyhow::bail!("not supported")
+        }
+
+        async fn session_cost(&self, _session_id: &str) -> Result<SessionCostReport> {
+            anyhow::bail!("not supported")
+        }
+
+        async fn cost_totals(
+            &self,
+            _grouping: CostGrouping,
+            _since: Option<DateTime<Utc>>,
+        ) -> Result<Vec<CostTotal>> {
+            anyhow::bail!("not supported")
+        }
+
+        async fn get_insights(&self) -> Result<SessionInsights> {
+            anyhow::bail!("not supported")
+        }
+
+        async fn search_chat_history(
+            &self,
+            _query: &str,
+            _limit: Option<usize>,
+            _after_date: Option<DateTime<Utc>>,
+            _before_date: Option<DateTime<Utc>>,
+            _exclude_session_id: Option<String>,
+        ) -> Result<ChatRecallResults> {
+            anyhow::bail!("not supported")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let sm = SessionManager::with_store(Arc::new(MemoryStore {
+            attachments_dir: temp_dir.path().join("a"),
+            ..Default::default()
+        }));
+        let original = sm
+            .create_session(
+                PathBuf::from("/tmp/test"),
+                "Original".to_string(),
+                SessionType::User,
+            )
+            .await
+            .unwrap();
+        sm.update(&original.id)
+            .provider_name("openai")
+            .apply()
+            .await
+            .unwrap();
+        for (id, text) in [("m1", "first"), ("m2", "second")] {
+            sm.add_message(&original.id, &Message::user().with_text(text).with_id(id))
+                .await
+                .unwrap();
+        }
+
+        let forked = sm.fork(&original.id, "m1").await.unwrap();
+        assert_ne!(forked.id, original.id);
+        assert_eq!(forked.provider_name.as_deref(), Some("openai"));
+        assert_eq!(forked.conversation.unwrap().messages().len(), 1);
+
+        let imported = sm
+            .import_session(&sm.export_session(&original.id).await.unwrap())
+            .await
+            .unwrap();
+        assert_ne!(imported.id, original.id);
+        assert_eq!(imported.message_count, 2);
+
+        let archive = sm.export_archive(&original.id).await.unwrap();
+        let elsewhere = SessionManager::with_store(Arc::new(MemoryStore {
+            attachments_dir: temp_dir.path().join("b"),
+            ..Default::default()
+        }));
+        let preserved = elsewhere
+            .import_archive(&archive, ArchiveIds::Preserve)
+            .await
+            .unwrap();
+        assert_eq!(preserved.id, original.id);
+        assert_eq!(preserved.provider_name.as_deref(), Some("openai"));
+        let ids: Vec<_> = preserved
+            .conversation
+            .unwrap()
+            .messages()
+            .iter()
+            .map(|message| message.id.clone().unwrap())
+            .collect();
+        assert_eq!(ids, ["m1", "m2"]);
+        assert!(elsewhere
+            .import_archive(&archive, ArchiveIds::Preserve)
+            .await
+            .is_err());
+    }
 }
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::conversation::message::{AttachmentContent, Message, MessageMetadata};
+use crate::conversation::Conversation;
+use crate::providers::base::ProviderUsage;
+use crate::session::attachments::AttachmentSource;
+use crate::session::chat_history_search::ChatRecallResults;
+use crate::session::cost::{CostGrouping, CostTotal, SessionCostReport};
+use crate::session::retention::{PrunedSession, RetentionPolicy};
+use crate::session::session_manager::{Session, SessionInsights, SessionType, SessionUpdate};
+
+pub type MetadataUpdate = Box<dyn FnOnce(MessageMetadata) -> MessageMetadata + Send>;
+
+/// Where a [`SessionManager`](super::SessionManager) keeps sessions, their messages and their
+/// usage. The default is the SQLite database in the data dir; deployments with several servers
+/// can plug in a shared store with [`SessionManager::with_store`](super::SessionManager::with_store).
+///
+/// Copies and forks are built on these operations by the manager, so a store only provides the
+/// basic reads and writes. Archive imports are written with [`SessionStore::insert_session`] so a
+/// failed one leaves nothing behind.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Creates a session under a new, unique id.
+    async fn create_session(
+        &self,
+        working_dir: PathBuf,
+        name: String,
+        session_type: SessionType,
+    ) -> Result<Session>;
+
+    /// Stores `session` with its metadata and messages all at once, under `id` or a new, unique
+    /// id when there is none. Fails if a session with `id` already exists.
+    async fn insert_session(&self, id: Option<&str>, session: &Session) -> Result<Session>;
+
+    async fn get_session(&self, id: &str, include_messages: bool) -> Result<Session>;
+
+    async fn apply_update(&self, session_id: &str, update: SessionUpdate) -> Result<()>;
+
+    async fn add_message(&self, session_id: &str, message: &Message) -> Result<()>;
+
+    async fn replace_conversation(
+        &self,
+        session_id: &str,
+        conversation: &Conversation,
+    ) -> Result<()>;
+
+    /// Deletes the messages created at or after `timestamp`.
+    async fn truncate_conversation(&self, session_id: &str, timestamp: i64) -> Result<()>;
+
+    async fn update_message_metadata(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        f: MetadataUpdate,
+    ) -> Result<()>;
+
+    /// Sessions of the given types that have messages, most recently updated first.
+    async fn list_sessions_by_types(&self, types: &[SessionType]) -> Result<Vec<Session>>;
+
+    async fn list_sessions(&self) -> Result<Vec<Session>> {
+        self.list_sessions_by_types(&[SessionType::User, SessionType::Scheduled])
+            .await
+    }
+
+    async fn delete_session(&self, session_id: &str) -> Result<()>;
+
+    /// Deletes the sessions that fall outside `policy`, except those in `keep`, and returns them.
+    async fn prune(
+        &self,
+        policy: &RetentionPolicy,
+        keep: &HashSet<String>,
+    ) -> Result<Vec<PrunedSession>>;
+
+    /// The local directory attachment files are kept in.
+    fn attachments_dir(&self) -> PathBuf;
+
+    async fn add_attachment(
+        &self,
+        session_id: &str,
+        source: AttachmentSource,
+        mime_type: Option<String>,
+    ) -> Result<AttachmentContent>;
+
+    async fn record_usage(
+        &self,
+        session_id: &str,
+        provider_name: &str,
+        usage: &ProviderUsage,
+    ) -> Result<()>;
+
+    async fn session_cost(&self, session_id: &str) -> Result<SessionCostReport>;
+
+    async fn cost_totals(
+        &self,
+        grouping: CostGrouping,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<CostTotal>>;
+
+    async fn get_insights(&self) -> Result<SessionInsights>;
+
+    async fn search_chat_history(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        after_date: Option<DateTime<Utc>>,
+        before_date: Option<DateTime<Utc>>,
+        exclude_session_id: Option<String>,
+    ) -> Result<ChatRecallResults>;
+}
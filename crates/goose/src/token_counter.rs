@@ -8,7 +8,8 @@ use tokio::sync::OnceCell;
 
 use crate::conversation::message::Message;
 
-static TOKENIZER: OnceCell<Arc<CoreBPE>> = OnceCell::const_new();
+static O200K_TOKENIZER: OnceCell<Arc<CoreBPE>> = OnceCell::const_new();
+static CL100K_TOKENIZER: OnceCell<Arc<CoreBPE>> = OnceCell::const_new();
 
 const MAX_TOKEN_CACHE_SIZE: usize = 10_000;
 
@@ -20,20 +21,82 @@ const ENUM_INIT: isize = -3;
 const ENUM_ITEM: usize = 3;
 const FUNC_END: usize = 12;
 
+// OpenAI model families on o200k_base that tiktoken-rs doesn't know about yet.
+const O200K_MODEL_PREFIXES: &[&str] = &[
+    "gpt-4o",
+    "gpt-4.1",
+    "gpt-4.5",
+    "gpt-5",
+    "gpt-oss",
+    "chatgpt-4o",
+    "codex",
+    "o1",
+    "o3",
+    "o4",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizerKind {
+    O200kBase,
+    Cl100kBase,
+}
+
+impl TokenizerKind {
+    /// The tokenizer for `model_name` and whether it is the one the model actually uses.
+    /// Models outside the OpenAI family are approximated with o200k_base.
+    pub fn for_model(model_name: &str) -> (Self, bool) {
+        let name = model_name
+            .rsplit('/')
+            .next()
+            .unwrap_or(model_name)
+            .to_ascii_lowercase();
+        let is_o200k = O200K_MODEL_PREFIXES.iter().any(|prefix| {
+            name.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['-', '.', ':']))
+        });
+        if is_o200k {
+            return (Self::O200kBase, true);
+        }
+        match tiktoken_rs::tokenizer::get_tokenizer(&name) {
+            Some(tiktoken_rs::tokenizer::Tokenizer::O200kBase) => (Self::O200kBase, true),
+            Some(tiktoken_rs::tokenizer::Tokenizer::Cl100kBase) => (Self::Cl100kBase, true),
+            _ => (Self::O200kBase, false),
+        }
+    }
+}
+
 pub struct TokenCounter {
     tokenizer: Arc<CoreBPE>,
     token_cache: Arc<DashMap<u64, usize>>,
+    exact: bool,
 }
 
 impl TokenCounter {
     pub async fn new() -> Result<Self, String> {
-        let tokenizer = get_tokenizer().await?;
+        Self::with_tokenizer(TokenizerKind::O200kBase, false).await
+    }
+
+    /// A counter using the tokenizer `model_name` is served with, so context size can be
+    /// measured before a request is sent rather than read back from provider usage.
+    pub async fn for_model(model_name: &str) -> Result<Self, String> {
+        let (kind, exact) = TokenizerKind::for_model(model_name);
+        Self::with_tokenizer(kind, exact).await
+    }
+
+    async fn with_tokenizer(kind: TokenizerKind, exact: bool) -> Result<Self, String> {
+        let tokenizer = get_tokenizer(kind).await?;
         Ok(Self {
             tokenizer,
             token_cache: Arc::new(DashMap::new()),
+            exact,
         })
     }
 
+    /// Whether counts match the model's own tokenizer rather than approximating it.
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
     pub fn count_tokens(&self, text: &str) -> usize {
         let mut hasher = AHasher::default();
         text.hash(&mut hasher);
@@ -182,15 +245,29 @@ impl TokenCounter {
     }
 }
 
-async fn get_tokenizer() -> Result<Arc<CoreBPE>, String> {
-    let tokenizer = TOKENIZER
-        .get_or_init(|| async {
-            match tiktoken_rs::o200k_base() {
-                Ok(bpe) => Arc::new(bpe),
-                Err(e) => panic!("Failed to initialize o200k_base tokenizer: {}", e),
-            }
-        })
-        .await;
+async fn get_tokenizer(kind: TokenizerKind) -> Result<Arc<CoreBPE>, String> {
+    let tokenizer = match kind {
+        TokenizerKind::O200kBase => {
+            O200K_TOKENIZER
+                .get_or_init(|| async {
+                    match tiktoken_rs::o200k_base() {
+                        Ok(bpe) => Arc::new(bpe),
+                        Err(e) => panic!("Failed to initialize o200k_base tokenizer: {}", e),
+                    }
+                })
+                .await
+        }
+        TokenizerKind::Cl100kBase => {
+            CL100K_TOKENIZER
+                .get_or_init(|| async {
+                    match tiktoken_rs::cl100k_base() {
+                        Ok(bpe) => Arc::new(bpe),
+                        Err(e) => panic!("Failed to initialize cl100k_base tokenizer: {}", e),
+                    }
+                })
+                .await
+        }
+    };
     Ok(tokenizer.clone())
 }
 
@@ -198,9 +275,34 @@ pub async fn create_token_counter() -> Result<TokenCounter, String> {
     TokenCounter::new().await
 }
 
+pub async fn create_token_counter_for_model(model_name: &str) -> Result<TokenCounter, String> {
+    TokenCounter::for_model(model_name).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use test_case::test_case;
+
+    #[test_case("gpt-4o-mini", TokenizerKind::O200kBase, true; "gpt-4o")]
+    #[test_case("openai/gpt-5", TokenizerKind::O200kBase, true; "routed gpt-5")]
+    #[test_case("o3-mini", TokenizerKind::O200kBase, true; "reasoning model")]
+    #[test_case("gpt-4-turbo", TokenizerKind::Cl100kBase, true; "gpt-4")]
+    #[test_case("gpt-3.5-turbo", TokenizerKind::Cl100kBase, true; "gpt-3.5")]
+    #[test_case("claude-sonnet-4-5", TokenizerKind::O200kBase, false; "other provider")]
+    #[test_case("o1ne", TokenizerKind::O200kBase, false; "prefix without boundary")]
+    fn test_tokenizer_for_model(model: &str, kind: TokenizerKind, exact: bool) {
+        assert_eq!(TokenizerKind::for_model(model), (kind, exact));
+    }
+
+    #[tokio::test]
+    async fn test_counts_with_model_tokenizer() {
+        let text = "Tokenizers split the same text differently: 🦆🦆🦆 ñandú";
+        let o200k = create_token_counter_for_model("gpt-4o").await.unwrap();
+        let cl100k = create_token_counter_for_model("gpt-4").await.unwrap();
+        assert!(o200k.is_exact() && cl100k.is_exact());
+        assert_ne!(o200k.count_tokens(text), cl100k.count_tokens(text));
+    }
 
     #[tokio::test]
     async fn test_token_caching() {
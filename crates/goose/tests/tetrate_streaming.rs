@@ -7,6 +7,7 @@ use goose::providers::tetrate::TetrateProvider;
 use rmcp::model::Tool;
 use rmcp::object;
 use serial_test::serial;
+use tokio_util::sync::CancellationToken;
 
 /// Test module for Tetrate Agent Router Service streaming functionality
 #[cfg(test)]
@@ -33,6 +34,7 @@ mod tetrate_streaming_tests {
                 "You are a helpful assistant that counts numbers.",
                 &messages,
                 &[],
+                CancellationToken::new(),
             )
             .await?;
 
@@ -105,6 +107,7 @@ mod tetrate_streaming_tests {
                 "You are a helpful assistant with access to weather information.",
                 &messages,
                 &[weather_tool],
+                CancellationToken::new(),
             )
             .await?;
 
@@ -153,6 +156,7 @@ mod tetrate_streaming_tests {
                 "You are a helpful assistant.",
                 &messages,
                 &[],
+                CancellationToken::new(),
             )
             .await?;
 
@@ -188,6 +192,7 @@ mod tetrate_streaming_tests {
                 "You are a helpful assistant that writes detailed essays.",
                 &messages,
                 &[],
+                CancellationToken::new(),
             )
             .await?;
 
@@ -248,6 +253,7 @@ mod tetrate_streaming_tests {
                 "You are a helpful assistant.",
                 &messages,
                 &[],
+                CancellationToken::new(),
             )
             .await;
 
@@ -276,6 +282,7 @@ mod tetrate_streaming_tests {
                 "You are a helpful assistant.",
                 &messages1,
                 &[],
+                CancellationToken::new(),
             )
             .await?;
 
@@ -285,6 +292,7 @@ mod tetrate_streaming_tests {
                 "You are a helpful assistant.",
                 &messages2,
                 &[],
+                CancellationToken::new(),
             )
             .await?;
 